@@ -46,7 +46,7 @@ then
     echo >&2 "    docker kill ${RUNNING_POSTGRES_CONTAINER}"
     exit 1
   fi
-  # Launch postgres using Docker
+  # Launch postgres with the postgis extension using Docker
   docker run \
       -e POSTGRES_USER=${DATABASE_USER} \
       -e POSTGRES_PASSWORD=${DATABASE_PASSWORD} \
@@ -54,7 +54,7 @@ then
       -p "${DATABASE_PORT}":5432 \
       -d \
       --name "postgres_$(date '+%s')" \
-      postgres -N 1000
+      postgis/postgis -N 1000
       # ^ Increased maximum number of connections for testing purposes
 fi
 
@@ -0,0 +1,79 @@
+-- Store boundaries for each geo id so the backend can serve vector tiles
+CREATE EXTENSION IF NOT EXISTS postgis;
+
+CREATE TABLE geo_boundary (
+    geography_type INT NOT NULL,
+    id INT8 NOT NULL,
+    geometry geometry(MultiPolygon, 4326) NOT NULL,
+    PRIMARY KEY (geography_type, id),
+    FOREIGN KEY (geography_type, id) REFERENCES geo_id (geography_type, id)
+);
+
+CREATE INDEX geo_boundary_geometry_index ON geo_boundary USING GIST (geometry);
+
+-- Every change to a dataset's data bumps its version, so anything derived from the data
+-- (like generated tiles) knows when it is stale
+CREATE TABLE data_version (
+    dataset INT PRIMARY KEY REFERENCES dataset (id) ON DELETE CASCADE,
+    version INT NOT NULL DEFAULT 1
+);
+
+INSERT INTO
+    data_version (dataset)
+SELECT
+    id
+FROM
+    dataset;
+
+CREATE FUNCTION bump_data_version() RETURNS TRIGGER AS $$
+BEGIN
+    INSERT INTO
+        data_version (dataset)
+    SELECT DISTINCT
+        dataset
+    FROM
+        changed_rows
+    WHERE
+        EXISTS (SELECT 1 FROM dataset WHERE dataset.id = changed_rows.dataset)
+    ON CONFLICT (dataset) DO UPDATE
+    SET
+        version = data_version.version + 1;
+
+    RETURN NULL;
+END;
+$$ LANGUAGE plpgsql;
+
+CREATE TRIGGER data_inserted
+AFTER
+    INSERT ON data REFERENCING NEW TABLE AS changed_rows FOR EACH STATEMENT EXECUTE FUNCTION bump_data_version();
+
+CREATE TRIGGER data_updated
+AFTER
+    UPDATE ON data REFERENCING NEW TABLE AS changed_rows FOR EACH STATEMENT EXECUTE FUNCTION bump_data_version();
+
+CREATE TRIGGER data_deleted
+AFTER
+    DELETE ON data REFERENCING OLD TABLE AS changed_rows FOR EACH STATEMENT EXECUTE FUNCTION bump_data_version();
+
+-- Generated mapbox vector tiles, valid as long as the dataset version matches
+CREATE TABLE tile_cache (
+    map_visualization INT NOT NULL REFERENCES map_visualization (id) ON DELETE CASCADE,
+    source INT NOT NULL,
+    start_date DATE NOT NULL,
+    end_date DATE NOT NULL,
+    z INT NOT NULL,
+    x INT NOT NULL,
+    y INT NOT NULL,
+    dataset INT NOT NULL REFERENCES dataset (id) ON DELETE CASCADE,
+    version INT NOT NULL,
+    tile BYTEA NOT NULL,
+    PRIMARY KEY (
+        map_visualization,
+        source,
+        start_date,
+        end_date,
+        z,
+        x,
+        y
+    )
+);
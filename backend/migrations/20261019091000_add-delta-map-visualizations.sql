@@ -0,0 +1,42 @@
+-- Delta map visualizations show the change between two slices of their dataset
+CREATE TABLE delta_operation (
+    id SERIAL NOT NULL,
+    name VARCHAR(30) NOT NULL UNIQUE,
+    PRIMARY KEY (id)
+);
+
+INSERT INTO
+    delta_operation (name)
+VALUES
+    ('difference'),
+    ('ratio'),
+    ('percent change');
+
+ALTER TABLE
+    map_visualization
+ADD
+    COLUMN delta_operation INT REFERENCES delta_operation (id),
+ADD
+    COLUMN delta_from_source INT REFERENCES data_source (id),
+ADD
+    COLUMN delta_from_start_date DATE,
+ADD
+    COLUMN delta_from_end_date DATE,
+ADD
+    COLUMN delta_to_source INT REFERENCES data_source (id),
+ADD
+    COLUMN delta_to_start_date DATE,
+ADD
+    COLUMN delta_to_end_date DATE,
+ADD
+    CONSTRAINT delta_slices_set CHECK (
+        delta_operation IS NULL
+        OR (
+            delta_from_source IS NOT NULL
+            AND delta_from_start_date IS NOT NULL
+            AND delta_from_end_date IS NOT NULL
+            AND delta_to_source IS NOT NULL
+            AND delta_to_start_date IS NOT NULL
+            AND delta_to_end_date IS NOT NULL
+        )
+    );
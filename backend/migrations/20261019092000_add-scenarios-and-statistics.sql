@@ -0,0 +1,94 @@
+-- Projections come in several scenarios (SSP/RCP), and each can have a statistic like the
+-- mean or a confidence bound. Existing data is observed and a mean.
+CREATE TABLE scenario (
+    id SERIAL NOT NULL,
+    name VARCHAR(30) NOT NULL UNIQUE,
+    description TEXT NOT NULL DEFAULT '',
+    PRIMARY KEY (id)
+);
+
+INSERT INTO
+    scenario (name, description)
+VALUES
+    ('observed', 'Historical observations, or data without a scenario'),
+    ('SSP1-2.6', 'Sustainability, low emissions'),
+    ('SSP2-4.5', 'Middle of the road, intermediate emissions'),
+    ('SSP3-7.0', 'Regional rivalry, high emissions'),
+    ('SSP5-8.5', 'Fossil-fueled development, very high emissions'),
+    ('RCP4.5', 'Intermediate emissions'),
+    ('RCP8.5', 'High emissions');
+
+-- percentile is set for statistics that are a percentile of an ensemble
+CREATE TABLE statistic (
+    id SERIAL NOT NULL,
+    name VARCHAR(30) NOT NULL UNIQUE,
+    percentile FLOAT,
+    PRIMARY KEY (id)
+);
+
+INSERT INTO
+    statistic (name, percentile)
+VALUES
+    ('mean', NULL),
+    ('lower confidence interval', NULL),
+    ('upper confidence interval', NULL),
+    ('10th percentile', 10),
+    ('median', 50),
+    ('90th percentile', 90);
+
+ALTER TABLE
+    data
+ADD
+    COLUMN scenario INT NOT NULL DEFAULT 1 REFERENCES scenario (id),
+ADD
+    COLUMN statistic INT NOT NULL DEFAULT 1 REFERENCES statistic (id);
+
+ALTER TABLE
+    data DROP CONSTRAINT data_pkey;
+
+ALTER TABLE
+    data
+ADD
+    PRIMARY KEY (
+        dataset,
+        source,
+        scenario,
+        statistic,
+        start_date,
+        end_date,
+        id
+    );
+
+-- cached tiles are per slice, which now includes the scenario and statistic
+ALTER TABLE
+    tile_cache
+ADD
+    COLUMN scenario INT NOT NULL DEFAULT 1,
+ADD
+    COLUMN statistic INT NOT NULL DEFAULT 1;
+
+ALTER TABLE
+    tile_cache DROP CONSTRAINT tile_cache_pkey;
+
+ALTER TABLE
+    tile_cache
+ADD
+    PRIMARY KEY (
+        map_visualization,
+        source,
+        scenario,
+        statistic,
+        start_date,
+        end_date,
+        z,
+        x,
+        y
+    );
+
+-- delta map visualizations can compare scenarios, like a projection against observations
+ALTER TABLE
+    map_visualization
+ADD
+    COLUMN delta_from_scenario INT REFERENCES scenario (id),
+ADD
+    COLUMN delta_to_scenario INT REFERENCES scenario (id);
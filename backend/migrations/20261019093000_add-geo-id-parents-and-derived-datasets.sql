@@ -0,0 +1,70 @@
+-- Which geo ids contain which, so data can be aggregated from one geography type up to another
+CREATE TABLE geo_id_parent (
+    child_geography_type INT NOT NULL,
+    child_id INT8 NOT NULL,
+    parent_geography_type INT NOT NULL,
+    parent_id INT8 NOT NULL,
+    PRIMARY KEY (
+        child_geography_type,
+        child_id,
+        parent_geography_type
+    ),
+    FOREIGN KEY (child_geography_type, child_id) REFERENCES geo_id (geography_type, id),
+    FOREIGN KEY (parent_geography_type, parent_id) REFERENCES geo_id (geography_type, id)
+);
+
+-- county ids are the state id * 1000 + the county id
+INSERT INTO
+    geo_id_parent (
+        child_geography_type,
+        child_id,
+        parent_geography_type,
+        parent_id
+    )
+SELECT
+    county.geography_type,
+    county.id,
+    state.geography_type,
+    state.id
+FROM
+    geo_id AS county
+    JOIN geo_id AS state ON state.geography_type = 3
+    AND state.id = county.id / 1000
+WHERE
+    county.geography_type = 1;
+
+-- city ids are the county id * 100000 + the place id
+INSERT INTO
+    geo_id_parent (
+        child_geography_type,
+        child_id,
+        parent_geography_type,
+        parent_id
+    )
+SELECT
+    city.geography_type,
+    city.id,
+    parent.geography_type,
+    parent.id
+FROM
+    geo_id AS city
+    JOIN geo_id AS parent ON (
+        parent.geography_type = 1
+        AND parent.id = city.id / 100000
+    )
+    OR (
+        parent.geography_type = 3
+        AND parent.id = city.id / 100000000
+    )
+WHERE
+    city.geography_type = 4;
+
+-- How a dataset was derived from another one, like by aggregating it to a parent geography type
+CREATE TABLE derived_dataset (
+    dataset INT PRIMARY KEY REFERENCES dataset (id) ON DELETE CASCADE,
+    derived_from INT REFERENCES dataset (id) ON DELETE SET NULL,
+    operation VARCHAR(30) NOT NULL,
+    method VARCHAR(30) NOT NULL,
+    weight_dataset INT REFERENCES dataset (id) ON DELETE SET NULL,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT NOW()
+);
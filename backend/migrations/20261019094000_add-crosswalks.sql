@@ -0,0 +1,67 @@
+-- Crosswalks relate geo ids of one geography type to overlapping geo ids of another, so data
+-- can be projected between them
+CREATE TABLE crosswalk (
+    id SERIAL NOT NULL,
+    name VARCHAR(60) NOT NULL UNIQUE,
+    from_geography_type INT NOT NULL REFERENCES geography_type (id),
+    to_geography_type INT NOT NULL REFERENCES geography_type (id),
+    PRIMARY KEY (id)
+);
+
+-- Each share is the fraction of the from (or to) geo id's area (or population) that is in the
+-- overlap of the two. Population shares can't be derived from boundaries and are imported.
+CREATE TABLE crosswalk_link (
+    crosswalk INT NOT NULL REFERENCES crosswalk (id) ON DELETE CASCADE,
+    from_id INT8 NOT NULL,
+    to_id INT8 NOT NULL,
+    from_area_share FLOAT,
+    to_area_share FLOAT,
+    from_population_share FLOAT,
+    to_population_share FLOAT,
+    PRIMARY KEY (crosswalk, from_id, to_id)
+);
+
+-- Connecticut replaced its counties with planning regions as county equivalents in 2022
+INSERT INTO
+    geo_id (geography_type, id, name)
+VALUES
+    (1, 9110, 'Capitol'),
+    (1, 9120, 'Greater Bridgeport'),
+    (1, 9130, 'Lower Connecticut River Valley'),
+    (1, 9140, 'Naugatuck Valley'),
+    (1, 9150, 'Northeastern Connecticut'),
+    (1, 9160, 'Northwest Hills'),
+    (1, 9170, 'South Central Connecticut'),
+    (1, 9180, 'Southeastern Connecticut'),
+    (1, 9190, 'Western Connecticut');
+
+INSERT INTO
+    geo_id_parent (
+        child_geography_type,
+        child_id,
+        parent_geography_type,
+        parent_id
+    )
+SELECT
+    1,
+    id,
+    3,
+    9
+FROM
+    geo_id
+WHERE
+    geography_type = 1
+    AND id BETWEEN 9110
+    AND 9190;
+
+INSERT INTO
+    crosswalk (name, from_geography_type, to_geography_type)
+VALUES
+    ('usa-county to usa-city', 1, 4),
+    ('connecticut counties to planning regions', 1, 1);
+
+-- Derived datasets can come from projecting another dataset with a crosswalk
+ALTER TABLE
+    derived_dataset
+ADD
+    COLUMN crosswalk INT REFERENCES crosswalk (id) ON DELETE SET NULL;
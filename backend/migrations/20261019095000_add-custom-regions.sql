@@ -0,0 +1,18 @@
+-- Partner defined regions, like service areas or watersheds, made of geo ids of one geography type
+CREATE TABLE custom_region (
+    id SERIAL NOT NULL,
+    name VARCHAR(100) NOT NULL,
+    owner VARCHAR(100) NOT NULL,
+    description TEXT NOT NULL DEFAULT '',
+    geography_type INT NOT NULL REFERENCES geography_type (id),
+    PRIMARY KEY (id),
+    UNIQUE (owner, name)
+);
+
+CREATE TABLE custom_region_member (
+    custom_region INT NOT NULL REFERENCES custom_region (id) ON DELETE CASCADE,
+    geography_type INT NOT NULL,
+    id INT8 NOT NULL,
+    PRIMARY KEY (custom_region, id),
+    FOREIGN KEY (geography_type, id) REFERENCES geo_id (geography_type, id)
+);
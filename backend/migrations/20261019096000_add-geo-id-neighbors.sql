@@ -0,0 +1,11 @@
+-- Which geo ids of a geography type are adjacent, derived from boundaries or imported. Each pair
+-- is stored in both directions.
+CREATE TABLE geo_id_neighbor (
+    geography_type INT NOT NULL,
+    id INT8 NOT NULL,
+    neighbor_id INT8 NOT NULL,
+    PRIMARY KEY (geography_type, id, neighbor_id),
+    FOREIGN KEY (geography_type, id) REFERENCES geo_id (geography_type, id) ON DELETE CASCADE,
+    FOREIGN KEY (geography_type, neighbor_id) REFERENCES geo_id (geography_type, id) ON DELETE CASCADE,
+    CHECK (id <> neighbor_id)
+);
@@ -0,0 +1,25 @@
+-- Bivariate choropleths color geo ids by the classes of their own slice and a second one
+INSERT INTO
+    map_type (name)
+VALUES
+    ('bivariate choropleth');
+
+ALTER TABLE
+    map_visualization
+ADD
+    COLUMN bivariate_dataset INT REFERENCES dataset (id) ON DELETE SET NULL,
+ADD
+    COLUMN bivariate_source INT REFERENCES data_source (id),
+ADD
+    COLUMN bivariate_start_date DATE,
+ADD
+    COLUMN bivariate_end_date DATE,
+ADD
+    CONSTRAINT bivariate_slice_set CHECK (
+        bivariate_dataset IS NULL
+        OR (
+            bivariate_source IS NOT NULL
+            AND bivariate_start_date IS NOT NULL
+            AND bivariate_end_date IS NOT NULL
+        )
+    );
@@ -0,0 +1,45 @@
+-- Map visualizations can split their data into classes, with breaks computed from the data
+CREATE TABLE classification_method (
+    id SERIAL NOT NULL,
+    name VARCHAR(30) NOT NULL UNIQUE,
+    PRIMARY KEY (id)
+);
+
+INSERT INTO
+    classification_method (name)
+VALUES
+    ('quantile'),
+    ('jenks'),
+    ('equal interval'),
+    ('standard deviation'),
+    ('manual');
+
+ALTER TABLE
+    map_visualization
+ADD
+    COLUMN classification_method INT REFERENCES classification_method (id),
+ADD
+    COLUMN classification_classes SMALLINT NOT NULL DEFAULT 5 CHECK (classification_classes > 0);
+
+-- Computed breaks, valid as long as the dataset version and the classification match
+CREATE TABLE classification_cache (
+    map_visualization INT NOT NULL REFERENCES map_visualization (id) ON DELETE CASCADE,
+    source INT NOT NULL,
+    scenario INT NOT NULL,
+    statistic INT NOT NULL,
+    start_date DATE NOT NULL,
+    end_date DATE NOT NULL,
+    dataset INT NOT NULL REFERENCES dataset (id) ON DELETE CASCADE,
+    version INT NOT NULL,
+    method INT NOT NULL,
+    classes SMALLINT NOT NULL,
+    breaks FLOAT8 [] NOT NULL,
+    PRIMARY KEY (
+        map_visualization,
+        source,
+        scenario,
+        statistic,
+        start_date,
+        end_date
+    )
+);
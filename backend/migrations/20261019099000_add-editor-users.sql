@@ -0,0 +1,36 @@
+-- Accounts for the editor server. Viewers can only read, curators can edit map visualizations,
+-- categories and derived data, and admins can also delete datasets and manage users.
+CREATE TABLE editor_role (
+    id SERIAL NOT NULL,
+    name VARCHAR(30) NOT NULL UNIQUE,
+    PRIMARY KEY (id)
+);
+
+INSERT INTO
+    editor_role (name)
+VALUES
+    ('viewer'),
+    ('curator'),
+    ('admin');
+
+CREATE TABLE editor_user (
+    id SERIAL NOT NULL,
+    username VARCHAR(100) NOT NULL UNIQUE,
+    -- An argon2 PHC string. Users without one can only use API tokens.
+    password_hash TEXT,
+    role INT NOT NULL REFERENCES editor_role (id),
+    created_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    PRIMARY KEY (id)
+);
+
+-- Bearer tokens, stored as SHA-256 hashes. Tokens from logging in expire, API tokens don't.
+CREATE TABLE api_token (
+    id SERIAL NOT NULL,
+    editor_user INT NOT NULL REFERENCES editor_user (id) ON DELETE CASCADE,
+    name VARCHAR(100) NOT NULL,
+    token_hash CHAR(64) NOT NULL UNIQUE,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    expires_at TIMESTAMPTZ,
+    last_used_at TIMESTAMPTZ,
+    PRIMARY KEY (id)
+);
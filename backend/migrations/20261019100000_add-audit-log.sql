@@ -0,0 +1,18 @@
+-- Who changed what in the editor, with the entity before and after the change
+CREATE TABLE audit_log (
+    id BIGSERIAL NOT NULL,
+    -- Kept when the user is deleted, along with their username at the time
+    editor_user INT REFERENCES editor_user (id) ON DELETE SET NULL,
+    username VARCHAR(100) NOT NULL,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    action VARCHAR(30) NOT NULL,
+    entity VARCHAR(50) NOT NULL,
+    entity_id INT,
+    before JSONB,
+    after JSONB,
+    PRIMARY KEY (id)
+);
+
+CREATE INDEX audit_log_entity ON audit_log (entity, entity_id, created_at);
+
+CREATE INDEX audit_log_created_at ON audit_log (created_at);
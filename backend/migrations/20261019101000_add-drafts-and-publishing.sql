@@ -0,0 +1,110 @@
+-- The read-only server reads map visualizations, data categories and their collections from the
+-- published schema, while the editor edits the tables in the public schema as drafts. Publishing
+-- copies the drafts to the published schema in one transaction, and reverting copies them back.
+-- Columns added to these tables need to be added to both schemas.
+CREATE SCHEMA published;
+
+-- Categories are reordered by swapping orders, which can't happen one row at a time
+ALTER TABLE
+    data_category DROP CONSTRAINT data_category_order_key,
+ADD
+    CONSTRAINT data_category_order_key UNIQUE ("order") DEFERRABLE INITIALLY DEFERRED;
+
+CREATE TABLE published.map_visualization (LIKE public.map_visualization INCLUDING DEFAULTS);
+
+ALTER TABLE
+    published.map_visualization
+ADD
+    PRIMARY KEY (id);
+
+CREATE TABLE published.data_category (LIKE public.data_category INCLUDING DEFAULTS);
+
+ALTER TABLE
+    published.data_category
+ADD
+    PRIMARY KEY (id);
+
+CREATE TABLE published.map_visualization_collection (
+    LIKE public.map_visualization_collection INCLUDING DEFAULTS
+);
+
+ALTER TABLE
+    published.map_visualization_collection
+ADD
+    PRIMARY KEY (category, map_visualization);
+
+INSERT INTO
+    published.map_visualization
+SELECT
+    *
+FROM
+    public.map_visualization;
+
+INSERT INTO
+    published.data_category
+SELECT
+    *
+FROM
+    public.data_category;
+
+INSERT INTO
+    published.map_visualization_collection
+SELECT
+    *
+FROM
+    public.map_visualization_collection;
+
+-- Deletes the rows of a table in the target schema that aren't in the source schema
+CREATE FUNCTION delete_missing_rows(source TEXT, target TEXT, relation TEXT, keys TEXT[])
+RETURNS VOID AS $$
+DECLARE
+    matches TEXT;
+BEGIN
+    SELECT
+        string_agg(format('target.%1$I = source.%1$I', key), ' AND ')
+    INTO matches
+    FROM unnest(keys) AS key;
+
+    EXECUTE format(
+        'DELETE FROM %I.%I AS target WHERE NOT EXISTS (SELECT FROM %I.%I AS source WHERE %s)',
+        target, relation, source, relation, matches
+    );
+END;
+$$ LANGUAGE plpgsql;
+
+-- Inserts or updates the rows of a table in the target schema to match the source schema
+CREATE FUNCTION upsert_rows(source TEXT, target TEXT, relation TEXT, keys TEXT[])
+RETURNS VOID AS $$
+DECLARE
+    columns TEXT;
+    updates TEXT;
+BEGIN
+    SELECT
+        string_agg(quote_ident(column_name::TEXT), ', ' ORDER BY ordinal_position),
+        string_agg(format('%1$I = EXCLUDED.%1$I', column_name), ', ' ORDER BY ordinal_position)
+            FILTER (WHERE column_name::TEXT <> ALL (keys))
+    INTO columns, updates
+    FROM information_schema.columns
+    WHERE table_schema::TEXT = target AND table_name::TEXT = relation;
+
+    EXECUTE format(
+        'INSERT INTO %I.%I (%s) SELECT %s FROM %I.%I ON CONFLICT (%s) DO %s',
+        target, relation, columns, columns, source, relation,
+        (SELECT string_agg(quote_ident(key), ', ') FROM unnest(keys) AS key),
+        COALESCE('UPDATE SET ' || updates, 'NOTHING')
+    );
+END;
+$$ LANGUAGE plpgsql;
+
+-- Makes the target schema's map visualizations, data categories and collections match the
+-- source's, deleting before inserting so foreign keys hold throughout
+CREATE FUNCTION copy_drafts(source TEXT, target TEXT) RETURNS VOID AS $$
+BEGIN
+    PERFORM delete_missing_rows(source, target, 'map_visualization_collection', ARRAY['category', 'map_visualization']);
+    PERFORM delete_missing_rows(source, target, 'map_visualization', ARRAY['id']);
+    PERFORM delete_missing_rows(source, target, 'data_category', ARRAY['id']);
+    PERFORM upsert_rows(source, target, 'data_category', ARRAY['id']);
+    PERFORM upsert_rows(source, target, 'map_visualization', ARRAY['id']);
+    PERFORM upsert_rows(source, target, 'map_visualization_collection', ARRAY['category', 'map_visualization']);
+END;
+$$ LANGUAGE plpgsql;
@@ -0,0 +1,44 @@
+-- Every version of each map visualization's settings, as the row in JSON
+CREATE TABLE map_visualization_revision (
+    map_visualization INT NOT NULL REFERENCES map_visualization (id) ON DELETE CASCADE,
+    -- Counts up from 1 for each map visualization
+    revision INT NOT NULL,
+    editor_user INT REFERENCES editor_user (id) ON DELETE SET NULL,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    settings JSONB NOT NULL,
+    PRIMARY KEY (map_visualization, revision)
+);
+
+INSERT INTO
+    map_visualization_revision (map_visualization, revision, settings)
+SELECT
+    id,
+    1,
+    to_jsonb(map_visualization)
+FROM
+    map_visualization;
+
+-- Sets a map visualization's columns to a revision's settings. Columns added since the revision
+-- keep their current values.
+CREATE FUNCTION restore_map_visualization(map_visualization_id INT, settings JSONB)
+RETURNS INT AS $$
+DECLARE
+    columns TEXT;
+BEGIN
+    SELECT
+        string_agg(quote_ident(column_name::TEXT), ', ' ORDER BY ordinal_position)
+    INTO columns
+    FROM information_schema.columns
+    WHERE table_schema = 'public'
+        AND table_name = 'map_visualization'
+        AND column_name::TEXT <> 'id';
+
+    EXECUTE format(
+        'UPDATE public.map_visualization AS map SET (%1$s) = ('
+            'SELECT %1$s FROM jsonb_populate_record(NULL::public.map_visualization, to_jsonb(map) || $1)'
+        ') WHERE map.id = $2',
+        columns
+    ) USING settings, map_visualization_id;
+    RETURN map_visualization_id;
+END;
+$$ LANGUAGE plpgsql;
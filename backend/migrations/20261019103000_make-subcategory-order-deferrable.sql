@@ -0,0 +1,5 @@
+-- Subcategories are reordered by renumbering them all at once, which can swap orders
+ALTER TABLE
+    subcategory DROP CONSTRAINT subcategory_order_key,
+ADD
+    CONSTRAINT subcategory_order_key UNIQUE ("order") DEFERRABLE INITIALLY DEFERRED;
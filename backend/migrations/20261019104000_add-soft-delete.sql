@@ -0,0 +1,57 @@
+-- Deleting from the editor puts rows in the trash by setting when they were deleted, so they can
+-- be restored until they're purged. Map visualizations and data categories are drafts, so the
+-- published schema gets the column too.
+ALTER TABLE
+    dataset
+ADD
+    COLUMN deleted_at TIMESTAMPTZ;
+
+ALTER TABLE
+    data_source
+ADD
+    COLUMN deleted_at TIMESTAMPTZ;
+
+ALTER TABLE
+    map_visualization
+ADD
+    COLUMN deleted_at TIMESTAMPTZ;
+
+ALTER TABLE
+    published.map_visualization
+ADD
+    COLUMN deleted_at TIMESTAMPTZ;
+
+ALTER TABLE
+    data_category
+ADD
+    COLUMN deleted_at TIMESTAMPTZ;
+
+ALTER TABLE
+    published.data_category
+ADD
+    COLUMN deleted_at TIMESTAMPTZ;
+
+-- Whether a map visualization is in the trash isn't one of its settings, so going back to a
+-- revision keeps it where it is
+CREATE OR REPLACE FUNCTION restore_map_visualization(map_visualization_id INT, settings JSONB)
+RETURNS INT AS $$
+DECLARE
+    columns TEXT;
+BEGIN
+    SELECT
+        string_agg(quote_ident(column_name::TEXT), ', ' ORDER BY ordinal_position)
+    INTO columns
+    FROM information_schema.columns
+    WHERE table_schema = 'public'
+        AND table_name = 'map_visualization'
+        AND column_name::TEXT NOT IN ('id', 'deleted_at');
+
+    EXECUTE format(
+        'UPDATE public.map_visualization AS map SET (%1$s) = ('
+            'SELECT %1$s FROM jsonb_populate_record(NULL::public.map_visualization, to_jsonb(map) || $1)'
+        ') WHERE map.id = $2',
+        columns
+    ) USING settings, map_visualization_id;
+    RETURN map_visualization_id;
+END;
+$$ LANGUAGE plpgsql;
@@ -0,0 +1,13 @@
+-- The change a delta map visualization shows for a geo id, computed like `Operation::apply` so
+-- tiles and exports match its data. A ratio or percent change from 0 is NULL.
+CREATE FUNCTION delta_change(operation INT, from_value FLOAT8, to_value FLOAT8)
+RETURNS FLOAT8 LANGUAGE SQL IMMUTABLE
+AS $$
+SELECT
+    CASE
+        operation
+        WHEN 1 THEN to_value - from_value
+        WHEN 2 THEN to_value / NULLIF(from_value, 0)
+        WHEN 3 THEN (to_value - from_value) / NULLIF(ABS(from_value), 0) * 100
+    END
+$$;
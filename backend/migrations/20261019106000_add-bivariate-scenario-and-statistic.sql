@@ -0,0 +1,15 @@
+-- A bivariate choropleth's second slice can be a projection or a statistic other than the mean,
+-- like delta slices. Unset, it's observed data and the mean.
+ALTER TABLE
+    map_visualization
+ADD
+    COLUMN bivariate_scenario INT REFERENCES scenario (id),
+ADD
+    COLUMN bivariate_statistic INT REFERENCES statistic (id);
+
+ALTER TABLE
+    published.map_visualization
+ADD
+    COLUMN bivariate_scenario INT,
+ADD
+    COLUMN bivariate_statistic INT;
@@ -0,0 +1,57 @@
+-- The published tables have no foreign keys, so they can keep rows whose parents have since been
+-- removed for good, like a map visualization of a purged dataset. Copying them back when
+-- reverting would break the drafts' foreign keys, so rows whose parents are missing from the
+-- target are skipped, and the target's own row, if it has one, is left as it is.
+CREATE OR REPLACE FUNCTION upsert_rows(source TEXT, target TEXT, relation TEXT, keys TEXT[])
+RETURNS VOID AS $$
+DECLARE
+    columns TEXT;
+    updates TEXT;
+    parents TEXT;
+BEGIN
+    SELECT
+        string_agg(quote_ident(column_name::TEXT), ', ' ORDER BY ordinal_position),
+        string_agg(format('%1$I = EXCLUDED.%1$I', column_name), ', ' ORDER BY ordinal_position)
+            FILTER (WHERE column_name::TEXT <> ALL (keys))
+    INTO columns, updates
+    FROM information_schema.columns
+    WHERE table_schema::TEXT = target AND table_name::TEXT = relation;
+
+    -- Like the foreign keys themselves, a row with any of a key's columns NULL has no parent
+    SELECT
+        string_agg(
+            format(
+                '(%s OR EXISTS (SELECT FROM %s AS parent WHERE %s))',
+                key_columns.nulls,
+                foreign_key.confrelid::regclass,
+                key_columns.matches
+            ),
+            ' AND '
+        )
+    INTO parents
+    FROM pg_constraint AS foreign_key
+    CROSS JOIN LATERAL (
+        SELECT
+            string_agg(format('source.%I IS NULL', child.attname), ' OR ') AS nulls,
+            string_agg(
+                format('parent.%I = source.%I', parent_column.attname, child.attname),
+                ' AND '
+            ) AS matches
+        FROM unnest(foreign_key.conkey, foreign_key.confkey) AS pair(child_number, parent_number)
+        JOIN pg_attribute AS child
+            ON child.attrelid = foreign_key.conrelid AND child.attnum = pair.child_number
+        JOIN pg_attribute AS parent_column
+            ON parent_column.attrelid = foreign_key.confrelid AND parent_column.attnum = pair.parent_number
+    ) AS key_columns
+    WHERE foreign_key.contype = 'f'
+    AND foreign_key.conrelid = format('%I.%I', target, relation)::regclass;
+
+    EXECUTE format(
+        'INSERT INTO %I.%I (%s) SELECT %s FROM %I.%I AS source WHERE %s ON CONFLICT (%s) DO %s',
+        target, relation, columns, columns, source, relation,
+        COALESCE(parents, 'TRUE'),
+        (SELECT string_agg(quote_ident(key), ', ') FROM unnest(keys) AS key),
+        COALESCE('UPDATE SET ' || updates, 'NOTHING')
+    );
+END;
+$$ LANGUAGE plpgsql;
@@ -0,0 +1,39 @@
+-- Generated tiles of delta map visualizations, keyed by both slices they compare, valid as long
+-- as the dataset version matches
+CREATE TABLE delta_tile_cache (
+    map_visualization INT NOT NULL REFERENCES map_visualization (id) ON DELETE CASCADE,
+    operation INT NOT NULL,
+    from_source INT NOT NULL,
+    from_scenario INT NOT NULL,
+    from_statistic INT NOT NULL,
+    from_start_date DATE NOT NULL,
+    from_end_date DATE NOT NULL,
+    to_source INT NOT NULL,
+    to_scenario INT NOT NULL,
+    to_statistic INT NOT NULL,
+    to_start_date DATE NOT NULL,
+    to_end_date DATE NOT NULL,
+    z INT NOT NULL,
+    x INT NOT NULL,
+    y INT NOT NULL,
+    dataset INT NOT NULL REFERENCES dataset (id) ON DELETE CASCADE,
+    version INT NOT NULL,
+    tile BYTEA NOT NULL,
+    PRIMARY KEY (
+        map_visualization,
+        operation,
+        from_source,
+        from_scenario,
+        from_statistic,
+        from_start_date,
+        from_end_date,
+        to_source,
+        to_scenario,
+        to_statistic,
+        to_start_date,
+        to_end_date,
+        z,
+        x,
+        y
+    )
+);
@@ -9,9 +9,15 @@ struct AppConfig {
 struct DaoConfig {
     url: String,
 }
+/// The first editor admin, created if the editor has no users yet
+pub struct AdminConfig {
+    pub username: String,
+    pub password: String,
+}
 pub struct Config {
     app: AppConfig,
     dao: DaoConfig,
+    admin: Option<AdminConfig>,
 }
 
 impl Config {
@@ -31,9 +37,17 @@ impl Config {
         let dao_config = DaoConfig {
             url: env::var("DATABASE_URL").expect("DATABASE_URL is not set"),
         };
+        let admin_config = match (
+            env::var("EDITOR_ADMIN_USERNAME"),
+            env::var("EDITOR_ADMIN_PASSWORD"),
+        ) {
+            (Ok(username), Ok(password)) => Some(AdminConfig { username, password }),
+            _ => None,
+        };
         Config {
             app: app_config,
             dao: dao_config,
+            admin: admin_config,
         }
     }
 
@@ -48,4 +62,8 @@ impl Config {
     pub fn database_url(&self) -> String {
         self.dao.url.to_string()
     }
+
+    pub fn admin(&self) -> Option<&AdminConfig> {
+        self.admin.as_ref()
+    }
 }
@@ -0,0 +1,154 @@
+use super::AppState;
+use crate::controller::auth::Curator;
+use crate::controller::derivation::{self, Error, Target};
+use crate::controller::format;
+use crate::model::aggregation::{self, Method};
+use crate::model::data::SourceAndDate;
+use crate::model::derived_dataset;
+use crate::model::{scenario, statistic};
+use actix_web::{get, post, web, HttpRequest, HttpResponse, Responder};
+use chrono::NaiveDate;
+use log::error;
+use serde::Deserialize;
+use std::collections::HashSet;
+
+pub fn init(cfg: &mut web::ServiceConfig) {
+    cfg.service(get_aggregate);
+    cfg.service(get_provenance);
+}
+
+pub fn init_editor(cfg: &mut web::ServiceConfig) {
+    cfg.service(materialize);
+}
+
+#[derive(Deserialize)]
+struct AggregateInfo {
+    source: i32,
+    #[serde(default = "scenario::observed")]
+    scenario: i32,
+    #[serde(default = "statistic::mean")]
+    statistic: i32,
+    start_date: NaiveDate,
+    end_date: NaiveDate,
+    /// The parent geography type to aggregate to
+    geography_type: i32,
+    method: Method,
+    weight_dataset: Option<i32>,
+}
+
+#[derive(Deserialize)]
+struct MaterializeInfo {
+    geography_type: i32,
+    method: Method,
+    weight_dataset: Option<i32>,
+    #[serde(flatten)]
+    target: Target,
+}
+
+#[get("/data/{dataset}/aggregate")]
+async fn get_aggregate(
+    request: HttpRequest,
+    dataset: web::Path<i32>,
+    info: web::Query<AggregateInfo>,
+    app_state: web::Data<AppState<'_>>,
+) -> Result<HttpResponse, Error> {
+    if info.method.needs_weights() && info.weight_dataset.is_none() {
+        return Err(Error::MissingWeights(info.method));
+    }
+    let children = app_state
+        .database
+        .data
+        .with_parents(
+            dataset.into_inner(),
+            &SourceAndDate {
+                source: info.source,
+                scenario: info.scenario,
+                statistic: info.statistic,
+                start_date: info.start_date,
+                end_date: info.end_date,
+            },
+            info.geography_type,
+            info.weight_dataset,
+        )
+        .await?;
+
+    Ok(format::respond(
+        &request,
+        aggregation::aggregate(info.method, children),
+    ))
+}
+
+/// Aggregates every slice of a dataset to a parent geography type and stores the result as a
+/// new dataset, recording how it was derived. Nothing is stored if any of it fails.
+#[post("/dataset/{dataset}/aggregate")]
+async fn materialize(
+    Curator(user): Curator,
+    dataset: web::Path<i32>,
+    info: web::Json<MaterializeInfo>,
+    app_state: web::Data<AppState<'_>>,
+) -> Result<HttpResponse, Error> {
+    let info = info.into_inner();
+    if info.method.needs_weights() && info.weight_dataset.is_none() {
+        return Err(Error::MissingWeights(info.method));
+    }
+    let (dataset, slices, creator) = derivation::prepare(
+        &app_state,
+        dataset.into_inner(),
+        info.target,
+        info.geography_type,
+    )
+    .await?;
+
+    let mut rows = HashSet::new();
+    for slice in &slices {
+        let children = app_state
+            .database
+            .data
+            .with_parents(dataset.id, slice, info.geography_type, info.weight_dataset)
+            .await?;
+        rows.extend(derivation::rows(
+            slice,
+            info.geography_type,
+            aggregation::aggregate(info.method, children),
+        ));
+    }
+
+    let created = derivation::store(
+        &app_state,
+        &creator,
+        rows,
+        derived_dataset::Creator {
+            dataset: 0,
+            derived_from: dataset.id,
+            operation: "aggregation".to_string(),
+            method: info.method.to_string(),
+            weight_dataset: info.weight_dataset,
+            crosswalk: None,
+        },
+        &user,
+    )
+    .await?;
+
+    Ok(HttpResponse::Ok().json(created))
+}
+
+#[get("/dataset/{dataset}/provenance")]
+async fn get_provenance(
+    dataset: web::Path<i32>,
+    app_state: web::Data<AppState<'_>>,
+) -> impl Responder {
+    let derived_dataset = app_state
+        .database
+        .derived_dataset
+        .by_dataset(dataset.into_inner())
+        .await;
+
+    match derived_dataset {
+        Err(e) => {
+            error!("Error getting provenance: {}", e);
+            HttpResponse::InternalServerError().finish()
+        }
+        Ok(None) => HttpResponse::NotFound().finish(),
+        Ok(Some(derived_dataset)) => HttpResponse::Ok().json(derived_dataset),
+    }
+}
@@ -0,0 +1,31 @@
+use super::AppState;
+use crate::controller::auth::Viewer;
+use crate::model::audit::Filter;
+use actix_web::{get, web, HttpResponse, Responder};
+use log::error;
+
+pub fn init_editor(cfg: &mut web::ServiceConfig) {
+    cfg.service(get_audit_log);
+}
+
+#[get("/audit-log")]
+async fn get_audit_log(
+    _: Viewer,
+    filter: web::Query<Filter>,
+    app_state: web::Data<AppState<'_>>,
+) -> impl Responder {
+    if let (Some(from), Some(to)) = (filter.from, filter.to) {
+        if from > to {
+            return HttpResponse::BadRequest().body("from is after to");
+        }
+    }
+    let entries = app_state.database.audit.filter(&filter).await;
+
+    match entries {
+        Err(e) => {
+            error!("Error getting audit log: {}", e);
+            HttpResponse::InternalServerError().finish()
+        }
+        Ok(entries) => HttpResponse::Ok().json(entries),
+    }
+}
@@ -0,0 +1,236 @@
+use super::AppState;
+use crate::model::user::{hash_token, Role, User};
+use actix_web::body::EitherBody;
+use actix_web::dev::{forward_ready, Payload, Service, ServiceRequest, ServiceResponse, Transform};
+use actix_web::http::{header, StatusCode};
+use actix_web::{web, FromRequest, HttpMessage, HttpRequest, HttpResponse};
+use derive_more::Display;
+use futures::future::{ready, LocalBoxFuture, Ready};
+use log::error;
+use serde::Serialize;
+use std::rc::Rc;
+
+/// Paths the editor serves without a bearer token
+const PUBLIC_PATHS: [&str; 1] = ["/login"];
+
+#[derive(Debug, Display, Serialize)]
+#[serde(tag = "name", content = "info")]
+pub enum AuthError {
+    #[display(fmt = "Missing bearer token")]
+    Unauthenticated,
+    #[display(fmt = "Invalid or expired token")]
+    InvalidToken,
+    #[display(fmt = "Invalid username or password")]
+    InvalidCredentials,
+    #[display(fmt = "Needs the {_0} role")]
+    Forbidden(String),
+    Internal(String),
+}
+
+impl std::error::Error for AuthError {}
+
+impl From<sqlx::Error> for AuthError {
+    fn from(error: sqlx::Error) -> Self {
+        AuthError::Internal(error.to_string())
+    }
+}
+
+impl actix_web::error::ResponseError for AuthError {
+    fn status_code(&self) -> StatusCode {
+        match self {
+            AuthError::Forbidden(_) => StatusCode::FORBIDDEN,
+            AuthError::Internal(_) => StatusCode::INTERNAL_SERVER_ERROR,
+            _ => StatusCode::UNAUTHORIZED,
+        }
+    }
+
+    fn error_response(&self) -> HttpResponse {
+        if let AuthError::Internal(e) = self {
+            error!("Error authenticating: {}", e);
+            return HttpResponse::build(self.status_code()).finish();
+        }
+        HttpResponse::build(self.status_code()).json(self)
+    }
+}
+
+/// The token from an `Authorization: Bearer <token>` header
+pub fn bearer_token(request: &HttpRequest) -> Option<&str> {
+    request
+        .headers()
+        .get(header::AUTHORIZATION)?
+        .to_str()
+        .ok()?
+        .strip_prefix("Bearer ")
+        .map(str::trim)
+        .filter(|token| !token.is_empty())
+}
+
+async fn authenticate(request: &ServiceRequest) -> Result<User, AuthError> {
+    let token = bearer_token(request.request()).ok_or(AuthError::Unauthenticated)?;
+    let app_state = request
+        .app_data::<web::Data<AppState<'static>>>()
+        .ok_or_else(|| AuthError::Internal("Missing app state".to_string()))?;
+    app_state
+        .database
+        .user
+        .by_token(&hash_token(token))
+        .await?
+        .ok_or(AuthError::InvalidToken)
+}
+
+/// Rejects editor requests without a valid bearer token, and makes the user available to the
+/// role extractors
+pub struct Authentication;
+
+impl<S, B> Transform<S, ServiceRequest> for Authentication
+where
+    S: Service<ServiceRequest, Response = ServiceResponse<B>, Error = actix_web::Error> + 'static,
+    B: 'static,
+{
+    type Response = ServiceResponse<EitherBody<B>>;
+    type Error = actix_web::Error;
+    type Transform = AuthenticationMiddleware<S>;
+    type InitError = ();
+    type Future = Ready<Result<Self::Transform, Self::InitError>>;
+
+    fn new_transform(&self, service: S) -> Self::Future {
+        ready(Ok(AuthenticationMiddleware {
+            service: Rc::new(service),
+        }))
+    }
+}
+
+pub struct AuthenticationMiddleware<S> {
+    service: Rc<S>,
+}
+
+impl<S, B> Service<ServiceRequest> for AuthenticationMiddleware<S>
+where
+    S: Service<ServiceRequest, Response = ServiceResponse<B>, Error = actix_web::Error> + 'static,
+    B: 'static,
+{
+    type Response = ServiceResponse<EitherBody<B>>;
+    type Error = actix_web::Error;
+    type Future = LocalBoxFuture<'static, Result<Self::Response, Self::Error>>;
+
+    forward_ready!(service);
+
+    fn call(&self, request: ServiceRequest) -> Self::Future {
+        let service = Rc::clone(&self.service);
+        Box::pin(async move {
+            if !PUBLIC_PATHS.contains(&request.path()) {
+                let user = authenticate(&request).await;
+                match user {
+                    Err(e) => return Ok(request.error_response(e).map_into_right_body()),
+                    Ok(user) => {
+                        request.extensions_mut().insert(user);
+                    }
+                }
+            }
+            service
+                .call(request)
+                .await
+                .map(ServiceResponse::map_into_left_body)
+        })
+    }
+}
+
+fn authorize(request: &HttpRequest, role: Role) -> Result<User, AuthError> {
+    let user = request
+        .extensions()
+        .get::<User>()
+        .cloned()
+        .ok_or(AuthError::Unauthenticated)?;
+    if user.can(role) {
+        Ok(user)
+    } else {
+        Err(AuthError::Forbidden(role.to_string()))
+    }
+}
+
+macro_rules! role {
+    ($(#[$meta:meta])* $name:ident, $role:expr) => {
+        $(#[$meta])*
+        pub struct $name(pub User);
+
+        impl FromRequest for $name {
+            type Error = AuthError;
+            type Future = Ready<Result<Self, Self::Error>>;
+
+            fn from_request(request: &HttpRequest, _: &mut Payload) -> Self::Future {
+                ready(authorize(request, $role).map($name))
+            }
+        }
+    };
+}
+
+role!(
+    /// Any signed in editor user
+    Viewer,
+    Role::Viewer
+);
+role!(
+    /// A user who can edit map visualizations, categories and derived data
+    Curator,
+    Role::Curator
+);
+role!(
+    /// A user who can also delete datasets and manage users
+    Admin,
+    Role::Admin
+);
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::controller::{dataset_controller, uploader_controller};
+    use actix_web::{test, App};
+    use assert_matches::assert_matches;
+
+    #[actix_web::test]
+    async fn it_rejects_unauthenticated_editor_requests() {
+        let app = test::init_service(
+            App::new()
+                .wrap(Authentication)
+                .configure(uploader_controller::init_editor)
+                .configure(dataset_controller::init_editor),
+        )
+        .await;
+
+        let requests = [
+            test::TestRequest::post().uri("/upload").to_request(),
+            test::TestRequest::delete().uri("/dataset/1").to_request(),
+            test::TestRequest::delete()
+                .uri("/dataset/1")
+                .insert_header((header::AUTHORIZATION, "Basic YWRtaW46YWRtaW4="))
+                .to_request(),
+        ];
+        for request in requests {
+            let response = test::call_service(&app, request).await;
+            assert_eq!(response.status(), StatusCode::UNAUTHORIZED);
+        }
+    }
+
+    #[test]
+    fn it_checks_roles() {
+        let request = test::TestRequest::default().to_http_request();
+        request.extensions_mut().insert(User {
+            id: 1,
+            username: "viewer".to_string(),
+            role: Role::Viewer.id(),
+        });
+
+        assert!(authorize(&request, Role::Viewer).is_ok());
+        assert_matches!(
+            authorize(&request, Role::Curator),
+            Err(AuthError::Forbidden(_))
+        );
+        assert_matches!(
+            authorize(
+                &test::TestRequest::default().to_http_request(),
+                Role::Viewer
+            ),
+            Err(AuthError::Unauthenticated)
+        );
+    }
+}
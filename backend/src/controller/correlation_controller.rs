@@ -0,0 +1,161 @@
+use super::AppState;
+use crate::controller::format;
+use crate::model::correlation::{self, Slice};
+use crate::model::data::SourceAndDate;
+use crate::model::map_visualization::BIVARIATE_CHOROPLETH;
+use actix_web::{get, http::StatusCode, post, web, HttpRequest, HttpResponse};
+use derive_more::Display;
+use futures::future::{try_join, try_join_all};
+use log::error;
+use serde::{Deserialize, Serialize};
+
+#[derive(Debug, Display, Serialize)]
+#[serde(tag = "name", content = "info")]
+enum Error {
+    #[display(fmt = "At least 2 slices are needed")]
+    TooFewSlices,
+    #[display(fmt = "Slices have different geography types: {_0:?}")]
+    MixedGeographyTypes(Vec<i32>),
+    #[display(fmt = "Map visualization {_0} is not a bivariate choropleth")]
+    NotBivariate(i32),
+    #[display(fmt = "Map visualization {_0} has no data")]
+    NoData(i32),
+    NotFound,
+    Internal(String),
+}
+
+impl std::error::Error for Error {}
+
+impl From<sqlx::Error> for Error {
+    fn from(error: sqlx::Error) -> Self {
+        match error {
+            sqlx::Error::RowNotFound => Error::NotFound,
+            error => Error::Internal(error.to_string()),
+        }
+    }
+}
+
+impl actix_web::error::ResponseError for Error {
+    fn status_code(&self) -> StatusCode {
+        match self {
+            Error::Internal(_) => StatusCode::INTERNAL_SERVER_ERROR,
+            Error::NotFound => StatusCode::NOT_FOUND,
+            _ => StatusCode::BAD_REQUEST,
+        }
+    }
+
+    fn error_response(&self) -> HttpResponse {
+        if let Error::Internal(e) = self {
+            error!("Error correlating data: {}", e);
+            return HttpResponse::build(self.status_code()).finish();
+        }
+        HttpResponse::build(self.status_code()).json(self)
+    }
+}
+
+pub fn init(cfg: &mut web::ServiceConfig) {
+    cfg.service(correlate);
+    cfg.service(get_bivariate);
+}
+
+#[derive(Deserialize)]
+struct CorrelationInfo {
+    slices: Vec<Slice>,
+}
+
+/// Pairs the values of two or more slices of the same geography type by geo id, and correlates
+/// each pair of slices
+#[post("/correlation")]
+async fn correlate(
+    info: web::Json<CorrelationInfo>,
+    app_state: web::Data<AppState<'_>>,
+) -> Result<HttpResponse, Error> {
+    if info.slices.len() < 2 {
+        return Err(Error::TooFewSlices);
+    }
+    let datasets = try_join_all(
+        info.slices
+            .iter()
+            .map(|slice| app_state.database.dataset.by_id(slice.dataset)),
+    )
+    .await?;
+    let mut geography_types: Vec<i32> = datasets
+        .iter()
+        .map(|dataset| dataset.geography_type)
+        .collect();
+    geography_types.sort_unstable();
+    geography_types.dedup();
+    if geography_types.len() > 1 {
+        return Err(Error::MixedGeographyTypes(geography_types));
+    }
+
+    let app_state = &app_state;
+    let values = try_join_all(info.slices.iter().map(|slice| async move {
+        let source_and_date = slice.source_and_date();
+        app_state
+            .database
+            .data
+            .by_dataset(slice.dataset, &source_and_date)
+            .await
+    }))
+    .await?;
+
+    Ok(HttpResponse::Ok().json(correlation::analyze(values)))
+}
+
+/// The bivariate class of each geo id, for the given slice of the map visualization's dataset or
+/// its default one. Both datasets need the same geography type for their geo ids to match.
+#[get("/map-visualization/{id}/bivariate")]
+async fn get_bivariate(
+    request: HttpRequest,
+    id: web::Path<i32>,
+    info: Option<web::Query<SourceAndDate>>,
+    app_state: web::Data<AppState<'_>>,
+) -> Result<HttpResponse, Error> {
+    let map_visualization = app_state
+        .database
+        .map_visualization
+        .get(id.into_inner())
+        .await?;
+    let bivariate = match map_visualization.bivariate() {
+        Some(bivariate) if map_visualization.map_type == BIVARIATE_CHOROPLETH => bivariate,
+        _ => return Err(Error::NotBivariate(map_visualization.id)),
+    };
+    let other = app_state.database.dataset.by_id(bivariate.dataset).await?;
+    if other.geography_type != map_visualization.geography_type {
+        return Err(Error::MixedGeographyTypes(vec![
+            map_visualization.geography_type,
+            other.geography_type,
+        ]));
+    }
+    let slice = match info {
+        Some(info) => info.into_inner(),
+        None => {
+            let slices = app_state
+                .database
+                .source_and_date
+                .by_dataset(map_visualization.dataset)
+                .await?;
+            map_visualization
+                .default_slice(&slices)
+                .ok_or(Error::NoData(map_visualization.id))?
+        }
+    };
+
+    let (x, y) = try_join(
+        app_state
+            .database
+            .data
+            .by_dataset(map_visualization.dataset, &slice),
+        app_state
+            .database
+            .data
+            .by_dataset(bivariate.dataset, &bivariate.slice),
+    )
+    .await?;
+
+    Ok(format::respond(
+        &request,
+        correlation::bivariate_classes(x, y),
+    ))
+}
@@ -0,0 +1,237 @@
+use super::AppState;
+use crate::controller::auth::Curator;
+use crate::controller::derivation::{self, Error, Target};
+use crate::controller::format;
+use crate::model::crosswalk::{self, Crosswalk, Kind, Link, Weight};
+use crate::model::data::SourceAndDate;
+use crate::model::derived_dataset;
+use crate::model::geo_id::GeoId;
+use crate::model::{scenario, statistic};
+use actix_web::{get, post, web, HttpRequest, HttpResponse, Responder};
+use chrono::NaiveDate;
+use log::error;
+use serde::Deserialize;
+use std::collections::HashSet;
+
+pub fn init(cfg: &mut web::ServiceConfig) {
+    cfg.service(get_all);
+    cfg.service(get_projection);
+}
+
+pub fn init_editor(cfg: &mut web::ServiceConfig) {
+    cfg.service(generate);
+    cfg.service(import_links);
+    cfg.service(materialize);
+}
+
+#[derive(Deserialize)]
+struct ProjectionInfo {
+    source: i32,
+    #[serde(default = "scenario::observed")]
+    scenario: i32,
+    #[serde(default = "statistic::mean")]
+    statistic: i32,
+    start_date: NaiveDate,
+    end_date: NaiveDate,
+    crosswalk: i32,
+    weight: Weight,
+    kind: Kind,
+    /// Project from the `to` geography type of the crosswalk to the `from` one
+    #[serde(default)]
+    reverse: bool,
+}
+
+#[derive(Deserialize)]
+struct MaterializeInfo {
+    crosswalk: i32,
+    weight: Weight,
+    kind: Kind,
+    #[serde(default)]
+    reverse: bool,
+    #[serde(flatten)]
+    target: Target,
+}
+
+#[derive(Deserialize)]
+struct GenerateInfo {
+    /// Only link these geo ids of the `from` geography type
+    from_ids: Option<Vec<i64>>,
+    /// Only link these geo ids of the `to` geography type
+    to_ids: Option<Vec<i64>>,
+}
+
+/// The geography type data ends up in after projecting it with a crosswalk
+fn target_geography_type(crosswalk: &Crosswalk, reverse: bool) -> i32 {
+    if reverse {
+        crosswalk.from_geography_type
+    } else {
+        crosswalk.to_geography_type
+    }
+}
+
+#[get("/crosswalk")]
+async fn get_all(app_state: web::Data<AppState<'_>>) -> impl Responder {
+    let crosswalks = app_state.database.crosswalk.all().await;
+
+    match crosswalks {
+        Err(_) => HttpResponse::NotFound().finish(),
+        Ok(crosswalks) => HttpResponse::Ok().json(crosswalks),
+    }
+}
+
+#[get("/data/{dataset}/crosswalk")]
+async fn get_projection(
+    request: HttpRequest,
+    dataset: web::Path<i32>,
+    info: web::Query<ProjectionInfo>,
+    app_state: web::Data<AppState<'_>>,
+) -> Result<HttpResponse, Error> {
+    let overlaps = app_state
+        .database
+        .data
+        .with_crosswalk(
+            dataset.into_inner(),
+            &SourceAndDate {
+                source: info.source,
+                scenario: info.scenario,
+                statistic: info.statistic,
+                start_date: info.start_date,
+                end_date: info.end_date,
+            },
+            info.crosswalk,
+            info.weight,
+            info.kind,
+            info.reverse,
+        )
+        .await?;
+
+    Ok(format::respond(
+        &request,
+        crosswalk::project(info.kind, overlaps),
+    ))
+}
+
+/// Derives area shares for a crosswalk from the boundaries of its geography types
+#[post("/crosswalk/{id}/generate")]
+async fn generate(
+    Curator(user): Curator,
+    id: web::Path<i32>,
+    info: web::Json<GenerateInfo>,
+    app_state: web::Data<AppState<'_>>,
+) -> impl Responder {
+    let result = app_state
+        .database
+        .crosswalk
+        .generate(
+            id.into_inner(),
+            info.from_ids.as_deref(),
+            info.to_ids.as_deref(),
+            &user,
+        )
+        .await;
+
+    match result {
+        Err(e) => {
+            error!("Error generating crosswalk: {}", e);
+            HttpResponse::InternalServerError().finish()
+        }
+        Ok(result) => HttpResponse::Ok().body(format!("linked {} geo ids", result.rows_affected())),
+    }
+}
+
+/// Imports links with area or population shares, like population weights from the census
+#[post("/crosswalk/{id}/link")]
+async fn import_links(
+    Curator(user): Curator,
+    id: web::Path<i32>,
+    links: web::Json<Vec<Link>>,
+    app_state: web::Data<AppState<'_>>,
+) -> Result<HttpResponse, Error> {
+    let crosswalk = app_state.database.crosswalk.by_id(id.into_inner()).await?;
+    let geo_ids: HashSet<GeoId> = links
+        .iter()
+        .flat_map(|link| {
+            [
+                GeoId {
+                    id: link.from_id,
+                    geography_type: crosswalk.from_geography_type,
+                },
+                GeoId {
+                    id: link.to_id,
+                    geography_type: crosswalk.to_geography_type,
+                },
+            ]
+        })
+        .collect();
+    let invalid_ids = app_state.database.geo_id.get_invalid_ids(&geo_ids).await?;
+    if !invalid_ids.is_empty() {
+        return Err(Error::InvalidGeoIds(invalid_ids));
+    }
+
+    let result = app_state
+        .database
+        .crosswalk
+        .upsert_links(crosswalk.id, &links, &user)
+        .await?;
+    Ok(HttpResponse::Ok().body(format!("linked {} geo ids", result.rows_affected())))
+}
+
+/// Projects every slice of a dataset with a crosswalk and stores the result as a new dataset,
+/// recording the crosswalk and method
+#[post("/dataset/{dataset}/crosswalk")]
+async fn materialize(
+    Curator(user): Curator,
+    dataset: web::Path<i32>,
+    info: web::Json<MaterializeInfo>,
+    app_state: web::Data<AppState<'_>>,
+) -> Result<HttpResponse, Error> {
+    let info = info.into_inner();
+    let crosswalk = app_state.database.crosswalk.by_id(info.crosswalk).await?;
+    let geography_type = target_geography_type(&crosswalk, info.reverse);
+    let (dataset, slices, creator) = derivation::prepare(
+        &app_state,
+        dataset.into_inner(),
+        info.target,
+        geography_type,
+    )
+    .await?;
+
+    let mut rows = HashSet::new();
+    for slice in &slices {
+        let overlaps = app_state
+            .database
+            .data
+            .with_crosswalk(
+                dataset.id,
+                slice,
+                crosswalk.id,
+                info.weight,
+                info.kind,
+                info.reverse,
+            )
+            .await?;
+        rows.extend(derivation::rows(
+            slice,
+            geography_type,
+            crosswalk::project(info.kind, overlaps),
+        ));
+    }
+
+    let created = derivation::store(
+        &app_state,
+        &creator,
+        rows,
+        derived_dataset::Creator {
+            dataset: 0,
+            derived_from: dataset.id,
+            operation: "crosswalk".to_string(),
+            method: crosswalk::method(info.weight, info.kind),
+            weight_dataset: None,
+            crosswalk: Some(crosswalk.id),
+        },
+        &user,
+    )
+    .await?;
+
+    Ok(HttpResponse::Ok().json(created))
+}
@@ -0,0 +1,303 @@
+use super::AppState;
+use crate::controller::auth::Curator;
+use crate::controller::derivation::Error;
+use crate::controller::format;
+use crate::model::aggregation::{self, Method};
+use crate::model::custom_region::{self, Creator, CustomRegion};
+use crate::model::data::{self, SourceAndDate};
+use crate::model::geo_id::GeoId;
+use crate::model::{scenario, statistic};
+use actix_web::{delete, get, patch, post, web, HttpRequest, HttpResponse, Responder};
+use chrono::NaiveDate;
+use log::error;
+use serde::Deserialize;
+use std::collections::HashSet;
+
+pub fn init(cfg: &mut web::ServiceConfig) {
+    cfg.service(get_all);
+    cfg.service(get);
+    cfg.service(get_aggregate);
+    cfg.service(get_comparison);
+    cfg.service(get_percentiles);
+}
+
+pub fn init_editor(cfg: &mut web::ServiceConfig) {
+    cfg.service(create);
+    cfg.service(update);
+    cfg.service(delete);
+}
+
+#[derive(Deserialize)]
+struct AggregateInfo {
+    dataset: i32,
+    source: i32,
+    #[serde(default = "scenario::observed")]
+    scenario: i32,
+    #[serde(default = "statistic::mean")]
+    statistic: i32,
+    start_date: NaiveDate,
+    end_date: NaiveDate,
+    method: Method,
+    weight_dataset: Option<i32>,
+}
+
+#[derive(Deserialize)]
+struct CompareInfo {
+    dataset: i32,
+    source: i32,
+    #[serde(default = "scenario::observed")]
+    scenario: i32,
+    #[serde(default = "statistic::mean")]
+    statistic: i32,
+    start_date: NaiveDate,
+    end_date: NaiveDate,
+    /// Rank lower values higher, like a map visualization's `invert_normalized`
+    #[serde(default)]
+    invert: bool,
+}
+
+#[derive(Deserialize)]
+struct PercentileInfo {
+    category: i32,
+    #[serde(default = "scenario::observed")]
+    scenario: i32,
+    #[serde(default = "statistic::mean")]
+    statistic: i32,
+}
+
+async fn validate_members(
+    app_state: &web::Data<AppState<'_>>,
+    geography_type: i32,
+    members: &[i64],
+) -> Result<(), Error> {
+    let geo_ids: HashSet<GeoId> = members
+        .iter()
+        .map(|&id| GeoId { id, geography_type })
+        .collect();
+    let invalid_ids = app_state.database.geo_id.get_invalid_ids(&geo_ids).await?;
+    if invalid_ids.is_empty() {
+        Ok(())
+    } else {
+        Err(Error::InvalidGeoIds(invalid_ids))
+    }
+}
+
+async fn region(
+    app_state: &web::Data<AppState<'_>>,
+    id: i32,
+) -> Result<Option<CustomRegion>, sqlx::Error> {
+    app_state.database.custom_region.by_id(id).await
+}
+
+#[get("/custom-region")]
+async fn get_all(app_state: web::Data<AppState<'_>>) -> impl Responder {
+    let regions = app_state.database.custom_region.all().await;
+    match regions {
+        Err(e) => {
+            error!("Error getting custom regions: {}", e);
+            HttpResponse::InternalServerError().finish()
+        }
+        Ok(regions) => HttpResponse::Ok().json(regions),
+    }
+}
+
+#[get("/custom-region/{id}")]
+async fn get(id: web::Path<i32>, app_state: web::Data<AppState<'_>>) -> impl Responder {
+    match region(&app_state, id.into_inner()).await {
+        Err(e) => {
+            error!("Error getting custom region: {}", e);
+            HttpResponse::InternalServerError().finish()
+        }
+        Ok(None) => HttpResponse::NotFound().finish(),
+        Ok(Some(region)) => HttpResponse::Ok().json(region),
+    }
+}
+
+/// A dataset's value for the whole region, combined like an aggregation to a parent geo id
+#[get("/custom-region/{id}/aggregate")]
+async fn get_aggregate(
+    request: HttpRequest,
+    id: web::Path<i32>,
+    info: web::Query<AggregateInfo>,
+    app_state: web::Data<AppState<'_>>,
+) -> Result<HttpResponse, Error> {
+    if info.method.needs_weights() && info.weight_dataset.is_none() {
+        return Err(Error::MissingWeights(info.method));
+    }
+    let children = app_state
+        .database
+        .data
+        .in_region(
+            info.dataset,
+            &SourceAndDate {
+                source: info.source,
+                scenario: info.scenario,
+                statistic: info.statistic,
+                start_date: info.start_date,
+                end_date: info.end_date,
+            },
+            id.into_inner(),
+            info.weight_dataset,
+        )
+        .await?;
+
+    Ok(format::respond(
+        &request,
+        aggregation::aggregate(info.method, children),
+    ))
+}
+
+/// The distribution of a slice within the region next to its distribution for every geo id
+#[get("/custom-region/{id}/compare")]
+async fn get_comparison(
+    id: web::Path<i32>,
+    info: web::Query<CompareInfo>,
+    app_state: web::Data<AppState<'_>>,
+) -> impl Responder {
+    let region = match region(&app_state, id.into_inner()).await {
+        Err(e) => {
+            error!("Error getting custom region: {}", e);
+            return HttpResponse::InternalServerError().finish();
+        }
+        Ok(None) => return HttpResponse::NotFound().finish(),
+        Ok(Some(region)) => region,
+    };
+    let slice = app_state
+        .database
+        .data
+        .by_dataset(
+            info.dataset,
+            &SourceAndDate {
+                source: info.source,
+                scenario: info.scenario,
+                statistic: info.statistic,
+                start_date: info.start_date,
+                end_date: info.end_date,
+            },
+        )
+        .await;
+    match slice {
+        Err(e) => {
+            error!("Error comparing custom region: {}", e);
+            HttpResponse::InternalServerError().finish()
+        }
+        Ok(slice) => {
+            let members: HashSet<i64> = region.members.into_iter().collect();
+            HttpResponse::Ok().json(custom_region::compare(&slice, &members, info.invert))
+        }
+    }
+}
+
+/// A report card for the region: its mean for each slice in a category, ranked among all geo
+/// ids of its geography type
+#[get("/custom-region/{id}/percentile")]
+async fn get_percentiles(
+    request: HttpRequest,
+    id: web::Path<i32>,
+    info: web::Query<PercentileInfo>,
+    app_state: web::Data<AppState<'_>>,
+) -> Result<HttpResponse, Error> {
+    let region = match region(&app_state, id.into_inner()).await? {
+        None => return Ok(HttpResponse::NotFound().finish()),
+        Some(region) => region,
+    };
+    let members: HashSet<i64> = region.members.into_iter().collect();
+    let entries = app_state
+        .database
+        .data
+        .report_card_entries(
+            info.category,
+            region.geography_type,
+            info.scenario,
+            info.statistic,
+        )
+        .await?;
+
+    let mut percentiles = Vec::new();
+    for entry in entries {
+        let slice = app_state
+            .database
+            .data
+            .by_dataset(
+                entry.dataset,
+                &SourceAndDate {
+                    source: entry.source,
+                    scenario: info.scenario,
+                    statistic: info.statistic,
+                    start_date: entry.start_date,
+                    end_date: entry.end_date,
+                },
+            )
+            .await?;
+        let comparison = custom_region::compare(&slice, &members, entry.invert_normalized);
+        percentiles.push(data::Percentile {
+            dataset: entry.dataset,
+            dataset_name: entry.dataset_name,
+            source: entry.source,
+            start_date: entry.start_date,
+            end_date: entry.end_date,
+            percent_rank: comparison.percentile,
+            value: comparison.region.map(|region| region.mean),
+            units: entry.units,
+            formatter_type: entry.formatter_type,
+            decimals: entry.decimals,
+        });
+    }
+
+    Ok(format::respond(&request, percentiles))
+}
+
+#[post("/custom-region")]
+async fn create(
+    Curator(user): Curator,
+    region: web::Json<Creator>,
+    app_state: web::Data<AppState<'_>>,
+) -> Result<HttpResponse, Error> {
+    validate_members(&app_state, region.geography_type, &region.members).await?;
+    let id = app_state
+        .database
+        .custom_region
+        .create(&region, &user)
+        .await?;
+    let created = app_state.database.custom_region.by_id(id).await?;
+    Ok(HttpResponse::Ok().json(created))
+}
+
+#[patch("/custom-region")]
+async fn update(
+    Curator(user): Curator,
+    region: web::Json<CustomRegion>,
+    app_state: web::Data<AppState<'_>>,
+) -> Result<HttpResponse, Error> {
+    let existing = match app_state.database.custom_region.by_id(region.id).await? {
+        None => return Ok(HttpResponse::NotFound().finish()),
+        Some(existing) => existing,
+    };
+    validate_members(&app_state, existing.geography_type, &region.members).await?;
+    app_state
+        .database
+        .custom_region
+        .update(&region, &user)
+        .await?;
+    Ok(HttpResponse::Ok().finish())
+}
+
+#[delete("/custom-region/{id}")]
+async fn delete(
+    Curator(user): Curator,
+    id: web::Path<i32>,
+    app_state: web::Data<AppState<'_>>,
+) -> impl Responder {
+    let result = app_state
+        .database
+        .custom_region
+        .delete(id.into_inner(), &user)
+        .await;
+    match result {
+        Err(e) => {
+            error!("Error deleting custom region: {}", e);
+            HttpResponse::InternalServerError().finish()
+        }
+        Ok(_) => HttpResponse::Ok().finish(),
+    }
+}
@@ -1,7 +1,12 @@
 use super::AppState;
+use crate::controller::auth::Curator;
 use crate::{
     controller::map_visualization_controller::MapVisualizationOptions,
+    controller::order::Error,
+    controller::trash_controller,
     model::data_category::{Creator, DataCategory},
+    model::deletion::Options,
+    model::trash::Kind,
 };
 use actix_web::{delete, get, patch, post, web, HttpResponse, Responder};
 use log::error;
@@ -14,6 +19,8 @@ pub fn init_editor(cfg: &mut web::ServiceConfig) {
     cfg.service(create);
     cfg.service(delete);
     cfg.service(update);
+    cfg.service(reorder);
+    cfg.service(reorder_map_visualizations);
 }
 
 #[get("/data-category")]
@@ -41,6 +48,7 @@ async fn get_all(
 
 #[post("/data-category")]
 async fn create(
+    Curator(user): Curator,
     app_state: web::Data<AppState<'_>>,
     new_data_category: web::Json<Creator>,
 ) -> impl Responder {
@@ -56,7 +64,7 @@ async fn create(
     let result = app_state
         .database
         .data_category
-        .create(&data_category)
+        .create(&data_category, &user)
         .await;
 
     match result {
@@ -68,32 +76,59 @@ async fn create(
     }
 }
 
+/// Puts a category in the trash. See [`trash_controller::delete`] for deleting it for good.
 #[delete("/data-category/{id}")]
-async fn delete(app_state: web::Data<AppState<'_>>, id: web::Path<i32>) -> impl Responder {
-    let result = app_state
-        .database
-        .data_category
-        .delete(id.into_inner())
-        .await;
-
-    match result {
-        Err(e) => {
-            error!("Error deleting data category: {}", e);
-            HttpResponse::InternalServerError().finish()
-        }
-        Ok(_) => HttpResponse::Ok().finish(),
-    }
+async fn delete(
+    Curator(user): Curator,
+    app_state: web::Data<AppState<'_>>,
+    id: web::Path<i32>,
+    options: web::Query<Options>,
+) -> Result<HttpResponse, trash_controller::Error> {
+    trash_controller::delete(&app_state, &user, Kind::DataCategory, *id, &options).await
 }
 
 #[patch("/data-category")]
 async fn update(
+    Curator(user): Curator,
     app_state: web::Data<AppState<'_>>,
     json: web::Json<DataCategory>,
 ) -> impl Responder {
-    let result = app_state.database.data_category.update(&json).await;
+    let result = app_state.database.data_category.update(&json, &user).await;
 
     match result {
         Err(_) => HttpResponse::InternalServerError().finish(),
         Ok(_) => HttpResponse::Ok().finish(),
     }
 }
+
+/// Renumbers all the categories in the order of their ids
+#[post("/data-category/reorder")]
+async fn reorder(
+    Curator(user): Curator,
+    app_state: web::Data<AppState<'_>>,
+    ids: web::Json<Vec<i32>>,
+) -> Result<HttpResponse, Error> {
+    app_state
+        .database
+        .data_category
+        .reorder(&ids, &user)
+        .await??;
+    Ok(HttpResponse::Ok().finish())
+}
+
+/// Renumbers all the map visualizations of a category in the order of their ids
+#[post("/data-category/{id}/reorder")]
+async fn reorder_map_visualizations(
+    Curator(user): Curator,
+    app_state: web::Data<AppState<'_>>,
+    id: web::Path<i32>,
+    ids: web::Json<Vec<i32>>,
+) -> Result<HttpResponse, Error> {
+    let id = id.into_inner();
+    app_state
+        .database
+        .map_visualization_collection
+        .reorder(id, &ids, &user)
+        .await??;
+    Ok(HttpResponse::Ok().finish())
+}
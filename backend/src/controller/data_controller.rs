@@ -1,16 +1,22 @@
 use super::AppState;
-use crate::controller::csv_converter;
-use crate::model::data::SourceAndDate;
+use crate::controller::auth::Admin;
+use crate::controller::format;
+use crate::model::data::{Simple, SourceAndDate};
+use crate::model::delta::{self, Delta, Operation};
+use crate::model::{scenario, statistic};
 use actix_web::delete;
-use actix_web::{get, web, HttpResponse, Responder};
+use actix_web::{get, web, HttpRequest, HttpResponse, Responder};
 use chrono::NaiveDate;
+use futures::future::try_join;
 use serde::Deserialize;
+use std::num::ParseIntError;
 
 pub fn init(cfg: &mut web::ServiceConfig) {
     cfg.service(get_by_dataset);
     cfg.service(get_percentiles);
     cfg.service(get_state_percentiles);
     cfg.service(get_by_map_visualization);
+    cfg.service(get_bulk);
 }
 
 pub fn init_editor(cfg: &mut web::ServiceConfig) {
@@ -20,19 +26,34 @@ pub fn init_editor(cfg: &mut web::ServiceConfig) {
 #[derive(Deserialize, Debug)]
 struct Info {
     source: i32,
+    #[serde(default = "scenario::observed")]
+    scenario: i32,
+    #[serde(default = "statistic::mean")]
+    statistic: i32,
     start_date: NaiveDate,
     end_date: NaiveDate,
 }
 
+#[derive(Deserialize)]
+struct BulkInfo {
+    /// Comma separated dataset ids
+    dataset: String,
+}
+
 #[derive(Deserialize)]
 pub struct PercentileInfo {
     pub category: i32,
     pub geo_id: i64,
     pub geography_type: i32,
+    #[serde(default = "scenario::observed")]
+    pub scenario: i32,
+    #[serde(default = "statistic::mean")]
+    pub statistic: i32,
 }
 
 #[get("/data/{dataset}")]
 async fn get_by_dataset(
+    request: HttpRequest,
     dataset: web::Path<i32>,
     info: web::Query<Info>,
     app_state: web::Data<AppState<'_>>,
@@ -44,6 +65,8 @@ async fn get_by_dataset(
             dataset.into_inner(),
             &SourceAndDate {
                 source: info.source,
+                scenario: info.scenario,
+                statistic: info.statistic,
                 start_date: info.start_date,
                 end_date: info.end_date,
             },
@@ -51,62 +74,115 @@ async fn get_by_dataset(
         .await;
 
     match data {
-        Ok(data) => match csv_converter::convert(data) {
-            Ok(csv) => HttpResponse::Ok().content_type("text/csv").body(csv),
-            Err(_) => HttpResponse::NotFound().finish(),
-        },
+        Ok(data) => format::respond(&request, data),
         Err(_) => HttpResponse::NotFound().finish(),
     }
 }
 
 #[get("/map-visualization/{map_visualization}/data")]
 async fn get_by_map_visualization(
+    request: HttpRequest,
     map_visualization: web::Path<i32>,
-    info: web::Query<Info>,
+    info: Option<web::Query<Info>>,
+    delta_info: web::Query<delta::Info>,
     app_state: web::Data<AppState<'_>>,
 ) -> impl Responder {
-    let result = app_state
+    let map_visualization = map_visualization.into_inner();
+    let delta = match app_state
         .database
-        .data
-        .by_map_visualization(
-            map_visualization.into_inner(),
-            &SourceAndDate {
-                source: info.source,
-                start_date: info.start_date,
-                end_date: info.end_date,
-            },
-        )
-        .await;
-    match result {
-        Ok(result) => {
-            let csv = csv_converter::convert(result);
-            match csv {
-                Ok(csv) => HttpResponse::Ok().content_type("text/csv").body(csv),
-                Err(_) => HttpResponse::NotFound().finish(),
-            }
+        .map_visualization
+        .get(map_visualization)
+        .await
+    {
+        Err(_) => return HttpResponse::NotFound().finish(),
+        Ok(map_visualization) => map_visualization.delta().map(|delta| {
+            (
+                map_visualization.dataset,
+                delta.with_statistic(delta_info.statistic),
+            )
+        }),
+    };
+
+    let result = match (delta, info) {
+        (Some((dataset, delta)), _) => delta_data(&app_state, dataset, delta).await,
+        (None, Some(info)) => {
+            app_state
+                .database
+                .data
+                .by_map_visualization(
+                    map_visualization,
+                    &SourceAndDate {
+                        source: info.source,
+                        scenario: info.scenario,
+                        statistic: info.statistic,
+                        start_date: info.start_date,
+                        end_date: info.end_date,
+                    },
+                )
+                .await
         }
+        (None, None) => return HttpResponse::BadRequest().finish(),
+    };
+    match result {
+        Ok(result) => format::respond(&request, result),
         Err(_) => HttpResponse::NotFound().finish(),
     }
 }
 
+/// The change between the two slices of a delta map visualization
+pub async fn delta_data(
+    app_state: &web::Data<AppState<'_>>,
+    dataset: i32,
+    delta: Delta,
+) -> Result<Vec<Simple>, sqlx::Error> {
+    let operation = Operation::from_id(delta.operation).ok_or_else(|| {
+        sqlx::Error::Decode(format!("Unknown delta operation {}", delta.operation).into())
+    })?;
+    let (from, to) = try_join(
+        app_state.database.data.by_dataset(dataset, &delta.from),
+        app_state.database.data.by_dataset(dataset, &delta.to),
+    )
+    .await?;
+    Ok(delta::compute(operation, from, to))
+}
+
+/// Parses comma separated ids like "1,2,3"
+pub fn parse_ids(ids: &str) -> Result<Vec<i32>, ParseIntError> {
+    ids.split(',').map(|id| id.trim().parse::<i32>()).collect()
+}
+
+#[get("/bulk-data")]
+async fn get_bulk(
+    request: HttpRequest,
+    info: web::Query<BulkInfo>,
+    app_state: web::Data<AppState<'_>>,
+) -> impl Responder {
+    match parse_ids(&info.dataset) {
+        Ok(datasets) => {
+            let rows = app_state.database.data.stream_by_datasets(datasets);
+            format::respond_stream(&request, rows).await
+        }
+        Err(_) => HttpResponse::BadRequest().finish(),
+    }
+}
+
 #[get("/percentile")]
 async fn get_percentiles(
+    request: HttpRequest,
     info: web::Query<PercentileInfo>,
     app_state: web::Data<AppState<'_>>,
 ) -> impl Responder {
     let data = app_state.database.data.percentile(info.into_inner()).await;
 
     match data {
-        Ok(data) => match csv_converter::convert(data) {
-            Ok(csv) => HttpResponse::Ok().content_type("text/csv").body(csv),
-            Err(_) => HttpResponse::NotFound().finish(),
-        },
+        Ok(data) => format::respond(&request, data),
         Err(_) => HttpResponse::NotFound().finish(),
     }
 }
 
 #[get("/state_percentile")]
 async fn get_state_percentiles(
+    request: HttpRequest,
     info: web::Query<PercentileInfo>,
     app_state: web::Data<AppState<'_>>,
 ) -> impl Responder {
@@ -117,20 +193,22 @@ async fn get_state_percentiles(
         .await;
 
     match data {
-        Ok(data) => match csv_converter::convert(data) {
-            Ok(csv) => HttpResponse::Ok().content_type("text/csv").body(csv),
-            Err(_) => HttpResponse::NotFound().finish(),
-        },
+        Ok(data) => format::respond(&request, data),
         Err(_) => HttpResponse::NotFound().finish(),
     }
 }
 
 #[delete("/dataset/{dataset}/data")]
-async fn delete(app_state: web::Data<AppState<'_>>, dataset: web::Path<i32>) -> impl Responder {
+async fn delete(
+    Admin(user): Admin,
+    app_state: web::Data<AppState<'_>>,
+    dataset: web::Path<i32>,
+) -> impl Responder {
+    let dataset = dataset.into_inner();
     let result = app_state
         .database
         .data
-        .delete_by_dataset(dataset.into_inner())
+        .delete_by_dataset(dataset, &user)
         .await;
 
     match result {
@@ -1,4 +1,8 @@
+use crate::controller::auth::{Admin, Curator};
+use crate::controller::trash_controller;
 use crate::model::data_source::Diff;
+use crate::model::deletion::Options;
+use crate::model::trash::Kind;
 
 use super::AppState;
 use actix_web::{delete, get, patch, web, HttpResponse, Responder};
@@ -14,14 +18,19 @@ pub fn init_editor(cfg: &mut web::ServiceConfig) {
 
 #[patch("/data-source")]
 async fn update(
+    Curator(user): Curator,
     app_state: web::Data<AppState<'_>>,
     data_source: web::Json<Diff>,
 ) -> impl Responder {
-    let result = app_state.database.data_source.update(&data_source).await;
+    let result = app_state
+        .database
+        .data_source
+        .update(&data_source, &user)
+        .await;
 
     match result {
-        Err(_) => HttpResponse::NotFound().finish(),
-        Ok(_) => HttpResponse::Ok().finish(),
+        Err(_) | Ok(None) => HttpResponse::NotFound().finish(),
+        Ok(Some(_)) => HttpResponse::Ok().finish(),
     }
 }
 
@@ -35,20 +44,13 @@ async fn get_all(app_state: web::Data<AppState<'_>>) -> impl Responder {
     }
 }
 
+/// Puts a data source in the trash. See [`trash_controller::delete`] for deleting it for good.
 #[delete("/data-source/{id}")]
-async fn delete(id: web::Path<i32>, app_state: web::Data<AppState<'_>>) -> impl Responder {
-    let id = id.into_inner();
-    let result = app_state.database.data.delete_by_source(id).await;
-
-    match result {
-        Err(_) => HttpResponse::NotFound().finish(),
-        Ok(_) => {
-            let result = app_state.database.data_source.delete(id).await;
-
-            match result {
-                Err(_) => HttpResponse::NotFound().finish(),
-                Ok(_) => HttpResponse::Ok().finish(),
-            }
-        }
-    }
+async fn delete(
+    Admin(user): Admin,
+    id: web::Path<i32>,
+    options: web::Query<Options>,
+    app_state: web::Data<AppState<'_>>,
+) -> Result<HttpResponse, trash_controller::Error> {
+    trash_controller::delete(&app_state, &user, Kind::DataSource, *id, &options).await
 }
@@ -1,8 +1,10 @@
 use super::AppState;
+use crate::controller::auth::{Admin, Curator};
+use crate::controller::trash_controller;
 use crate::model::dataset::Diff;
+use crate::model::deletion::Options;
+use crate::model::trash::Kind;
 use actix_web::{delete, get, patch, web, HttpResponse, Responder};
-use derive_more::Display;
-use log::error;
 
 pub fn init(cfg: &mut web::ServiceConfig) {
     cfg.service(get_all);
@@ -15,12 +17,16 @@ pub fn init_editor(cfg: &mut web::ServiceConfig) {
 }
 
 #[patch("/dataset")]
-async fn update(app_state: web::Data<AppState<'_>>, dataset: web::Json<Diff>) -> impl Responder {
-    let result = app_state.database.dataset.update(&dataset).await;
+async fn update(
+    Curator(user): Curator,
+    app_state: web::Data<AppState<'_>>,
+    dataset: web::Json<Diff>,
+) -> impl Responder {
+    let result = app_state.database.dataset.update(&dataset, &user).await;
 
     match result {
-        Err(_) => HttpResponse::NotFound().finish(),
-        Ok(_) => HttpResponse::Ok().finish(),
+        Err(_) | Ok(None) => HttpResponse::NotFound().finish(),
+        Ok(Some(_)) => HttpResponse::Ok().finish(),
     }
 }
 
@@ -44,37 +50,13 @@ async fn get_all(app_state: web::Data<AppState<'_>>) -> impl Responder {
     }
 }
 
+/// Puts a dataset in the trash. See [`trash_controller::delete`] for deleting it for good.
 #[delete("/dataset/{id}")]
-async fn delete(id: web::Path<i32>, app_state: web::Data<AppState<'_>>) -> Result<String, Error> {
-    let id = id.into_inner();
-    app_state
-        .database
-        .map_visualization_collection
-        .delete_by_dataset(id)
-        .await
-        .map_err(Error)?;
-    app_state
-        .database
-        .map_visualization
-        .delete_by_dataset(id)
-        .await
-        .map_err(Error)?;
-    app_state
-        .database
-        .data
-        .delete_by_dataset(id)
-        .await
-        .map_err(Error)?;
-    app_state.database.dataset.delete(id).await.map_err(Error)?;
-    Ok("deleted".to_string())
-}
-
-#[derive(Debug, Display)]
-struct Error(sqlx::Error);
-
-impl actix_web::error::ResponseError for Error {
-    fn error_response(&self) -> HttpResponse {
-        error!("{self}");
-        HttpResponse::build(self.status_code()).finish()
-    }
+async fn delete(
+    Admin(user): Admin,
+    id: web::Path<i32>,
+    options: web::Query<Options>,
+    app_state: web::Data<AppState<'_>>,
+) -> Result<HttpResponse, trash_controller::Error> {
+    trash_controller::delete(&app_state, &user, Kind::Dataset, *id, &options).await
 }
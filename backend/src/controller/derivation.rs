@@ -0,0 +1,140 @@
+use super::AppState;
+use crate::model::aggregation::Method;
+use crate::model::data::{self, Simple, SourceAndDate};
+use crate::model::dataset::{self, Dataset};
+use crate::model::derived_dataset;
+use crate::model::geo_id::GeoId;
+use crate::model::user::User;
+use actix_web::{http::StatusCode, web, HttpResponse};
+use derive_more::Display;
+use log::error;
+use serde::{Deserialize, Serialize};
+use std::collections::HashSet;
+use str_slug::slug;
+
+/// Errors deriving data from a dataset, shared by aggregations and crosswalk projections
+#[derive(Debug, Display, Serialize)]
+#[serde(tag = "name", content = "info")]
+pub enum Error {
+    #[display(fmt = "Method {_0} needs a weight dataset")]
+    MissingWeights(Method),
+    #[display(fmt = "Dataset {_0} has no data to derive from")]
+    NoData(i32),
+    #[display(fmt = "Duplicate datasets: {_0:#?}")]
+    DuplicateDatasets(Vec<Dataset>),
+    #[display(fmt = "Invalid geo ids: {_0:#?}")]
+    InvalidGeoIds(Vec<GeoId>),
+    Internal(String),
+}
+
+impl std::error::Error for Error {}
+
+impl From<sqlx::Error> for Error {
+    fn from(error: sqlx::Error) -> Self {
+        Error::Internal(error.to_string())
+    }
+}
+
+impl actix_web::error::ResponseError for Error {
+    fn status_code(&self) -> StatusCode {
+        match self {
+            Error::Internal(_) => StatusCode::INTERNAL_SERVER_ERROR,
+            _ => StatusCode::BAD_REQUEST,
+        }
+    }
+
+    fn error_response(&self) -> HttpResponse {
+        if let Error::Internal(e) = self {
+            error!("Error deriving data: {}", e);
+            return HttpResponse::build(self.status_code()).finish();
+        }
+        HttpResponse::build(self.status_code()).json(self)
+    }
+}
+
+/// The dataset to store derived data in
+#[derive(Deserialize)]
+pub struct Target {
+    pub name: String,
+    /// Defaults to the original dataset's description
+    pub description: Option<String>,
+    /// Defaults to the original dataset's units
+    pub units: Option<String>,
+}
+
+/// The original dataset and its slices, and the new dataset to derive from them
+pub async fn prepare(
+    app_state: &web::Data<AppState<'_>>,
+    dataset: i32,
+    target: Target,
+    geography_type: i32,
+) -> Result<(Dataset, Vec<SourceAndDate>, dataset::Creator), Error> {
+    let dataset = app_state.database.dataset.by_id(dataset).await?;
+    let slices = app_state
+        .database
+        .source_and_date
+        .by_dataset(dataset.id)
+        .await?;
+    if slices.is_empty() {
+        return Err(Error::NoData(dataset.id));
+    }
+
+    let creator = dataset::Creator {
+        column: String::new(),
+        short_name: slug(&target.name),
+        name: target.name,
+        units: target.units.unwrap_or_else(|| dataset.units.clone()),
+        description: target
+            .description
+            .unwrap_or_else(|| dataset.description.clone()),
+        geography_type,
+    };
+    let duplicates = app_state
+        .database
+        .dataset
+        .find_duplicates(std::slice::from_ref(&creator))
+        .await?;
+    if !duplicates.is_empty() {
+        return Err(Error::DuplicateDatasets(duplicates));
+    }
+    Ok((dataset, slices, creator))
+}
+
+/// Derived values for a slice, as rows of the new dataset
+pub fn rows<'a>(
+    slice: &'a SourceAndDate,
+    geography_type: i32,
+    values: Vec<Simple>,
+) -> impl Iterator<Item = data::Creator> + 'a {
+    values.into_iter().map(move |row| data::Creator {
+        id: row.id,
+        geography_type,
+        source: slice.source,
+        scenario: slice.scenario,
+        statistic: slice.statistic,
+        // set once the dataset is created
+        dataset: 0,
+        start_date: slice.start_date,
+        end_date: slice.end_date,
+        value: row.value,
+    })
+}
+
+/// Creates the new dataset with the derived rows in one transaction, recording how it was derived
+pub async fn store(
+    app_state: &web::Data<AppState<'_>>,
+    creator: &dataset::Creator,
+    rows: HashSet<data::Creator>,
+    derivation: derived_dataset::Creator,
+    user: &User,
+) -> Result<Dataset, Error> {
+    if rows.is_empty() {
+        return Err(Error::NoData(derivation.derived_from));
+    }
+    let created = app_state
+        .database
+        .derived_dataset
+        .store(creator, rows, derivation, user)
+        .await?;
+    Ok(created)
+}
@@ -0,0 +1,223 @@
+use super::AppState;
+use crate::controller::{geojson_converter, geopackage_converter};
+use crate::model::data::SourceAndDate;
+use crate::model::delta::{self, Delta};
+use crate::model::export::Metadata;
+use actix_web::http::header::{ContentDisposition, DispositionParam, DispositionType};
+use actix_web::{get, web, HttpResponse, Responder};
+use futures::future::try_join;
+use log::error;
+
+pub fn init(cfg: &mut web::ServiceConfig) {
+    cfg.service(get_geojson);
+    cfg.service(get_geopackage);
+    cfg.service(get_map_visualization_geojson);
+    cfg.service(get_map_visualization_geopackage);
+}
+
+async fn metadata(
+    app_state: &web::Data<AppState<'_>>,
+    dataset: i32,
+    source_and_date: &SourceAndDate,
+) -> Result<Metadata, sqlx::Error> {
+    let (dataset, data_source) = try_join(
+        app_state.database.dataset.by_id(dataset),
+        app_state.database.data_source.by_id(source_and_date.source),
+    )
+    .await?;
+    Ok(Metadata {
+        dataset: dataset.name,
+        short_name: dataset.short_name,
+        description: dataset.description,
+        units: dataset.units,
+        source: data_source.name,
+        source_link: data_source.link,
+        start_date: source_and_date.start_date,
+        end_date: source_and_date.end_date,
+    })
+}
+
+/// Short names come from uploads, so the header quotes and escapes them
+fn attachment(file_name: String) -> ContentDisposition {
+    ContentDisposition {
+        disposition: DispositionType::Attachment,
+        parameters: vec![DispositionParam::Filename(file_name)],
+    }
+}
+
+/// The map visualization's dataset, and its delta if it is one
+async fn map_visualization_export(
+    app_state: &web::Data<AppState<'_>>,
+    map_visualization: i32,
+    statistic: i32,
+) -> Result<(i32, Option<Delta>), sqlx::Error> {
+    let map_visualization = app_state
+        .database
+        .map_visualization
+        .get(map_visualization)
+        .await?;
+    let delta = map_visualization
+        .delta()
+        .map(|delta| delta.with_statistic(statistic));
+    Ok((map_visualization.dataset, delta))
+}
+
+/// A slice of a dataset as GeoJSON, or the change of a delta between two slices. A delta's
+/// metadata describes the slice it changes to.
+async fn geojson(
+    app_state: &web::Data<AppState<'_>>,
+    dataset: i32,
+    slice: &SourceAndDate,
+    delta: Option<&Delta>,
+) -> HttpResponse {
+    let geo_boundary = &app_state.database.geo_boundary;
+    let features = async {
+        match delta {
+            Some(delta) => geo_boundary.geojson_by_delta(dataset, delta).await,
+            None => geo_boundary.geojson_by_dataset(dataset, slice).await,
+        }
+    };
+    let slice = delta.map_or(slice, |delta| &delta.to);
+    let result = try_join(metadata(app_state, dataset, slice), features).await;
+
+    match result {
+        Err(e) => {
+            error!("Error exporting geojson: {}", e);
+            HttpResponse::NotFound().finish()
+        }
+        Ok((metadata, features)) => match geojson_converter::convert(features, &metadata) {
+            Err(e) => {
+                error!("Error converting geojson: {}", e);
+                HttpResponse::InternalServerError().finish()
+            }
+            Ok(geojson) => HttpResponse::Ok()
+                .content_type("application/geo+json")
+                .insert_header(attachment(format!("{}.geojson", metadata.short_name)))
+                .body(geojson),
+        },
+    }
+}
+
+/// Like `geojson`, as a GeoPackage
+async fn geopackage(
+    app_state: &web::Data<AppState<'_>>,
+    dataset: i32,
+    slice: &SourceAndDate,
+    delta: Option<&Delta>,
+) -> HttpResponse {
+    let geo_boundary = &app_state.database.geo_boundary;
+    let features = async {
+        match delta {
+            Some(delta) => geo_boundary.wkb_by_delta(dataset, delta).await,
+            None => geo_boundary.wkb_by_dataset(dataset, slice).await,
+        }
+    };
+    let slice = delta.map_or(slice, |delta| &delta.to);
+    let result = try_join(metadata(app_state, dataset, slice), features).await;
+
+    let (metadata, features) = match result {
+        Err(e) => {
+            error!("Error exporting geopackage: {}", e);
+            return HttpResponse::NotFound().finish();
+        }
+        Ok(result) => result,
+    };
+    let file_name = format!("{}.gpkg", metadata.short_name);
+    let geopackage = web::block(move || geopackage_converter::convert(features, &metadata)).await;
+
+    match geopackage {
+        Ok(Ok(geopackage)) => HttpResponse::Ok()
+            .content_type("application/geopackage+sqlite3")
+            .insert_header(attachment(file_name))
+            .body(geopackage),
+        Ok(Err(e)) => {
+            error!("Error writing geopackage: {}", e);
+            HttpResponse::InternalServerError().finish()
+        }
+        Err(e) => {
+            error!("Error writing geopackage: {}", e);
+            HttpResponse::InternalServerError().finish()
+        }
+    }
+}
+
+#[get("/data/{dataset}/geojson")]
+async fn get_geojson(
+    dataset: web::Path<i32>,
+    info: web::Query<SourceAndDate>,
+    app_state: web::Data<AppState<'_>>,
+) -> impl Responder {
+    geojson(&app_state, dataset.into_inner(), &info, None).await
+}
+
+#[get("/data/{dataset}/geopackage")]
+async fn get_geopackage(
+    dataset: web::Path<i32>,
+    info: web::Query<SourceAndDate>,
+    app_state: web::Data<AppState<'_>>,
+) -> impl Responder {
+    geopackage(&app_state, dataset.into_inner(), &info, None).await
+}
+
+#[get("/map-visualization/{map_visualization}/geojson")]
+async fn get_map_visualization_geojson(
+    map_visualization: web::Path<i32>,
+    info: Option<web::Query<SourceAndDate>>,
+    delta_info: web::Query<delta::Info>,
+    app_state: web::Data<AppState<'_>>,
+) -> impl Responder {
+    let export = map_visualization_export(
+        &app_state,
+        map_visualization.into_inner(),
+        delta_info.statistic,
+    );
+    match (export.await, info) {
+        (Err(e), _) => {
+            error!("Error exporting geojson: {}", e);
+            HttpResponse::NotFound().finish()
+        }
+        (Ok((dataset, Some(delta))), _) => {
+            geojson(&app_state, dataset, &delta.to, Some(&delta)).await
+        }
+        (Ok((dataset, None)), Some(info)) => geojson(&app_state, dataset, &info, None).await,
+        (Ok((_, None)), None) => HttpResponse::BadRequest().finish(),
+    }
+}
+
+#[get("/map-visualization/{map_visualization}/geopackage")]
+async fn get_map_visualization_geopackage(
+    map_visualization: web::Path<i32>,
+    info: Option<web::Query<SourceAndDate>>,
+    delta_info: web::Query<delta::Info>,
+    app_state: web::Data<AppState<'_>>,
+) -> impl Responder {
+    let export = map_visualization_export(
+        &app_state,
+        map_visualization.into_inner(),
+        delta_info.statistic,
+    );
+    match (export.await, info) {
+        (Err(e), _) => {
+            error!("Error exporting geopackage: {}", e);
+            HttpResponse::NotFound().finish()
+        }
+        (Ok((dataset, Some(delta))), _) => {
+            geopackage(&app_state, dataset, &delta.to, Some(&delta)).await
+        }
+        (Ok((dataset, None)), Some(info)) => geopackage(&app_state, dataset, &info, None).await,
+        (Ok((_, None)), None) => HttpResponse::BadRequest().finish(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn it_escapes_file_names() {
+        assert_eq!(
+            attachment("heat \"index\".gpkg".to_string()).to_string(),
+            "attachment; filename=\"heat \\\"index\\\".gpkg\""
+        );
+    }
+}
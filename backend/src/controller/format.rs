@@ -0,0 +1,510 @@
+use actix_web::web::Bytes;
+use actix_web::{http::header, web, HttpRequest, HttpResponse};
+use arrow::datatypes::{FieldRef, Schema};
+use arrow::ipc::writer::StreamWriter;
+use arrow::record_batch::RecordBatch;
+use futures::stream::{self, Stream, StreamExt};
+use log::error;
+use parquet::arrow::ArrowWriter;
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Serialize};
+use serde_arrow::schema::{SchemaLike, TracingOptions};
+use std::error::Error;
+use std::sync::Arc;
+
+/// A serialization format for tabular data, chosen with `?format=` or the `Accept` header
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub enum Format {
+    Csv,
+    Json,
+    Arrow,
+    Parquet,
+}
+
+#[derive(Debug, PartialEq)]
+pub enum NegotiationError {
+    UnknownFormat(String),
+    NotAcceptable,
+}
+
+#[derive(Deserialize)]
+struct FormatQuery {
+    format: Option<String>,
+}
+
+const DEFAULT: Format = Format::Csv;
+const ALL: [Format; 4] = [Format::Csv, Format::Json, Format::Arrow, Format::Parquet];
+/// The number of rows serialized at a time when streaming
+const STREAM_CHUNK_SIZE: usize = 10_000;
+
+impl Format {
+    pub fn name(&self) -> &'static str {
+        match self {
+            Format::Csv => "csv",
+            Format::Json => "json",
+            Format::Arrow => "arrow",
+            Format::Parquet => "parquet",
+        }
+    }
+
+    pub fn content_type(&self) -> &'static str {
+        match self {
+            Format::Csv => "text/csv",
+            Format::Json => "application/json",
+            Format::Arrow => "application/vnd.apache.arrow.stream",
+            Format::Parquet => "application/vnd.apache.parquet",
+        }
+    }
+
+    fn from_name(name: &str) -> Option<Format> {
+        ALL.iter()
+            .copied()
+            .find(|format| format.name().eq_ignore_ascii_case(name))
+    }
+
+    /// A wildcard like `text/*` matches the first format of that type
+    fn from_media_type(media_type: &str) -> Option<Format> {
+        match media_type {
+            "*/*" => Some(DEFAULT),
+            "application/x-parquet" => Some(Format::Parquet),
+            _ => ALL.iter().copied().find(|format| {
+                let content_type = format.content_type();
+                match media_type.strip_suffix("/*") {
+                    Some(kind) => content_type
+                        .split('/')
+                        .next()
+                        .map_or(false, |prefix| prefix.eq_ignore_ascii_case(kind)),
+                    None => content_type.eq_ignore_ascii_case(media_type),
+                }
+            }),
+        }
+    }
+
+    /// The format in the `format` query parameter takes precedence over the `Accept` header
+    pub fn negotiate(request: &HttpRequest) -> Result<Format, NegotiationError> {
+        let query = web::Query::<FormatQuery>::from_query(request.query_string());
+        if let Ok(web::Query(FormatQuery { format: Some(name) })) = query {
+            return Format::from_name(&name).ok_or(NegotiationError::UnknownFormat(name));
+        }
+
+        let accept = match request.headers().get(header::ACCEPT) {
+            None => return Ok(DEFAULT),
+            Some(accept) => accept.to_str().unwrap_or_default(),
+        };
+        let mut media_ranges: Vec<(&str, f32)> = accept
+            .split(',')
+            .map(|media_range| {
+                let mut parameters = media_range.split(';').map(str::trim);
+                let media_type = parameters.next().unwrap_or_default();
+                let quality = parameters
+                    .find_map(|parameter| parameter.strip_prefix("q="))
+                    .and_then(|quality| quality.parse::<f32>().ok())
+                    .unwrap_or(1.0);
+                (media_type, quality)
+            })
+            .filter(|(_, quality)| *quality > 0.0)
+            .collect();
+        media_ranges.sort_by(|(_, a), (_, b)| b.total_cmp(a));
+
+        media_ranges
+            .into_iter()
+            .find_map(|(media_type, _)| Format::from_media_type(media_type))
+            .ok_or(NegotiationError::NotAcceptable)
+    }
+
+    pub fn serialize<S: Serialize + DeserializeOwned>(
+        &self,
+        data: Vec<S>,
+    ) -> Result<Vec<u8>, Box<dyn Error>> {
+        let mut encoder = StreamEncoder::new(*self);
+        let mut bytes = encoder.encode(&data)?;
+        bytes.extend(encoder.finish::<S>()?);
+        Ok(bytes)
+    }
+}
+
+/// Serializes rows a chunk at a time, so large responses can be streamed
+pub enum StreamEncoder {
+    Csv { wrote_headers: bool },
+    Json { wrote_rows: bool },
+    Arrow(Option<StreamWriter<Vec<u8>>>),
+    Parquet(Option<ArrowWriter<Vec<u8>>>),
+}
+
+impl StreamEncoder {
+    pub fn new(format: Format) -> StreamEncoder {
+        match format {
+            Format::Csv => StreamEncoder::Csv {
+                wrote_headers: false,
+            },
+            Format::Json => StreamEncoder::Json { wrote_rows: false },
+            Format::Arrow => StreamEncoder::Arrow(None),
+            Format::Parquet => StreamEncoder::Parquet(None),
+        }
+    }
+
+    /// The bytes for the next chunk of rows
+    pub fn encode<S: Serialize + DeserializeOwned>(
+        &mut self,
+        rows: &[S],
+    ) -> Result<Vec<u8>, Box<dyn Error>> {
+        match self {
+            StreamEncoder::Csv { wrote_headers } => {
+                let mut writer = csv::WriterBuilder::new()
+                    .has_headers(!*wrote_headers)
+                    .from_writer(vec![]);
+                for row in rows {
+                    writer.serialize(row)?;
+                    *wrote_headers = true;
+                }
+                Ok(writer.into_inner()?)
+            }
+            StreamEncoder::Json { wrote_rows } => {
+                let mut bytes = vec![];
+                for row in rows {
+                    bytes.push(if *wrote_rows { b',' } else { b'[' });
+                    serde_json::to_writer(&mut bytes, row)?;
+                    *wrote_rows = true;
+                }
+                Ok(bytes)
+            }
+            StreamEncoder::Arrow(slot) => {
+                if rows.is_empty() {
+                    return Ok(vec![]);
+                }
+                let batch = record_batch(rows)?;
+                let writer = match slot.take() {
+                    Some(writer) => writer,
+                    None => StreamWriter::try_new(vec![], &batch.schema())?,
+                };
+                let writer = slot.insert(writer);
+                writer.write(&batch)?;
+                Ok(std::mem::take(writer.get_mut()))
+            }
+            StreamEncoder::Parquet(slot) => {
+                if rows.is_empty() {
+                    return Ok(vec![]);
+                }
+                let batch = record_batch(rows)?;
+                let writer = match slot.take() {
+                    Some(writer) => writer,
+                    None => ArrowWriter::try_new(vec![], batch.schema(), None)?,
+                };
+                let writer = slot.insert(writer);
+                writer.write(&batch)?;
+                writer.flush()?;
+                Ok(std::mem::take(writer.inner_mut()))
+            }
+        }
+    }
+
+    /// The bytes that end the response. Without any rows, Arrow and Parquet files still get the
+    /// columns of the row type.
+    pub fn finish<S: DeserializeOwned>(self) -> Result<Vec<u8>, Box<dyn Error>> {
+        match self {
+            StreamEncoder::Csv { .. } => Ok(vec![]),
+            StreamEncoder::Json { wrote_rows: true } => Ok(b"]".to_vec()),
+            StreamEncoder::Json { wrote_rows: false } => Ok(b"[]".to_vec()),
+            StreamEncoder::Arrow(writer) => {
+                let writer = match writer {
+                    Some(writer) => writer,
+                    None => StreamWriter::try_new(vec![], &*schema::<S>()?)?,
+                };
+                Ok(writer.into_inner()?)
+            }
+            StreamEncoder::Parquet(writer) => {
+                let mut writer = match writer {
+                    Some(writer) => writer,
+                    None => ArrowWriter::try_new(vec![], schema::<S>()?, None)?,
+                };
+                writer.finish()?;
+                Ok(std::mem::take(writer.inner_mut()))
+            }
+        }
+    }
+}
+
+/// Every chunk is built against the columns of the row type, so chunks whose values happen to
+/// trace differently, like a column that is null throughout one chunk, still share one schema
+fn record_batch<S: Serialize + DeserializeOwned>(
+    data: &[S],
+) -> Result<RecordBatch, Box<dyn Error>> {
+    let schema = schema::<S>()?;
+    Ok(serde_arrow::to_record_batch(schema.fields(), &data)?)
+}
+
+/// The columns of a row type, traced from its fields rather than from rows
+fn schema<S: DeserializeOwned>() -> Result<Arc<Schema>, Box<dyn Error>> {
+    let fields =
+        Vec::<FieldRef>::from_type::<S>(TracingOptions::default().allow_null_fields(true))?;
+    Ok(Arc::new(Schema::new(fields)))
+}
+
+fn negotiate_or_reject(request: &HttpRequest) -> Result<Format, HttpResponse> {
+    match Format::negotiate(request) {
+        Err(NegotiationError::UnknownFormat(name)) => {
+            Err(HttpResponse::BadRequest().body(format!("Unknown format {name}")))
+        }
+        Err(NegotiationError::NotAcceptable) => Err(HttpResponse::NotAcceptable().finish()),
+        Ok(format) => Ok(format),
+    }
+}
+
+/// Respond with the data in the format the client asked for
+pub fn respond<S: Serialize + DeserializeOwned>(
+    request: &HttpRequest,
+    data: Vec<S>,
+) -> HttpResponse {
+    let format = match negotiate_or_reject(request) {
+        Err(response) => return response,
+        Ok(format) => format,
+    };
+    match format.serialize(data) {
+        Err(e) => {
+            error!("Error serializing {}: {}", format.name(), e);
+            HttpResponse::InternalServerError().finish()
+        }
+        Ok(body) => HttpResponse::Ok()
+            .content_type(format.content_type())
+            .insert_header((header::VARY, "Accept"))
+            .body(body),
+    }
+}
+
+/// Stream the rows in the format the client asked for, without holding them all in memory. The
+/// first chunk is read before responding, so an error there is still a 500 rather than a cut off
+/// body.
+pub async fn respond_stream<S, R>(request: &HttpRequest, rows: R) -> HttpResponse
+where
+    S: Serialize + DeserializeOwned + 'static,
+    R: Stream<Item = Result<S, sqlx::Error>> + 'static,
+{
+    let format = match negotiate_or_reject(request) {
+        Err(response) => return response,
+        Ok(format) => format,
+    };
+    let mut chunks = rows
+        .chunks(STREAM_CHUNK_SIZE)
+        .map(|rows| rows.into_iter().collect::<Result<Vec<S>, sqlx::Error>>())
+        .boxed_local();
+    let first = match chunks.next().await.transpose() {
+        Err(e) => {
+            error!("Error streaming {}: {}", format.name(), e);
+            return HttpResponse::InternalServerError().finish();
+        }
+        Ok(first) => first,
+    };
+    let chunks = stream::iter(first.map(Ok)).chain(chunks).boxed_local();
+    let body = stream::unfold(
+        (chunks, Some(StreamEncoder::new(format))),
+        move |(mut chunks, encoder)| async move {
+            let mut encoder = encoder?;
+            match chunks.next().await {
+                None => Some((encoder.finish::<S>().map(Bytes::from), (chunks, None))),
+                Some(rows) => {
+                    let bytes = rows
+                        .map_err(Box::<dyn Error>::from)
+                        .and_then(|rows| encoder.encode(&rows));
+                    let encoder = match bytes {
+                        Err(ref e) => {
+                            error!("Error streaming {}: {}", format.name(), e);
+                            None
+                        }
+                        Ok(_) => Some(encoder),
+                    };
+                    Some((bytes.map(Bytes::from), (chunks, encoder)))
+                }
+            }
+        },
+    );
+    HttpResponse::Ok()
+        .content_type(format.content_type())
+        .insert_header((header::VARY, "Accept"))
+        .streaming(body)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::model::data::Simple;
+    use actix_web::test::TestRequest;
+    use arrow::ipc::reader::StreamReader;
+
+    fn data() -> Vec<Simple> {
+        vec![
+            Simple {
+                id: 123,
+                value: 3.0,
+            },
+            Simple {
+                id: 456,
+                value: 6.0,
+            },
+        ]
+    }
+
+    #[test]
+    fn it_defaults_to_csv() {
+        let request = TestRequest::default().to_http_request();
+        assert_eq!(Format::negotiate(&request), Ok(Format::Csv));
+
+        let request = TestRequest::default()
+            .insert_header((header::ACCEPT, "text/html,*/*;q=0.8"))
+            .to_http_request();
+        assert_eq!(Format::negotiate(&request), Ok(Format::Csv));
+    }
+
+    #[test]
+    fn it_prefers_the_format_query_over_accept() {
+        let request = TestRequest::with_uri("/data/1?source=1&format=parquet")
+            .insert_header((header::ACCEPT, "application/json"))
+            .to_http_request();
+        assert_eq!(Format::negotiate(&request), Ok(Format::Parquet));
+    }
+
+    #[test]
+    fn it_uses_the_highest_quality_accepted_format() {
+        let request = TestRequest::default()
+            .insert_header((
+                header::ACCEPT,
+                "text/csv;q=0.5, application/vnd.apache.arrow.stream, application/json;q=0.9",
+            ))
+            .to_http_request();
+        assert_eq!(Format::negotiate(&request), Ok(Format::Arrow));
+    }
+
+    #[test]
+    fn it_matches_wildcards_to_a_format_of_that_type() {
+        let request = TestRequest::default()
+            .insert_header((header::ACCEPT, "application/*"))
+            .to_http_request();
+        assert_eq!(Format::negotiate(&request), Ok(Format::Json));
+
+        let request = TestRequest::default()
+            .insert_header((header::ACCEPT, "image/png, text/*;q=0.5"))
+            .to_http_request();
+        assert_eq!(Format::negotiate(&request), Ok(Format::Csv));
+    }
+
+    #[test]
+    fn it_rejects_unknown_formats() {
+        let request = TestRequest::with_uri("/?format=xlsx").to_http_request();
+        assert_eq!(
+            Format::negotiate(&request),
+            Err(NegotiationError::UnknownFormat("xlsx".to_string()))
+        );
+
+        let request = TestRequest::default()
+            .insert_header((header::ACCEPT, "text/html"))
+            .to_http_request();
+        assert_eq!(
+            Format::negotiate(&request),
+            Err(NegotiationError::NotAcceptable)
+        );
+    }
+
+    #[test]
+    fn it_serializes_json() {
+        let json = Format::Json.serialize(data()).unwrap();
+        assert_eq!(
+            String::from_utf8(json).unwrap(),
+            r#"[{"id":123,"value":3.0},{"id":456,"value":6.0}]"#
+        );
+    }
+
+    #[test]
+    fn it_serializes_arrow() {
+        let arrow = Format::Arrow.serialize(data()).unwrap();
+        let batches = StreamReader::try_new(arrow.as_slice(), None)
+            .unwrap()
+            .collect::<Result<Vec<_>, _>>()
+            .unwrap();
+        assert_eq!(batches[0].num_rows(), 2);
+        assert_eq!(batches[0].schema().field(0).name(), "id");
+        assert_eq!(batches[0].schema().field(1).name(), "value");
+    }
+
+    #[test]
+    fn it_serializes_csv_in_chunks() {
+        let mut encoder = StreamEncoder::new(Format::Csv);
+        let data = data();
+        let mut csv = encoder.encode(&data[0..1]).unwrap();
+        csv.extend(encoder.encode(&data[1..2]).unwrap());
+        csv.extend(encoder.finish::<Simple>().unwrap());
+        assert_eq!(
+            String::from_utf8(csv).unwrap(),
+            "id,value\n123,3.0\n456,6.0\n"
+        );
+    }
+
+    #[test]
+    fn it_serializes_arrow_in_chunks() {
+        let mut encoder = StreamEncoder::new(Format::Arrow);
+        let data = data();
+        let mut arrow = encoder.encode(&data[0..1]).unwrap();
+        arrow.extend(encoder.encode(&data[1..2]).unwrap());
+        arrow.extend(encoder.finish::<Simple>().unwrap());
+        let batches = StreamReader::try_new(arrow.as_slice(), None)
+            .unwrap()
+            .collect::<Result<Vec<_>, _>>()
+            .unwrap();
+        assert_eq!(batches.len(), 2);
+        assert_eq!(batches[1].num_rows(), 1);
+    }
+
+    #[derive(Serialize, Deserialize)]
+    struct Sparse {
+        id: i32,
+        value: Option<f64>,
+    }
+
+    #[test]
+    fn it_keeps_one_schema_across_arrow_chunks() {
+        let mut encoder = StreamEncoder::new(Format::Arrow);
+        let mut arrow = encoder
+            .encode(&[Sparse {
+                id: 123,
+                value: None,
+            }])
+            .unwrap();
+        arrow.extend(
+            encoder
+                .encode(&[Sparse {
+                    id: 456,
+                    value: Some(6.0),
+                }])
+                .unwrap(),
+        );
+        arrow.extend(encoder.finish::<Sparse>().unwrap());
+        let batches = StreamReader::try_new(arrow.as_slice(), None)
+            .unwrap()
+            .collect::<Result<Vec<_>, _>>()
+            .unwrap();
+        assert_eq!(batches.len(), 2);
+        assert_eq!(batches[0].schema(), batches[1].schema());
+        assert_eq!(batches[1].column(1).null_count(), 0);
+    }
+
+    #[test]
+    fn it_serializes_empty_json() {
+        let json = Format::Json.serialize(Vec::<Simple>::new()).unwrap();
+        assert_eq!(String::from_utf8(json).unwrap(), "[]");
+    }
+
+    #[test]
+    fn it_keeps_the_columns_of_empty_arrow() {
+        let arrow = Format::Arrow.serialize(Vec::<Simple>::new()).unwrap();
+        let reader = StreamReader::try_new(arrow.as_slice(), None).unwrap();
+        let schema = reader.schema();
+        assert_eq!(schema.fields().len(), 2);
+        assert_eq!(schema.field(0).name(), "id");
+        assert_eq!(schema.field(1).name(), "value");
+    }
+
+    #[test]
+    fn it_serializes_parquet() {
+        let parquet = Format::Parquet.serialize(data()).unwrap();
+        assert_eq!(&parquet[0..4], b"PAR1");
+        assert_eq!(&parquet[parquet.len() - 4..], b"PAR1");
+    }
+}
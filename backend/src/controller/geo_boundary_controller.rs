@@ -0,0 +1,129 @@
+use super::AppState;
+use crate::controller::auth::Admin;
+use crate::model::geo_boundary::{Boundary, FeatureCollection, UploadMetadata};
+use crate::model::geo_id::GeoId;
+use actix_web::{http::StatusCode, post, web, HttpResponse};
+use derive_more::Display;
+use log::error;
+use serde::Serialize;
+use std::collections::HashSet;
+use std::io::BufReader;
+
+#[derive(Debug, Display, Serialize)]
+#[serde(tag = "name", content = "info")]
+enum Error {
+    InvalidGeoJson(String),
+    #[display(fmt = "Feature {index} has no numeric geo id")]
+    MissingGeoId {
+        index: usize,
+    },
+    #[display(fmt = "Invalid geo ids: {_0:#?}")]
+    InvalidGeoIds(Vec<GeoId>),
+    MissingMetadata,
+    InvalidMetadata(String),
+    MissingFile,
+    Internal(String),
+}
+
+impl std::error::Error for Error {}
+
+impl From<std::io::Error> for Error {
+    fn from(error: std::io::Error) -> Self {
+        Error::Internal(error.to_string())
+    }
+}
+
+impl From<sqlx::Error> for Error {
+    fn from(error: sqlx::Error) -> Self {
+        Error::Internal(error.to_string())
+    }
+}
+
+impl actix_web::error::ResponseError for Error {
+    fn status_code(&self) -> StatusCode {
+        match self {
+            Error::Internal(_) => StatusCode::INTERNAL_SERVER_ERROR,
+            _ => StatusCode::BAD_REQUEST,
+        }
+    }
+
+    fn error_response(&self) -> HttpResponse {
+        if let Error::Internal(e) = self {
+            error!("Error uploading boundaries: {}", e);
+            return HttpResponse::build(self.status_code()).finish();
+        }
+        HttpResponse::build(self.status_code()).json(self)
+    }
+}
+
+pub fn init_editor(cfg: &mut web::ServiceConfig) {
+    cfg.service(upload);
+}
+
+fn parse_boundaries(
+    collection: FeatureCollection,
+    metadata: &UploadMetadata,
+) -> Result<Vec<Boundary>, Error> {
+    collection
+        .features
+        .into_iter()
+        .enumerate()
+        .map(|(index, feature)| {
+            let id = feature
+                .geo_id(&metadata.id_property)
+                .ok_or(Error::MissingGeoId { index })?;
+            Ok(Boundary {
+                id,
+                geometry: feature.geometry.to_string(),
+            })
+        })
+        .collect()
+}
+
+/// Upload a GeoJSON FeatureCollection of unprojected (EPSG:4326) boundaries for a geography type
+#[post("/geo-boundary")]
+async fn upload(
+    Admin(user): Admin,
+    mut parts: awmp::Parts,
+    app_state: web::Data<AppState<'_>>,
+) -> Result<String, Error> {
+    let metadata: UploadMetadata = parts
+        .texts
+        .as_hash_map()
+        .get("metadata")
+        .ok_or(Error::MissingMetadata)
+        .map(|s| serde_json::from_str(s))?
+        .map_err(|e| Error::InvalidMetadata(e.to_string()))?;
+
+    let file = parts
+        .files
+        .take("file")
+        .pop()
+        .ok_or(Error::MissingFile)?
+        .into_inner()
+        .reopen()?;
+
+    let collection: FeatureCollection = serde_json::from_reader(BufReader::new(file))
+        .map_err(|e| Error::InvalidGeoJson(e.to_string()))?;
+    let boundaries = parse_boundaries(collection, &metadata)?;
+
+    let geo_ids: HashSet<GeoId> = boundaries
+        .iter()
+        .map(|boundary| GeoId {
+            id: boundary.id,
+            geography_type: metadata.geography_type,
+        })
+        .collect();
+    let invalid_ids = app_state.database.geo_id.get_invalid_ids(&geo_ids).await?;
+    if !invalid_ids.is_empty() {
+        return Err(Error::InvalidGeoIds(invalid_ids));
+    }
+
+    let result = app_state
+        .database
+        .geo_boundary
+        .upsert(metadata.geography_type, &boundaries, &user)
+        .await?;
+
+    Ok(format!("saved {} boundaries", result.rows_affected()))
+}
@@ -1,23 +1,62 @@
 use super::AppState;
-use crate::controller::csv_converter;
-use actix_web::{get, web, HttpResponse, Responder};
+use crate::controller::data_controller::parse_ids;
+use crate::controller::format;
+use actix_web::{get, web, HttpRequest, HttpResponse, Responder};
+use log::error;
+use serde::Deserialize;
 
 pub fn init(cfg: &mut web::ServiceConfig) {
     cfg.service(get_all);
+    cfg.service(get_timeseries);
+}
+
+#[derive(Deserialize)]
+struct TimeseriesInfo {
+    /// Comma separated dataset ids
+    dataset: String,
+    geography_type: Option<i32>,
+    /// Include the national median and percentile of each slice
+    #[serde(default)]
+    context: bool,
 }
 
 #[get("/geo-id.csv")]
-async fn get_all(app_state: web::Data<AppState<'_>>) -> impl Responder {
+async fn get_all(request: HttpRequest, app_state: web::Data<AppState<'_>>) -> impl Responder {
     let geo_ids = app_state.database.geo_id.all().await;
 
     match geo_ids {
         Err(_) => HttpResponse::NotFound().finish(),
-        Ok(geo_ids) => {
-            let csv = csv_converter::convert(geo_ids);
-            match csv {
-                Err(_) => HttpResponse::NotFound().finish(),
-                Ok(csv) => HttpResponse::Ok().content_type("text/csv").body(csv),
-            }
+        Ok(geo_ids) => format::respond(&request, geo_ids),
+    }
+}
+
+#[get("/geo-id/{id}/timeseries")]
+async fn get_timeseries(
+    request: HttpRequest,
+    id: web::Path<i64>,
+    info: web::Query<TimeseriesInfo>,
+    app_state: web::Data<AppState<'_>>,
+) -> impl Responder {
+    let datasets = match parse_ids(&info.dataset) {
+        Ok(datasets) => datasets,
+        Err(_) => return HttpResponse::BadRequest().finish(),
+    };
+    let timeseries = app_state
+        .database
+        .data
+        .timeseries(
+            id.into_inner(),
+            info.geography_type,
+            &datasets,
+            info.context,
+        )
+        .await;
+
+    match timeseries {
+        Err(e) => {
+            error!("Error getting timeseries: {}", e);
+            HttpResponse::InternalServerError().finish()
         }
+        Ok(timeseries) => format::respond(&request, timeseries),
     }
 }
@@ -0,0 +1,69 @@
+use crate::model::export::{Feature, Metadata};
+use serde_json::{json, Value};
+use std::error::Error;
+
+pub fn convert(features: Vec<Feature>, metadata: &Metadata) -> Result<String, Box<dyn Error>> {
+    let features = features
+        .into_iter()
+        .map(|feature| {
+            Ok(json!({
+                "type": "Feature",
+                "id": feature.id,
+                "geometry": serde_json::from_str::<Value>(&feature.geometry)?,
+                "properties": {
+                    "id": feature.id,
+                    "name": feature.name,
+                    "value": feature.value,
+                    "units": metadata.units,
+                    "source": metadata.source,
+                    "start_date": metadata.start_date,
+                    "end_date": metadata.end_date,
+                },
+            }))
+        })
+        .collect::<Result<Vec<Value>, serde_json::Error>>()?;
+    let collection = json!({
+        "type": "FeatureCollection",
+        "metadata": metadata,
+        "features": features,
+    });
+    Ok(collection.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::NaiveDate;
+
+    #[test]
+    fn it_joins_values_and_metadata_onto_features() {
+        let metadata = Metadata {
+            dataset: "Population".to_string(),
+            short_name: "population".to_string(),
+            description: "description".to_string(),
+            units: "people".to_string(),
+            source: "US Census Bureau".to_string(),
+            source_link: "https://www.census.gov".to_string(),
+            start_date: NaiveDate::from_ymd_opt(2020, 1, 1).unwrap(),
+            end_date: NaiveDate::from_ymd_opt(2020, 12, 31).unwrap(),
+        };
+        let features = vec![Feature {
+            id: 1001,
+            name: "Autauga".to_string(),
+            value: 58805.0,
+            geometry: r#"{"type":"MultiPolygon","coordinates":[]}"#.to_string(),
+        }];
+
+        let geojson: Value = serde_json::from_str(&convert(features, &metadata).unwrap()).unwrap();
+
+        assert_eq!(geojson["type"], "FeatureCollection");
+        assert_eq!(geojson["metadata"]["units"], "people");
+        assert_eq!(geojson["features"][0]["geometry"]["type"], "MultiPolygon");
+        assert_eq!(geojson["features"][0]["properties"]["name"], "Autauga");
+        assert_eq!(geojson["features"][0]["properties"]["value"], 58805.0);
+        assert_eq!(
+            geojson["features"][0]["properties"]["start_date"],
+            "2020-01-01"
+        );
+    }
+}
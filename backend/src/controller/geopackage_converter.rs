@@ -0,0 +1,191 @@
+use crate::model::export::{Metadata, WkbFeature};
+use rusqlite::{params, Connection};
+use std::error::Error;
+
+/// "GPKG" in ASCII, identifying the sqlite file as a GeoPackage
+const APPLICATION_ID: i32 = 0x4750_4B47;
+/// GeoPackage version 1.3
+const USER_VERSION: i32 = 10300;
+const SRS_ID: i32 = 4326;
+const TABLE_NAME: &str = "data";
+
+const SCHEMA: &str = "
+CREATE TABLE gpkg_spatial_ref_sys (
+    srs_name TEXT NOT NULL,
+    srs_id INTEGER NOT NULL PRIMARY KEY,
+    organization TEXT NOT NULL,
+    organization_coordsys_id INTEGER NOT NULL,
+    definition TEXT NOT NULL,
+    description TEXT
+);
+
+INSERT INTO gpkg_spatial_ref_sys VALUES
+    ('Undefined cartesian SRS', -1, 'NONE', -1, 'undefined', 'undefined cartesian coordinate reference system'),
+    ('Undefined geographic SRS', 0, 'NONE', 0, 'undefined', 'undefined geographic coordinate reference system'),
+    ('WGS 84 geodetic', 4326, 'EPSG', 4326, 'GEOGCS[\"WGS 84\",DATUM[\"WGS_1984\",SPHEROID[\"WGS 84\",6378137,298.257223563,AUTHORITY[\"EPSG\",\"7030\"]],AUTHORITY[\"EPSG\",\"6326\"]],PRIMEM[\"Greenwich\",0,AUTHORITY[\"EPSG\",\"8901\"]],UNIT[\"degree\",0.0174532925199433,AUTHORITY[\"EPSG\",\"9122\"]],AUTHORITY[\"EPSG\",\"4326\"]]', 'longitude/latitude coordinates in decimal degrees on the WGS 84 spheroid');
+
+CREATE TABLE gpkg_contents (
+    table_name TEXT NOT NULL PRIMARY KEY,
+    data_type TEXT NOT NULL,
+    identifier TEXT UNIQUE,
+    description TEXT DEFAULT '',
+    last_change DATETIME NOT NULL DEFAULT (strftime('%Y-%m-%dT%H:%M:%fZ', 'now')),
+    min_x DOUBLE,
+    min_y DOUBLE,
+    max_x DOUBLE,
+    max_y DOUBLE,
+    srs_id INTEGER,
+    CONSTRAINT fk_gc_r_srs_id FOREIGN KEY (srs_id) REFERENCES gpkg_spatial_ref_sys(srs_id)
+);
+
+CREATE TABLE gpkg_geometry_columns (
+    table_name TEXT NOT NULL,
+    column_name TEXT NOT NULL,
+    geometry_type_name TEXT NOT NULL,
+    srs_id INTEGER NOT NULL,
+    z TINYINT NOT NULL,
+    m TINYINT NOT NULL,
+    CONSTRAINT pk_geom_cols PRIMARY KEY (table_name, column_name),
+    CONSTRAINT fk_gc_tn FOREIGN KEY (table_name) REFERENCES gpkg_contents(table_name),
+    CONSTRAINT fk_gc_srs FOREIGN KEY (srs_id) REFERENCES gpkg_spatial_ref_sys (srs_id)
+);
+
+CREATE TABLE data (
+    fid INTEGER PRIMARY KEY AUTOINCREMENT NOT NULL,
+    geometry MULTIPOLYGON,
+    geo_id INTEGER NOT NULL,
+    name TEXT NOT NULL,
+    value DOUBLE NOT NULL,
+    units TEXT NOT NULL,
+    source TEXT NOT NULL,
+    start_date DATE NOT NULL,
+    end_date DATE NOT NULL
+);
+";
+
+/// Write the features to a GeoPackage, with one layer holding the data values and metadata
+pub fn convert(
+    features: Vec<WkbFeature>,
+    metadata: &Metadata,
+) -> Result<Vec<u8>, Box<dyn Error + Send + Sync>> {
+    let file = tempfile::NamedTempFile::new()?;
+    {
+        let mut connection = Connection::open(file.path())?;
+        write(&mut connection, features, metadata)?;
+    }
+    Ok(std::fs::read(file.path())?)
+}
+
+fn write(
+    connection: &mut Connection,
+    features: Vec<WkbFeature>,
+    metadata: &Metadata,
+) -> rusqlite::Result<()> {
+    connection.pragma_update(None, "application_id", APPLICATION_ID)?;
+    connection.pragma_update(None, "user_version", USER_VERSION)?;
+
+    let transaction = connection.transaction()?;
+    transaction.execute_batch(SCHEMA)?;
+    transaction.execute(
+        "INSERT INTO gpkg_contents (table_name, data_type, identifier, description, srs_id)
+        VALUES (?1, 'features', ?2, ?3, ?4)",
+        params![TABLE_NAME, metadata.dataset, description(metadata), SRS_ID],
+    )?;
+    transaction.execute(
+        "INSERT INTO gpkg_geometry_columns VALUES (?1, 'geometry', 'MULTIPOLYGON', ?2, 0, 0)",
+        params![TABLE_NAME, SRS_ID],
+    )?;
+    {
+        let mut insert = transaction.prepare(
+            "INSERT INTO data (geometry, geo_id, name, value, units, source, start_date, end_date)
+            VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8)",
+        )?;
+        for feature in features {
+            insert.execute(params![
+                geometry_blob(&feature.geometry),
+                feature.id,
+                feature.name,
+                feature.value,
+                metadata.units,
+                metadata.source,
+                metadata.start_date.to_string(),
+                metadata.end_date.to_string(),
+            ])?;
+        }
+    }
+    transaction.commit()
+}
+
+fn description(metadata: &Metadata) -> String {
+    format!(
+        "{} ({}). Source: {} {}. From {} to {}.",
+        metadata.description,
+        metadata.units,
+        metadata.source,
+        metadata.source_link,
+        metadata.start_date,
+        metadata.end_date
+    )
+}
+
+/// Prefix well-known binary with the GeoPackage geometry header
+fn geometry_blob(wkb: &[u8]) -> Vec<u8> {
+    let mut blob = Vec::with_capacity(8 + wkb.len());
+    blob.extend_from_slice(b"GP");
+    blob.push(0); // version 1
+    blob.push(0b0000_0001); // little endian, no envelope
+    blob.extend_from_slice(&SRS_ID.to_le_bytes());
+    blob.extend_from_slice(wkb);
+    blob
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::NaiveDate;
+
+    #[test]
+    fn it_writes_a_geopackage() {
+        let metadata = Metadata {
+            dataset: "Population".to_string(),
+            short_name: "population".to_string(),
+            description: "description".to_string(),
+            units: "people".to_string(),
+            source: "US Census Bureau".to_string(),
+            source_link: "https://www.census.gov".to_string(),
+            start_date: NaiveDate::from_ymd_opt(2020, 1, 1).unwrap(),
+            end_date: NaiveDate::from_ymd_opt(2020, 12, 31).unwrap(),
+        };
+        // an empty multipolygon
+        let wkb = vec![1, 6, 0, 0, 0, 0, 0, 0, 0];
+        let features = vec![WkbFeature {
+            id: 1001,
+            name: "Autauga".to_string(),
+            value: 58805.0,
+            geometry: wkb.clone(),
+        }];
+
+        let bytes = convert(features, &metadata).unwrap();
+        let file = tempfile::NamedTempFile::new().unwrap();
+        std::fs::write(file.path(), bytes).unwrap();
+        let connection = Connection::open(file.path()).unwrap();
+
+        let application_id: i32 = connection
+            .query_row("PRAGMA application_id", [], |row| row.get(0))
+            .unwrap();
+        assert_eq!(application_id, APPLICATION_ID);
+
+        let (geometry, name, value, units): (Vec<u8>, String, f64, String) = connection
+            .query_row(
+                "SELECT geometry, name, value, units FROM data WHERE geo_id = 1001",
+                [],
+                |row| Ok((row.get(0)?, row.get(1)?, row.get(2)?, row.get(3)?)),
+            )
+            .unwrap();
+        assert_eq!(&geometry[0..2], b"GP");
+        assert_eq!(&geometry[8..], &wkb[..]);
+        assert_eq!(name, "Autauga");
+        assert_eq!(value, 58805.0);
+        assert_eq!(units, "people");
+    }
+}
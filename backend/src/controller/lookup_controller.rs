@@ -0,0 +1,204 @@
+use super::AppState;
+use crate::controller::data_controller::{delta_data, parse_ids};
+use crate::controller::format;
+use crate::model::lookup::{self, Point, Values};
+use actix_web::{get, http::StatusCode, post, web, HttpRequest, HttpResponse};
+use derive_more::Display;
+use futures::StreamExt;
+use log::error;
+use serde::{Deserialize, Serialize};
+use std::collections::HashSet;
+
+/// The most points looked up in one request
+const MAX_POINTS: usize = 10_000;
+
+/// The largest CSV of points accepted, plenty for `MAX_POINTS` rows of coordinates
+const MAX_BODY_BYTES: usize = 1024 * 1024;
+
+#[derive(Debug, Display, Serialize)]
+#[serde(tag = "name", content = "info")]
+enum Error {
+    InvalidCsv(String),
+    #[display(fmt = "Point {index} is not a valid latitude and longitude")]
+    InvalidPoint {
+        index: usize,
+    },
+    #[display(fmt = "At most {_0} points can be looked up at once")]
+    TooManyPoints(usize),
+    #[display(fmt = "At most {_0} bytes of points can be posted at once")]
+    TooLarge(usize),
+    InvalidMapVisualizations(String),
+    #[display(fmt = "Map visualization {_0} not found")]
+    UnknownMapVisualization(i32),
+    Internal(String),
+}
+
+impl std::error::Error for Error {}
+
+impl From<csv::Error> for Error {
+    fn from(error: csv::Error) -> Self {
+        Error::InvalidCsv(error.to_string())
+    }
+}
+
+impl From<sqlx::Error> for Error {
+    fn from(error: sqlx::Error) -> Self {
+        Error::Internal(error.to_string())
+    }
+}
+
+impl actix_web::error::ResponseError for Error {
+    fn status_code(&self) -> StatusCode {
+        match self {
+            Error::Internal(_) => StatusCode::INTERNAL_SERVER_ERROR,
+            Error::UnknownMapVisualization(_) => StatusCode::NOT_FOUND,
+            Error::TooLarge(_) => StatusCode::PAYLOAD_TOO_LARGE,
+            _ => StatusCode::BAD_REQUEST,
+        }
+    }
+
+    fn error_response(&self) -> HttpResponse {
+        if let Error::Internal(e) = self {
+            error!("Error looking up points: {}", e);
+            return HttpResponse::build(self.status_code()).finish();
+        }
+        HttpResponse::build(self.status_code()).json(self)
+    }
+}
+
+pub fn init(cfg: &mut web::ServiceConfig) {
+    cfg.service(get_lookup);
+    cfg.service(batch_lookup);
+}
+
+#[derive(Deserialize)]
+struct LookupInfo {
+    #[serde(alias = "lat")]
+    latitude: f64,
+    #[serde(alias = "lon", alias = "lng")]
+    longitude: f64,
+    /// Comma separated map visualization ids to add values for
+    map_visualization: Option<String>,
+}
+
+#[derive(Deserialize)]
+struct BatchInfo {
+    map_visualization: Option<String>,
+}
+
+async fn lookup(
+    app_state: &web::Data<AppState<'_>>,
+    points: Vec<Point>,
+    map_visualizations: &Option<String>,
+) -> Result<Vec<lookup::Row>, Error> {
+    if points.len() > MAX_POINTS {
+        return Err(Error::TooManyPoints(MAX_POINTS));
+    }
+    if let Some(index) = points.iter().position(|point| !point.is_valid()) {
+        return Err(Error::InvalidPoint { index });
+    }
+    let map_visualizations = match map_visualizations {
+        None => vec![],
+        Some(ids) => {
+            parse_ids(ids).map_err(|_| Error::InvalidMapVisualizations(ids.to_string()))?
+        }
+    };
+
+    let containing = app_state.database.geo_boundary.containing(&points).await?;
+
+    let mut values = Vec::new();
+    for id in map_visualizations {
+        let map_visualization = match app_state.database.map_visualization.get(id).await {
+            Err(sqlx::Error::RowNotFound) => return Err(Error::UnknownMapVisualization(id)),
+            result => result?,
+        };
+        let slices = app_state
+            .database
+            .source_and_date
+            .by_dataset(map_visualization.dataset)
+            .await?;
+        let ids: HashSet<i64> = containing
+            .iter()
+            .filter(|geo_id| geo_id.geography_type == map_visualization.geography_type)
+            .map(|geo_id| geo_id.id)
+            .collect();
+        if let Some(delta) = map_visualization.delta() {
+            let slice = delta.to;
+            let changes = delta_data(app_state, map_visualization.dataset, delta).await?;
+            values.push(Values {
+                map_visualization: map_visualization.id,
+                dataset: map_visualization.dataset,
+                geography_type: map_visualization.geography_type,
+                slice: Some(slice),
+                ranks: lookup::rank(&changes, &ids, map_visualization.invert_normalized),
+            });
+            continue;
+        }
+        let slice = map_visualization.default_slice(&slices);
+        let ids: Vec<i64> = ids.into_iter().collect();
+        let ranks = match &slice {
+            Some(slice) if !ids.is_empty() => app_state
+                .database
+                .data
+                .ranked(
+                    map_visualization.dataset,
+                    slice,
+                    &ids,
+                    map_visualization.invert_normalized,
+                )
+                .await?
+                .into_iter()
+                .map(|ranked| (ranked.id, ranked))
+                .collect(),
+            _ => Default::default(),
+        };
+        values.push(Values {
+            map_visualization: map_visualization.id,
+            dataset: map_visualization.dataset,
+            geography_type: map_visualization.geography_type,
+            slice,
+            ranks,
+        });
+    }
+
+    Ok(lookup::rows(&points, &containing, &values))
+}
+
+/// The geo ids containing a point, with the selected map visualizations' default values there
+#[get("/lookup")]
+async fn get_lookup(
+    request: HttpRequest,
+    info: web::Query<LookupInfo>,
+    app_state: web::Data<AppState<'_>>,
+) -> Result<HttpResponse, Error> {
+    let point = Point {
+        latitude: info.latitude,
+        longitude: info.longitude,
+    };
+    let rows = lookup(&app_state, vec![point], &info.map_visualization).await?;
+    Ok(format::respond(&request, rows))
+}
+
+/// Looks up a CSV of points with `latitude` and `longitude` (or `lat` and `lon`) columns, of at
+/// most `MAX_BODY_BYTES`
+#[post("/lookup")]
+async fn batch_lookup(
+    request: HttpRequest,
+    mut payload: web::Payload,
+    info: web::Query<BatchInfo>,
+    app_state: web::Data<AppState<'_>>,
+) -> Result<HttpResponse, Error> {
+    let mut body = web::BytesMut::new();
+    while let Some(chunk) = payload.next().await {
+        let chunk = chunk.map_err(|e| Error::InvalidCsv(e.to_string()))?;
+        if body.len() + chunk.len() > MAX_BODY_BYTES {
+            return Err(Error::TooLarge(MAX_BODY_BYTES));
+        }
+        body.extend_from_slice(&chunk);
+    }
+    let points = csv::Reader::from_reader(body.as_ref())
+        .deserialize()
+        .collect::<Result<Vec<Point>, csv::Error>>()?;
+    let rows = lookup(&app_state, points, &info.map_visualization).await?;
+    Ok(format::respond(&request, rows))
+}
@@ -1,3 +1,4 @@
+use crate::controller::auth::Curator;
 use actix_web::{delete, post, web, HttpResponse, Responder};
 use log::error;
 
@@ -12,11 +13,15 @@ pub fn init_editor(cfg: &mut web::ServiceConfig) {
 }
 
 #[delete("/map-visualization-collection")]
-async fn delete(app_state: web::Data<AppState<'_>>, json: web::Json<Id>) -> impl Responder {
+async fn delete(
+    Curator(user): Curator,
+    app_state: web::Data<AppState<'_>>,
+    json: web::Json<Id>,
+) -> impl Responder {
     let result = app_state
         .database
         .map_visualization_collection
-        .delete(json.into_inner())
+        .delete(json.into_inner(), &user)
         .await;
     match result {
         Err(e) => {
@@ -28,7 +33,11 @@ async fn delete(app_state: web::Data<AppState<'_>>, json: web::Json<Id>) -> impl
 }
 
 #[post("/map-visualization-collection")]
-async fn create(app_state: web::Data<AppState<'_>>, json: web::Json<Id>) -> impl Responder {
+async fn create(
+    Curator(user): Curator,
+    app_state: web::Data<AppState<'_>>,
+    json: web::Json<Id>,
+) -> impl Responder {
     let order = app_state
         .database
         .map_visualization_collection
@@ -41,14 +50,15 @@ async fn create(app_state: web::Data<AppState<'_>>, json: web::Json<Id>) -> impl
         }
         Ok(o) => o,
     };
+    let collection = Collection {
+        order: order + 1,
+        category: json.category,
+        map_visualization: json.map_visualization,
+    };
     let result = app_state
         .database
         .map_visualization_collection
-        .create(Collection {
-            order: order + 1,
-            category: json.category,
-            map_visualization: json.map_visualization,
-        })
+        .create(collection, &user)
         .await;
     match result {
         Err(e) => {
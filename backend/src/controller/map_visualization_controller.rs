@@ -1,9 +1,18 @@
+use crate::controller::auth::Curator;
+use crate::controller::data_controller::delta_data;
+use crate::controller::trash_controller;
+use crate::controller::validation::{self, validate};
 use crate::{
+    model::classification::{self, Cached, Method},
+    model::data::SourceAndDate,
+    model::deletion::Options,
     model::map_visualization::{Creator, Error, Json, JsonPatch, MapVisualization, Patch},
+    model::statistic,
+    model::trash::Kind,
     AppState,
 };
 use actix_web::{delete, get, patch, post, web, HttpResponse, Responder};
-use futures::future::try_join;
+use futures::future::try_join3;
 use log::error;
 use serde::Deserialize;
 use std::collections::HashMap;
@@ -17,6 +26,7 @@ pub fn init(cfg: &mut web::ServiceConfig) {
 pub fn init_editor(cfg: &mut web::ServiceConfig) {
     cfg.service(patch);
     cfg.service(create);
+    cfg.service(duplicate);
     cfg.service(delete);
 }
 
@@ -26,8 +36,105 @@ pub struct MapVisualizationOptions {
     pub geography_type: Option<i32>,
 }
 
+/// The class breaks of the map visualization for a slice, cached until its data or
+/// classification change. Delta map visualizations are classified by their change, cached by
+/// both slices. Without `compute`, breaks that aren't cached yet are left empty.
+async fn breaks(
+    map_visualization: &MapVisualization,
+    slice: Option<SourceAndDate>,
+    compute: bool,
+    app_state: &web::Data<AppState<'_>>,
+) -> Result<Vec<f64>, sqlx::Error> {
+    let (method_id, method) = match map_visualization
+        .classification_method
+        .and_then(|id| Some((id, Method::from_id(id)?)))
+    {
+        None => return Ok(vec![]),
+        Some(method) => method,
+    };
+    let classes = map_visualization.classification_classes.max(1) as usize;
+    if method == Method::Manual {
+        let mut breaks = map_visualization.color_domain.clone();
+        breaks.sort_by(|a, b| a.total_cmp(b));
+        return Ok(breaks);
+    }
+    if let Some(delta) = map_visualization.delta() {
+        let statistic = slice
+            .as_ref()
+            .map_or(statistic::MEAN, |slice| slice.statistic);
+        let delta = delta.with_statistic(statistic);
+        let cache = &app_state.database.classification;
+        if let Some(cached) = cache.cached_delta(map_visualization.id, &delta).await? {
+            return Ok(cached.breaks);
+        }
+        if !compute {
+            return Ok(vec![]);
+        }
+        let version = cache.version(map_visualization.dataset).await?;
+        let values = delta_data(app_state, map_visualization.dataset, delta).await?;
+        let values: Vec<f64> = values.iter().map(|row| row.value).collect();
+        let cached = Cached {
+            dataset: map_visualization.dataset,
+            version,
+            breaks: classification::breaks(method, &values, classes),
+        };
+        let stored = cache
+            .store_delta(
+                map_visualization.id,
+                &delta,
+                method_id,
+                map_visualization.classification_classes,
+                &cached,
+            )
+            .await;
+        if let Err(e) = stored {
+            error!("Error caching breaks: {}", e);
+        }
+        return Ok(cached.breaks);
+    }
+    let slice = match slice {
+        None => return Ok(vec![]),
+        Some(slice) => slice,
+    };
+
+    let cache = &app_state.database.classification;
+    if let Some(cached) = cache.cached(map_visualization.id, &slice).await? {
+        return Ok(cached.breaks);
+    }
+    if !compute {
+        return Ok(vec![]);
+    }
+    let version = cache.version(map_visualization.dataset).await?;
+    let values = app_state
+        .database
+        .data
+        .by_dataset(map_visualization.dataset, &slice)
+        .await?;
+    let values: Vec<f64> = values.iter().map(|row| row.value).collect();
+    let cached = Cached {
+        dataset: map_visualization.dataset,
+        version,
+        breaks: classification::breaks(method, &values, classes),
+    };
+    let stored = cache
+        .store(
+            map_visualization.id,
+            &slice,
+            method_id,
+            map_visualization.classification_classes,
+            &cached,
+        )
+        .await;
+    if let Err(e) = stored {
+        error!("Error caching breaks: {}", e);
+    }
+    Ok(cached.breaks)
+}
+
 async fn get_map_visualization_model(
     map_visualization: MapVisualization,
+    slice: Option<SourceAndDate>,
+    compute_breaks: bool,
     app_state: &web::Data<AppState<'_>>,
 ) -> Result<Json, sqlx::Error> {
     let sources_and_dates = app_state
@@ -38,10 +145,14 @@ async fn get_map_visualization_model(
         .database
         .data_source
         .by_dataset(map_visualization.dataset);
-    let result = try_join(sources_and_dates, data_sources).await;
+    let scenarios = app_state
+        .database
+        .scenario
+        .by_dataset(map_visualization.dataset);
+    let result = try_join3(sources_and_dates, data_sources, scenarios).await;
     match result {
         Err(e) => Err(e),
-        Ok((source_and_dates, data_sources)) => {
+        Ok((source_and_dates, data_sources, scenarios)) => {
             if data_sources.is_empty() {
                 return Err(sqlx::Error::Decode(Box::new(Error {
                     message: format!(
@@ -49,13 +160,25 @@ async fn get_map_visualization_model(
                     ),
                 })));
             }
-            Ok(Json::new(map_visualization, source_and_dates, data_sources))
+            let slice = slice.or_else(|| map_visualization.default_slice(&source_and_dates));
+            let breaks = breaks(&map_visualization, slice, compute_breaks, app_state).await?;
+            Ok(Json::new(
+                map_visualization,
+                source_and_dates,
+                data_sources,
+                scenarios,
+                breaks,
+            ))
         }
     }
 }
 
 #[get("/map-visualization/{id}")]
-async fn get(app_state: web::Data<AppState<'_>>, id: web::Path<i32>) -> impl Responder {
+async fn get(
+    app_state: web::Data<AppState<'_>>,
+    id: web::Path<i32>,
+    slice: Option<web::Query<SourceAndDate>>,
+) -> impl Responder {
     let map_visualization = app_state
         .database
         .map_visualization
@@ -67,8 +190,13 @@ async fn get(app_state: web::Data<AppState<'_>>, id: web::Path<i32>) -> impl Res
             HttpResponse::InternalServerError().finish()
         }
         Ok(map_visualization) => {
-            let map_visualization_model =
-                get_map_visualization_model(map_visualization, &app_state).await;
+            let map_visualization_model = get_map_visualization_model(
+                map_visualization,
+                slice.map(|slice| slice.into_inner()),
+                true,
+                &app_state,
+            )
+            .await;
             match map_visualization_model {
                 Err(e) => {
                     error!("map vis model: {}", e);
@@ -110,7 +238,7 @@ async fn by_tab(
     for map_visualization in map_visualizations {
         let data_tab = map_visualization.data_tab;
         let map_visualization_model =
-            get_map_visualization_model(map_visualization, app_state).await?;
+            get_map_visualization_model(map_visualization, None, false, app_state).await?;
         let map_visualizations_for_category = map_visualizations_by_category
             .entry(data_tab.unwrap_or(-1)) // store uncategorized map visualizations in category -1
             .or_insert_with(HashMap::new);
@@ -147,59 +275,89 @@ async fn get_all(
     }
 }
 
+/// Updates a map visualization. The delta, bivariate and classification settings left out of the
+/// patch keep their stored values.
 #[patch("/map-visualization")]
-async fn patch(patch: web::Json<JsonPatch>, app_state: web::Data<AppState<'_>>) -> impl Responder {
-    let result = app_state
+async fn patch(
+    Curator(user): Curator,
+    patch: web::Json<JsonPatch>,
+    app_state: web::Data<AppState<'_>>,
+) -> Result<HttpResponse, validation::Error> {
+    let patch = patch.into_inner();
+    let stored = match app_state
         .database
         .map_visualization
-        .update(&Patch::new(patch.into_inner()))
-        .await;
-    match result {
-        Err(_) => HttpResponse::InternalServerError().finish(),
-        Ok(_) => HttpResponse::Ok().finish(),
+        .settings(patch.id)
+        .await?
+    {
+        None => return Ok(HttpResponse::NotFound().finish()),
+        Some(stored) => stored,
+    };
+    let patch = Patch::new(patch, stored);
+    validate(&app_state, &patch).await?;
+    let revision = app_state
+        .database
+        .map_visualization
+        .update(&patch, &user)
+        .await?;
+    if revision.is_none() {
+        return Ok(HttpResponse::NotFound().finish());
     }
+    Ok(HttpResponse::Ok().finish())
 }
 
+/// Creates a map visualization from its settings, and returns it
 #[post("/map-visualization")]
-async fn create(app_state: web::Data<AppState<'_>>) -> impl Responder {
-    let dataset = app_state.database.dataset.first().await;
-    match dataset {
-        Err(e) => {
-            error!("{}", e);
-            HttpResponse::InternalServerError().finish()
-        }
-        Ok(dataset) => {
-            let result = app_state
-                .database
-                .map_visualization
-                .create(&Creator {
-                    dataset: dataset.id,
-                    map_type: 1,
-                    color_palette: 1,
-                    scale_type: 2,
-                    formatter_type: 3,
-                })
-                .await;
-            match result {
-                Err(e) => {
-                    error!("{}", e);
-                    HttpResponse::InternalServerError().finish()
-                }
-                Ok(_) => HttpResponse::Ok().finish(),
-            }
-        }
-    }
+async fn create(
+    Curator(user): Curator,
+    creator: web::Json<Creator>,
+    app_state: web::Data<AppState<'_>>,
+) -> Result<HttpResponse, validation::Error> {
+    validate(&app_state, &creator.patch()).await?;
+    let id = app_state
+        .database
+        .map_visualization
+        .create(&creator, &user)
+        .await?;
+    let map_visualization = app_state.database.map_visualization.get(id).await?;
+    Ok(HttpResponse::Ok().json(map_visualization))
 }
 
-#[delete("/map-visualization/{id}")]
-async fn delete(id: web::Path<i32>, app_state: web::Data<AppState<'_>>) -> impl Responder {
-    let result = app_state
+/// Copies a map visualization to make a variant of it, and returns the copy. The settings are
+/// checked first, since the data they refer to may have changed since they were saved.
+#[post("/map-visualization/{id}/clone")]
+async fn duplicate(
+    Curator(user): Curator,
+    id: web::Path<i32>,
+    app_state: web::Data<AppState<'_>>,
+) -> Result<HttpResponse, validation::Error> {
+    let id = id.into_inner();
+    let settings = match app_state.database.map_visualization.settings(id).await? {
+        None => return Ok(HttpResponse::NotFound().finish()),
+        Some(settings) => settings,
+    };
+    validate(&app_state, &settings).await?;
+    let copy = app_state
         .database
         .map_visualization
-        .delete(id.into_inner())
-        .await;
-    match result {
-        Err(_) => HttpResponse::InternalServerError().finish(),
-        Ok(_) => HttpResponse::Ok().finish(),
-    }
+        .duplicate(id, &user)
+        .await?;
+    let copy = match copy {
+        None => return Ok(HttpResponse::NotFound().finish()),
+        Some(copy) => copy,
+    };
+    let map_visualization = app_state.database.map_visualization.get(copy).await?;
+    Ok(HttpResponse::Ok().json(map_visualization))
+}
+
+/// Puts a map visualization in the trash. See [`trash_controller::delete`] for deleting it for
+/// good.
+#[delete("/map-visualization/{id}")]
+async fn delete(
+    Curator(user): Curator,
+    id: web::Path<i32>,
+    options: web::Query<Options>,
+    app_state: web::Data<AppState<'_>>,
+) -> Result<HttpResponse, trash_controller::Error> {
+    trash_controller::delete(&app_state, &user, Kind::MapVisualization, *id, &options).await
 }
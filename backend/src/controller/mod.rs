@@ -1,17 +1,69 @@
 use super::AppState;
+use actix_web::web;
 
+pub mod aggregation_controller;
+pub mod audit_controller;
+pub mod auth;
 pub mod color_palette_controller;
+pub mod correlation_controller;
 pub mod county_controller;
-pub mod csv_converter;
+pub mod crosswalk_controller;
+pub mod custom_region_controller;
 pub mod data_category_controller;
 pub mod data_controller;
 pub mod data_source_controller;
 pub mod dataset_controller;
+pub mod derivation;
+pub mod export_controller;
+pub mod format;
+pub mod geo_boundary_controller;
 pub mod geo_id_controller;
 pub mod geography_type_controller;
+pub mod geojson_converter;
+pub mod geopackage_converter;
+pub mod lookup_controller;
 pub mod map_visualization_collection_controller;
 pub mod map_visualization_controller;
+pub mod neighbor_controller;
+pub mod order;
+pub mod publication_controller;
+pub mod revision_controller;
 pub mod scale_type_controller;
+pub mod scenario_controller;
 pub mod state_controller;
+pub mod statistic_controller;
+pub mod stats_controller;
 pub mod subcategory_controller;
+pub mod tile_controller;
+pub mod trash_controller;
 pub mod uploader_controller;
+pub mod user_controller;
+pub mod validation;
+
+/// The read-only server's endpoints. The editor serves them too, over the drafts, to preview
+/// unpublished changes.
+pub fn init(cfg: &mut web::ServiceConfig) {
+    cfg.configure(state_controller::init);
+    cfg.configure(county_controller::init);
+    cfg.configure(data_controller::init);
+    cfg.configure(dataset_controller::init);
+    cfg.configure(map_visualization_controller::init);
+    cfg.configure(data_category_controller::init);
+    cfg.configure(color_palette_controller::init);
+    cfg.configure(scale_type_controller::init);
+    cfg.configure(scenario_controller::init);
+    cfg.configure(statistic_controller::init);
+    cfg.configure(subcategory_controller::init);
+    cfg.configure(data_source_controller::init);
+    cfg.configure(geography_type_controller::init);
+    cfg.configure(geo_id_controller::init);
+    cfg.configure(tile_controller::init);
+    cfg.configure(export_controller::init);
+    cfg.configure(aggregation_controller::init);
+    cfg.configure(crosswalk_controller::init);
+    cfg.configure(correlation_controller::init);
+    cfg.configure(custom_region_controller::init);
+    cfg.configure(lookup_controller::init);
+    cfg.configure(neighbor_controller::init);
+    cfg.configure(stats_controller::init);
+}
@@ -0,0 +1,198 @@
+use super::AppState;
+use crate::controller::auth::Curator;
+use crate::controller::derivation::Error;
+use crate::controller::format;
+use crate::model::data::{Simple, SourceAndDate};
+use crate::model::geo_id::GeoId;
+use crate::model::neighbor::{self, Neighbor};
+use actix_web::{delete, get, post, web, HttpRequest, HttpResponse, Responder};
+use futures::future::try_join;
+use log::error;
+use serde::Deserialize;
+use std::collections::HashSet;
+
+pub fn init(cfg: &mut web::ServiceConfig) {
+    cfg.service(get_neighbors);
+    cfg.service(get_neighbor_averages);
+    cfg.service(get_morans_i);
+    cfg.service(get_hot_spots);
+}
+
+pub fn init_editor(cfg: &mut web::ServiceConfig) {
+    cfg.service(generate);
+    cfg.service(import);
+    cfg.service(delete);
+}
+
+#[derive(Deserialize)]
+struct NeighborInfo {
+    geography_type: i32,
+}
+
+async fn slice_with_neighbors(
+    app_state: &web::Data<AppState<'_>>,
+    dataset: i32,
+    slice: &SourceAndDate,
+) -> Result<(Vec<Simple>, Vec<Neighbor>), sqlx::Error> {
+    try_join(
+        app_state.database.data.by_dataset(dataset, slice),
+        app_state.database.neighbor.by_dataset(dataset),
+    )
+    .await
+}
+
+#[get("/geo-id/{id}/neighbors")]
+async fn get_neighbors(
+    request: HttpRequest,
+    id: web::Path<i64>,
+    info: web::Query<NeighborInfo>,
+    app_state: web::Data<AppState<'_>>,
+) -> impl Responder {
+    let neighbors = app_state
+        .database
+        .neighbor
+        .by_geo_id(info.geography_type, id.into_inner())
+        .await;
+
+    match neighbors {
+        Err(e) => {
+            error!("Error getting neighbors: {}", e);
+            HttpResponse::InternalServerError().finish()
+        }
+        Ok(neighbors) => format::respond(&request, neighbors),
+    }
+}
+
+#[get("/data/{dataset}/neighbor-average")]
+async fn get_neighbor_averages(
+    request: HttpRequest,
+    dataset: web::Path<i32>,
+    info: web::Query<SourceAndDate>,
+    app_state: web::Data<AppState<'_>>,
+) -> impl Responder {
+    match slice_with_neighbors(&app_state, dataset.into_inner(), &info).await {
+        Err(e) => {
+            error!("Error getting neighbor averages: {}", e);
+            HttpResponse::InternalServerError().finish()
+        }
+        Ok((values, neighbors)) => {
+            format::respond(&request, neighbor::neighbor_averages(&values, &neighbors))
+        }
+    }
+}
+
+/// Global spatial autocorrelation of a slice, or null if it is undefined
+#[get("/data/{dataset}/morans-i")]
+async fn get_morans_i(
+    dataset: web::Path<i32>,
+    info: web::Query<SourceAndDate>,
+    app_state: web::Data<AppState<'_>>,
+) -> impl Responder {
+    match slice_with_neighbors(&app_state, dataset.into_inner(), &info).await {
+        Err(e) => {
+            error!("Error getting Moran's I: {}", e);
+            HttpResponse::InternalServerError().finish()
+        }
+        Ok((values, neighbors)) => HttpResponse::Ok().json(neighbor::morans_i(&values, &neighbors)),
+    }
+}
+
+#[get("/data/{dataset}/hot-spots")]
+async fn get_hot_spots(
+    request: HttpRequest,
+    dataset: web::Path<i32>,
+    info: web::Query<SourceAndDate>,
+    app_state: web::Data<AppState<'_>>,
+) -> impl Responder {
+    match slice_with_neighbors(&app_state, dataset.into_inner(), &info).await {
+        Err(e) => {
+            error!("Error getting hot spots: {}", e);
+            HttpResponse::InternalServerError().finish()
+        }
+        Ok((values, neighbors)) => {
+            format::respond(&request, neighbor::getis_ord(&values, &neighbors))
+        }
+    }
+}
+
+/// Derives neighbors from the uploaded boundaries of a geography type
+#[post("/geography-type/{id}/neighbors/generate")]
+async fn generate(
+    Curator(user): Curator,
+    id: web::Path<i32>,
+    app_state: web::Data<AppState<'_>>,
+) -> impl Responder {
+    let result = app_state
+        .database
+        .neighbor
+        .generate(id.into_inner(), &user)
+        .await;
+
+    match result {
+        Err(e) => {
+            error!("Error generating neighbors: {}", e);
+            HttpResponse::InternalServerError().finish()
+        }
+        Ok(result) => {
+            HttpResponse::Ok().body(format!("linked {} neighbors", result.rows_affected()))
+        }
+    }
+}
+
+/// Imports an adjacency list, like one published with a census geography
+#[post("/geography-type/{id}/neighbors")]
+async fn import(
+    Curator(user): Curator,
+    id: web::Path<i32>,
+    neighbors: web::Json<Vec<Neighbor>>,
+    app_state: web::Data<AppState<'_>>,
+) -> Result<HttpResponse, Error> {
+    let geography_type = id.into_inner();
+    let geo_ids: HashSet<GeoId> = neighbors
+        .iter()
+        .flat_map(|neighbor| {
+            [
+                GeoId {
+                    id: neighbor.id,
+                    geography_type,
+                },
+                GeoId {
+                    id: neighbor.neighbor_id,
+                    geography_type,
+                },
+            ]
+        })
+        .collect();
+    let invalid_ids = app_state.database.geo_id.get_invalid_ids(&geo_ids).await?;
+    if !invalid_ids.is_empty() {
+        return Err(Error::InvalidGeoIds(invalid_ids));
+    }
+
+    let result = app_state
+        .database
+        .neighbor
+        .insert(geography_type, &neighbors, &user)
+        .await?;
+    Ok(HttpResponse::Ok().body(format!("linked {} neighbors", result.rows_affected())))
+}
+
+#[delete("/geography-type/{id}/neighbors")]
+async fn delete(
+    Curator(user): Curator,
+    id: web::Path<i32>,
+    app_state: web::Data<AppState<'_>>,
+) -> impl Responder {
+    let result = app_state
+        .database
+        .neighbor
+        .delete_by_geography_type(id.into_inner(), &user)
+        .await;
+
+    match result {
+        Err(e) => {
+            error!("Error deleting neighbors: {}", e);
+            HttpResponse::InternalServerError().finish()
+        }
+        Ok(_) => HttpResponse::Ok().finish(),
+    }
+}
@@ -0,0 +1,45 @@
+use crate::model::order::Mismatch;
+use actix_web::{http::StatusCode, HttpResponse};
+use derive_more::Display;
+use log::error;
+use serde::Serialize;
+
+/// Errors reordering categories, subcategories or the map visualizations of a category
+#[derive(Debug, Display, Serialize)]
+#[serde(tag = "name", content = "info")]
+pub enum Error {
+    #[display(fmt = "The new order must list every id once: {_0:?}")]
+    Mismatch(Mismatch),
+    Internal(String),
+}
+
+impl std::error::Error for Error {}
+
+impl From<Mismatch> for Error {
+    fn from(mismatch: Mismatch) -> Self {
+        Error::Mismatch(mismatch)
+    }
+}
+
+impl From<sqlx::Error> for Error {
+    fn from(error: sqlx::Error) -> Self {
+        Error::Internal(error.to_string())
+    }
+}
+
+impl actix_web::error::ResponseError for Error {
+    fn status_code(&self) -> StatusCode {
+        match self {
+            Error::Internal(_) => StatusCode::INTERNAL_SERVER_ERROR,
+            _ => StatusCode::BAD_REQUEST,
+        }
+    }
+
+    fn error_response(&self) -> HttpResponse {
+        if let Error::Internal(e) = self {
+            error!("Error reordering: {}", e);
+            return HttpResponse::build(self.status_code()).finish();
+        }
+        HttpResponse::build(self.status_code()).json(self)
+    }
+}
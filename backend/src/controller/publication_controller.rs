@@ -0,0 +1,50 @@
+use super::AppState;
+use crate::controller::auth::{Curator, Viewer};
+use actix_web::{get, post, web, HttpResponse, Responder};
+use log::error;
+
+pub fn init_editor(cfg: &mut web::ServiceConfig) {
+    cfg.service(get_changes);
+    cfg.service(publish);
+    cfg.service(revert);
+}
+
+/// The map visualizations, data categories and collections with unpublished changes
+#[get("/publish")]
+async fn get_changes(_: Viewer, app_state: web::Data<AppState<'_>>) -> impl Responder {
+    let changes = app_state.database.publication.changes().await;
+
+    match changes {
+        Err(e) => {
+            error!("Error getting unpublished changes: {}", e);
+            HttpResponse::InternalServerError().finish()
+        }
+        Ok(changes) => HttpResponse::Ok().json(changes),
+    }
+}
+
+#[post("/publish")]
+async fn publish(Curator(user): Curator, app_state: web::Data<AppState<'_>>) -> impl Responder {
+    let changes = app_state.database.publication.publish(&user).await;
+
+    match changes {
+        Err(e) => {
+            error!("Error publishing: {}", e);
+            HttpResponse::InternalServerError().finish()
+        }
+        Ok(changes) => HttpResponse::Ok().json(changes),
+    }
+}
+
+#[post("/revert")]
+async fn revert(Curator(user): Curator, app_state: web::Data<AppState<'_>>) -> impl Responder {
+    let changes = app_state.database.publication.revert(&user).await;
+
+    match changes {
+        Err(e) => {
+            error!("Error reverting drafts: {}", e);
+            HttpResponse::InternalServerError().finish()
+        }
+        Ok(changes) => HttpResponse::Ok().json(changes),
+    }
+}
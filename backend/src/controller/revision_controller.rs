@@ -0,0 +1,108 @@
+use super::AppState;
+use crate::controller::auth::{Curator, Viewer};
+use crate::controller::validation::{self, validate};
+use crate::model::revision::{self, DiffInfo};
+use actix_web::{get, post, web, HttpResponse, Responder};
+use futures::future::try_join;
+use log::error;
+
+pub fn init_editor(cfg: &mut web::ServiceConfig) {
+    cfg.service(get_revisions);
+    cfg.service(get_revision);
+    cfg.service(get_diff);
+    cfg.service(restore);
+}
+
+/// A map visualization's revisions, newest first, with the fields each one changed
+#[get("/map-visualization/{id}/revision")]
+async fn get_revisions(
+    _: Viewer,
+    id: web::Path<i32>,
+    app_state: web::Data<AppState<'_>>,
+) -> impl Responder {
+    let revisions = app_state
+        .database
+        .revision
+        .by_map_visualization(id.into_inner())
+        .await;
+
+    match revisions {
+        Err(e) => {
+            error!("Error getting revisions: {}", e);
+            HttpResponse::InternalServerError().finish()
+        }
+        Ok(revisions) if revisions.is_empty() => HttpResponse::NotFound().finish(),
+        Ok(revisions) => HttpResponse::Ok().json(revision::summaries(&revisions)),
+    }
+}
+
+#[get("/map-visualization/{id}/revision/{revision}")]
+async fn get_revision(
+    _: Viewer,
+    path: web::Path<(i32, i32)>,
+    app_state: web::Data<AppState<'_>>,
+) -> impl Responder {
+    let (id, revision) = path.into_inner();
+    let revision = app_state.database.revision.get(id, Some(revision)).await;
+
+    match revision {
+        Err(e) => {
+            error!("Error getting revision: {}", e);
+            HttpResponse::InternalServerError().finish()
+        }
+        Ok(None) => HttpResponse::NotFound().finish(),
+        Ok(Some(revision)) => HttpResponse::Ok().json(revision),
+    }
+}
+
+/// The fields that changed between two revisions
+#[get("/map-visualization/{id}/diff")]
+async fn get_diff(
+    _: Viewer,
+    id: web::Path<i32>,
+    info: web::Query<DiffInfo>,
+    app_state: web::Data<AppState<'_>>,
+) -> impl Responder {
+    let id = id.into_inner();
+    let revisions = try_join(
+        app_state.database.revision.get(id, Some(info.from)),
+        app_state.database.revision.get(id, info.to),
+    )
+    .await;
+
+    match revisions {
+        Err(e) => {
+            error!("Error getting revisions: {}", e);
+            HttpResponse::InternalServerError().finish()
+        }
+        Ok((Some(from), Some(to))) => {
+            HttpResponse::Ok().json(revision::diff(&from.settings, &to.settings))
+        }
+        Ok(_) => HttpResponse::NotFound().finish(),
+    }
+}
+
+/// Sets a map visualization back to a revision, which becomes its newest revision. The restored
+/// settings are checked like any other edit, since the data may have changed since.
+#[post("/map-visualization/{id}/revision/{revision}/restore")]
+async fn restore(
+    Curator(user): Curator,
+    path: web::Path<(i32, i32)>,
+    app_state: web::Data<AppState<'_>>,
+) -> Result<HttpResponse, validation::Error> {
+    let (id, revision) = path.into_inner();
+    let restored = match app_state.database.revision.restored(id, revision).await? {
+        None => return Ok(HttpResponse::NotFound().finish()),
+        Some(restored) => restored,
+    };
+    validate(&app_state, &restored).await?;
+    let revision = app_state
+        .database
+        .map_visualization
+        .update(&restored, &user)
+        .await?;
+    match revision {
+        None => Ok(HttpResponse::NotFound().finish()),
+        Some(revision) => Ok(HttpResponse::Ok().json(revision)),
+    }
+}
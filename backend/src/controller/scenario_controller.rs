@@ -0,0 +1,16 @@
+use super::AppState;
+use actix_web::{get, web, HttpResponse, Responder};
+
+pub fn init(cfg: &mut web::ServiceConfig) {
+    cfg.service(get_all);
+}
+
+#[get("/scenario")]
+async fn get_all(app_state: web::Data<AppState<'_>>) -> impl Responder {
+    let scenarios = app_state.database.scenario.all().await;
+
+    match scenarios {
+        Err(_) => HttpResponse::NotFound().finish(),
+        Ok(scenarios) => HttpResponse::Ok().json(scenarios),
+    }
+}
@@ -0,0 +1,16 @@
+use super::AppState;
+use actix_web::{get, web, HttpResponse, Responder};
+
+pub fn init(cfg: &mut web::ServiceConfig) {
+    cfg.service(get_all);
+}
+
+#[get("/statistic")]
+async fn get_all(app_state: web::Data<AppState<'_>>) -> impl Responder {
+    let statistics = app_state.database.statistic.all().await;
+
+    match statistics {
+        Err(_) => HttpResponse::NotFound().finish(),
+        Ok(statistics) => HttpResponse::Ok().json(statistics),
+    }
+}
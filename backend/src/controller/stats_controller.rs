@@ -0,0 +1,138 @@
+use super::AppState;
+use crate::model::classification::{self, Method};
+use crate::model::data::SourceAndDate;
+use crate::model::stats::{self, Stats};
+use crate::model::validation::MAX_CLASSES;
+use crate::model::{scenario, statistic};
+use actix_web::{get, web, HttpResponse, Responder};
+use chrono::NaiveDate;
+use futures::future::try_join;
+use log::error;
+use serde::{Deserialize, Serialize};
+
+/// The most histogram bins a request can ask for
+const MAX_BINS: usize = 1000;
+
+pub fn init(cfg: &mut web::ServiceConfig) {
+    cfg.service(get_stats);
+}
+
+fn default_quantiles() -> String {
+    "0.05,0.25,0.5,0.75,0.95".to_string()
+}
+
+fn default_bins() -> usize {
+    20
+}
+
+fn default_classes() -> usize {
+    5
+}
+
+#[derive(Deserialize)]
+struct StatsInfo {
+    source: i32,
+    #[serde(default = "scenario::observed")]
+    scenario: i32,
+    #[serde(default = "statistic::mean")]
+    statistic: i32,
+    start_date: NaiveDate,
+    end_date: NaiveDate,
+    /// Comma separated quantiles between 0 and 1
+    #[serde(default = "default_quantiles")]
+    quantiles: String,
+    #[serde(default = "default_bins")]
+    bins: usize,
+    /// Suggests domains for this scale type when set
+    scale_type: Option<i32>,
+    method: Option<Method>,
+    #[serde(default = "default_classes")]
+    classes: usize,
+}
+
+/// Domains for a map visualization, from classes of the slice
+#[derive(Serialize)]
+struct Suggestion {
+    method: Method,
+    breaks: Vec<f64>,
+    color_domain: Vec<f64>,
+    pdf_domain: Vec<f64>,
+}
+
+#[derive(Serialize)]
+struct Response {
+    #[serde(flatten)]
+    stats: Stats,
+    suggestion: Option<Suggestion>,
+}
+
+fn parse_quantiles(quantiles: &str) -> Option<Vec<f64>> {
+    quantiles
+        .split(',')
+        .map(|q| {
+            q.trim()
+                .parse::<f64>()
+                .ok()
+                .filter(|q| (0.0..=1.0).contains(q))
+        })
+        .collect()
+}
+
+#[get("/data/{dataset}/stats")]
+async fn get_stats(
+    dataset: web::Path<i32>,
+    info: web::Query<StatsInfo>,
+    app_state: web::Data<AppState<'_>>,
+) -> impl Responder {
+    let quantiles = match parse_quantiles(&info.quantiles) {
+        None => return HttpResponse::BadRequest().body("quantiles must be between 0 and 1"),
+        Some(quantiles) => quantiles,
+    };
+    let dataset = dataset.into_inner();
+    let result = try_join(
+        app_state.database.data.by_dataset(
+            dataset,
+            &SourceAndDate {
+                source: info.source,
+                scenario: info.scenario,
+                statistic: info.statistic,
+                start_date: info.start_date,
+                end_date: info.end_date,
+            },
+        ),
+        app_state.database.geo_id.count_by_dataset(dataset),
+    )
+    .await;
+
+    match result {
+        Err(e) => {
+            error!("Error getting stats: {}", e);
+            HttpResponse::InternalServerError().finish()
+        }
+        Ok((data, geo_ids)) => {
+            let values: Vec<f64> = data.iter().map(|row| row.value).collect();
+            let stats = stats::summarize(
+                &values,
+                geo_ids as usize,
+                &quantiles,
+                info.bins.min(MAX_BINS),
+            );
+            let suggestion = info.scale_type.and_then(|scale_type| {
+                let method = info.method.unwrap_or(Method::Quantile);
+                let breaks =
+                    classification::breaks(method, &values, info.classes.min(MAX_CLASSES as usize));
+                Some(Suggestion {
+                    method,
+                    color_domain: classification::suggest_domain(
+                        scale_type,
+                        &breaks,
+                        stats.median?,
+                    ),
+                    pdf_domain: vec![stats.min?, stats.max?],
+                    breaks,
+                })
+            });
+            HttpResponse::Ok().json(Response { stats, suggestion })
+        }
+    }
+}
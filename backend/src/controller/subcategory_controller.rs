@@ -1,10 +1,16 @@
 use super::AppState;
-use actix_web::{get, web, HttpResponse, Responder};
+use crate::controller::auth::Curator;
+use crate::controller::order::Error;
+use actix_web::{get, post, web, HttpResponse, Responder};
 
 pub fn init(cfg: &mut web::ServiceConfig) {
     cfg.service(get_all);
 }
 
+pub fn init_editor(cfg: &mut web::ServiceConfig) {
+    cfg.service(reorder);
+}
+
 #[get("/subcategory")]
 async fn get_all(app_state: web::Data<AppState<'_>>) -> impl Responder {
     let subcategories = app_state.database.subcategory.all().await;
@@ -14,3 +20,18 @@ async fn get_all(app_state: web::Data<AppState<'_>>) -> impl Responder {
         Ok(subcategories) => HttpResponse::Ok().json(subcategories),
     }
 }
+
+/// Renumbers all the subcategories in the order of their ids
+#[post("/subcategory/reorder")]
+async fn reorder(
+    Curator(user): Curator,
+    app_state: web::Data<AppState<'_>>,
+    ids: web::Json<Vec<i32>>,
+) -> Result<HttpResponse, Error> {
+    app_state
+        .database
+        .subcategory
+        .reorder(&ids, &user)
+        .await??;
+    Ok(HttpResponse::Ok().finish())
+}
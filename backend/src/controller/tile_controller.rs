@@ -0,0 +1,122 @@
+use super::AppState;
+use crate::model::data::SourceAndDate;
+use crate::model::delta::{self, Delta};
+use crate::model::tile::Coordinates;
+use actix_web::{get, web, HttpResponse, Responder};
+use log::error;
+
+pub fn init(cfg: &mut web::ServiceConfig) {
+    cfg.service(get_tile);
+}
+
+/// A tile of the map visualization's slice in the query parameters. Delta map visualizations
+/// show their change instead, and only take the `statistic` parameter.
+#[get("/map-visualization/{map_visualization}/tiles/{z}/{x}/{y}.mvt")]
+async fn get_tile(
+    path: web::Path<(i32, i32, i32, i32)>,
+    info: Option<web::Query<SourceAndDate>>,
+    delta_info: web::Query<delta::Info>,
+    app_state: web::Data<AppState<'_>>,
+) -> impl Responder {
+    let (map_visualization, z, x, y) = path.into_inner();
+    let coordinates = Coordinates { z, x, y };
+    if !coordinates.is_valid() {
+        return HttpResponse::BadRequest().finish();
+    }
+
+    let delta = match app_state
+        .database
+        .map_visualization
+        .get(map_visualization)
+        .await
+    {
+        Err(sqlx::Error::RowNotFound) => return HttpResponse::NotFound().finish(),
+        Err(e) => {
+            error!("Error generating tile: {}", e);
+            return HttpResponse::InternalServerError().finish();
+        }
+        Ok(map_visualization) => map_visualization
+            .delta()
+            .map(|delta| delta.with_statistic(delta_info.statistic)),
+    };
+    match (delta, info) {
+        (Some(delta), _) => delta_tile(&app_state, map_visualization, &delta, &coordinates).await,
+        (None, Some(info)) => tile(&app_state, map_visualization, &info, &coordinates).await,
+        (None, None) => HttpResponse::BadRequest().finish(),
+    }
+}
+
+async fn tile(
+    app_state: &AppState<'_>,
+    map_visualization: i32,
+    source_and_date: &SourceAndDate,
+    coordinates: &Coordinates,
+) -> HttpResponse {
+    let tiles = &app_state.database.tile;
+    if let Ok(Some(cached)) = tiles
+        .cached(map_visualization, source_and_date, coordinates)
+        .await
+    {
+        return mvt_response(cached.tile);
+    }
+    let tile = tiles
+        .generate(map_visualization, source_and_date, coordinates, None)
+        .await;
+    match tile {
+        Err(sqlx::Error::RowNotFound) => HttpResponse::NotFound().finish(),
+        Err(e) => {
+            error!("Error generating tile: {}", e);
+            HttpResponse::InternalServerError().finish()
+        }
+        Ok(tile) => {
+            let stored = tiles
+                .store(map_visualization, source_and_date, coordinates, &tile)
+                .await;
+            if let Err(e) = stored {
+                error!("Error caching tile: {}", e);
+            }
+            mvt_response(tile.tile)
+        }
+    }
+}
+
+/// Tiles of delta map visualizations are cached by both slices they compare
+async fn delta_tile(
+    app_state: &AppState<'_>,
+    map_visualization: i32,
+    delta: &Delta,
+    coordinates: &Coordinates,
+) -> HttpResponse {
+    let tiles = &app_state.database.tile;
+    if let Ok(Some(cached)) = tiles
+        .cached_delta(map_visualization, delta, coordinates)
+        .await
+    {
+        return mvt_response(cached.tile);
+    }
+    let tile = tiles
+        .generate(map_visualization, &delta.to, coordinates, Some(delta))
+        .await;
+    match tile {
+        Err(sqlx::Error::RowNotFound) => HttpResponse::NotFound().finish(),
+        Err(e) => {
+            error!("Error generating tile: {}", e);
+            HttpResponse::InternalServerError().finish()
+        }
+        Ok(tile) => {
+            let stored = tiles
+                .store_delta(map_visualization, delta, coordinates, &tile)
+                .await;
+            if let Err(e) = stored {
+                error!("Error caching tile: {}", e);
+            }
+            mvt_response(tile.tile)
+        }
+    }
+}
+
+fn mvt_response(tile: Vec<u8>) -> HttpResponse {
+    HttpResponse::Ok()
+        .content_type("application/vnd.mapbox-vector-tile")
+        .body(tile)
+}
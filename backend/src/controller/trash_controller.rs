@@ -0,0 +1,200 @@
+use super::AppState;
+use crate::controller::auth::{Admin, Curator, Viewer};
+use crate::model::deletion::{Conflict, Impact, Options};
+use crate::model::trash::{Item, Kind, Purge, Purged};
+use crate::model::user::User;
+use actix_web::{get, http::StatusCode, post, web, HttpResponse};
+use chrono::{DateTime, Utc};
+use derive_more::Display;
+use log::error;
+use serde::Serialize;
+
+pub fn init_editor(cfg: &mut web::ServiceConfig) {
+    cfg.service(get_all);
+    cfg.service(restore);
+    cfg.service(purge);
+}
+
+#[derive(Debug, Display, Serialize)]
+#[serde(tag = "name", content = "info")]
+pub enum Error {
+    #[display(fmt = "Can't remove it for good: {_0:?}")]
+    Conflict(Conflict),
+    Internal(String),
+}
+
+impl From<Conflict> for Error {
+    fn from(conflict: Conflict) -> Self {
+        Error::Conflict(conflict)
+    }
+}
+
+impl From<sqlx::Error> for Error {
+    fn from(error: sqlx::Error) -> Self {
+        Error::Internal(error.to_string())
+    }
+}
+
+impl actix_web::error::ResponseError for Error {
+    fn status_code(&self) -> StatusCode {
+        match self {
+            Error::Conflict(_) => StatusCode::CONFLICT,
+            Error::Internal(_) => StatusCode::INTERNAL_SERVER_ERROR,
+        }
+    }
+
+    fn error_response(&self) -> HttpResponse {
+        if let Error::Internal(e) = self {
+            error!("Error deleting: {}", e);
+            return HttpResponse::build(self.status_code()).finish();
+        }
+        HttpResponse::build(self.status_code()).json(self)
+    }
+}
+
+async fn put_in_trash(
+    app_state: &AppState<'_>,
+    user: &User,
+    kind: Kind,
+    id: i32,
+) -> Result<Option<Item>, sqlx::Error> {
+    let database = &app_state.database;
+    match kind {
+        Kind::Dataset => database.dataset.trash(id, user).await,
+        Kind::DataSource => database.data_source.trash(id, user).await,
+        Kind::MapVisualization => database.map_visualization.trash(id, user).await,
+        Kind::DataCategory => database.data_category.trash(id, user).await,
+    }
+}
+
+async fn take_out_of_trash(
+    app_state: &AppState<'_>,
+    user: &User,
+    kind: Kind,
+    id: i32,
+) -> Result<Option<Item>, sqlx::Error> {
+    let database = &app_state.database;
+    match kind {
+        Kind::Dataset => database.dataset.restore(id, user).await,
+        Kind::DataSource => database.data_source.restore(id, user).await,
+        Kind::MapVisualization => database.map_visualization.restore(id, user).await,
+        Kind::DataCategory => database.data_category.restore(id, user).await,
+    }
+}
+
+/// Removes something for good along with what depends on it, or with `dry_run` only finds them.
+/// With `trashed_before`, only if it's still in the trash from before then.
+async fn remove(
+    app_state: &AppState<'_>,
+    user: &User,
+    kind: Kind,
+    id: i32,
+    (dry_run, with_data): (bool, bool),
+    trashed_before: Option<DateTime<Utc>>,
+) -> Result<Option<Result<Impact, Conflict>>, sqlx::Error> {
+    let deletion = &app_state.database.deletion;
+    let impact = match kind {
+        Kind::Dataset => deletion.dataset(id, dry_run, trashed_before, user).await,
+        Kind::DataSource => {
+            return deletion
+                .data_source(id, dry_run, with_data, trashed_before, user)
+                .await
+        }
+        Kind::MapVisualization => {
+            deletion
+                .map_visualization(id, dry_run, trashed_before, user)
+                .await
+        }
+        Kind::DataCategory => {
+            deletion
+                .data_category(id, dry_run, trashed_before, user)
+                .await
+        }
+    };
+    Ok(impact?.map(Ok))
+}
+
+/// Everything in the trash that was deleted before a time, oldest first
+async fn trashed(
+    app_state: &AppState<'_>,
+    before: DateTime<Utc>,
+) -> Result<Vec<Item>, sqlx::Error> {
+    let database = &app_state.database;
+    let mut items = database.dataset.trashed(before).await?;
+    items.extend(database.data_source.trashed(before).await?);
+    items.extend(database.map_visualization.trashed(before).await?);
+    items.extend(database.data_category.trashed(before).await?);
+    items.sort_by_key(|item| item.deleted_at);
+    Ok(items)
+}
+
+/// Deletes something for the delete endpoints. By default it goes in the trash and the response
+/// is the trash item. With `permanent=true` it's removed along with what depends on it, and with
+/// `dry_run=true` nothing is removed; both respond with what is or would be removed. A data
+/// source with data is refused with a conflict unless `with_data=true`.
+pub async fn delete(
+    app_state: &web::Data<AppState<'_>>,
+    user: &User,
+    kind: Kind,
+    id: i32,
+    options: &Options,
+) -> Result<HttpResponse, Error> {
+    if !options.permanent && !options.dry_run {
+        return match put_in_trash(app_state, user, kind, id).await? {
+            None => Ok(HttpResponse::NotFound().finish()),
+            Some(item) => Ok(HttpResponse::Ok().json(item)),
+        };
+    }
+
+    let removal = (options.dry_run, options.with_data);
+    match remove(app_state, user, kind, id, removal, None).await? {
+        None => Ok(HttpResponse::NotFound().finish()),
+        Some(impact) => Ok(HttpResponse::Ok().json(impact?)),
+    }
+}
+
+#[get("/trash")]
+async fn get_all(_: Viewer, app_state: web::Data<AppState<'_>>) -> Result<HttpResponse, Error> {
+    let mut items = trashed(&app_state, Utc::now()).await?;
+    items.reverse();
+    Ok(HttpResponse::Ok().json(items))
+}
+
+#[post("/trash/{kind}/{id}/restore")]
+async fn restore(
+    Curator(user): Curator,
+    path: web::Path<(Kind, i32)>,
+    app_state: web::Data<AppState<'_>>,
+) -> Result<HttpResponse, Error> {
+    let (kind, id) = path.into_inner();
+    match take_out_of_trash(&app_state, &user, kind, id).await? {
+        None => Ok(HttpResponse::NotFound().finish()),
+        Some(item) => Ok(HttpResponse::Ok().json(item)),
+    }
+}
+
+/// Removes for good everything deleted more than `days` ago, 30 by default, and returns what
+/// was removed. With `dry_run=true` it only returns what would be. Data sources with data stay
+/// in the trash unless `with_data=true`.
+#[post("/trash/purge")]
+async fn purge(
+    Admin(user): Admin,
+    options: web::Query<Purge>,
+    app_state: web::Data<AppState<'_>>,
+) -> Result<HttpResponse, Error> {
+    let cutoff = options.cutoff(Utc::now());
+    let items = trashed(&app_state, cutoff).await?;
+    let mut purged = Vec::new();
+    for item in items {
+        // Purging a dataset removes its map visualizations, which may be in the trash too, and
+        // anything restored since it was listed is skipped
+        let removal = (options.dry_run, options.with_data);
+        let removed = remove(&app_state, &user, item.kind, item.id, removal, Some(cutoff));
+        let impact = match removed.await? {
+            None | Some(Err(_)) => continue,
+            Some(Ok(impact)) => impact,
+        };
+        purged.push(Purged { item, impact });
+    }
+    Ok(HttpResponse::Ok().json(purged))
+}
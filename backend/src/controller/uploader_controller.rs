@@ -1,9 +1,11 @@
+use crate::controller::auth::Curator;
 use crate::model::{
     data,
     data_source::DataSource,
     dataset::{self, Dataset},
     geo_id::GeoId,
-    upload_metadata::{Source, UploadMetadata},
+    upload_metadata::{Rejected, Source, UploadMetadata},
+    validation::FieldError,
 };
 
 use super::AppState;
@@ -52,9 +54,17 @@ enum Error {
     DuplicateDataSource(DataSource),
     DataSourceIncomplete,
     DataSourceLinkInvalid(String),
+    #[display(fmt = "Data source {_0} doesn't exist or is in the trash")]
+    UnknownDataSource(i32),
     MissingMetadata,
     InvalidMetadata(String),
     MissingFile,
+    /// The settings suggested for a new dataset's map visualization don't fit its data
+    #[display(fmt = "Invalid map visualization of column {column}: {errors:#?}")]
+    InvalidMapVisualization {
+        column: String,
+        errors: Vec<FieldError>,
+    },
     Internal(String),
 }
 
@@ -190,6 +200,7 @@ fn parse_csv(file: &File, metadata: &UploadMetadata) -> Result<HashSet<data::Par
 
 #[post("/upload")]
 async fn upload(
+    Curator(user): Curator,
     mut parts: awmp::Parts,
     app_state: web::Data<AppState<'_>>,
 ) -> Result<String, Error> {
@@ -240,55 +251,42 @@ async fn upload(
         return Err(Error::InvalidGeoIds(invalid_ids));
     }
 
-    let source_id = match metadata.source {
-        Source::ExistingId(id) => id,
-        Source::New(ref new_data_source) => {
-            new_data_source
-                .link
-                .parse::<Uri>()
-                .map_err(|_| Error::DataSourceLinkInvalid(new_data_source.link.clone()))?;
-
-            if new_data_source.name.is_empty() || new_data_source.description.is_empty() {
-                return Err(Error::DataSourceIncomplete);
-            }
+    if let Source::New(ref new_data_source) = metadata.source {
+        new_data_source
+            .link
+            .parse::<Uri>()
+            .map_err(|_| Error::DataSourceLinkInvalid(new_data_source.link.clone()))?;
 
-            if let Some(data_source) = app_state
-                .database
-                .data_source
-                .by_name(&new_data_source.name)
-                .await?
-            {
-                return Err(Error::DuplicateDataSource(data_source));
-            }
-
-            app_state
-                .database
-                .data_source
-                .create(new_data_source)
-                .await?
+        if new_data_source.name.is_empty() || new_data_source.description.is_empty() {
+            return Err(Error::DataSourceIncomplete);
         }
-    };
-
-    let mut column_to_dataset: HashMap<String, Dataset> = HashMap::new();
 
-    for draft_dataset in &datasets {
-        let created_dataset = app_state.database.dataset.create(draft_dataset).await?;
-        column_to_dataset.insert(draft_dataset.column.clone(), created_dataset.clone());
+        if let Some(data_source) = app_state
+            .database
+            .data_source
+            .by_name(&new_data_source.name)
+            .await?
+        {
+            return Err(Error::DuplicateDataSource(data_source));
+        }
     }
 
-    let data = data
-        .into_iter()
-        .map(|data| {
-            column_to_dataset.get(&data.dataset).map(|dataset| {
-                data::Creator::new(&data, dataset.id, source_id, dataset.geography_type)
-            })
-        })
-        .collect::<Option<HashSet<_>>>()
-        .ok_or_else(|| Error::Internal("Could not match datasets to data".to_string()))?;
-
-    let result = app_state.database.data.insert(&data).await?;
+    let uploaded = app_state
+        .database
+        .data
+        .upload(&metadata, &data, &user)
+        .await?
+        .map_err(|rejected| match rejected {
+            Rejected::UnknownSource(id) => Error::UnknownDataSource(id),
+            Rejected::UnmatchedData => {
+                Error::Internal("Could not match datasets to data".to_string())
+            }
+            Rejected::InvalidMapVisualization { column, errors } => {
+                Error::InvalidMapVisualization { column, errors }
+            }
+        })?;
 
-    Ok(format!("inserted {} rows of data", result.rows_affected()))
+    Ok(format!("inserted {} rows of data", uploaded.data.len()))
 }
 
 #[cfg(test)]
@@ -323,6 +321,9 @@ mod tests {
             id_column: "id".to_string(),
             date_column: "date".to_string(),
             geography_type: 1,
+            scenario: 1,
+            statistic: 1,
+            map_visualizations: None,
         }
     }
 
@@ -0,0 +1,216 @@
+use super::AppState;
+use crate::controller::auth::{bearer_token, Admin, AuthError, Viewer};
+use crate::model::user::{
+    generate_token, hash_password, hash_token, verify_password, Creator, Credentials, NewToken,
+    Patch,
+};
+use actix_web::{delete, get, patch, post, web, HttpRequest, HttpResponse, Responder};
+use chrono::{Duration, Utc};
+use log::error;
+use serde::Deserialize;
+
+/// How long a token from logging in lasts
+const SESSION_HOURS: i64 = 12;
+
+pub fn init_editor(cfg: &mut web::ServiceConfig) {
+    cfg.service(login);
+    cfg.service(logout);
+    cfg.service(me);
+    cfg.service(create_token);
+    cfg.service(get_tokens);
+    cfg.service(delete_token);
+    cfg.service(get_users);
+    cfg.service(create_user);
+    cfg.service(update_user);
+    cfg.service(delete_user);
+}
+
+#[derive(Deserialize)]
+struct TokenInfo {
+    name: String,
+}
+
+fn hash(password: &str) -> Result<String, AuthError> {
+    hash_password(password).map_err(|e| AuthError::Internal(e.to_string()))
+}
+
+/// Exchanges a username and password for a session token
+#[post("/login")]
+async fn login(
+    credentials: web::Json<Credentials>,
+    app_state: web::Data<AppState<'_>>,
+) -> Result<HttpResponse, AuthError> {
+    let user = app_state
+        .database
+        .user
+        .by_username(&credentials.username)
+        .await?
+        .ok_or(AuthError::InvalidCredentials)?;
+    let verified = user
+        .password_hash
+        .as_deref()
+        .map_or(false, |hash| verify_password(&credentials.password, hash));
+    if !verified {
+        return Err(AuthError::InvalidCredentials);
+    }
+
+    if let Err(e) = app_state.database.token.delete_expired().await {
+        error!("Error deleting expired tokens: {}", e);
+    }
+    let token = generate_token();
+    let expires_at = Some(Utc::now() + Duration::hours(SESSION_HOURS));
+    let id = app_state
+        .database
+        .token
+        .create(&user.user(), "session", &hash_token(&token), expires_at)
+        .await?;
+    Ok(HttpResponse::Ok().json(NewToken {
+        id,
+        token,
+        expires_at,
+    }))
+}
+
+#[post("/logout")]
+async fn logout(
+    request: HttpRequest,
+    Viewer(user): Viewer,
+    app_state: web::Data<AppState<'_>>,
+) -> Result<HttpResponse, AuthError> {
+    let token = bearer_token(&request).ok_or(AuthError::Unauthenticated)?;
+    app_state
+        .database
+        .token
+        .delete_by_hash(&user, &hash_token(token))
+        .await?;
+    Ok(HttpResponse::Ok().finish())
+}
+
+#[get("/me")]
+async fn me(Viewer(user): Viewer) -> impl Responder {
+    HttpResponse::Ok().json(user)
+}
+
+/// Creates a token that doesn't expire, for scripts
+#[post("/token")]
+async fn create_token(
+    Viewer(user): Viewer,
+    info: web::Json<TokenInfo>,
+    app_state: web::Data<AppState<'_>>,
+) -> Result<HttpResponse, AuthError> {
+    let token = generate_token();
+    let id = app_state
+        .database
+        .token
+        .create(&user, &info.name, &hash_token(&token), None)
+        .await?;
+    Ok(HttpResponse::Ok().json(NewToken {
+        id,
+        token,
+        expires_at: None,
+    }))
+}
+
+#[get("/token")]
+async fn get_tokens(Viewer(user): Viewer, app_state: web::Data<AppState<'_>>) -> impl Responder {
+    let tokens = app_state.database.token.by_user(user.id).await;
+
+    match tokens {
+        Err(e) => {
+            error!("Error getting tokens: {}", e);
+            HttpResponse::InternalServerError().finish()
+        }
+        Ok(tokens) => HttpResponse::Ok().json(tokens),
+    }
+}
+
+#[delete("/token/{id}")]
+async fn delete_token(
+    Viewer(user): Viewer,
+    id: web::Path<i32>,
+    app_state: web::Data<AppState<'_>>,
+) -> impl Responder {
+    let result = app_state
+        .database
+        .token
+        .delete(&user, id.into_inner())
+        .await;
+
+    match result {
+        Err(e) => {
+            error!("Error deleting token: {}", e);
+            HttpResponse::InternalServerError().finish()
+        }
+        Ok(None) => HttpResponse::NotFound().finish(),
+        Ok(Some(_)) => HttpResponse::Ok().finish(),
+    }
+}
+
+#[get("/user")]
+async fn get_users(_: Admin, app_state: web::Data<AppState<'_>>) -> impl Responder {
+    let users = app_state.database.user.all().await;
+
+    match users {
+        Err(e) => {
+            error!("Error getting users: {}", e);
+            HttpResponse::InternalServerError().finish()
+        }
+        Ok(users) => HttpResponse::Ok().json(users),
+    }
+}
+
+#[post("/user")]
+async fn create_user(
+    Admin(admin): Admin,
+    creator: web::Json<Creator>,
+    app_state: web::Data<AppState<'_>>,
+) -> Result<HttpResponse, AuthError> {
+    let password_hash = creator.password.as_deref().map(hash).transpose()?;
+    let id = app_state
+        .database
+        .user
+        .create(
+            &creator.username,
+            password_hash.as_deref(),
+            creator.role.id(),
+            &admin,
+        )
+        .await?;
+    Ok(HttpResponse::Ok().json(id))
+}
+
+#[patch("/user")]
+async fn update_user(
+    Admin(admin): Admin,
+    patch: web::Json<Patch>,
+    app_state: web::Data<AppState<'_>>,
+) -> Result<HttpResponse, AuthError> {
+    let password_hash = patch.password.as_deref().map(hash).transpose()?;
+    let result = app_state
+        .database
+        .user
+        .update(patch.id, password_hash.as_deref(), patch.role.id(), &admin)
+        .await?;
+    if result.is_none() {
+        return Ok(HttpResponse::NotFound().finish());
+    }
+    Ok(HttpResponse::Ok().finish())
+}
+
+#[delete("/user/{id}")]
+async fn delete_user(
+    Admin(admin): Admin,
+    id: web::Path<i32>,
+    app_state: web::Data<AppState<'_>>,
+) -> Result<HttpResponse, AuthError> {
+    let id = id.into_inner();
+    // Keeps the editor from being left without an admin by accident
+    if id == admin.id {
+        return Ok(HttpResponse::BadRequest().body("Can't delete yourself"));
+    }
+    let result = app_state.database.user.delete(id, &admin).await?;
+    if result.rows_affected() == 0 {
+        return Ok(HttpResponse::NotFound().finish());
+    }
+    Ok(HttpResponse::Ok().finish())
+}
@@ -0,0 +1,56 @@
+use super::AppState;
+use crate::model::map_visualization::Patch;
+use crate::model::validation::{self, FieldError};
+use actix_web::{http::StatusCode, web, HttpResponse};
+use derive_more::Display;
+use log::error;
+use serde::Serialize;
+
+/// Errors saving map visualization settings
+#[derive(Debug, Display, Serialize)]
+#[serde(tag = "name", content = "info")]
+pub enum Error {
+    #[display(fmt = "Invalid fields: {_0:#?}")]
+    InvalidFields(Vec<FieldError>),
+    Internal(String),
+}
+
+impl std::error::Error for Error {}
+
+impl From<sqlx::Error> for Error {
+    fn from(error: sqlx::Error) -> Self {
+        Error::Internal(error.to_string())
+    }
+}
+
+impl actix_web::error::ResponseError for Error {
+    fn status_code(&self) -> StatusCode {
+        match self {
+            Error::Internal(_) => StatusCode::INTERNAL_SERVER_ERROR,
+            _ => StatusCode::BAD_REQUEST,
+        }
+    }
+
+    fn error_response(&self) -> HttpResponse {
+        if let Error::Internal(e) = self {
+            error!("Error saving map visualization: {}", e);
+            return HttpResponse::build(self.status_code()).finish();
+        }
+        HttpResponse::build(self.status_code()).json(self)
+    }
+}
+
+/// Checks map visualization settings against the data of their dataset
+pub async fn validate(app_state: &web::Data<AppState<'_>>, patch: &Patch) -> Result<(), Error> {
+    let slices = app_state
+        .database
+        .source_and_date
+        .by_dataset(patch.dataset)
+        .await?;
+    let errors = validation::validate(patch, &slices);
+    if errors.is_empty() {
+        Ok(())
+    } else {
+        Err(Error::InvalidFields(errors))
+    }
+}
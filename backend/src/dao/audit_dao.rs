@@ -0,0 +1,61 @@
+use super::Table;
+use crate::model::audit::{Change, Entry, Filter};
+use crate::model::user::User;
+use sqlx::postgres::PgExecutor;
+
+/// Records a change in the audit log. Writes call it in their own transaction, so a change is
+/// never made without its entry, or recorded without being made.
+pub(super) async fn record<'e>(
+    executor: impl PgExecutor<'e>,
+    user: &User,
+    change: &Change,
+) -> Result<i64, sqlx::Error> {
+    sqlx::query!(
+        "
+        INSERT INTO audit_log (editor_user, username, action, entity, entity_id, before, after)
+        VALUES ($1, $2, $3, $4, $5, $6, $7)
+        RETURNING id
+        ",
+        user.id,
+        user.username,
+        change.action.to_string(),
+        change.entity.to_string(),
+        change.entity_id,
+        change.before,
+        change.after,
+    )
+    .fetch_one(executor)
+    .await
+    .map(|row| row.id)
+}
+
+impl<'c> Table<'c, Entry> {
+    pub async fn filter(&self, filter: &Filter) -> Result<Vec<Entry>, sqlx::Error> {
+        sqlx::query_as!(
+            Entry,
+            "
+            SELECT id, editor_user, username, created_at, action, entity, entity_id, before, after
+            FROM audit_log
+            WHERE ($1::TEXT IS NULL OR entity = $1)
+                AND ($2::INT IS NULL OR entity_id = $2)
+                AND ($3::INT IS NULL OR editor_user = $3)
+                AND ($4::TEXT IS NULL OR action = $4)
+                AND ($5::TIMESTAMPTZ IS NULL OR created_at >= $5)
+                AND ($6::TIMESTAMPTZ IS NULL OR created_at < $6)
+            ORDER BY created_at DESC, id DESC
+            LIMIT $7
+            OFFSET $8
+            ",
+            filter.entity.map(|entity| entity.to_string()),
+            filter.entity_id,
+            filter.editor_user,
+            filter.action.map(|action| action.to_string()),
+            filter.from,
+            filter.to,
+            filter.limit(),
+            filter.offset(),
+        )
+        .fetch_all(&*self.pool)
+        .await
+    }
+}
@@ -0,0 +1,246 @@
+use super::Table;
+use crate::model::classification::Cached;
+use crate::model::data::SourceAndDate;
+use crate::model::delta::Delta;
+use sqlx::postgres::PgQueryResult;
+
+impl<'c> Table<'c, Cached> {
+    /// Previously computed breaks, if neither the dataset's data nor the map visualization's
+    /// classification have changed since
+    pub async fn cached(
+        &self,
+        map_visualization: i32,
+        source_and_date: &SourceAndDate,
+    ) -> Result<Option<Cached>, sqlx::Error> {
+        sqlx::query_as!(
+            Cached,
+            "
+            SELECT classification_cache.dataset, classification_cache.version, classification_cache.breaks
+            FROM classification_cache
+            JOIN map_visualization
+                ON map_visualization.id = classification_cache.map_visualization
+                AND map_visualization.dataset = classification_cache.dataset
+                AND map_visualization.classification_method = classification_cache.method
+                AND map_visualization.classification_classes = classification_cache.classes
+            JOIN data_version
+                ON data_version.dataset = classification_cache.dataset
+                AND data_version.version = classification_cache.version
+            WHERE classification_cache.map_visualization = $1
+            AND classification_cache.source = $2
+            AND classification_cache.scenario = $3
+            AND classification_cache.statistic = $4
+            AND classification_cache.start_date = $5
+            AND classification_cache.end_date = $6
+            AND map_visualization.deleted_at IS NULL
+            AND classification_cache.dataset NOT IN (SELECT id FROM dataset WHERE deleted_at IS NOT NULL)
+            AND classification_cache.source NOT IN (SELECT id FROM data_source WHERE deleted_at IS NOT NULL)
+            ",
+            map_visualization,
+            source_and_date.source,
+            source_and_date.scenario,
+            source_and_date.statistic,
+            source_and_date.start_date,
+            source_and_date.end_date,
+        )
+        .fetch_optional(&*self.pool)
+        .await
+    }
+
+    /// The current version of a dataset's data, to cache breaks computed from it
+    pub async fn version(&self, dataset: i32) -> Result<i32, sqlx::Error> {
+        sqlx::query!(
+            r#"
+            SELECT COALESCE(
+                (SELECT version FROM data_version WHERE dataset = $1),
+                0
+            ) AS "version!"
+            "#,
+            dataset
+        )
+        .fetch_one(&*self.pool)
+        .await
+        .map(|row| row.version)
+    }
+
+    pub async fn store(
+        &self,
+        map_visualization: i32,
+        source_and_date: &SourceAndDate,
+        method: i32,
+        classes: i16,
+        cached: &Cached,
+    ) -> Result<PgQueryResult, sqlx::Error> {
+        sqlx::query!(
+            "
+            INSERT INTO classification_cache
+            (
+                map_visualization,
+                source,
+                scenario,
+                statistic,
+                start_date,
+                end_date,
+                dataset,
+                version,
+                method,
+                classes,
+                breaks
+            )
+            VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10, $11)
+            ON CONFLICT (
+                map_visualization,
+                source,
+                scenario,
+                statistic,
+                start_date,
+                end_date
+            ) DO UPDATE
+            SET dataset = EXCLUDED.dataset,
+                version = EXCLUDED.version,
+                method = EXCLUDED.method,
+                classes = EXCLUDED.classes,
+                breaks = EXCLUDED.breaks
+            ",
+            map_visualization,
+            source_and_date.source,
+            source_and_date.scenario,
+            source_and_date.statistic,
+            source_and_date.start_date,
+            source_and_date.end_date,
+            cached.dataset,
+            cached.version,
+            method,
+            classes,
+            &cached.breaks,
+        )
+        .execute(&*self.pool)
+        .await
+    }
+
+    /// Previously computed breaks of a delta map visualization, if it still compares the same
+    /// slices and neither the dataset's data nor its classification have changed since
+    pub async fn cached_delta(
+        &self,
+        map_visualization: i32,
+        delta: &Delta,
+    ) -> Result<Option<Cached>, sqlx::Error> {
+        sqlx::query_as!(
+            Cached,
+            "
+            SELECT cache.dataset, cache.version, cache.breaks
+            FROM delta_classification_cache cache
+            JOIN map_visualization
+                ON map_visualization.id = cache.map_visualization
+                AND map_visualization.dataset = cache.dataset
+                AND map_visualization.classification_method = cache.method
+                AND map_visualization.classification_classes = cache.classes
+            JOIN data_version
+                ON data_version.dataset = cache.dataset
+                AND data_version.version = cache.version
+            WHERE cache.map_visualization = $1
+            AND cache.operation = $2
+            AND cache.from_source = $3
+            AND cache.from_scenario = $4
+            AND cache.from_statistic = $5
+            AND cache.from_start_date = $6
+            AND cache.from_end_date = $7
+            AND cache.to_source = $8
+            AND cache.to_scenario = $9
+            AND cache.to_statistic = $10
+            AND cache.to_start_date = $11
+            AND cache.to_end_date = $12
+            AND map_visualization.deleted_at IS NULL
+            AND cache.dataset NOT IN (SELECT id FROM dataset WHERE deleted_at IS NOT NULL)
+            AND cache.from_source NOT IN (SELECT id FROM data_source WHERE deleted_at IS NOT NULL)
+            AND cache.to_source NOT IN (SELECT id FROM data_source WHERE deleted_at IS NOT NULL)
+            ",
+            map_visualization,
+            delta.operation,
+            delta.from.source,
+            delta.from.scenario,
+            delta.from.statistic,
+            delta.from.start_date,
+            delta.from.end_date,
+            delta.to.source,
+            delta.to.scenario,
+            delta.to.statistic,
+            delta.to.start_date,
+            delta.to.end_date,
+        )
+        .fetch_optional(&*self.pool)
+        .await
+    }
+
+    pub async fn store_delta(
+        &self,
+        map_visualization: i32,
+        delta: &Delta,
+        method: i32,
+        classes: i16,
+        cached: &Cached,
+    ) -> Result<PgQueryResult, sqlx::Error> {
+        sqlx::query!(
+            "
+            INSERT INTO delta_classification_cache
+            (
+                map_visualization,
+                operation,
+                from_source,
+                from_scenario,
+                from_statistic,
+                from_start_date,
+                from_end_date,
+                to_source,
+                to_scenario,
+                to_statistic,
+                to_start_date,
+                to_end_date,
+                dataset,
+                version,
+                method,
+                classes,
+                breaks
+            )
+            VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10, $11, $12, $13, $14, $15, $16, $17)
+            ON CONFLICT (
+                map_visualization,
+                operation,
+                from_source,
+                from_scenario,
+                from_statistic,
+                from_start_date,
+                from_end_date,
+                to_source,
+                to_scenario,
+                to_statistic,
+                to_start_date,
+                to_end_date
+            ) DO UPDATE
+            SET dataset = EXCLUDED.dataset,
+                version = EXCLUDED.version,
+                method = EXCLUDED.method,
+                classes = EXCLUDED.classes,
+                breaks = EXCLUDED.breaks
+            ",
+            map_visualization,
+            delta.operation,
+            delta.from.source,
+            delta.from.scenario,
+            delta.from.statistic,
+            delta.from.start_date,
+            delta.from.end_date,
+            delta.to.source,
+            delta.to.scenario,
+            delta.to.statistic,
+            delta.to.start_date,
+            delta.to.end_date,
+            cached.dataset,
+            cached.version,
+            method,
+            classes,
+            &cached.breaks,
+        )
+        .execute(&*self.pool)
+        .await
+    }
+}
@@ -0,0 +1,158 @@
+use crate::model::audit::{Change, Entity};
+use crate::model::crosswalk::{Crosswalk, Link};
+use crate::model::user::User;
+use serde_json::json;
+use sqlx::postgres::PgQueryResult;
+
+use super::{audit_dao, Table};
+
+impl<'c> Table<'c, Crosswalk> {
+    pub async fn all(&self) -> Result<Vec<Crosswalk>, sqlx::Error> {
+        sqlx::query_as!(
+            Crosswalk,
+            "
+            SELECT id, name, from_geography_type, to_geography_type
+            FROM crosswalk
+            ORDER BY id
+            "
+        )
+        .fetch_all(&*self.pool)
+        .await
+    }
+
+    pub async fn by_id(&self, id: i32) -> Result<Crosswalk, sqlx::Error> {
+        sqlx::query_as!(
+            Crosswalk,
+            "
+            SELECT id, name, from_geography_type, to_geography_type
+            FROM crosswalk
+            WHERE id = $1
+            ",
+            id
+        )
+        .fetch_one(&*self.pool)
+        .await
+    }
+
+    /// Derive area shares from the boundaries of both geography types, optionally only for
+    /// some geo ids on either side. Imported population shares are kept.
+    pub async fn generate(
+        &self,
+        id: i32,
+        from_ids: Option<&[i64]>,
+        to_ids: Option<&[i64]>,
+        user: &User,
+    ) -> Result<PgQueryResult, sqlx::Error> {
+        let mut transaction = self.pool.begin().await?;
+        let result = sqlx::query!(
+            "
+            INSERT INTO crosswalk_link (crosswalk, from_id, to_id, from_area_share, to_area_share)
+            SELECT
+                crosswalk.id,
+                origin.id,
+                destination.id,
+                overlap.area / ST_Area(origin.geometry::geography),
+                overlap.area / ST_Area(destination.geometry::geography)
+            FROM crosswalk
+            JOIN geo_boundary AS origin
+                ON origin.geography_type = crosswalk.from_geography_type
+            JOIN geo_boundary AS destination
+                ON destination.geography_type = crosswalk.to_geography_type
+                AND ST_Intersects(origin.geometry, destination.geometry)
+            CROSS JOIN LATERAL (
+                SELECT ST_Area(ST_Intersection(origin.geometry, destination.geometry)::geography) AS area
+            ) AS overlap
+            WHERE crosswalk.id = $1
+            AND ($2::bigint[] IS NULL OR origin.id = ANY($2))
+            AND ($3::bigint[] IS NULL OR destination.id = ANY($3))
+            AND overlap.area > 0
+            ON CONFLICT (crosswalk, from_id, to_id) DO UPDATE
+            SET from_area_share = EXCLUDED.from_area_share,
+                to_area_share = EXCLUDED.to_area_share
+            ",
+            id,
+            from_ids,
+            to_ids,
+        )
+        .execute(&mut transaction)
+        .await?;
+        let after = json!({
+            "from_ids": from_ids,
+            "to_ids": to_ids,
+            "links": result.rows_affected(),
+        });
+        let change = Change::created(Entity::CrosswalkLink, id, &after);
+        audit_dao::record(&mut transaction, user, &change).await?;
+        transaction.commit().await?;
+        Ok(result)
+    }
+
+    pub async fn upsert_links(
+        &self,
+        id: i32,
+        links: &[Link],
+        user: &User,
+    ) -> Result<PgQueryResult, sqlx::Error> {
+        let from_ids: Vec<i64> = links.iter().map(|link| link.from_id).collect();
+        let to_ids: Vec<i64> = links.iter().map(|link| link.to_id).collect();
+        let from_area_shares: Vec<Option<f64>> =
+            links.iter().map(|link| link.from_area_share).collect();
+        let to_area_shares: Vec<Option<f64>> =
+            links.iter().map(|link| link.to_area_share).collect();
+        let from_population_shares: Vec<Option<f64>> = links
+            .iter()
+            .map(|link| link.from_population_share)
+            .collect();
+        let to_population_shares: Vec<Option<f64>> =
+            links.iter().map(|link| link.to_population_share).collect();
+
+        let mut transaction = self.pool.begin().await?;
+        let result = sqlx::query!(
+            "
+            INSERT INTO crosswalk_link (
+                crosswalk,
+                from_id,
+                to_id,
+                from_area_share,
+                to_area_share,
+                from_population_share,
+                to_population_share
+            )
+            SELECT $1, *
+            FROM UNNEST(
+                $2::bigint[],
+                $3::bigint[],
+                $4::float[],
+                $5::float[],
+                $6::float[],
+                $7::float[]
+            )
+            ON CONFLICT (crosswalk, from_id, to_id) DO UPDATE
+            SET from_area_share = COALESCE(EXCLUDED.from_area_share, crosswalk_link.from_area_share),
+                to_area_share = COALESCE(EXCLUDED.to_area_share, crosswalk_link.to_area_share),
+                from_population_share = COALESCE(
+                    EXCLUDED.from_population_share,
+                    crosswalk_link.from_population_share
+                ),
+                to_population_share = COALESCE(
+                    EXCLUDED.to_population_share,
+                    crosswalk_link.to_population_share
+                )
+            ",
+            id,
+            &from_ids,
+            &to_ids,
+            &from_area_shares as &[Option<f64>],
+            &to_area_shares as &[Option<f64>],
+            &from_population_shares as &[Option<f64>],
+            &to_population_shares as &[Option<f64>],
+        )
+        .execute(&mut transaction)
+        .await?;
+        let after = json!({ "links": result.rows_affected() });
+        let change = Change::uploaded(Entity::CrosswalkLink, id, &after);
+        audit_dao::record(&mut transaction, user, &change).await?;
+        transaction.commit().await?;
+        Ok(result)
+    }
+}
@@ -0,0 +1,173 @@
+use super::{audit_dao, Table};
+use crate::model::audit::{Change, Entity};
+use crate::model::custom_region::{Creator, CustomRegion};
+use crate::model::user::User;
+use sqlx::postgres::{PgConnection, PgExecutor, PgQueryResult};
+
+async fn by_id<'e>(
+    executor: impl PgExecutor<'e>,
+    id: i32,
+) -> Result<Option<CustomRegion>, sqlx::Error> {
+    sqlx::query_as!(
+        CustomRegion,
+        r#"
+        SELECT
+            custom_region.id,
+            custom_region.name,
+            custom_region.owner,
+            custom_region.description,
+            custom_region.geography_type,
+            COALESCE(
+                array_agg(custom_region_member.id ORDER BY custom_region_member.id)
+                    FILTER (WHERE custom_region_member.id IS NOT NULL),
+                '{}'
+            ) AS "members!"
+        FROM custom_region
+        LEFT JOIN custom_region_member
+            ON custom_region_member.custom_region = custom_region.id
+        WHERE custom_region.id = $1
+        GROUP BY custom_region.id
+        "#,
+        id
+    )
+    .fetch_optional(executor)
+    .await
+}
+
+/// A region locked until the transaction ends
+async fn locked(
+    connection: &mut PgConnection,
+    id: i32,
+) -> Result<Option<CustomRegion>, sqlx::Error> {
+    sqlx::query!("SELECT id FROM custom_region WHERE id = $1 FOR UPDATE", id)
+        .fetch_optional(&mut *connection)
+        .await?;
+    by_id(connection, id).await
+}
+
+impl<'c> Table<'c, CustomRegion> {
+    pub async fn all(&self) -> Result<Vec<CustomRegion>, sqlx::Error> {
+        sqlx::query_as!(
+            CustomRegion,
+            r#"
+            SELECT
+                custom_region.id,
+                custom_region.name,
+                custom_region.owner,
+                custom_region.description,
+                custom_region.geography_type,
+                COALESCE(
+                    array_agg(custom_region_member.id ORDER BY custom_region_member.id)
+                        FILTER (WHERE custom_region_member.id IS NOT NULL),
+                    '{}'
+                ) AS "members!"
+            FROM custom_region
+            LEFT JOIN custom_region_member
+                ON custom_region_member.custom_region = custom_region.id
+            GROUP BY custom_region.id
+            ORDER BY custom_region.owner, custom_region.name
+            "#
+        )
+        .fetch_all(&*self.pool)
+        .await
+    }
+
+    pub async fn by_id(&self, id: i32) -> Result<Option<CustomRegion>, sqlx::Error> {
+        by_id(&*self.pool, id).await
+    }
+
+    pub async fn create(&self, region: &Creator, user: &User) -> Result<i32, sqlx::Error> {
+        let mut transaction = self.pool.begin().await?;
+        let id = sqlx::query!(
+            "
+            INSERT INTO custom_region (name, owner, description, geography_type)
+            VALUES ($1, $2, $3, $4)
+            RETURNING id
+            ",
+            region.name,
+            region.owner,
+            region.description,
+            region.geography_type,
+        )
+        .fetch_one(&mut transaction)
+        .await?
+        .id;
+        sqlx::query!(
+            "
+            INSERT INTO custom_region_member (custom_region, geography_type, id)
+            SELECT $1, $2, UNNEST($3::INT8[])
+            ON CONFLICT DO NOTHING
+            ",
+            id,
+            region.geography_type,
+            &region.members,
+        )
+        .execute(&mut transaction)
+        .await?;
+        let after = by_id(&mut transaction, id).await?;
+        let change = Change::created(Entity::CustomRegion, id, &after);
+        audit_dao::record(&mut transaction, user, &change).await?;
+        transaction.commit().await?;
+        Ok(id)
+    }
+
+    /// Replaces a region's fields and members. The geography type can't change, since the
+    /// members belong to it.
+    pub async fn update(
+        &self,
+        region: &CustomRegion,
+        user: &User,
+    ) -> Result<PgQueryResult, sqlx::Error> {
+        let mut transaction = self.pool.begin().await?;
+        let before = locked(&mut transaction, region.id).await?;
+        let result = sqlx::query!(
+            "UPDATE custom_region SET name = $1, owner = $2, description = $3 WHERE id = $4",
+            region.name,
+            region.owner,
+            region.description,
+            region.id
+        )
+        .execute(&mut transaction)
+        .await?;
+        sqlx::query!(
+            "DELETE FROM custom_region_member WHERE custom_region = $1",
+            region.id
+        )
+        .execute(&mut transaction)
+        .await?;
+        sqlx::query!(
+            "
+            INSERT INTO custom_region_member (custom_region, geography_type, id)
+            SELECT id, geography_type, UNNEST($2::INT8[])
+            FROM custom_region
+            WHERE id = $1
+            ON CONFLICT DO NOTHING
+            ",
+            region.id,
+            &region.members,
+        )
+        .execute(&mut transaction)
+        .await?;
+        if result.rows_affected() > 0 {
+            let after = by_id(&mut transaction, region.id).await?;
+            let change = Change::updated(Entity::CustomRegion, region.id, &before, &after);
+            audit_dao::record(&mut transaction, user, &change).await?;
+        }
+        transaction.commit().await?;
+        Ok(result)
+    }
+
+    pub async fn delete(&self, id: i32, user: &User) -> Result<PgQueryResult, sqlx::Error> {
+        let mut transaction = self.pool.begin().await?;
+        let before = locked(&mut transaction, id).await?;
+        let result = sqlx::query!("DELETE FROM custom_region WHERE id = $1", id)
+            .execute(&mut transaction)
+            .await?;
+        if result.rows_affected() > 0 {
+            let change = Change::deleted(Entity::CustomRegion, id, &before);
+            audit_dao::record(&mut transaction, user, &change).await?;
+        }
+        transaction.commit().await?;
+        Ok(result)
+    }
+}
@@ -1,45 +1,148 @@
-use super::Table;
+use super::{audit_dao, Table};
+use crate::model::audit::{Change, Entity};
 use crate::model::data_category::DataCategory;
-use sqlx::postgres::PgQueryResult;
+use crate::model::order::{self, Mismatch};
+use crate::model::trash::{Item, Kind};
+use crate::model::user::User;
+use chrono::{DateTime, Utc};
+use sqlx::postgres::{PgExecutor, PgQueryResult};
+
+/// A category, even one in the trash, locked until the transaction ends
+pub(super) async fn locked<'e>(
+    executor: impl PgExecutor<'e>,
+    id: i32,
+) -> Result<Option<DataCategory>, sqlx::Error> {
+    sqlx::query_as!(
+        DataCategory,
+        "SELECT id, name, normalized, \"order\" FROM data_category WHERE id = $1 FOR UPDATE",
+        id
+    )
+    .fetch_optional(executor)
+    .await
+}
+
+/// The ids of the categories outside the trash, in order, locked until the transaction ends
+async fn locked_ids<'e>(executor: impl PgExecutor<'e>) -> Result<Vec<i32>, sqlx::Error> {
+    sqlx::query!(
+        "SELECT id FROM data_category WHERE deleted_at IS NULL ORDER BY \"order\" FOR UPDATE"
+    )
+    .fetch_all(executor)
+    .await
+    .map(|rows| rows.into_iter().map(|row| row.id).collect())
+}
 
 impl<'c> Table<'c, DataCategory> {
     pub async fn all(&self) -> Result<Vec<DataCategory>, sqlx::Error> {
         sqlx::query_as!(
             DataCategory,
-            "SELECT id, name, normalized, \"order\" FROM data_category ORDER BY \"order\""
+            "
+            SELECT id, name, normalized, \"order\"
+            FROM data_category
+            WHERE deleted_at IS NULL
+            ORDER BY \"order\"
+            "
         )
         .fetch_all(&*self.pool)
         .await
     }
 
-    pub async fn create(&self, data_category: &DataCategory) -> Result<DataCategory, sqlx::Error> {
+    pub async fn by_id(&self, id: i32) -> Result<DataCategory, sqlx::Error> {
         sqlx::query_as!(
+            DataCategory,
+            "
+            SELECT id, name, normalized, \"order\"
+            FROM data_category
+            WHERE id = $1 AND deleted_at IS NULL
+            ",
+            id
+        )
+        .fetch_one(&*self.pool)
+        .await
+    }
+
+    pub async fn create(
+        &self,
+        data_category: &DataCategory,
+        user: &User,
+    ) -> Result<DataCategory, sqlx::Error> {
+        let mut transaction = self.pool.begin().await?;
+        let created = sqlx::query_as!(
             DataCategory,
             "INSERT INTO data_category (name, normalized, \"order\") VALUES ($1, $2, $3) RETURNING id, name, normalized, \"order\"",
             data_category.name,
             data_category.normalized,
             data_category.order
         )
-        .fetch_one(&*self.pool)
-        .await
+        .fetch_one(&mut transaction)
+        .await?;
+        let change = Change::created(Entity::DataCategory, created.id, &created);
+        audit_dao::record(&mut transaction, user, &change).await?;
+        transaction.commit().await?;
+        Ok(created)
     }
 
-    pub async fn update(&self, data_category: &DataCategory) -> Result<PgQueryResult, sqlx::Error> {
-        sqlx::query!(
+    pub async fn update(
+        &self,
+        data_category: &DataCategory,
+        user: &User,
+    ) -> Result<PgQueryResult, sqlx::Error> {
+        let mut transaction = self.pool.begin().await?;
+        let before = locked(&mut transaction, data_category.id).await?;
+        let result = sqlx::query!(
             "UPDATE data_category SET name = $1, normalized = $2, \"order\" = $3 WHERE id = $4",
             data_category.name,
             data_category.normalized,
             data_category.order,
             data_category.id
         )
-        .execute(&*self.pool)
-        .await
+        .execute(&mut transaction)
+        .await?;
+        let change = Change::updated(
+            Entity::DataCategory,
+            data_category.id,
+            &before,
+            data_category,
+        );
+        audit_dao::record(&mut transaction, user, &change).await?;
+        transaction.commit().await?;
+        Ok(result)
     }
 
-    pub async fn delete(&self, id: i32) -> Result<PgQueryResult, sqlx::Error> {
-        sqlx::query!("DELETE FROM data_category WHERE id = $1", id)
-            .execute(&*self.pool)
-            .await
+    /// Numbers categories from 1 in the order of their ids, which must list every category outside
+    /// the trash once. Categories in the trash keep their order after them, so they can't take an
+    /// order that's in use.
+    pub async fn reorder(
+        &self,
+        ids: &[i32],
+        user: &User,
+    ) -> Result<Result<(), Mismatch>, sqlx::Error> {
+        let mut transaction = self.pool.begin().await?;
+        let before = locked_ids(&mut transaction).await?;
+        if let Err(mismatch) = order::check(ids, &before) {
+            return Ok(Err(mismatch));
+        }
+        sqlx::query!(
+            "
+            UPDATE data_category
+            SET \"order\" = new.position::SMALLINT
+            FROM (
+                SELECT id, position
+                FROM unnest($1::INT[]) WITH ORDINALITY AS listed (id, position)
+                UNION ALL
+                SELECT id, cardinality($1::INT[]) + ROW_NUMBER() OVER (ORDER BY \"order\")
+                FROM data_category
+                WHERE deleted_at IS NOT NULL
+            ) AS new
+            WHERE data_category.id = new.id
+            ",
+            ids
+        )
+        .execute(&mut transaction)
+        .await?;
+        let change = Change::reordered(Entity::DataCategory, None, &before, ids);
+        audit_dao::record(&mut transaction, user, &change).await?;
+        transaction.commit().await?;
+        Ok(Ok(()))
     }
 
     pub async fn last_order(&self) -> Result<i16, sqlx::Error> {
@@ -48,4 +151,87 @@ impl<'c> Table<'c, DataCategory> {
             .await
             .map(|r| r.order)
     }
+
+    /// Puts a category in the trash. `None` if there's no such category outside the trash.
+    pub async fn trash(&self, id: i32, user: &User) -> Result<Option<Item>, sqlx::Error> {
+        let mut transaction = self.pool.begin().await?;
+        let before = locked(&mut transaction, id).await?;
+        let item = sqlx::query!(
+            r#"
+            UPDATE data_category
+            SET deleted_at = NOW()
+            WHERE id = $1 AND deleted_at IS NULL
+            RETURNING id, name, deleted_at AS "deleted_at!"
+            "#,
+            id
+        )
+        .fetch_optional(&mut transaction)
+        .await?
+        .map(|row| Item {
+            kind: Kind::DataCategory,
+            id: row.id,
+            name: Some(row.name),
+            deleted_at: row.deleted_at,
+        });
+        if item.is_some() {
+            let change = Change::deleted(Entity::DataCategory, id, &before);
+            audit_dao::record(&mut transaction, user, &change).await?;
+        }
+        transaction.commit().await?;
+        Ok(item)
+    }
+
+    /// Takes a category out of the trash, returning when it was deleted. `None` if there's no such
+    /// category in the trash.
+    pub async fn restore(&self, id: i32, user: &User) -> Result<Option<Item>, sqlx::Error> {
+        let mut transaction = self.pool.begin().await?;
+        let item = sqlx::query!(
+            r#"
+            UPDATE data_category
+            SET deleted_at = NULL
+            FROM data_category AS old
+            WHERE data_category.id = $1 AND old.id = data_category.id AND old.deleted_at IS NOT NULL
+            RETURNING data_category.id, data_category.name, old.deleted_at AS "deleted_at!"
+            "#,
+            id
+        )
+        .fetch_optional(&mut transaction)
+        .await?
+        .map(|row| Item {
+            kind: Kind::DataCategory,
+            id: row.id,
+            name: Some(row.name),
+            deleted_at: row.deleted_at,
+        });
+        if let Some(item) = &item {
+            let change = Change::restored(Entity::DataCategory, id, item);
+            audit_dao::record(&mut transaction, user, &change).await?;
+        }
+        transaction.commit().await?;
+        Ok(item)
+    }
+
+    /// The categories in the trash that were deleted before a time
+    pub async fn trashed(&self, before: DateTime<Utc>) -> Result<Vec<Item>, sqlx::Error> {
+        sqlx::query!(
+            r#"
+            SELECT id, name, deleted_at AS "deleted_at!"
+            FROM data_category
+            WHERE deleted_at < $1
+            "#,
+            before
+        )
+        .fetch_all(&*self.pool)
+        .await
+        .map(|rows| {
+            rows.into_iter()
+                .map(|row| Item {
+                    kind: Kind::DataCategory,
+                    id: row.id,
+                    name: Some(row.name),
+                    deleted_at: row.deleted_at,
+                })
+                .collect()
+        })
+    }
 }
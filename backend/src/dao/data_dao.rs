@@ -1,9 +1,25 @@
-use super::Table;
+use super::{audit_dao, data_source_dao, dataset_dao, map_visualization_dao, Table};
 use crate::controller::data_controller::PercentileInfo;
-use crate::model::data::{self, Creator, Data, Simple, SourceAndDate};
+use crate::model::aggregation::Child;
+use crate::model::audit::{Change, Entity};
+use crate::model::crosswalk::{Kind, Overlap, Weight};
+use crate::model::data::{self, Creator, Data, Long, Simple, SourceAndDate, TimeseriesPoint};
+use crate::model::dataset;
+use crate::model::lookup::Ranked;
+use crate::model::upload_metadata::{Rejected, Source, UploadMetadata, Uploaded};
+use crate::model::user::User;
+use crate::model::validation;
+use crate::model::{scenario, statistic};
 use chrono::NaiveDate;
-use sqlx::postgres::PgQueryResult;
-use std::collections::HashSet;
+use futures::stream::{self, BoxStream, StreamExt, TryStreamExt};
+use serde_json::json;
+use sqlx::postgres::{PgExecutor, PgQueryResult};
+use sqlx::PgPool;
+use std::collections::{HashMap, HashSet};
+use std::sync::Arc;
+
+/// How many rows a bulk export reads from the database at a time
+const BULK_PAGE_SIZE: i64 = 10_000;
 
 impl<'c> Table<'c, Data> {
     pub async fn by_dataset(
@@ -20,11 +36,17 @@ impl<'c> Table<'c, Data> {
             AND source = $2
             AND start_date = $3
             AND end_date = $4
+            AND scenario = $5
+            AND statistic = $6
+            AND dataset NOT IN (SELECT id FROM dataset WHERE deleted_at IS NOT NULL)
+            AND source NOT IN (SELECT id FROM data_source WHERE deleted_at IS NOT NULL)
             ",
             dataset,
             source_and_date.source,
             source_and_date.start_date,
             source_and_date.end_date,
+            source_and_date.scenario,
+            source_and_date.statistic,
         )
         .fetch_all(&*self.pool)
         .await
@@ -45,11 +67,398 @@ impl<'c> Table<'c, Data> {
             AND data.source = $2
             AND data.start_date = $3
             AND data.end_date = $4
+            AND data.scenario = $5
+            AND data.statistic = $6
+            AND data.dataset NOT IN (SELECT id FROM dataset WHERE deleted_at IS NOT NULL)
+            AND data.source NOT IN (SELECT id FROM data_source WHERE deleted_at IS NOT NULL)
             ",
             map_visualization,
             source_and_date.source,
             source_and_date.start_date,
-            source_and_date.end_date
+            source_and_date.end_date,
+            source_and_date.scenario,
+            source_and_date.statistic
+        )
+        .fetch_all(&*self.pool)
+        .await
+    }
+
+    /**
+     * All rows of the given datasets in long format, ordered by dataset, source, scenario,
+     * statistic, date range and geo id. That's the primary key, so rows are read a page at a time
+     * after the last one and the stream doesn't hold on to a connection or the whole result.
+     */
+    pub fn stream_by_datasets(
+        &self,
+        datasets: Vec<i32>,
+    ) -> BoxStream<'static, Result<Long, sqlx::Error>> {
+        let pool = self.pool.clone();
+        // The state is the last row read, or None once the final page was read
+        stream::try_unfold(Some(None), move |after: Option<Option<Long>>| {
+            let pool = pool.clone();
+            let datasets = datasets.clone();
+            async move {
+                let after = match after {
+                    None => return Ok::<_, sqlx::Error>(None),
+                    Some(after) => after,
+                };
+                let page = bulk_page(&pool, &datasets, after.as_ref()).await?;
+                let next = if (page.len() as i64) < BULK_PAGE_SIZE {
+                    None
+                } else {
+                    page.last().cloned().map(Some)
+                };
+                Ok(Some((page, next)))
+            }
+        })
+        .map_ok(|page| stream::iter(page.into_iter().map(Ok)))
+        .try_flatten()
+        .boxed()
+    }
+
+    /**
+     * Every slice of the given datasets for a single geo id. With `context`, each point also has
+     * the median of its slice and the geo id's percentile within it, among geo ids of the same
+     * geography type. The percentile is the one `percentile` reports, without inverting.
+     */
+    pub async fn timeseries(
+        &self,
+        id: i64,
+        geography_type: Option<i32>,
+        datasets: &[i32],
+        context: bool,
+    ) -> Result<Vec<TimeseriesPoint>, sqlx::Error> {
+        sqlx::query_as!(
+            TimeseriesPoint,
+            r#"
+            SELECT
+                data.dataset,
+                data.source,
+                data_source.name AS source_name,
+                data.scenario,
+                data.statistic,
+                data.start_date,
+                data.end_date,
+                data.value,
+                slice.national_median AS "national_median?",
+                slice.percentile AS "percentile?"
+            FROM data
+            JOIN data_source ON data_source.id = data.source
+            LEFT JOIN LATERAL (
+                SELECT
+                    percentile_cont(0.5) WITHIN GROUP (ORDER BY other.value) AS national_median,
+                    -- ranked like /percentile, where zeros rank 0 and count once
+                    CASE WHEN data.value = 0 THEN 0 ELSE (
+                        COUNT(*) FILTER (WHERE other.value != 0 AND other.value < data.value)
+                            + (data.value > 0)::int
+                    )::float8 / NULLIF(COUNT(*) FILTER (WHERE other.value != 0), 0)
+                    END AS percentile
+                FROM data AS other
+                WHERE $4::boolean
+                AND other.dataset = data.dataset
+                AND other.source = data.source
+                AND other.start_date = data.start_date
+                AND other.end_date = data.end_date
+                AND other.scenario = data.scenario
+                AND other.statistic = data.statistic
+                AND other.geography_type = data.geography_type
+                HAVING COUNT(*) > 0
+            ) AS slice ON TRUE
+            WHERE data.id = $1
+            AND ($2::int IS NULL OR data.geography_type = $2)
+            AND data.dataset = ANY($3)
+            AND data.dataset NOT IN (SELECT id FROM dataset WHERE deleted_at IS NOT NULL)
+            AND data.source NOT IN (SELECT id FROM data_source WHERE deleted_at IS NOT NULL)
+            ORDER BY
+                data.dataset,
+                data.scenario,
+                data.statistic,
+                data.start_date,
+                data.end_date,
+                data.source
+            "#,
+            id,
+            geography_type,
+            datasets,
+            context
+        )
+        .fetch_all(&*self.pool)
+        .await
+    }
+
+    /**
+     * A slice of a dataset, with the parent of each geo id in the given geography type. With a
+     * weight dataset, each geo id is weighted by its most recent value in that dataset.
+     */
+    pub async fn with_parents(
+        &self,
+        dataset: i32,
+        source_and_date: &SourceAndDate,
+        parent_geography_type: i32,
+        weight_dataset: Option<i32>,
+    ) -> Result<Vec<Child>, sqlx::Error> {
+        sqlx::query_as!(
+            Child,
+            r#"
+            SELECT
+                geo_id_parent.parent_id AS parent,
+                data.value,
+                weight.value AS "weight?"
+            FROM data
+            JOIN geo_id_parent
+                ON geo_id_parent.child_geography_type = data.geography_type
+                AND geo_id_parent.child_id = data.id
+                AND geo_id_parent.parent_geography_type = $7
+            LEFT JOIN LATERAL (
+                SELECT weight.value
+                FROM data AS weight
+                WHERE weight.dataset = $8
+                AND weight.geography_type = data.geography_type
+                AND weight.id = data.id
+                AND weight.scenario = $9
+                AND weight.statistic = $10
+                AND weight.dataset NOT IN (SELECT id FROM dataset WHERE deleted_at IS NOT NULL)
+                AND weight.source NOT IN (SELECT id FROM data_source WHERE deleted_at IS NOT NULL)
+                ORDER BY weight.end_date DESC, weight.source
+                LIMIT 1
+            ) AS weight ON TRUE
+            WHERE data.dataset = $1
+            AND data.source = $2
+            AND data.start_date = $3
+            AND data.end_date = $4
+            AND data.scenario = $5
+            AND data.statistic = $6
+            AND data.dataset NOT IN (SELECT id FROM dataset WHERE deleted_at IS NOT NULL)
+            AND data.source NOT IN (SELECT id FROM data_source WHERE deleted_at IS NOT NULL)
+            "#,
+            dataset,
+            source_and_date.source,
+            source_and_date.start_date,
+            source_and_date.end_date,
+            source_and_date.scenario,
+            source_and_date.statistic,
+            parent_geography_type,
+            weight_dataset,
+            scenario::OBSERVED,
+            statistic::MEAN,
+        )
+        .fetch_all(&*self.pool)
+        .await
+    }
+
+    /// A slice of a dataset limited to a custom region's members, each with the region as parent
+    pub async fn in_region(
+        &self,
+        dataset: i32,
+        source_and_date: &SourceAndDate,
+        region: i32,
+        weight_dataset: Option<i32>,
+    ) -> Result<Vec<Child>, sqlx::Error> {
+        sqlx::query_as!(
+            Child,
+            r#"
+            SELECT
+                int8(custom_region_member.custom_region) AS "parent!",
+                data.value,
+                weight.value AS "weight?"
+            FROM data
+            JOIN custom_region_member
+                ON custom_region_member.geography_type = data.geography_type
+                AND custom_region_member.id = data.id
+                AND custom_region_member.custom_region = $7
+            LEFT JOIN LATERAL (
+                SELECT weight.value
+                FROM data AS weight
+                WHERE weight.dataset = $8
+                AND weight.geography_type = data.geography_type
+                AND weight.id = data.id
+                AND weight.scenario = $9
+                AND weight.statistic = $10
+                AND weight.dataset NOT IN (SELECT id FROM dataset WHERE deleted_at IS NOT NULL)
+                AND weight.source NOT IN (SELECT id FROM data_source WHERE deleted_at IS NOT NULL)
+                ORDER BY weight.end_date DESC, weight.source
+                LIMIT 1
+            ) AS weight ON TRUE
+            WHERE data.dataset = $1
+            AND data.source = $2
+            AND data.start_date = $3
+            AND data.end_date = $4
+            AND data.scenario = $5
+            AND data.statistic = $6
+            AND data.dataset NOT IN (SELECT id FROM dataset WHERE deleted_at IS NOT NULL)
+            AND data.source NOT IN (SELECT id FROM data_source WHERE deleted_at IS NOT NULL)
+            "#,
+            dataset,
+            source_and_date.source,
+            source_and_date.start_date,
+            source_and_date.end_date,
+            source_and_date.scenario,
+            source_and_date.statistic,
+            region,
+            weight_dataset,
+            scenario::OBSERVED,
+            statistic::MEAN,
+        )
+        .fetch_all(&*self.pool)
+        .await
+    }
+
+    /// Some geo ids' values in a slice, each with its percent rank among the whole slice
+    pub async fn ranked(
+        &self,
+        dataset: i32,
+        source_and_date: &SourceAndDate,
+        ids: &[i64],
+        invert: bool,
+    ) -> Result<Vec<Ranked>, sqlx::Error> {
+        sqlx::query_as!(
+            Ranked,
+            r#"
+            SELECT
+                id AS "id!",
+                value AS "value!",
+                percent_rank AS "percent_rank!"
+            FROM (
+                SELECT
+                    id,
+                    value,
+                    percent_rank() OVER (ORDER BY CASE WHEN $8 THEN -value ELSE value END)
+                FROM data
+                WHERE dataset = $1
+                AND source = $2
+                AND start_date = $3
+                AND end_date = $4
+                AND scenario = $5
+                AND statistic = $6
+                AND dataset NOT IN (SELECT id FROM dataset WHERE deleted_at IS NOT NULL)
+                AND source NOT IN (SELECT id FROM data_source WHERE deleted_at IS NOT NULL)
+            ) AS ranked
+            WHERE id = ANY($7)
+            "#,
+            dataset,
+            source_and_date.source,
+            source_and_date.start_date,
+            source_and_date.end_date,
+            source_and_date.scenario,
+            source_and_date.statistic,
+            ids,
+            invert,
+        )
+        .fetch_all(&*self.pool)
+        .await
+    }
+
+    /// The slices shown for a category, the same ones `percentile` ranks a single geo id in
+    pub async fn report_card_entries(
+        &self,
+        category: i32,
+        geography_type: i32,
+        scenario: i32,
+        statistic: i32,
+    ) -> Result<Vec<data::ReportCardEntry>, sqlx::Error> {
+        sqlx::query_as!(
+            data::ReportCardEntry,
+            r#"
+            SELECT
+                map_visualization.dataset,
+                dataset.name AS dataset_name,
+                COALESCE(default_source, source) AS "source!",
+                COALESCE(default_start_date, start_date) AS "start_date!",
+                COALESCE(default_end_date, end_date) AS "end_date!",
+                invert_normalized,
+                units,
+                formatter_type,
+                decimals
+            FROM
+                map_visualization,
+                map_visualization_collection,
+                dataset,
+                (
+                    SELECT
+                        dataset,
+                        MAX(end_date) AS end_date,
+                        MAX(start_date) AS start_date,
+                        MAX("source") AS source
+                    FROM
+                        data
+                    WHERE
+                        scenario = $3
+                        AND statistic = $4
+                        AND dataset NOT IN (SELECT id FROM dataset WHERE deleted_at IS NOT NULL)
+                        AND source NOT IN (SELECT id FROM data_source WHERE deleted_at IS NOT NULL)
+                    GROUP BY
+                        dataset
+                ) AS cd
+            WHERE
+                map_visualization_collection.category = $1
+                AND map_visualization.dataset = dataset.id
+                AND cd.dataset = map_visualization.dataset
+                AND map_visualization_collection.map_visualization = map_visualization.id
+                AND dataset.geography_type = $2
+            ORDER BY map_visualization_collection.order
+            "#,
+            category,
+            geography_type,
+            scenario,
+            statistic,
+        )
+        .fetch_all(&*self.pool)
+        .await
+    }
+
+    /**
+     * A slice of a dataset, with the geo ids it overlaps in the crosswalk and the share of
+     * each overlap. In reverse, the dataset is on the `to` side of the crosswalk.
+     */
+    pub async fn with_crosswalk(
+        &self,
+        dataset: i32,
+        source_and_date: &SourceAndDate,
+        crosswalk: i32,
+        weight: Weight,
+        kind: Kind,
+        reverse: bool,
+    ) -> Result<Vec<Overlap>, sqlx::Error> {
+        sqlx::query_as!(
+            Overlap,
+            r#"
+            SELECT
+                CASE WHEN $8 THEN link.from_id ELSE link.to_id END AS "target!",
+                data.value,
+                CASE
+                    WHEN $9 AND $10 THEN link.from_population_share
+                    WHEN $9 THEN link.from_area_share
+                    WHEN $10 THEN link.to_population_share
+                    ELSE link.to_area_share
+                END AS share
+            FROM data
+            JOIN crosswalk ON crosswalk.id = $7
+            JOIN crosswalk_link AS link
+                ON link.crosswalk = crosswalk.id
+                AND data.id = CASE WHEN $8 THEN link.to_id ELSE link.from_id END
+            WHERE data.dataset = $1
+            AND data.source = $2
+            AND data.start_date = $3
+            AND data.end_date = $4
+            AND data.scenario = $5
+            AND data.statistic = $6
+            AND data.dataset NOT IN (SELECT id FROM dataset WHERE deleted_at IS NOT NULL)
+            AND data.source NOT IN (SELECT id FROM data_source WHERE deleted_at IS NOT NULL)
+            AND data.geography_type = CASE
+                WHEN $8 THEN crosswalk.to_geography_type
+                ELSE crosswalk.from_geography_type
+            END
+            "#,
+            dataset,
+            source_and_date.source,
+            source_and_date.start_date,
+            source_and_date.end_date,
+            source_and_date.scenario,
+            source_and_date.statistic,
+            crosswalk,
+            reverse,
+            kind.uses_from_share(reverse),
+            weight == Weight::Population,
         )
         .fetch_all(&*self.pool)
         .await
@@ -85,6 +494,8 @@ impl<'c> Table<'c, Data> {
                     AND source = entry.source
                     AND start_date = entry.start_date
                     AND end_date = entry.end_date
+                    AND scenario = $4
+                    AND statistic = $5
             ),
             CASE (
                 SELECT
@@ -97,6 +508,8 @@ impl<'c> Table<'c, Data> {
                     AND source = entry.source
                     AND start_date = entry.start_date
                     AND end_date = entry.end_date
+                    AND scenario = $4
+                    AND statistic = $5
             ) 
             WHEN NULL THEN NULL 
             WHEN 0 THEN 0
@@ -111,6 +524,8 @@ impl<'c> Table<'c, Data> {
                             AND source = entry.source
                             AND start_date = entry.start_date
                             AND end_date = entry.end_date
+                            AND scenario = $4
+                            AND statistic = $5
                             AND value != 0
                     UNION
                     SELECT
@@ -120,7 +535,9 @@ impl<'c> Table<'c, Data> {
                       '2000-01-01' AS end_date,
                       0 AS value,
                       0 AS id,
-                      0 AS geography_type)
+                      0 AS geography_type,
+                      0 AS scenario,
+                      0 AS statistic)
                             AS state_data) AS percents
                 WHERE
                     id = $1
@@ -128,6 +545,8 @@ impl<'c> Table<'c, Data> {
                     AND source = entry.source
                     AND start_date = entry.start_date
                     AND end_date = entry.end_date
+                    AND scenario = $4
+                    AND statistic = $5
             )
         END
         FROM
@@ -155,6 +574,11 @@ impl<'c> Table<'c, Data> {
                             MAX("source") AS source
                         FROM
                             data
+                        WHERE
+                            scenario = $4
+                            AND statistic = $5
+                            AND dataset NOT IN (SELECT id FROM dataset WHERE deleted_at IS NOT NULL)
+                            AND source NOT IN (SELECT id FROM data_source WHERE deleted_at IS NOT NULL)
                         GROUP BY
                             dataset
                     ) AS cd
@@ -170,6 +594,10 @@ impl<'c> Table<'c, Data> {
             AND data.source = entry.source
             AND data.start_date = entry.start_date
             AND data.end_date = entry.end_date
+            AND data.scenario = $4
+            AND data.statistic = $5
+            AND data.dataset NOT IN (SELECT id FROM dataset WHERE deleted_at IS NOT NULL)
+            AND data.source NOT IN (SELECT id FROM data_source WHERE deleted_at IS NOT NULL)
         GROUP BY
             entry.dataset,
             entry.dataset_name,
@@ -183,7 +611,9 @@ impl<'c> Table<'c, Data> {
         "#,
             info.geo_id,
             info.category,
-            info.geography_type
+            info.geography_type,
+            info.scenario,
+            info.statistic
         )
         .fetch_all(&*self.pool)
         .await
@@ -220,6 +650,8 @@ impl<'c> Table<'c, Data> {
                     AND source = entry.source
                     AND start_date = entry.start_date
                     AND end_date = entry.end_date
+                    AND scenario = $4
+                    AND statistic = $5
             ),
             CASE (
                 SELECT
@@ -233,6 +665,8 @@ impl<'c> Table<'c, Data> {
                     AND source = entry.source
                     AND start_date = entry.start_date
                     AND end_date = entry.end_date
+                    AND scenario = $4
+                    AND statistic = $5
             ) 
             WHEN NULL THEN NULL 
             WHEN 0 THEN 0
@@ -248,6 +682,8 @@ impl<'c> Table<'c, Data> {
                             AND source = entry.source
                             AND start_date = entry.start_date
                             AND end_date = entry.end_date
+                            AND scenario = $4
+                            AND statistic = $5
                             AND value != 0
                     UNION
                     SELECT
@@ -257,7 +693,9 @@ impl<'c> Table<'c, Data> {
                       '2000-01-01' AS end_date,
                       0 AS value,
                       0 AS id,
-                      0 AS geography_type) 
+                      0 AS geography_type,
+                      0 AS scenario,
+                      0 AS statistic) 
                             AS state_data) AS percents
                 WHERE
                     id = $1
@@ -265,6 +703,8 @@ impl<'c> Table<'c, Data> {
                     AND source = entry.source
                     AND start_date = entry.start_date
                     AND end_date = entry.end_date
+                    AND scenario = $4
+                    AND statistic = $5
             )
         END
         FROM
@@ -293,6 +733,11 @@ impl<'c> Table<'c, Data> {
                             MAX("source") AS source
                         FROM
                             data
+                        WHERE
+                            scenario = $4
+                            AND statistic = $5
+                            AND dataset NOT IN (SELECT id FROM dataset WHERE deleted_at IS NOT NULL)
+                            AND source NOT IN (SELECT id FROM data_source WHERE deleted_at IS NOT NULL)
                         GROUP BY
                             dataset
                     ) AS cd
@@ -308,6 +753,10 @@ impl<'c> Table<'c, Data> {
             AND state_data.source = entry.source
             AND state_data.start_date = entry.start_date
             AND state_data.end_date = entry.end_date
+            AND state_data.scenario = $4
+            AND state_data.statistic = $5
+            AND state_data.dataset NOT IN (SELECT id FROM dataset WHERE deleted_at IS NOT NULL)
+            AND state_data.source NOT IN (SELECT id FROM data_source WHERE deleted_at IS NOT NULL)
         GROUP BY
             entry.dataset,
             entry.dataset_name,
@@ -321,69 +770,255 @@ impl<'c> Table<'c, Data> {
         "#,
             info.geo_id,
             info.category,
-            info.geography_type
+            info.geography_type,
+            info.scenario,
+            info.statistic
         )
         .fetch_all(&*self.pool)
         .await
     }
 
-    pub async fn insert(&self, data: &HashSet<Creator>) -> Result<PgQueryResult, sqlx::Error> {
-        let mut ids: Vec<i64> = Vec::with_capacity(data.len());
-        let mut sources: Vec<i32> = Vec::with_capacity(data.len());
-        let mut datasets: Vec<i32> = Vec::with_capacity(data.len());
-        let mut start_dates: Vec<NaiveDate> = Vec::with_capacity(data.len());
-        let mut end_dates: Vec<NaiveDate> = Vec::with_capacity(data.len());
-        let mut values: Vec<f64> = Vec::with_capacity(data.len());
-        let mut geography_types: Vec<i32> = Vec::with_capacity(data.len());
+    pub async fn delete_by_dataset(
+        &self,
+        dataset: i32,
+        user: &User,
+    ) -> Result<PgQueryResult, sqlx::Error> {
+        let mut transaction = self.pool.begin().await?;
+        let result = sqlx::query!("DELETE FROM data WHERE dataset = $1", dataset)
+            .execute(&mut transaction)
+            .await?;
+        let change = Change::deleted(
+            Entity::Data,
+            dataset,
+            &json!({ "rows": result.rows_affected() }),
+        );
+        audit_dao::record(&mut transaction, user, &change).await?;
+        transaction.commit().await?;
+        Ok(result)
+    }
 
-        data.iter().for_each(|row| {
-            ids.push(row.id);
-            sources.push(row.source);
-            datasets.push(row.dataset);
-            start_dates.push(row.start_date);
-            end_dates.push(row.end_date);
-            values.push(row.value);
-            geography_types.push(row.geography_type);
-        });
+    /// Stores an upload in one transaction with its audit entries: its data source if it's new,
+    /// its datasets, their data and the map visualizations asked for. Nothing is stored if the
+    /// upload is rejected, like for an existing data source that's missing or in the trash.
+    pub async fn upload(
+        &self,
+        metadata: &UploadMetadata,
+        data: &HashSet<data::Parsed>,
+        user: &User,
+    ) -> Result<Result<Uploaded, Rejected>, sqlx::Error> {
+        let mut transaction = self.pool.begin().await?;
+        let source = match &metadata.source {
+            Source::ExistingId(id) => {
+                // Shared, so the source can't be put in the trash until the upload is stored
+                let existing = sqlx::query!(
+                    "SELECT id FROM data_source WHERE id = $1 AND deleted_at IS NULL FOR SHARE",
+                    id
+                )
+                .fetch_optional(&mut transaction)
+                .await?;
+                match existing {
+                    None => return Ok(Err(Rejected::UnknownSource(*id))),
+                    Some(existing) => existing.id,
+                }
+            }
+            Source::New(data_source) => {
+                let id = data_source_dao::insert(&mut transaction, data_source).await?;
+                let change = Change::created(Entity::DataSource, id, data_source);
+                audit_dao::record(&mut transaction, user, &change).await?;
+                id
+            }
+        };
 
-        // https://github.com/launchbadge/sqlx/issues/294#issuecomment-886080306
-        sqlx::query!(
-            "
-            INSERT INTO
-            data (
-                id,
-                source,
-                dataset,
-                start_date,
-                end_date,
-                value,
-                geography_type
-            )
-            SELECT *
-            FROM
-            UNNEST ($1::bigint[], $2::int[], $3::int[], $4::date[], $5::date[], $6::float[], $7::int[])
-            ",
-            &ids,
-            &sources,
-            &datasets,
-            &start_dates,
-            &end_dates,
-            &values,
-            &geography_types
-        )
-        .execute(&*self.pool)
-        .await
-    }
+        let mut datasets = HashMap::new();
+        for dataset in &metadata.datasets {
+            let creator = dataset::Creator::from(dataset.clone(), metadata.geography_type);
+            let created = dataset_dao::insert(&mut transaction, &creator).await?;
+            datasets.insert(dataset.column.clone(), created);
+        }
 
-    pub async fn delete_by_dataset(&self, dataset: i32) -> Result<PgQueryResult, sqlx::Error> {
-        sqlx::query!("DELETE FROM data WHERE dataset = $1", dataset)
-            .execute(&*self.pool)
-            .await
+        let data = data
+            .iter()
+            .map(|row| {
+                datasets.get(&row.dataset).map(|dataset| {
+                    Creator::new(
+                        row,
+                        dataset.id,
+                        source,
+                        dataset.geography_type,
+                        metadata.scenario,
+                        metadata.statistic,
+                    )
+                })
+            })
+            .collect::<Option<HashSet<_>>>();
+        let data = match data {
+            None => return Ok(Err(Rejected::UnmatchedData)),
+            Some(data) => data,
+        };
+        insert(&mut transaction, &data).await?;
+
+        for dataset in datasets.values() {
+            let after = json!({
+                "dataset": dataset,
+                "source": source,
+                "scenario": metadata.scenario,
+                "statistic": metadata.statistic,
+                "rows": data.iter().filter(|row| row.dataset == dataset.id).count(),
+            });
+            let change = Change::uploaded(Entity::Dataset, dataset.id, &after);
+            audit_dao::record(&mut transaction, user, &change).await?;
+        }
+
+        let uploaded = Uploaded {
+            source,
+            datasets,
+            data,
+        };
+        if let Some(ref options) = metadata.map_visualizations {
+            let mut columns: Vec<&String> = uploaded.datasets.keys().collect();
+            columns.sort();
+            for column in columns {
+                let dataset = &uploaded.datasets[column];
+                let creator = uploaded.map_visualization(dataset, options);
+                let errors = validation::validate(&creator.patch(), &uploaded.slices(dataset.id));
+                if !errors.is_empty() {
+                    return Ok(Err(Rejected::InvalidMapVisualization {
+                        column: column.clone(),
+                        errors,
+                    }));
+                }
+                map_visualization_dao::insert(&mut transaction, &creator, user).await?;
+            }
+        }
+        transaction.commit().await?;
+        Ok(Ok(uploaded))
     }
+}
+
+/// Inserts rows of data, for writes that insert them along with other rows
+pub(super) async fn insert<'e>(
+    executor: impl PgExecutor<'e>,
+    data: &HashSet<Creator>,
+) -> Result<PgQueryResult, sqlx::Error> {
+    let mut ids: Vec<i64> = Vec::with_capacity(data.len());
+    let mut sources: Vec<i32> = Vec::with_capacity(data.len());
+    let mut datasets: Vec<i32> = Vec::with_capacity(data.len());
+    let mut start_dates: Vec<NaiveDate> = Vec::with_capacity(data.len());
+    let mut end_dates: Vec<NaiveDate> = Vec::with_capacity(data.len());
+    let mut values: Vec<f64> = Vec::with_capacity(data.len());
+    let mut geography_types: Vec<i32> = Vec::with_capacity(data.len());
+    let mut scenarios: Vec<i32> = Vec::with_capacity(data.len());
+    let mut statistics: Vec<i32> = Vec::with_capacity(data.len());
 
-    pub async fn delete_by_source(&self, source: i32) -> Result<PgQueryResult, sqlx::Error> {
-        sqlx::query!("DELETE FROM data WHERE source = $1", source)
-            .execute(&*self.pool)
+    data.iter().for_each(|row| {
+        ids.push(row.id);
+        sources.push(row.source);
+        datasets.push(row.dataset);
+        start_dates.push(row.start_date);
+        end_dates.push(row.end_date);
+        values.push(row.value);
+        geography_types.push(row.geography_type);
+        scenarios.push(row.scenario);
+        statistics.push(row.statistic);
+    });
+
+    // https://github.com/launchbadge/sqlx/issues/294#issuecomment-886080306
+    sqlx::query!(
+        "
+        INSERT INTO
+        data (
+            id,
+            source,
+            dataset,
+            start_date,
+            end_date,
+            value,
+            geography_type,
+            scenario,
+            statistic
+        )
+        SELECT *
+        FROM
+        UNNEST (
+            $1::bigint[],
+            $2::int[],
+            $3::int[],
+            $4::date[],
+            $5::date[],
+            $6::float[],
+            $7::int[],
+            $8::int[],
+            $9::int[]
+        )
+        ",
+        &ids,
+        &sources,
+        &datasets,
+        &start_dates,
+        &end_dates,
+        &values,
+        &geography_types,
+        &scenarios,
+        &statistics
+    )
+    .execute(executor)
+    .await
+}
+
+async fn bulk_page(
+    pool: &Arc<PgPool>,
+    datasets: &[i32],
+    after: Option<&Long>,
+) -> Result<Vec<Long>, sqlx::Error> {
+    match after {
+        None => {
+            sqlx::query_as!(
+                Long,
+                "
+                SELECT
+                    dataset, source, scenario, statistic, start_date, end_date, geography_type, id,
+                    value
+                FROM data
+                WHERE dataset = ANY($1)
+                AND dataset NOT IN (SELECT id FROM dataset WHERE deleted_at IS NOT NULL)
+                AND source NOT IN (SELECT id FROM data_source WHERE deleted_at IS NOT NULL)
+                ORDER BY dataset, source, scenario, statistic, start_date, end_date, id
+                LIMIT $2
+                ",
+                datasets,
+                BULK_PAGE_SIZE
+            )
+            .fetch_all(&**pool)
+            .await
+        }
+        Some(after) => {
+            sqlx::query_as!(
+                Long,
+                "
+                SELECT
+                    dataset, source, scenario, statistic, start_date, end_date, geography_type, id,
+                    value
+                FROM data
+                WHERE dataset = ANY($1)
+                AND dataset NOT IN (SELECT id FROM dataset WHERE deleted_at IS NOT NULL)
+                AND source NOT IN (SELECT id FROM data_source WHERE deleted_at IS NOT NULL)
+                AND (dataset, source, scenario, statistic, start_date, end_date, id)
+                    > ($3, $4, $5, $6, $7, $8, $9)
+                ORDER BY dataset, source, scenario, statistic, start_date, end_date, id
+                LIMIT $2
+                ",
+                datasets,
+                BULK_PAGE_SIZE,
+                after.dataset,
+                after.source,
+                after.scenario,
+                after.statistic,
+                after.start_date,
+                after.end_date,
+                after.id
+            )
+            .fetch_all(&**pool)
             .await
+        }
     }
 }
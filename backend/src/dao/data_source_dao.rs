@@ -1,18 +1,78 @@
-use super::Table;
+use super::{audit_dao, Table};
+use crate::model::audit::{Change, Entity};
 use crate::model::data_source::{self, DataSource};
-use sqlx::postgres::PgQueryResult;
+use crate::model::trash::{Item, Kind};
+use crate::model::user::User;
+use chrono::{DateTime, Utc};
+use sqlx::postgres::PgExecutor;
+
+/// Creates a data source and returns its id, for writes that create it along with other rows
+pub(super) async fn insert<'e>(
+    executor: impl PgExecutor<'e>,
+    data_source: &data_source::Creator,
+) -> Result<i32, sqlx::Error> {
+    sqlx::query!(
+        "
+        INSERT INTO data_source (name, description, link)
+        VALUES ($1, $2, $3)
+        RETURNING id
+        ",
+        data_source.name,
+        data_source.description,
+        data_source.link,
+    )
+    .fetch_one(executor)
+    .await
+    .map(|row| row.id)
+}
+
+/// A data source, even one in the trash, locked until the transaction ends
+pub(super) async fn locked<'e>(
+    executor: impl PgExecutor<'e>,
+    id: i32,
+) -> Result<Option<DataSource>, sqlx::Error> {
+    sqlx::query_as!(
+        DataSource,
+        "SELECT id, name, description, link FROM data_source WHERE id = $1 FOR UPDATE",
+        id
+    )
+    .fetch_optional(executor)
+    .await
+}
 
 impl<'c> Table<'c, DataSource> {
     pub async fn all(&self) -> Result<Vec<DataSource>, sqlx::Error> {
-        sqlx::query_as!(DataSource, "SELECT * FROM data_source ORDER BY id")
-            .fetch_all(&*self.pool)
-            .await
+        sqlx::query_as!(
+            DataSource,
+            "
+            SELECT id, name, description, link
+            FROM data_source
+            WHERE deleted_at IS NULL
+            ORDER BY id
+            "
+        )
+        .fetch_all(&*self.pool)
+        .await
+    }
+
+    pub async fn by_id(&self, id: i32) -> Result<DataSource, sqlx::Error> {
+        sqlx::query_as!(
+            DataSource,
+            "
+            SELECT id, name, description, link
+            FROM data_source
+            WHERE id = $1 AND deleted_at IS NULL
+            ",
+            id
+        )
+        .fetch_one(&*self.pool)
+        .await
     }
 
     pub async fn by_name(&self, name: &str) -> Result<Option<DataSource>, sqlx::Error> {
         sqlx::query_as!(
             DataSource,
-            "SELECT * FROM data_source WHERE name = $1",
+            "SELECT id, name, description, link FROM data_source WHERE name = $1",
             name
         )
         .fetch_optional(&*self.pool)
@@ -27,6 +87,8 @@ impl<'c> Table<'c, DataSource> {
             FROM data, data_source
             WHERE dataset = $1
             AND data_source.id = data.source
+            AND data_source.deleted_at IS NULL
+            AND dataset NOT IN (SELECT id FROM dataset WHERE deleted_at IS NOT NULL)
             ",
             id
         )
@@ -34,46 +96,134 @@ impl<'c> Table<'c, DataSource> {
         .await
     }
 
-    pub async fn create(&self, data_source: &data_source::Creator) -> Result<i32, sqlx::Error> {
-        sqlx::query!(
-            "
-            INSERT INTO data_source (name, description, link)
-            VALUES ($1, $2, $3)
-            RETURNING id
-            ",
-            data_source.name,
-            data_source.description,
-            data_source.link,
-        )
-        .fetch_one(&*self.pool)
-        .await
-        .map(|row| row.id)
+    pub async fn create(
+        &self,
+        data_source: &data_source::Creator,
+        user: &User,
+    ) -> Result<i32, sqlx::Error> {
+        let mut transaction = self.pool.begin().await?;
+        let id = insert(&mut transaction, data_source).await?;
+        let change = Change::created(Entity::DataSource, id, data_source);
+        audit_dao::record(&mut transaction, user, &change).await?;
+        transaction.commit().await?;
+        Ok(id)
     }
 
+    /// Updates a data source and returns it, or `None` if there's no such data source outside the
+    /// trash
     pub async fn update(
         &self,
         data_source: &data_source::Diff,
-    ) -> Result<PgQueryResult, sqlx::Error> {
-        return sqlx::query!(
+        user: &User,
+    ) -> Result<Option<DataSource>, sqlx::Error> {
+        let mut transaction = self.pool.begin().await?;
+        let before = locked(&mut transaction, data_source.id).await?;
+        let after = sqlx::query_as!(
+            DataSource,
             "
             UPDATE data_source
             SET name = COALESCE($1, name),
                 description = COALESCE($2, description),
                 link = COALESCE($3, link)
-            WHERE id = $4
+            WHERE id = $4 AND deleted_at IS NULL
+            RETURNING id, name, description, link
             ",
             data_source.name,
             data_source.description,
             data_source.link,
             data_source.id
         )
-        .execute(&*self.pool)
-        .await;
+        .fetch_optional(&mut transaction)
+        .await?;
+        if let Some(after) = &after {
+            let change = Change::updated(Entity::DataSource, data_source.id, &before, after);
+            audit_dao::record(&mut transaction, user, &change).await?;
+        }
+        transaction.commit().await?;
+        Ok(after)
     }
 
-    pub async fn delete(&self, id: i32) -> Result<PgQueryResult, sqlx::Error> {
-        sqlx::query!("DELETE FROM data_source WHERE id = $1", id)
-            .execute(&*self.pool)
-            .await
+    /// Puts a data source in the trash. `None` if there's no such data source outside the
+    /// trash.
+    pub async fn trash(&self, id: i32, user: &User) -> Result<Option<Item>, sqlx::Error> {
+        let mut transaction = self.pool.begin().await?;
+        let before = locked(&mut transaction, id).await?;
+        let item = sqlx::query!(
+            r#"
+            UPDATE data_source
+            SET deleted_at = NOW()
+            WHERE id = $1 AND deleted_at IS NULL
+            RETURNING id, name, deleted_at AS "deleted_at!"
+            "#,
+            id
+        )
+        .fetch_optional(&mut transaction)
+        .await?
+        .map(|row| Item {
+            kind: Kind::DataSource,
+            id: row.id,
+            name: Some(row.name),
+            deleted_at: row.deleted_at,
+        });
+        if item.is_some() {
+            let change = Change::deleted(Entity::DataSource, id, &before);
+            audit_dao::record(&mut transaction, user, &change).await?;
+        }
+        transaction.commit().await?;
+        Ok(item)
+    }
+
+    /// Takes a data source out of the trash, returning when it was deleted. `None` if there's no such
+    /// data source in the trash.
+    pub async fn restore(&self, id: i32, user: &User) -> Result<Option<Item>, sqlx::Error> {
+        let mut transaction = self.pool.begin().await?;
+        let item = sqlx::query!(
+            r#"
+            UPDATE data_source
+            SET deleted_at = NULL
+            FROM data_source AS old
+            WHERE data_source.id = $1 AND old.id = data_source.id AND old.deleted_at IS NOT NULL
+            RETURNING data_source.id, data_source.name, old.deleted_at AS "deleted_at!"
+            "#,
+            id
+        )
+        .fetch_optional(&mut transaction)
+        .await?
+        .map(|row| Item {
+            kind: Kind::DataSource,
+            id: row.id,
+            name: Some(row.name),
+            deleted_at: row.deleted_at,
+        });
+        if let Some(item) = &item {
+            let change = Change::restored(Entity::DataSource, id, item);
+            audit_dao::record(&mut transaction, user, &change).await?;
+        }
+        transaction.commit().await?;
+        Ok(item)
+    }
+
+    /// The data sources in the trash that were deleted before a time
+    pub async fn trashed(&self, before: DateTime<Utc>) -> Result<Vec<Item>, sqlx::Error> {
+        sqlx::query!(
+            r#"
+            SELECT id, name, deleted_at AS "deleted_at!"
+            FROM data_source
+            WHERE deleted_at < $1
+            "#,
+            before
+        )
+        .fetch_all(&*self.pool)
+        .await
+        .map(|rows| {
+            rows.into_iter()
+                .map(|row| Item {
+                    kind: Kind::DataSource,
+                    id: row.id,
+                    name: Some(row.name),
+                    deleted_at: row.deleted_at,
+                })
+                .collect()
+        })
     }
 }
@@ -1,16 +1,30 @@
+use crate::model::audit;
+use crate::model::classification;
 use crate::model::color_palette::ColorPalette;
+use crate::model::crosswalk::Crosswalk;
+use crate::model::custom_region::CustomRegion;
 use crate::model::data::{Data, SourceAndDate};
 use crate::model::data_category::DataCategory;
 use crate::model::data_source::DataSource;
 use crate::model::dataset::Dataset;
+use crate::model::deletion::Dependent;
+use crate::model::derived_dataset::DerivedDataset;
+use crate::model::geo_boundary::Boundary;
 use crate::model::geo_id::{County, GeoId, State};
 use crate::model::geography_type;
 use crate::model::map_visualization::MapVisualization;
 use crate::model::map_visualization_collection::Collection;
+use crate::model::neighbor::Neighbor;
+use crate::model::publication::Changes;
+use crate::model::revision::Revision;
 use crate::model::scale_type;
+use crate::model::scenario::Scenario;
+use crate::model::statistic::Statistic;
 use crate::model::subcategory::Subcategory;
-use sqlx::postgres::PgRow;
-use sqlx::{FromRow, PgPool};
+use crate::model::tile::Tile;
+use crate::model::user::{Token, User};
+use sqlx::postgres::{PgPoolOptions, PgRow};
+use sqlx::{Executor, FromRow, PgPool};
 use std::sync::Arc;
 
 pub struct Table<'c, T>
@@ -35,41 +49,93 @@ where
 
 pub struct Database<'c> {
     pub state: Arc<Table<'c, State>>,
+    pub audit: Arc<Table<'c, audit::Entry>>,
+    pub classification: Arc<Table<'c, classification::Cached>>,
     pub county: Arc<Table<'c, County>>,
+    pub crosswalk: Arc<Table<'c, Crosswalk>>,
+    pub custom_region: Arc<Table<'c, CustomRegion>>,
     pub data: Arc<Table<'c, Data>>,
     pub dataset: Arc<Table<'c, Dataset>>,
+    pub derived_dataset: Arc<Table<'c, DerivedDataset>>,
+    pub deletion: Arc<Table<'c, Dependent>>,
     pub map_visualization: Arc<Table<'c, MapVisualization>>,
     pub map_visualization_collection: Arc<Table<'c, Collection>>,
+    pub neighbor: Arc<Table<'c, Neighbor>>,
+    pub publication: Arc<Table<'c, Changes>>,
+    pub revision: Arc<Table<'c, Revision>>,
     pub data_category: Arc<Table<'c, DataCategory>>,
     pub source_and_date: Arc<Table<'c, SourceAndDate>>,
     pub data_source: Arc<Table<'c, DataSource>>,
     pub color_palette: Arc<Table<'c, ColorPalette>>,
     pub scale_type: Arc<Table<'c, scale_type::Type>>,
+    pub scenario: Arc<Table<'c, Scenario>>,
+    pub statistic: Arc<Table<'c, Statistic>>,
     pub subcategory: Arc<Table<'c, Subcategory>>,
     pub geo_id: Arc<Table<'c, GeoId>>,
     pub geography_type: Arc<Table<'c, geography_type::Type>>,
+    pub geo_boundary: Arc<Table<'c, Boundary>>,
+    pub tile: Arc<Table<'c, Tile>>,
+    pub token: Arc<Table<'c, Token>>,
+    pub user: Arc<Table<'c, User>>,
 }
 
 impl Database<'_> {
     pub async fn new(sql_url: &str) -> Database<'_> {
         let pool = PgPool::connect(sql_url).await.unwrap();
+        Database::from_pool(pool)
+    }
+
+    /// A database that reads the published map visualizations, data categories and collections
+    /// instead of the drafts
+    pub async fn published(sql_url: &str) -> Database<'_> {
+        let pool = PgPoolOptions::new()
+            .after_connect(|connection, _| {
+                Box::pin(async move {
+                    connection
+                        .execute("SET search_path = published, public")
+                        .await?;
+                    Ok(())
+                })
+            })
+            .connect(sql_url)
+            .await
+            .unwrap();
+        Database::from_pool(pool)
+    }
+
+    pub(super) fn from_pool<'c>(pool: PgPool) -> Database<'c> {
         let pool = Arc::new(pool);
 
         Database {
             geography_type: Arc::from(Table::new(pool.clone())),
+            geo_boundary: Arc::from(Table::new(pool.clone())),
+            tile: Arc::from(Table::new(pool.clone())),
             geo_id: Arc::from(Table::new(pool.clone())),
             state: Arc::from(Table::new(pool.clone())),
+            audit: Arc::from(Table::new(pool.clone())),
+            classification: Arc::from(Table::new(pool.clone())),
             county: Arc::from(Table::new(pool.clone())),
+            crosswalk: Arc::from(Table::new(pool.clone())),
+            custom_region: Arc::from(Table::new(pool.clone())),
             data: Arc::from(Table::new(pool.clone())),
             dataset: Arc::from(Table::new(pool.clone())),
+            derived_dataset: Arc::from(Table::new(pool.clone())),
+            deletion: Arc::from(Table::new(pool.clone())),
             map_visualization: Arc::from(Table::new(pool.clone())),
             map_visualization_collection: Arc::from(Table::new(pool.clone())),
+            neighbor: Arc::from(Table::new(pool.clone())),
+            publication: Arc::from(Table::new(pool.clone())),
+            revision: Arc::from(Table::new(pool.clone())),
             data_category: Arc::from(Table::new(pool.clone())),
             source_and_date: Arc::from(Table::new(pool.clone())),
             data_source: Arc::from(Table::new(pool.clone())),
             color_palette: Arc::from(Table::new(pool.clone())),
             scale_type: Arc::from(Table::new(pool.clone())),
-            subcategory: Arc::from(Table::new(pool)),
+            scenario: Arc::from(Table::new(pool.clone())),
+            statistic: Arc::from(Table::new(pool.clone())),
+            subcategory: Arc::from(Table::new(pool.clone())),
+            token: Arc::from(Table::new(pool.clone())),
+            user: Arc::from(Table::new(pool)),
         }
     }
 }
@@ -1,6 +1,51 @@
-use super::Table;
+use super::{audit_dao, Table};
+use crate::model::audit::{Change, Entity};
 use crate::model::dataset::{self, Creator, Dataset};
-use sqlx::postgres::PgQueryResult;
+use crate::model::trash::{Item, Kind};
+use crate::model::user::User;
+use chrono::{DateTime, Utc};
+use sqlx::postgres::PgExecutor;
+
+/// Creates a dataset, for writes that create it along with other rows
+pub(super) async fn insert<'e>(
+    executor: impl PgExecutor<'e>,
+    dataset: &Creator,
+) -> Result<Dataset, sqlx::Error> {
+    sqlx::query_as!(
+        Dataset,
+        "
+        INSERT INTO dataset (short_name, name, description, units, geography_type)
+        VALUES ($1, $2, $3, $4, $5)
+        RETURNING id, short_name, name, description, units, geography_type
+        ",
+        dataset.short_name,
+        dataset.name,
+        dataset.description,
+        dataset.units,
+        dataset.geography_type,
+    )
+    .fetch_one(executor)
+    .await
+}
+
+/// A dataset, even one in the trash, locked until the transaction ends
+pub(super) async fn locked<'e>(
+    executor: impl PgExecutor<'e>,
+    id: i32,
+) -> Result<Option<Dataset>, sqlx::Error> {
+    sqlx::query_as!(
+        Dataset,
+        "
+        SELECT id, short_name, name, description, units, geography_type
+        FROM dataset
+        WHERE id = $1
+        FOR UPDATE
+        ",
+        id
+    )
+    .fetch_optional(executor)
+    .await
+}
 
 impl<'c> Table<'c, dataset::Dataset> {
     pub async fn find_duplicates(
@@ -10,7 +55,7 @@ impl<'c> Table<'c, dataset::Dataset> {
         sqlx::query_as!(
             Dataset,
             "
-            SELECT *
+            SELECT id, short_name, name, description, units, geography_type
             FROM dataset
             WHERE short_name = ANY($1)
             OR name = ANY($2)
@@ -28,9 +73,17 @@ impl<'c> Table<'c, dataset::Dataset> {
         .await
     }
 
-    pub async fn update(&self, dataset: &dataset::Diff) -> Result<PgQueryResult, sqlx::Error> {
+    /// Updates a dataset and returns it, or `None` if there's no such dataset outside the trash
+    pub async fn update(
+        &self,
+        dataset: &dataset::Diff,
+        user: &User,
+    ) -> Result<Option<Dataset>, sqlx::Error> {
+        let mut transaction = self.pool.begin().await?;
+        let before = locked(&mut transaction, dataset.id).await?;
         // COALESCE values to update only if they are not None
-        sqlx::query!(
+        let after = sqlx::query_as!(
+            Dataset,
             "
             UPDATE dataset
             SET short_name = COALESCE($1, short_name),
@@ -38,7 +91,8 @@ impl<'c> Table<'c, dataset::Dataset> {
                 description = COALESCE($3, description),
                 units = COALESCE($4, units),
                 geography_type = COALESCE($5, geography_type)
-            WHERE id = $6
+            WHERE id = $6 AND deleted_at IS NULL
+            RETURNING id, short_name, name, description, units, geography_type
             ",
             dataset.short_name,
             dataset.name,
@@ -47,8 +101,14 @@ impl<'c> Table<'c, dataset::Dataset> {
             dataset.geography_type,
             dataset.id,
         )
-        .execute(&*self.pool)
-        .await
+        .fetch_optional(&mut transaction)
+        .await?;
+        if let Some(after) = &after {
+            let change = Change::updated(Entity::Dataset, dataset.id, &before, after);
+            audit_dao::record(&mut transaction, user, &change).await?;
+        }
+        transaction.commit().await?;
+        Ok(after)
     }
 
     pub async fn by_id(&self, id: i32) -> Result<dataset::Dataset, sqlx::Error> {
@@ -57,7 +117,7 @@ impl<'c> Table<'c, dataset::Dataset> {
             "
             SELECT id, short_name, name, description, units, geography_type
             FROM dataset
-            WHERE id = $1
+            WHERE id = $1 AND deleted_at IS NULL
             ",
             id
         )
@@ -71,6 +131,7 @@ impl<'c> Table<'c, dataset::Dataset> {
             "
             SELECT id, short_name, name, description, units, geography_type
             FROM dataset
+            WHERE deleted_at IS NULL
             ORDER BY id
             "
         )
@@ -78,41 +139,90 @@ impl<'c> Table<'c, dataset::Dataset> {
         .await
     }
 
-    pub async fn first(&self) -> Result<dataset::Dataset, sqlx::Error> {
-        sqlx::query_as!(
-            dataset::Dataset,
-            "
-            SELECT id, short_name, name, description, units, geography_type
-            FROM dataset
-            ORDER BY id
-            LIMIT 1
-            "
+    pub async fn create(&self, dataset: &Creator) -> Result<dataset::Dataset, sqlx::Error> {
+        insert(&*self.pool, dataset).await
+    }
+
+    /// Puts a dataset in the trash. `None` if there's no such dataset outside the trash.
+    pub async fn trash(&self, id: i32, user: &User) -> Result<Option<Item>, sqlx::Error> {
+        let mut transaction = self.pool.begin().await?;
+        let before = locked(&mut transaction, id).await?;
+        let item = sqlx::query!(
+            r#"
+            UPDATE dataset
+            SET deleted_at = NOW()
+            WHERE id = $1 AND deleted_at IS NULL
+            RETURNING id, name, deleted_at AS "deleted_at!"
+            "#,
+            id
         )
-        .fetch_one(&*self.pool)
-        .await
+        .fetch_optional(&mut transaction)
+        .await?
+        .map(|row| Item {
+            kind: Kind::Dataset,
+            id: row.id,
+            name: Some(row.name),
+            deleted_at: row.deleted_at,
+        });
+        if item.is_some() {
+            let change = Change::deleted(Entity::Dataset, id, &before);
+            audit_dao::record(&mut transaction, user, &change).await?;
+        }
+        transaction.commit().await?;
+        Ok(item)
     }
 
-    pub async fn create(&self, dataset: &Creator) -> Result<dataset::Dataset, sqlx::Error> {
-        sqlx::query_as!(
-            dataset::Dataset,
-            "
-            INSERT INTO dataset (short_name, name, description, units, geography_type)
-            VALUES ($1, $2, $3, $4, $5)
-            RETURNING *
-            ",
-            dataset.short_name,
-            dataset.name,
-            dataset.description,
-            dataset.units,
-            dataset.geography_type,
+    /// Takes a dataset out of the trash, returning when it was deleted. `None` if there's no such
+    /// dataset in the trash.
+    pub async fn restore(&self, id: i32, user: &User) -> Result<Option<Item>, sqlx::Error> {
+        let mut transaction = self.pool.begin().await?;
+        let item = sqlx::query!(
+            r#"
+            UPDATE dataset
+            SET deleted_at = NULL
+            FROM dataset AS old
+            WHERE dataset.id = $1 AND old.id = dataset.id AND old.deleted_at IS NOT NULL
+            RETURNING dataset.id, dataset.name, old.deleted_at AS "deleted_at!"
+            "#,
+            id
         )
-        .fetch_one(&*self.pool)
-        .await
+        .fetch_optional(&mut transaction)
+        .await?
+        .map(|row| Item {
+            kind: Kind::Dataset,
+            id: row.id,
+            name: Some(row.name),
+            deleted_at: row.deleted_at,
+        });
+        if let Some(item) = &item {
+            let change = Change::restored(Entity::Dataset, id, item);
+            audit_dao::record(&mut transaction, user, &change).await?;
+        }
+        transaction.commit().await?;
+        Ok(item)
     }
 
-    pub async fn delete(&self, id: i32) -> Result<PgQueryResult, sqlx::Error> {
-        sqlx::query!("DELETE FROM dataset WHERE id = $1", id)
-            .execute(&*self.pool)
-            .await
+    /// The datasets in the trash that were deleted before a time
+    pub async fn trashed(&self, before: DateTime<Utc>) -> Result<Vec<Item>, sqlx::Error> {
+        sqlx::query!(
+            r#"
+            SELECT id, name, deleted_at AS "deleted_at!"
+            FROM dataset
+            WHERE deleted_at < $1
+            "#,
+            before
+        )
+        .fetch_all(&*self.pool)
+        .await
+        .map(|rows| {
+            rows.into_iter()
+                .map(|row| Item {
+                    kind: Kind::Dataset,
+                    id: row.id,
+                    name: Some(row.name),
+                    deleted_at: row.deleted_at,
+                })
+                .collect()
+        })
     }
 }
@@ -0,0 +1,421 @@
+use super::Table;
+use super::{audit_dao, data_category_dao, data_source_dao, dataset_dao, map_visualization_dao};
+use crate::model::audit::{Change, Entity};
+use crate::model::deletion::{Conflict, DataRows, Dependent, Group, Impact};
+use crate::model::map_visualization_collection::Id;
+use crate::model::trash::Kind;
+use crate::model::user::User;
+use chrono::{DateTime, Utc};
+use sqlx::postgres::{PgConnection, PgExecutor};
+
+/// The map visualizations pointing at a dataset or data source, locked until the transaction
+/// ends
+async fn dependents<'e>(
+    executor: impl PgExecutor<'e>,
+    dataset: Option<i32>,
+    source: Option<i32>,
+) -> Result<Vec<Dependent>, sqlx::Error> {
+    sqlx::query_as!(
+        Dependent,
+        "
+        SELECT
+            id,
+            dataset,
+            default_source,
+            delta_from_source,
+            delta_to_source,
+            bivariate_dataset,
+            bivariate_source
+        FROM map_visualization
+        WHERE dataset = $1
+            OR bivariate_dataset = $1
+            OR $2 IN (default_source, delta_from_source, delta_to_source, bivariate_source)
+        ORDER BY id
+        FOR UPDATE
+        ",
+        dataset,
+        source
+    )
+    .fetch_all(executor)
+    .await
+}
+
+async fn collections<'e>(
+    executor: impl PgExecutor<'e>,
+    map_visualizations: &[i32],
+    category: Option<i32>,
+) -> Result<Vec<Id>, sqlx::Error> {
+    sqlx::query_as!(
+        Id,
+        "
+        SELECT category, map_visualization
+        FROM map_visualization_collection
+        WHERE map_visualization = ANY($1) OR category = $2
+        ORDER BY category, map_visualization
+        FOR UPDATE
+        ",
+        map_visualizations,
+        category
+    )
+    .fetch_all(executor)
+    .await
+}
+
+async fn data_rows<'e>(
+    executor: impl PgExecutor<'e>,
+    dataset: Option<i32>,
+    source: Option<i32>,
+) -> Result<Vec<DataRows>, sqlx::Error> {
+    sqlx::query_as!(
+        DataRows,
+        r#"
+        SELECT dataset, source, COUNT(*) AS "rows!"
+        FROM data
+        WHERE dataset = $1 OR source = $2
+        GROUP BY dataset, source
+        ORDER BY dataset, source
+        "#,
+        dataset,
+        source
+    )
+    .fetch_all(executor)
+    .await
+}
+
+async fn delete_collections<'e>(
+    executor: impl PgExecutor<'e>,
+    impact: &Impact,
+) -> Result<(), sqlx::Error> {
+    let (categories, map_visualizations): (Vec<i32>, Vec<i32>) = impact
+        .collections
+        .iter()
+        .map(|collection| (collection.category, collection.map_visualization))
+        .unzip();
+    sqlx::query!(
+        "
+        DELETE FROM map_visualization_collection
+        USING unnest($1::INT[], $2::INT[]) AS deleted (category, map_visualization)
+        WHERE map_visualization_collection.category = deleted.category
+            AND map_visualization_collection.map_visualization = deleted.map_visualization
+        ",
+        &categories,
+        &map_visualizations
+    )
+    .execute(executor)
+    .await?;
+    Ok(())
+}
+
+/// Clears the settings of the map visualizations that are kept. A delta or bivariate map
+/// visualization loses all its delta or bivariate settings, since they are only valid together.
+async fn clear_references<'e>(
+    executor: impl PgExecutor<'e>,
+    impact: &Impact,
+) -> Result<(), sqlx::Error> {
+    sqlx::query!(
+        "
+        UPDATE map_visualization
+        SET
+            default_source = CASE WHEN id = ANY($1) THEN NULL ELSE default_source END,
+            delta_operation = CASE WHEN id = ANY($2) THEN NULL ELSE delta_operation END,
+            delta_from_source = CASE WHEN id = ANY($2) THEN NULL ELSE delta_from_source END,
+            delta_from_start_date =
+                CASE WHEN id = ANY($2) THEN NULL ELSE delta_from_start_date END,
+            delta_from_end_date = CASE WHEN id = ANY($2) THEN NULL ELSE delta_from_end_date END,
+            delta_from_scenario = CASE WHEN id = ANY($2) THEN NULL ELSE delta_from_scenario END,
+            delta_to_source = CASE WHEN id = ANY($2) THEN NULL ELSE delta_to_source END,
+            delta_to_start_date = CASE WHEN id = ANY($2) THEN NULL ELSE delta_to_start_date END,
+            delta_to_end_date = CASE WHEN id = ANY($2) THEN NULL ELSE delta_to_end_date END,
+            delta_to_scenario = CASE WHEN id = ANY($2) THEN NULL ELSE delta_to_scenario END,
+            bivariate_dataset = CASE WHEN id = ANY($3) THEN NULL ELSE bivariate_dataset END,
+            bivariate_source = CASE WHEN id = ANY($3) THEN NULL ELSE bivariate_source END,
+            bivariate_start_date =
+                CASE WHEN id = ANY($3) THEN NULL ELSE bivariate_start_date END,
+            bivariate_end_date = CASE WHEN id = ANY($3) THEN NULL ELSE bivariate_end_date END,
+            bivariate_scenario = CASE WHEN id = ANY($3) THEN NULL ELSE bivariate_scenario END,
+            bivariate_statistic = CASE WHEN id = ANY($3) THEN NULL ELSE bivariate_statistic END
+        WHERE id = ANY($1 || $2 || $3)
+        ",
+        &impact.clearing(Group::DefaultSource),
+        &impact.clearing(Group::Delta),
+        &impact.clearing(Group::Bivariate),
+    )
+    .execute(executor)
+    .await?;
+    Ok(())
+}
+
+/// Whether something is still in the trash from before a time. Checked once it's locked, so
+/// something restored since the trash was listed isn't removed.
+async fn in_trash_before(
+    connection: &mut PgConnection,
+    kind: Kind,
+    id: i32,
+    before: DateTime<Utc>,
+) -> Result<bool, sqlx::Error> {
+    let trashed = match kind {
+        Kind::Dataset => {
+            sqlx::query_scalar!(
+                r#"SELECT EXISTS (SELECT FROM dataset WHERE id = $1 AND deleted_at < $2) AS "trashed!""#,
+                id,
+                before
+            )
+            .fetch_one(connection)
+            .await?
+        }
+        Kind::DataSource => {
+            sqlx::query_scalar!(
+                r#"SELECT EXISTS (SELECT FROM data_source WHERE id = $1 AND deleted_at < $2) AS "trashed!""#,
+                id,
+                before
+            )
+            .fetch_one(connection)
+            .await?
+        }
+        Kind::MapVisualization => {
+            sqlx::query_scalar!(
+                r#"SELECT EXISTS (SELECT FROM map_visualization WHERE id = $1 AND deleted_at < $2) AS "trashed!""#,
+                id,
+                before
+            )
+            .fetch_one(connection)
+            .await?
+        }
+        Kind::DataCategory => {
+            sqlx::query_scalar!(
+                r#"SELECT EXISTS (SELECT FROM data_category WHERE id = $1 AND deleted_at < $2) AS "trashed!""#,
+                id,
+                before
+            )
+            .fetch_one(connection)
+            .await?
+        }
+    };
+    Ok(trashed)
+}
+
+impl<'c> Table<'c, Dependent> {
+    /// Deletes a dataset along with its map visualizations and data, or with `dry_run` only
+    /// finds them. `None` if there's no such dataset, or with `trashed_before`, if it isn't in
+    /// the trash from before then.
+    pub async fn dataset(
+        &self,
+        id: i32,
+        dry_run: bool,
+        trashed_before: Option<DateTime<Utc>>,
+        user: &User,
+    ) -> Result<Option<Impact>, sqlx::Error> {
+        let mut transaction = self.pool.begin().await?;
+        let before = match dataset_dao::locked(&mut transaction, id).await? {
+            None => return Ok(None),
+            Some(before) => before,
+        };
+        if let Some(cutoff) = trashed_before {
+            if !in_trash_before(&mut transaction, Kind::Dataset, id, cutoff).await? {
+                return Ok(None);
+            }
+        }
+        let dependents = dependents(&mut transaction, Some(id), None).await?;
+        let ids: Vec<i32> = dependents
+            .iter()
+            .filter(|dependent| dependent.dataset == id)
+            .map(|dependent| dependent.id)
+            .collect();
+        let collections = collections(&mut transaction, &ids, None).await?;
+        let data = data_rows(&mut transaction, Some(id), None).await?;
+        let impact = Impact::of_dataset(id, &dependents, &collections, &data);
+        if dry_run {
+            return Ok(Some(impact));
+        }
+
+        clear_references(&mut transaction, &impact).await?;
+        delete_collections(&mut transaction, &impact).await?;
+        sqlx::query!(
+            "DELETE FROM map_visualization WHERE id = ANY($1)",
+            &impact.map_visualizations
+        )
+        .execute(&mut transaction)
+        .await?;
+        sqlx::query!("DELETE FROM data WHERE dataset = $1", id)
+            .execute(&mut transaction)
+            .await?;
+        sqlx::query!("DELETE FROM dataset WHERE id = $1", id)
+            .execute(&mut transaction)
+            .await?;
+        let change = Change::purged(Entity::Dataset, id, &before);
+        audit_dao::record(&mut transaction, user, &change).await?;
+        transaction.commit().await?;
+        Ok(Some(impact))
+    }
+
+    /// Deletes a data source, clearing it from map visualizations, or with `dry_run` only finds
+    /// them. A data source with data is only deleted `with_data`, along with its data in every
+    /// dataset. `None` if there's no such data source, or with `trashed_before`, if it isn't in
+    /// the trash from before then.
+    pub async fn data_source(
+        &self,
+        id: i32,
+        dry_run: bool,
+        with_data: bool,
+        trashed_before: Option<DateTime<Utc>>,
+        user: &User,
+    ) -> Result<Option<Result<Impact, Conflict>>, sqlx::Error> {
+        let mut transaction = self.pool.begin().await?;
+        let before = match data_source_dao::locked(&mut transaction, id).await? {
+            None => return Ok(None),
+            Some(before) => before,
+        };
+        if let Some(cutoff) = trashed_before {
+            if !in_trash_before(&mut transaction, Kind::DataSource, id, cutoff).await? {
+                return Ok(None);
+            }
+        }
+        let dependents = dependents(&mut transaction, None, Some(id)).await?;
+        let data = data_rows(&mut transaction, None, Some(id)).await?;
+        let impact = match Impact::of_data_source(id, &dependents, &data, with_data) {
+            Err(conflict) => return Ok(Some(Err(conflict))),
+            Ok(impact) => impact,
+        };
+        if dry_run {
+            return Ok(Some(Ok(impact)));
+        }
+
+        clear_references(&mut transaction, &impact).await?;
+        sqlx::query!("DELETE FROM data WHERE source = $1", id)
+            .execute(&mut transaction)
+            .await?;
+        sqlx::query!("DELETE FROM data_source WHERE id = $1", id)
+            .execute(&mut transaction)
+            .await?;
+        let change = Change::purged(Entity::DataSource, id, &before);
+        audit_dao::record(&mut transaction, user, &change).await?;
+        transaction.commit().await?;
+        Ok(Some(Ok(impact)))
+    }
+
+    /// Deletes a map visualization and takes it out of its categories, or with `dry_run` only
+    /// finds them. `None` if there's no such map visualization, or with `trashed_before`, if it
+    /// isn't in the trash from before then.
+    pub async fn map_visualization(
+        &self,
+        id: i32,
+        dry_run: bool,
+        trashed_before: Option<DateTime<Utc>>,
+        user: &User,
+    ) -> Result<Option<Impact>, sqlx::Error> {
+        let mut transaction = self.pool.begin().await?;
+        let before = match map_visualization_dao::locked(&mut transaction, id).await? {
+            None => return Ok(None),
+            Some(before) => before,
+        };
+        if let Some(cutoff) = trashed_before {
+            if !in_trash_before(&mut transaction, Kind::MapVisualization, id, cutoff).await? {
+                return Ok(None);
+            }
+        }
+        let collections = collections(&mut transaction, &[id], None).await?;
+        let impact = Impact::of_map_visualization(id, &collections);
+        if dry_run {
+            return Ok(Some(impact));
+        }
+
+        delete_collections(&mut transaction, &impact).await?;
+        sqlx::query!("DELETE FROM map_visualization WHERE id = $1", id)
+            .execute(&mut transaction)
+            .await?;
+        let change = Change::purged(Entity::MapVisualization, id, &before);
+        audit_dao::record(&mut transaction, user, &change).await?;
+        transaction.commit().await?;
+        Ok(Some(impact))
+    }
+
+    /// Deletes a category and the places of map visualizations in it, or with `dry_run` only
+    /// finds them. `None` if there's no such category, or with `trashed_before`, if it isn't in
+    /// the trash from before then.
+    pub async fn data_category(
+        &self,
+        id: i32,
+        dry_run: bool,
+        trashed_before: Option<DateTime<Utc>>,
+        user: &User,
+    ) -> Result<Option<Impact>, sqlx::Error> {
+        let mut transaction = self.pool.begin().await?;
+        let before = match data_category_dao::locked(&mut transaction, id).await? {
+            None => return Ok(None),
+            Some(before) => before,
+        };
+        if let Some(cutoff) = trashed_before {
+            if !in_trash_before(&mut transaction, Kind::DataCategory, id, cutoff).await? {
+                return Ok(None);
+            }
+        }
+        let collections = collections(&mut transaction, &[], Some(id)).await?;
+        let impact = Impact::of_data_category(id, &collections);
+        if dry_run {
+            return Ok(Some(impact));
+        }
+
+        delete_collections(&mut transaction, &impact).await?;
+        sqlx::query!("DELETE FROM data_category WHERE id = $1", id)
+            .execute(&mut transaction)
+            .await?;
+        let change = Change::purged(Entity::DataCategory, id, &before);
+        audit_dao::record(&mut transaction, user, &change).await?;
+        transaction.commit().await?;
+        Ok(Some(impact))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::{database::Database, dataset_dao};
+    use crate::model::dataset;
+    use crate::model::user::{Role, User};
+    use chrono::Utc;
+    use sqlx::PgPool;
+
+    async fn admin(pool: &PgPool) -> Result<User, sqlx::Error> {
+        sqlx::query_as(
+            "INSERT INTO editor_user (username, role) VALUES ($1, $2) RETURNING id, username, role",
+        )
+        .bind("admin")
+        .bind(Role::Admin.id())
+        .fetch_one(pool)
+        .await
+    }
+
+    #[sqlx::test]
+    async fn it_keeps_what_was_restored_since_the_trash_was_listed(
+        pool: PgPool,
+    ) -> Result<(), sqlx::Error> {
+        let user = admin(&pool).await?;
+        let database = Database::from_pool(pool.clone());
+        let json = dataset::Json {
+            column: "heat_days".to_string(),
+            name: "Heat days".to_string(),
+            units: "days".to_string(),
+            description: "Days above 90°F".to_string(),
+        };
+        let dataset = dataset_dao::insert(&pool, &dataset::Creator::from(json, 1)).await?;
+        database.dataset.trash(dataset.id, &user).await?;
+        let cutoff = Utc::now();
+        assert_eq!(database.dataset.trashed(cutoff).await?.len(), 1);
+
+        database.dataset.restore(dataset.id, &user).await?;
+        let removed = database
+            .deletion
+            .dataset(dataset.id, false, Some(cutoff), &user)
+            .await?;
+        assert!(removed.is_none());
+        assert!(database.dataset.by_id(dataset.id).await.is_ok());
+
+        database.dataset.trash(dataset.id, &user).await?;
+        let removed = database
+            .deletion
+            .dataset(dataset.id, false, Some(Utc::now()), &user)
+            .await?;
+        assert!(removed.is_some());
+        assert!(database.dataset.by_id(dataset.id).await.is_err());
+        Ok(())
+    }
+}
@@ -0,0 +1,82 @@
+use crate::model::audit::{Change, Entity};
+use crate::model::data;
+use crate::model::dataset::{self, Dataset};
+use crate::model::derived_dataset::{Creator, DerivedDataset};
+use crate::model::user::User;
+use serde_json::json;
+use sqlx::postgres::{PgExecutor, PgQueryResult};
+use std::collections::HashSet;
+
+use super::{audit_dao, data_dao, dataset_dao, Table};
+
+async fn insert<'e>(
+    executor: impl PgExecutor<'e>,
+    derived_dataset: &Creator,
+) -> Result<PgQueryResult, sqlx::Error> {
+    sqlx::query!(
+        "
+        INSERT INTO derived_dataset
+        (dataset, derived_from, operation, method, weight_dataset, crosswalk)
+        VALUES ($1, $2, $3, $4, $5, $6)
+        ",
+        derived_dataset.dataset,
+        derived_dataset.derived_from,
+        derived_dataset.operation,
+        derived_dataset.method,
+        derived_dataset.weight_dataset,
+        derived_dataset.crosswalk,
+    )
+    .execute(executor)
+    .await
+}
+
+impl<'c> Table<'c, DerivedDataset> {
+    pub async fn by_dataset(&self, dataset: i32) -> Result<Option<DerivedDataset>, sqlx::Error> {
+        sqlx::query_as!(
+            DerivedDataset,
+            "
+            SELECT dataset, derived_from, operation, method, weight_dataset, crosswalk, created_at
+            FROM derived_dataset
+            WHERE dataset = $1
+            ",
+            dataset
+        )
+        .fetch_optional(&*self.pool)
+        .await
+    }
+
+    /// Creates a dataset with derived rows and records how it was derived, all or nothing, so a
+    /// failed derivation doesn't leave an empty dataset behind
+    pub async fn store(
+        &self,
+        dataset: &dataset::Creator,
+        rows: HashSet<data::Creator>,
+        derivation: Creator,
+        user: &User,
+    ) -> Result<Dataset, sqlx::Error> {
+        let mut transaction = self.pool.begin().await?;
+        let created = dataset_dao::insert(&mut transaction, dataset).await?;
+        let rows = rows
+            .into_iter()
+            .map(|row| data::Creator {
+                dataset: created.id,
+                ..row
+            })
+            .collect();
+        data_dao::insert(&mut transaction, &rows).await?;
+        let derivation = Creator {
+            dataset: created.id,
+            ..derivation
+        };
+        insert(&mut transaction, &derivation).await?;
+        let after = json!({
+            "dataset": created,
+            "derivation": derivation,
+            "rows": rows.len(),
+        });
+        let change = Change::created(Entity::Dataset, created.id, &after);
+        audit_dao::record(&mut transaction, user, &change).await?;
+        transaction.commit().await?;
+        Ok(created)
+    }
+}
@@ -0,0 +1,282 @@
+use super::tile_dao;
+use super::{audit_dao, Table};
+use crate::model::audit::{Change, Entity};
+use crate::model::data::SourceAndDate;
+use crate::model::delta::Delta;
+use crate::model::export::{Feature, WkbFeature};
+use crate::model::geo_boundary::Boundary;
+use crate::model::lookup::{Containing, Point};
+use crate::model::user::User;
+use serde_json::json;
+use sqlx::postgres::PgQueryResult;
+
+impl<'c> Table<'c, Boundary> {
+    /// Saves a geography type's boundaries and forgets its cached tiles, all or nothing
+    pub async fn upsert(
+        &self,
+        geography_type: i32,
+        boundaries: &[Boundary],
+        user: &User,
+    ) -> Result<PgQueryResult, sqlx::Error> {
+        let ids: Vec<i64> = boundaries.iter().map(|boundary| boundary.id).collect();
+        let geometries: Vec<String> = boundaries
+            .iter()
+            .map(|boundary| boundary.geometry.clone())
+            .collect();
+
+        let mut transaction = self.pool.begin().await?;
+        let result = sqlx::query!(
+            "
+            INSERT INTO geo_boundary (geography_type, id, geometry)
+            SELECT $1, boundary.id, ST_Multi(ST_SetSRID(ST_GeomFromGeoJSON(boundary.geometry), 4326))
+            FROM UNNEST($2::bigint[], $3::text[]) AS boundary(id, geometry)
+            ON CONFLICT (geography_type, id) DO UPDATE
+            SET geometry = EXCLUDED.geometry
+            ",
+            geography_type,
+            &ids,
+            &geometries,
+        )
+        .execute(&mut transaction)
+        .await?;
+        tile_dao::clear_by_geography_type(&mut transaction, geography_type).await?;
+        let after = json!({ "boundaries": result.rows_affected() });
+        let change = Change::uploaded(Entity::GeoBoundary, geography_type, &after);
+        audit_dao::record(&mut transaction, user, &change).await?;
+        transaction.commit().await?;
+        Ok(result)
+    }
+
+    pub async fn geojson_by_dataset(
+        &self,
+        dataset: i32,
+        source_and_date: &SourceAndDate,
+    ) -> Result<Vec<Feature>, sqlx::Error> {
+        sqlx::query_as!(
+            Feature,
+            r#"
+            SELECT
+                geo_id.id,
+                geo_id.name,
+                data.value,
+                ST_AsGeoJSON(geo_boundary.geometry) as "geometry!"
+            FROM data
+            JOIN geo_id
+                ON geo_id.geography_type = data.geography_type
+                AND geo_id.id = data.id
+            JOIN geo_boundary
+                ON geo_boundary.geography_type = data.geography_type
+                AND geo_boundary.id = data.id
+            WHERE data.dataset = $1
+            AND data.source = $2
+            AND data.start_date = $3
+            AND data.end_date = $4
+            AND data.scenario = $5
+            AND data.statistic = $6
+            AND data.dataset NOT IN (SELECT id FROM dataset WHERE deleted_at IS NOT NULL)
+            AND data.source NOT IN (SELECT id FROM data_source WHERE deleted_at IS NOT NULL)
+            ORDER BY geo_id.id
+            "#,
+            dataset,
+            source_and_date.source,
+            source_and_date.start_date,
+            source_and_date.end_date,
+            source_and_date.scenario,
+            source_and_date.statistic,
+        )
+        .fetch_all(&*self.pool)
+        .await
+    }
+
+    pub async fn wkb_by_dataset(
+        &self,
+        dataset: i32,
+        source_and_date: &SourceAndDate,
+    ) -> Result<Vec<WkbFeature>, sqlx::Error> {
+        sqlx::query_as!(
+            WkbFeature,
+            r#"
+            SELECT
+                geo_id.id,
+                geo_id.name,
+                data.value,
+                ST_AsBinary(geo_boundary.geometry) as "geometry!"
+            FROM data
+            JOIN geo_id
+                ON geo_id.geography_type = data.geography_type
+                AND geo_id.id = data.id
+            JOIN geo_boundary
+                ON geo_boundary.geography_type = data.geography_type
+                AND geo_boundary.id = data.id
+            WHERE data.dataset = $1
+            AND data.source = $2
+            AND data.start_date = $3
+            AND data.end_date = $4
+            AND data.scenario = $5
+            AND data.statistic = $6
+            AND data.dataset NOT IN (SELECT id FROM dataset WHERE deleted_at IS NOT NULL)
+            AND data.source NOT IN (SELECT id FROM data_source WHERE deleted_at IS NOT NULL)
+            ORDER BY geo_id.id
+            "#,
+            dataset,
+            source_and_date.source,
+            source_and_date.start_date,
+            source_and_date.end_date,
+            source_and_date.scenario,
+            source_and_date.statistic,
+        )
+        .fetch_all(&*self.pool)
+        .await
+    }
+
+    /// The change of a delta between two slices of a dataset, for geo ids in both slices where
+    /// it is defined
+    pub async fn geojson_by_delta(
+        &self,
+        dataset: i32,
+        delta: &Delta,
+    ) -> Result<Vec<Feature>, sqlx::Error> {
+        sqlx::query_as!(
+            Feature,
+            r#"
+            SELECT
+                geo_id.id,
+                geo_id.name,
+                delta_change($12, delta_from.value, delta_to.value) AS "value!",
+                ST_AsGeoJSON(geo_boundary.geometry) as "geometry!"
+            FROM data AS delta_from
+            JOIN data AS delta_to
+                ON delta_to.dataset = delta_from.dataset
+                AND delta_to.geography_type = delta_from.geography_type
+                AND delta_to.id = delta_from.id
+            JOIN geo_id
+                ON geo_id.geography_type = delta_from.geography_type
+                AND geo_id.id = delta_from.id
+            JOIN geo_boundary
+                ON geo_boundary.geography_type = delta_from.geography_type
+                AND geo_boundary.id = delta_from.id
+            WHERE delta_from.dataset = $1
+            AND delta_from.source = $2
+            AND delta_from.start_date = $3
+            AND delta_from.end_date = $4
+            AND delta_from.scenario = $5
+            AND delta_from.statistic = $6
+            AND delta_to.source = $7
+            AND delta_to.start_date = $8
+            AND delta_to.end_date = $9
+            AND delta_to.scenario = $10
+            AND delta_to.statistic = $11
+            AND delta_from.dataset NOT IN (SELECT id FROM dataset WHERE deleted_at IS NOT NULL)
+            AND delta_from.source NOT IN (SELECT id FROM data_source WHERE deleted_at IS NOT NULL)
+            AND delta_to.source NOT IN (SELECT id FROM data_source WHERE deleted_at IS NOT NULL)
+            AND delta_change($12, delta_from.value, delta_to.value) IS NOT NULL
+            ORDER BY geo_id.id
+            "#,
+            dataset,
+            delta.from.source,
+            delta.from.start_date,
+            delta.from.end_date,
+            delta.from.scenario,
+            delta.from.statistic,
+            delta.to.source,
+            delta.to.start_date,
+            delta.to.end_date,
+            delta.to.scenario,
+            delta.to.statistic,
+            delta.operation,
+        )
+        .fetch_all(&*self.pool)
+        .await
+    }
+
+    /// Like `geojson_by_delta`, with the geometry as well-known binary
+    pub async fn wkb_by_delta(
+        &self,
+        dataset: i32,
+        delta: &Delta,
+    ) -> Result<Vec<WkbFeature>, sqlx::Error> {
+        sqlx::query_as!(
+            WkbFeature,
+            r#"
+            SELECT
+                geo_id.id,
+                geo_id.name,
+                delta_change($12, delta_from.value, delta_to.value) AS "value!",
+                ST_AsBinary(geo_boundary.geometry) as "geometry!"
+            FROM data AS delta_from
+            JOIN data AS delta_to
+                ON delta_to.dataset = delta_from.dataset
+                AND delta_to.geography_type = delta_from.geography_type
+                AND delta_to.id = delta_from.id
+            JOIN geo_id
+                ON geo_id.geography_type = delta_from.geography_type
+                AND geo_id.id = delta_from.id
+            JOIN geo_boundary
+                ON geo_boundary.geography_type = delta_from.geography_type
+                AND geo_boundary.id = delta_from.id
+            WHERE delta_from.dataset = $1
+            AND delta_from.source = $2
+            AND delta_from.start_date = $3
+            AND delta_from.end_date = $4
+            AND delta_from.scenario = $5
+            AND delta_from.statistic = $6
+            AND delta_to.source = $7
+            AND delta_to.start_date = $8
+            AND delta_to.end_date = $9
+            AND delta_to.scenario = $10
+            AND delta_to.statistic = $11
+            AND delta_from.dataset NOT IN (SELECT id FROM dataset WHERE deleted_at IS NOT NULL)
+            AND delta_from.source NOT IN (SELECT id FROM data_source WHERE deleted_at IS NOT NULL)
+            AND delta_to.source NOT IN (SELECT id FROM data_source WHERE deleted_at IS NOT NULL)
+            AND delta_change($12, delta_from.value, delta_to.value) IS NOT NULL
+            ORDER BY geo_id.id
+            "#,
+            dataset,
+            delta.from.source,
+            delta.from.start_date,
+            delta.from.end_date,
+            delta.from.scenario,
+            delta.from.statistic,
+            delta.to.source,
+            delta.to.start_date,
+            delta.to.end_date,
+            delta.to.scenario,
+            delta.to.statistic,
+            delta.operation,
+        )
+        .fetch_all(&*self.pool)
+        .await
+    }
+
+    /// The geo ids of every geography type whose boundaries contain each point. A point on a
+    /// shared border is in both geo ids.
+    pub async fn containing(&self, points: &[Point]) -> Result<Vec<Containing>, sqlx::Error> {
+        let latitudes: Vec<f64> = points.iter().map(|point| point.latitude).collect();
+        let longitudes: Vec<f64> = points.iter().map(|point| point.longitude).collect();
+
+        sqlx::query_as!(
+            Containing,
+            r#"
+            SELECT
+                int4(point.index - 1) AS "point!",
+                geo_boundary.geography_type,
+                geo_boundary.id,
+                geo_id.name
+            FROM UNNEST($1::FLOAT8[], $2::FLOAT8[]) WITH ORDINALITY AS point(latitude, longitude, index)
+            JOIN geo_boundary
+                ON ST_Intersects(
+                    geo_boundary.geometry,
+                    ST_SetSRID(ST_MakePoint(point.longitude, point.latitude), 4326)
+                )
+            JOIN geo_id
+                ON geo_id.geography_type = geo_boundary.geography_type
+                AND geo_id.id = geo_boundary.id
+            ORDER BY point.index, geo_boundary.geography_type, geo_boundary.id
+            "#,
+            &latitudes,
+            &longitudes,
+        )
+        .fetch_all(&*self.pool)
+        .await
+    }
+}
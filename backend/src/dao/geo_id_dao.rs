@@ -48,4 +48,20 @@ impl<'c> Table<'c, GeoId> {
         .fetch_all(&*self.pool)
         .await
     }
+
+    /// The number of geo ids of a dataset's geography type
+    pub async fn count_by_dataset(&self, dataset: i32) -> Result<i64, sqlx::Error> {
+        sqlx::query!(
+            r#"
+            SELECT COUNT(*) AS "count!"
+            FROM geo_id
+            JOIN dataset ON dataset.geography_type = geo_id.geography_type
+            WHERE dataset.id = $1
+            "#,
+            dataset
+        )
+        .fetch_one(&*self.pool)
+        .await
+        .map(|row| row.count)
+    }
 }
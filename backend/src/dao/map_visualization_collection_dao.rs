@@ -1,9 +1,32 @@
-use sqlx::postgres::PgQueryResult;
+use sqlx::postgres::{PgExecutor, PgQueryResult};
 use sqlx::query;
 
+use crate::model::audit::{Change, Entity};
 use crate::model::map_visualization_collection::{Collection, Id};
+use crate::model::order::{self, Mismatch};
+use crate::model::user::User;
 
-use super::Table;
+use super::{audit_dao, Table};
+
+/// The map visualizations of a category outside the trash, in order, locked until the
+/// transaction ends
+async fn locked_ids<'e>(
+    executor: impl PgExecutor<'e>,
+    category: i32,
+) -> Result<Vec<i32>, sqlx::Error> {
+    query!(
+        "SELECT collection.map_visualization FROM map_visualization_collection AS collection
+            JOIN map_visualization ON map_visualization.id = collection.map_visualization
+            WHERE collection.category = $1
+            AND map_visualization.deleted_at IS NULL
+            ORDER BY collection.\"order\"
+            FOR UPDATE OF collection",
+        category
+    )
+    .fetch_all(executor)
+    .await
+    .map(|rows| rows.into_iter().map(|row| row.map_visualization).collect())
+}
 
 impl<'c> Table<'c, Collection> {
     pub async fn get_last_order(&self, category: i32) -> Result<i16, sqlx::Error> {
@@ -16,8 +39,58 @@ impl<'c> Table<'c, Collection> {
         Ok(result.max.unwrap_or(0))
     }
 
-    pub async fn create(&self, collection: Collection) -> Result<PgQueryResult, sqlx::Error> {
+    /// Numbers the map visualizations of a category from 1 in the order of their ids, which must
+    /// list every one outside the trash once. Those in the trash keep their order after them.
+    pub async fn reorder(
+        &self,
+        category: i32,
+        ids: &[i32],
+        user: &User,
+    ) -> Result<Result<(), Mismatch>, sqlx::Error> {
+        let mut transaction = self.pool.begin().await?;
+        let before = locked_ids(&mut transaction, category).await?;
+        if let Err(mismatch) = order::check(ids, &before) {
+            return Ok(Err(mismatch));
+        }
         query!(
+            "UPDATE map_visualization_collection
+                SET \"order\" = new.position::SMALLINT
+                FROM (
+                    SELECT id, position
+                    FROM unnest($2::INT[]) WITH ORDINALITY AS listed (id, position)
+                    UNION ALL
+                    SELECT collection.map_visualization, cardinality($2::INT[])
+                        + ROW_NUMBER() OVER (ORDER BY collection.\"order\")
+                    FROM map_visualization_collection AS collection
+                    JOIN map_visualization ON map_visualization.id = collection.map_visualization
+                    WHERE collection.category = $1
+                    AND map_visualization.deleted_at IS NOT NULL
+                ) AS new
+                WHERE category = $1
+                AND map_visualization = new.id",
+            category,
+            ids
+        )
+        .execute(&mut transaction)
+        .await?;
+        let change = Change::reordered(
+            Entity::MapVisualizationCollection,
+            Some(category),
+            &before,
+            ids,
+        );
+        audit_dao::record(&mut transaction, user, &change).await?;
+        transaction.commit().await?;
+        Ok(Ok(()))
+    }
+
+    pub async fn create(
+        &self,
+        collection: Collection,
+        user: &User,
+    ) -> Result<PgQueryResult, sqlx::Error> {
+        let mut transaction = self.pool.begin().await?;
+        let result = query!(
             "INSERT INTO map_visualization_collection
                 (map_visualization, category, \"order\")
                 VALUES ($1, $2, $3)",
@@ -25,33 +98,38 @@ impl<'c> Table<'c, Collection> {
             collection.category,
             collection.order
         )
-        .execute(&*self.pool)
-        .await
+        .execute(&mut transaction)
+        .await?;
+        let change = Change::created(
+            Entity::MapVisualizationCollection,
+            collection.map_visualization,
+            &collection,
+        );
+        audit_dao::record(&mut transaction, user, &change).await?;
+        transaction.commit().await?;
+        Ok(result)
     }
 
-    pub async fn delete(&self, id: Id) -> Result<PgQueryResult, sqlx::Error> {
-        query!(
+    pub async fn delete(&self, id: Id, user: &User) -> Result<PgQueryResult, sqlx::Error> {
+        let mut transaction = self.pool.begin().await?;
+        let result = query!(
             "DELETE FROM map_visualization_collection
                 WHERE map_visualization = $1
                 AND category = $2",
             id.map_visualization,
             id.category
         )
-        .execute(&*self.pool)
-        .await
-    }
-
-    pub async fn delete_by_dataset(&self, dataset: i32) -> Result<PgQueryResult, sqlx::Error> {
-        sqlx::query!(
-            "
-            DELETE FROM map_visualization_collection
-            USING map_visualization
-            WHERE map_visualization_collection.map_visualization = map_visualization.id
-            AND map_visualization.dataset = $1
-            ",
-            dataset
-        )
-        .execute(&*self.pool)
-        .await
+        .execute(&mut transaction)
+        .await?;
+        if result.rows_affected() > 0 {
+            let change = Change::deleted(
+                Entity::MapVisualizationCollection,
+                id.map_visualization,
+                &id,
+            );
+            audit_dao::record(&mut transaction, user, &change).await?;
+        }
+        transaction.commit().await?;
+        Ok(result)
     }
 }
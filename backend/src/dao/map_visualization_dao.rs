@@ -1,6 +1,10 @@
-use super::Table;
+use super::{audit_dao, revision_dao, Table};
+use crate::model::audit::{Change, Entity};
 use crate::model::map_visualization::{Creator, MapVisualization, Patch};
-use sqlx::postgres::PgQueryResult;
+use crate::model::trash::{Item, Kind};
+use crate::model::user::User;
+use chrono::{DateTime, Utc};
+use sqlx::postgres::{PgConnection, PgExecutor};
 
 macro_rules! select {
     () => {
@@ -21,7 +25,10 @@ macro_rules! select {
     ($id:ident) => {
         select!("LEFT JOIN",,,id=$id)
     };
-    ($join_type:expr, $(geography_type=$geography_type:expr)?, $(dataset=$dataset:expr)?, $(id=$id:expr)?) => {
+    (including_trash, $id:ident) => {
+        select!(@where " WHERE TRUE", "LEFT JOIN",,,id=$id)
+    };
+    (@where $where:expr, $join_type:expr, $(geography_type=$geography_type:expr)?, $(dataset=$dataset:expr)?, $(id=$id:expr)?) => {
         sqlx::query_as!(
             MapVisualization,
             r#"
@@ -46,6 +53,23 @@ macro_rules! select {
                 map."name",
                 dataset.geography_type as "geography_type!",
                 map.bubble_color as "bubble_color!",
+                map.delta_operation,
+                map.delta_from_source,
+                map.delta_from_start_date,
+                map.delta_from_end_date,
+                map.delta_to_source,
+                map.delta_to_start_date,
+                map.delta_to_end_date,
+                map.delta_from_scenario,
+                map.delta_to_scenario,
+                map.bivariate_dataset,
+                map.bivariate_source,
+                map.bivariate_start_date,
+                map.bivariate_end_date,
+                map.bivariate_scenario,
+                map.bivariate_statistic,
+                map.classification_method,
+                map.classification_classes,
                 
                 dataset."name" as "dataset_name!",
                 dataset.units as "units!",
@@ -67,11 +91,133 @@ macro_rules! select {
             "#
             + $join_type
             + " map_visualization_collection ON map.id = map_visualization_collection.map_visualization"
-            $( + " WHERE dataset.geography_type = $1", $geography_type)?
-            $( + " WHERE map.id = $1", $id)?
-            $( + " WHERE map.dataset = $1", $dataset)?
+            + " AND map_visualization_collection.category NOT IN (SELECT id FROM data_category WHERE deleted_at IS NOT NULL)"
+            + $where
+            $( + " AND dataset.geography_type = $1", $geography_type)?
+            $( + " AND map.id = $1", $id)?
+            $( + " AND map.dataset = $1", $dataset)?
         )
     };
+    ($join_type:expr, $(geography_type=$geography_type:expr)?, $(dataset=$dataset:expr)?, $(id=$id:expr)?) => {
+        select!(
+            @where " WHERE map.deleted_at IS NULL AND dataset.deleted_at IS NULL",
+            $join_type,
+            $(geography_type=$geography_type)?,
+            $(dataset=$dataset)?,
+            $(id=$id)?
+        )
+    };
+}
+
+/// A map visualization outside the trash, read in a write's transaction
+pub(super) async fn by_id<'e>(
+    executor: impl PgExecutor<'e>,
+    id: i32,
+) -> Result<Option<MapVisualization>, sqlx::Error> {
+    select!(id).fetch_optional(executor).await
+}
+
+/// A map visualization, even one in the trash or of a dataset in the trash, locked until the
+/// transaction ends
+pub(super) async fn locked(
+    connection: &mut PgConnection,
+    id: i32,
+) -> Result<Option<MapVisualization>, sqlx::Error> {
+    sqlx::query!(
+        "SELECT id FROM map_visualization WHERE id = $1 FOR UPDATE",
+        id
+    )
+    .fetch_optional(&mut *connection)
+    .await?;
+    select!(including_trash, id)
+        .fetch_optional(connection)
+        .await
+}
+
+/// Inserts a map visualization with its first revision and audit entry, for writes that create
+/// one along with other rows
+pub(super) async fn insert(
+    connection: &mut PgConnection,
+    map: &Creator,
+    user: &User,
+) -> Result<i32, sqlx::Error> {
+    let id = sqlx::query!(
+        "
+        INSERT INTO map_visualization (
+            dataset,
+            map_type,
+            color_palette,
+            scale_type,
+            formatter_type,
+            subcategory,
+            name,
+            legend_ticks,
+            reverse_scale,
+            invert_normalized,
+            show_pdf,
+            default_start_date,
+            default_end_date,
+            default_source,
+            legend_formatter_type,
+            decimals,
+            legend_decimals,
+            color_domain,
+            pdf_domain,
+            bubble_color,
+            classification_method,
+            classification_classes
+        )
+        VALUES (
+            $1, $2, $3, $4, $5, $6, $7, $8, $9, $10, $11,
+            $12, $13, $14, $15, $16, $17, $18, $19, $20, $21, $22
+        )
+        RETURNING id
+        ",
+        map.dataset,
+        map.map_type,
+        map.color_palette,
+        map.scale_type,
+        map.formatter_type,
+        map.subcategory,
+        map.name,
+        map.legend_ticks,
+        map.reverse_scale,
+        map.invert_normalized,
+        map.show_pdf,
+        map.default_start_date,
+        map.default_end_date,
+        map.default_source,
+        map.legend_formatter_type,
+        map.decimals,
+        map.legend_decimals,
+        &map.color_domain,
+        &map.pdf_domain,
+        map.bubble_color,
+        map.classification_method,
+        map.classification_classes,
+    )
+    .fetch_one(&mut *connection)
+    .await?
+    .id;
+    if let Some(category) = map.category {
+        sqlx::query!(
+            "
+            INSERT INTO map_visualization_collection (map_visualization, category, \"order\")
+            SELECT $1, $2, COALESCE(MAX(\"order\"), 0) + 1
+            FROM map_visualization_collection
+            WHERE category = $2
+            ",
+            id,
+            category,
+        )
+        .execute(&mut *connection)
+        .await?;
+    }
+    revision_dao::record(&mut *connection, &[id], Some(user.id)).await?;
+    let after = by_id(&mut *connection, id).await?;
+    let change = Change::created(Entity::MapVisualization, id, &after);
+    audit_dao::record(&mut *connection, user, &change).await?;
+    Ok(id)
 }
 
 impl<'c> Table<'c, MapVisualization> {
@@ -99,8 +245,12 @@ impl<'c> Table<'c, MapVisualization> {
         select!(id).fetch_one(&*self.pool).await
     }
 
-    pub async fn update(&self, patch: &Patch) -> Result<PgQueryResult, sqlx::Error> {
-        sqlx::query!(
+    /// Updates a map visualization and stores its new settings as a revision. Returns the
+    /// revision, or `None` if there's no such map visualization outside the trash.
+    pub async fn update(&self, patch: &Patch, user: &User) -> Result<Option<i32>, sqlx::Error> {
+        let mut transaction = self.pool.begin().await?;
+        let before = locked(&mut transaction, patch.id).await?;
+        let result = sqlx::query!(
             "UPDATE map_visualization
             SET dataset = $1,
                 map_type = $2,
@@ -121,8 +271,25 @@ impl<'c> Table<'c, MapVisualization> {
                 legend_decimals = $17,
                 color_domain = $18,
                 pdf_domain = $19,
-                bubble_color = $20
-            WHERE id = $21",
+                bubble_color = $20,
+                delta_operation = $21,
+                delta_from_source = $22,
+                delta_from_start_date = $23,
+                delta_from_end_date = $24,
+                delta_to_source = $25,
+                delta_to_start_date = $26,
+                delta_to_end_date = $27,
+                delta_from_scenario = $28,
+                delta_to_scenario = $29,
+                bivariate_dataset = $30,
+                bivariate_source = $31,
+                bivariate_start_date = $32,
+                bivariate_end_date = $33,
+                classification_method = $34,
+                classification_classes = $35,
+                bivariate_scenario = $36,
+                bivariate_statistic = $37
+            WHERE id = $38 AND deleted_at IS NULL",
             patch.dataset,
             patch.map_type,
             patch.subcategory,
@@ -143,39 +310,218 @@ impl<'c> Table<'c, MapVisualization> {
             &patch.color_domain,
             &patch.pdf_domain,
             patch.bubble_color,
+            patch.delta_operation,
+            patch.delta_from_source,
+            patch.delta_from_start_date,
+            patch.delta_from_end_date,
+            patch.delta_to_source,
+            patch.delta_to_start_date,
+            patch.delta_to_end_date,
+            patch.delta_from_scenario,
+            patch.delta_to_scenario,
+            patch.bivariate_dataset,
+            patch.bivariate_source,
+            patch.bivariate_start_date,
+            patch.bivariate_end_date,
+            patch.classification_method,
+            patch.classification_classes,
+            patch.bivariate_scenario,
+            patch.bivariate_statistic,
             patch.id,
         )
-        .execute(&*self.pool)
-        .await
+        .execute(&mut transaction)
+        .await?;
+        if result.rows_affected() == 0 {
+            return Ok(None);
+        }
+        let revision = revision_dao::record(&mut transaction, &[patch.id], Some(user.id)).await?;
+        let after = locked(&mut transaction, patch.id).await?;
+        let change = Change::updated(Entity::MapVisualization, patch.id, &before, &after);
+        audit_dao::record(&mut transaction, user, &change).await?;
+        transaction.commit().await?;
+        Ok(revision.first().copied())
     }
 
-    pub async fn create(&self, map: &Creator) -> Result<PgQueryResult, sqlx::Error> {
-        sqlx::query!(
-            "
-            INSERT INTO map_visualization
-            (dataset, map_type, color_palette, scale_type, formatter_type)
-            VALUES ($1, $2, $3, $4, $5)
-            ",
-            map.dataset,
-            map.map_type,
-            map.color_palette,
-            map.scale_type,
-            map.formatter_type,
+    /// A map visualization's settings, to validate them before copying it
+    pub async fn settings(&self, id: i32) -> Result<Option<Patch>, sqlx::Error> {
+        sqlx::query_as!(
+            Patch,
+            r#"
+            SELECT
+                id AS "id!",
+                dataset AS "dataset!",
+                map_type AS "map_type!",
+                subcategory,
+                NULL::INT AS data_tab,
+                name,
+                legend_ticks,
+                color_palette AS "color_palette_id!",
+                reverse_scale AS "reverse_scale!",
+                invert_normalized AS "invert_normalized!",
+                scale_type AS "scale_type!",
+                show_pdf AS "show_pdf!",
+                default_start_date,
+                default_end_date,
+                default_source,
+                formatter_type AS "formatter_type!",
+                legend_formatter_type,
+                decimals AS "decimals!",
+                legend_decimals,
+                color_domain AS "color_domain!",
+                pdf_domain AS "pdf_domain!",
+                bubble_color AS "bubble_color!",
+                delta_operation,
+                delta_from_source,
+                delta_from_start_date,
+                delta_from_end_date,
+                delta_to_source,
+                delta_to_start_date,
+                delta_to_end_date,
+                delta_from_scenario,
+                delta_to_scenario,
+                bivariate_dataset,
+                bivariate_source,
+                bivariate_start_date,
+                bivariate_end_date,
+                bivariate_scenario,
+                bivariate_statistic,
+                classification_method,
+                classification_classes AS "classification_classes!"
+            FROM map_visualization
+            WHERE id = $1 AND deleted_at IS NULL
+            "#,
+            id
         )
-        .execute(&*self.pool)
+        .fetch_optional(&*self.pool)
         .await
     }
 
-    pub async fn delete(&self, id: i32) -> Result<PgQueryResult, sqlx::Error> {
-        sqlx::query!("DELETE FROM map_visualization WHERE id = $1", id)
-            .execute(&*self.pool)
-            .await
+    /// Creates a map visualization, listed in its category if it has one, and stores its
+    /// settings as its first revision
+    pub async fn create(&self, map: &Creator, user: &User) -> Result<i32, sqlx::Error> {
+        let mut transaction = self.pool.begin().await?;
+        let id = insert(&mut transaction, map, user).await?;
+        transaction.commit().await?;
+        Ok(id)
     }
 
-    pub async fn delete_by_dataset(&self, dataset_id: i32) -> Result<PgQueryResult, sqlx::Error> {
-        sqlx::query!("DELETE FROM map_visualization WHERE id = $1", dataset_id)
-            .execute(&*self.pool)
-            .await
+    /// Copies a map visualization's settings into a new one, named as a copy and listed after
+    /// the others in the original's categories. `None` if there's nothing to copy.
+    pub async fn duplicate(&self, id: i32, user: &User) -> Result<Option<i32>, sqlx::Error> {
+        let mut transaction = self.pool.begin().await?;
+        let copy = sqlx::query!(
+            "
+            INSERT INTO map_visualization (
+                dataset,
+                map_type,
+                subcategory,
+                name,
+                legend_ticks,
+                color_palette,
+                reverse_scale,
+                invert_normalized,
+                scale_type,
+                show_pdf,
+                default_start_date,
+                default_end_date,
+                default_source,
+                formatter_type,
+                legend_formatter_type,
+                decimals,
+                legend_decimals,
+                color_domain,
+                pdf_domain,
+                bubble_color,
+                delta_operation,
+                delta_from_source,
+                delta_from_start_date,
+                delta_from_end_date,
+                delta_to_source,
+                delta_to_start_date,
+                delta_to_end_date,
+                delta_from_scenario,
+                delta_to_scenario,
+                bivariate_dataset,
+                bivariate_source,
+                bivariate_start_date,
+                bivariate_end_date,
+                bivariate_scenario,
+                bivariate_statistic,
+                classification_method,
+                classification_classes
+            )
+            SELECT
+                dataset,
+                map_type,
+                subcategory,
+                name || ' (copy)',
+                legend_ticks,
+                color_palette,
+                reverse_scale,
+                invert_normalized,
+                scale_type,
+                show_pdf,
+                default_start_date,
+                default_end_date,
+                default_source,
+                formatter_type,
+                legend_formatter_type,
+                decimals,
+                legend_decimals,
+                color_domain,
+                pdf_domain,
+                bubble_color,
+                delta_operation,
+                delta_from_source,
+                delta_from_start_date,
+                delta_from_end_date,
+                delta_to_source,
+                delta_to_start_date,
+                delta_to_end_date,
+                delta_from_scenario,
+                delta_to_scenario,
+                bivariate_dataset,
+                bivariate_source,
+                bivariate_start_date,
+                bivariate_end_date,
+                bivariate_scenario,
+                bivariate_statistic,
+                classification_method,
+                classification_classes
+            FROM map_visualization
+            WHERE id = $1 AND deleted_at IS NULL
+            RETURNING id
+            ",
+            id
+        )
+        .fetch_optional(&mut transaction)
+        .await?;
+        let copy = match copy {
+            None => return Ok(None),
+            Some(copy) => copy.id,
+        };
+        sqlx::query!(
+            "
+            INSERT INTO map_visualization_collection (map_visualization, category, \"order\")
+            SELECT $2, category, (
+                SELECT MAX(\"order\") + 1
+                FROM map_visualization_collection AS other
+                WHERE other.category = collection.category
+            )
+            FROM map_visualization_collection AS collection
+            WHERE map_visualization = $1
+            ",
+            id,
+            copy
+        )
+        .execute(&mut transaction)
+        .await?;
+        revision_dao::record(&mut transaction, &[copy], Some(user.id)).await?;
+        let after = by_id(&mut transaction, copy).await?;
+        let change = Change::created(Entity::MapVisualization, copy, &after);
+        audit_dao::record(&mut transaction, user, &change).await?;
+        transaction.commit().await?;
+        Ok(Some(copy))
     }
 
     pub async fn get_by_dataset(
@@ -184,4 +530,88 @@ impl<'c> Table<'c, MapVisualization> {
     ) -> Result<Vec<MapVisualization>, sqlx::Error> {
         select!(dataset = dataset_id).fetch_all(&*self.pool).await
     }
+
+    /// Puts a map visualization in the trash. `None` if there's no such map visualization
+    /// outside the trash.
+    pub async fn trash(&self, id: i32, user: &User) -> Result<Option<Item>, sqlx::Error> {
+        let mut transaction = self.pool.begin().await?;
+        let before = locked(&mut transaction, id).await?;
+        let item = sqlx::query!(
+            r#"
+            UPDATE map_visualization
+            SET deleted_at = NOW()
+            WHERE id = $1 AND deleted_at IS NULL
+            RETURNING id, name, deleted_at AS "deleted_at!"
+            "#,
+            id
+        )
+        .fetch_optional(&mut transaction)
+        .await?
+        .map(|row| Item {
+            kind: Kind::MapVisualization,
+            id: row.id,
+            name: row.name,
+            deleted_at: row.deleted_at,
+        });
+        if item.is_some() {
+            let change = Change::deleted(Entity::MapVisualization, id, &before);
+            audit_dao::record(&mut transaction, user, &change).await?;
+        }
+        transaction.commit().await?;
+        Ok(item)
+    }
+
+    /// Takes a map visualization out of the trash, returning when it was deleted. `None` if there's no such
+    /// map visualization in the trash.
+    pub async fn restore(&self, id: i32, user: &User) -> Result<Option<Item>, sqlx::Error> {
+        let mut transaction = self.pool.begin().await?;
+        let item = sqlx::query!(
+            r#"
+            UPDATE map_visualization
+            SET deleted_at = NULL
+            FROM map_visualization AS old
+            WHERE map_visualization.id = $1 AND old.id = map_visualization.id AND old.deleted_at IS NOT NULL
+            RETURNING map_visualization.id, map_visualization.name, old.deleted_at AS "deleted_at!"
+            "#,
+            id
+        )
+        .fetch_optional(&mut transaction)
+        .await?
+        .map(|row| Item {
+            kind: Kind::MapVisualization,
+            id: row.id,
+            name: row.name,
+            deleted_at: row.deleted_at,
+        });
+        if let Some(item) = &item {
+            let change = Change::restored(Entity::MapVisualization, id, item);
+            audit_dao::record(&mut transaction, user, &change).await?;
+        }
+        transaction.commit().await?;
+        Ok(item)
+    }
+
+    /// The map visualizations in the trash that were deleted before a time
+    pub async fn trashed(&self, before: DateTime<Utc>) -> Result<Vec<Item>, sqlx::Error> {
+        sqlx::query!(
+            r#"
+            SELECT id, name, deleted_at AS "deleted_at!"
+            FROM map_visualization
+            WHERE deleted_at < $1
+            "#,
+            before
+        )
+        .fetch_all(&*self.pool)
+        .await
+        .map(|rows| {
+            rows.into_iter()
+                .map(|row| Item {
+                    kind: Kind::MapVisualization,
+                    id: row.id,
+                    name: row.name,
+                    deleted_at: row.deleted_at,
+                })
+                .collect()
+        })
+    }
 }
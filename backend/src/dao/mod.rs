@@ -1,18 +1,33 @@
+mod audit_dao;
+mod classification_dao;
 mod color_palette_dao;
 mod county_dao;
+mod crosswalk_dao;
+mod custom_region_dao;
 mod data_category_dao;
 mod data_dao;
 mod data_source_dao;
 pub mod database;
 mod dataset_dao;
+mod deletion_dao;
+mod derived_dataset_dao;
+mod geo_boundary_dao;
 mod geo_id_dao;
 mod geography_type_dao;
 mod map_visualization_collection_dao;
 mod map_visualization_dao;
+mod neighbor_dao;
+mod publication_dao;
+mod revision_dao;
 mod scale_type_dao;
+mod scenario_dao;
 mod source_and_date_dao;
 mod state_dao;
+mod statistic_dao;
 mod subcategory_dao;
+mod tile_dao;
+mod token_dao;
+mod user_dao;
 
 pub type Database<'c> = database::Database<'c>;
 pub type Table<'c, T> = database::Table<'c, T>;
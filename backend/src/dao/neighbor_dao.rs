@@ -0,0 +1,132 @@
+use super::{audit_dao, Table};
+use crate::model::audit::{Change, Entity};
+use crate::model::neighbor::Neighbor;
+use crate::model::user::User;
+use serde_json::json;
+use sqlx::postgres::PgQueryResult;
+
+impl<'c> Table<'c, Neighbor> {
+    pub async fn by_dataset(&self, dataset: i32) -> Result<Vec<Neighbor>, sqlx::Error> {
+        sqlx::query_as!(
+            Neighbor,
+            "
+            SELECT geo_id_neighbor.id, geo_id_neighbor.neighbor_id
+            FROM geo_id_neighbor
+            JOIN dataset ON dataset.geography_type = geo_id_neighbor.geography_type
+            WHERE dataset.id = $1
+            ",
+            dataset
+        )
+        .fetch_all(&*self.pool)
+        .await
+    }
+
+    pub async fn by_geo_id(
+        &self,
+        geography_type: i32,
+        id: i64,
+    ) -> Result<Vec<Neighbor>, sqlx::Error> {
+        sqlx::query_as!(
+            Neighbor,
+            "
+            SELECT id, neighbor_id
+            FROM geo_id_neighbor
+            WHERE geography_type = $1
+            AND id = $2
+            ORDER BY neighbor_id
+            ",
+            geography_type,
+            id
+        )
+        .fetch_all(&*self.pool)
+        .await
+    }
+
+    /// Links geo ids whose boundaries touch, including at a single corner
+    pub async fn generate(
+        &self,
+        geography_type: i32,
+        user: &User,
+    ) -> Result<PgQueryResult, sqlx::Error> {
+        let mut transaction = self.pool.begin().await?;
+        let result = sqlx::query!(
+            "
+            INSERT INTO geo_id_neighbor (geography_type, id, neighbor_id)
+            SELECT $1, boundary.id, neighbor.id
+            FROM geo_boundary AS boundary
+            JOIN geo_boundary AS neighbor
+                ON neighbor.geography_type = boundary.geography_type
+                AND neighbor.id <> boundary.id
+                AND ST_Intersects(boundary.geometry, neighbor.geometry)
+            WHERE boundary.geography_type = $1
+            ON CONFLICT DO NOTHING
+            ",
+            geography_type,
+        )
+        .execute(&mut transaction)
+        .await?;
+        let after = json!({ "neighbors": result.rows_affected() });
+        let change = Change::created(Entity::Neighbor, geography_type, &after);
+        audit_dao::record(&mut transaction, user, &change).await?;
+        transaction.commit().await?;
+        Ok(result)
+    }
+
+    /// Adds each pair in both directions
+    pub async fn insert(
+        &self,
+        geography_type: i32,
+        neighbors: &[Neighbor],
+        user: &User,
+    ) -> Result<PgQueryResult, sqlx::Error> {
+        let ids: Vec<i64> = neighbors.iter().map(|neighbor| neighbor.id).collect();
+        let neighbor_ids: Vec<i64> = neighbors
+            .iter()
+            .map(|neighbor| neighbor.neighbor_id)
+            .collect();
+
+        let mut transaction = self.pool.begin().await?;
+        let result = sqlx::query!(
+            "
+            INSERT INTO geo_id_neighbor (geography_type, id, neighbor_id)
+            SELECT $1, pair.id, pair.neighbor_id
+            FROM (
+                SELECT * FROM UNNEST($2::bigint[], $3::bigint[]) AS pair(id, neighbor_id)
+                UNION
+                SELECT * FROM UNNEST($3::bigint[], $2::bigint[]) AS pair(id, neighbor_id)
+            ) AS pair
+            WHERE pair.id <> pair.neighbor_id
+            ON CONFLICT DO NOTHING
+            ",
+            geography_type,
+            &ids,
+            &neighbor_ids,
+        )
+        .execute(&mut transaction)
+        .await?;
+        let after = json!({ "neighbors": result.rows_affected() });
+        let change = Change::uploaded(Entity::Neighbor, geography_type, &after);
+        audit_dao::record(&mut transaction, user, &change).await?;
+        transaction.commit().await?;
+        Ok(result)
+    }
+
+    pub async fn delete_by_geography_type(
+        &self,
+        geography_type: i32,
+        user: &User,
+    ) -> Result<PgQueryResult, sqlx::Error> {
+        let mut transaction = self.pool.begin().await?;
+        let result = sqlx::query!(
+            "DELETE FROM geo_id_neighbor WHERE geography_type = $1",
+            geography_type
+        )
+        .execute(&mut transaction)
+        .await?;
+        let before = json!({ "neighbors": result.rows_affected() });
+        let change = Change::deleted(Entity::Neighbor, geography_type, &before);
+        audit_dao::record(&mut transaction, user, &change).await?;
+        transaction.commit().await?;
+        Ok(result)
+    }
+}
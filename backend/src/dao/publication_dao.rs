@@ -0,0 +1,238 @@
+use super::{audit_dao, revision_dao, Table};
+use crate::model::audit::{Action, Change};
+use crate::model::publication::Changes;
+use crate::model::user::User;
+use sqlx::postgres::{PgConnection, PgExecutor, Postgres};
+use sqlx::Transaction;
+
+async fn find_changes<'e>(executor: impl PgExecutor<'e>) -> Result<Changes, sqlx::Error> {
+    sqlx::query_as!(
+        Changes,
+        r#"
+        SELECT
+            ARRAY(
+                SELECT id
+                FROM public.map_visualization AS draft
+                FULL JOIN published.map_visualization AS published USING (id)
+                WHERE to_jsonb(draft) IS DISTINCT FROM to_jsonb(published)
+                ORDER BY id
+            ) AS "map_visualizations!",
+            ARRAY(
+                SELECT id
+                FROM public.data_category AS draft
+                FULL JOIN published.data_category AS published USING (id)
+                WHERE to_jsonb(draft) IS DISTINCT FROM to_jsonb(published)
+                ORDER BY id
+            ) AS "data_categories!",
+            ARRAY(
+                SELECT DISTINCT map_visualization
+                FROM public.map_visualization_collection AS draft
+                FULL JOIN published.map_visualization_collection AS published
+                    USING (category, map_visualization)
+                WHERE to_jsonb(draft) IS DISTINCT FROM to_jsonb(published)
+                ORDER BY map_visualization
+            ) AS "collections!"
+        "#
+    )
+    .fetch_one(executor)
+    .await
+}
+
+/// Makes the target schema's drafts match the source's
+async fn copy_drafts(
+    connection: &mut PgConnection,
+    source: &str,
+    target: &str,
+) -> Result<(), sqlx::Error> {
+    sqlx::query!("SELECT copy_drafts($1, $2)", source, target)
+        .execute(connection)
+        .await?;
+    Ok(())
+}
+
+impl<'c> Table<'c, Changes> {
+    pub async fn changes(&self) -> Result<Changes, sqlx::Error> {
+        find_changes(&*self.pool).await
+    }
+
+    /// A transaction that sees the drafts as they were at its first query, so the changes it
+    /// finds are the ones it copies, even if an edit is committed in between
+    async fn begin(&self) -> Result<Transaction<'static, Postgres>, sqlx::Error> {
+        let mut transaction = self.pool.begin().await?;
+        sqlx::query!("SET TRANSACTION ISOLATION LEVEL REPEATABLE READ")
+            .execute(&mut transaction)
+            .await?;
+        Ok(transaction)
+    }
+
+    /// Copies the drafts to what the read-only server serves, all at once
+    pub async fn publish(&self, user: &User) -> Result<Changes, sqlx::Error> {
+        let mut transaction = self.begin().await?;
+        let changes = find_changes(&mut transaction).await?;
+        copy_drafts(&mut transaction, "public", "published").await?;
+        if !changes.is_empty() {
+            let change = Change::of_drafts(Action::Publish, &changes);
+            audit_dao::record(&mut transaction, user, &change).await?;
+        }
+        transaction.commit().await?;
+        Ok(changes)
+    }
+
+    /// Discards the drafts, going back to what the read-only server serves. The reverted map
+    /// visualizations get a new revision.
+    pub async fn revert(&self, user: &User) -> Result<Changes, sqlx::Error> {
+        let mut transaction = self.begin().await?;
+        let changes = find_changes(&mut transaction).await?;
+        copy_drafts(&mut transaction, "published", "public").await?;
+        revision_dao::record(&mut transaction, &changes.map_visualizations, Some(user.id)).await?;
+        if !changes.is_empty() {
+            let change = Change::of_drafts(Action::Revert, &changes);
+            audit_dao::record(&mut transaction, user, &change).await?;
+        }
+        transaction.commit().await?;
+        Ok(changes)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::{database::Database, dataset_dao};
+    use super::{copy_drafts, find_changes};
+    use crate::model::data_category::DataCategory;
+    use crate::model::dataset;
+    use crate::model::map_visualization::Creator;
+    use crate::model::user::{Role, User};
+    use sqlx::PgPool;
+
+    async fn curator(pool: &PgPool) -> Result<User, sqlx::Error> {
+        sqlx::query_as(
+            "INSERT INTO editor_user (username, role) VALUES ($1, $2) RETURNING id, username, role",
+        )
+        .bind("curator")
+        .bind(Role::Curator.id())
+        .fetch_one(pool)
+        .await
+    }
+
+    async fn published_name(pool: &PgPool, id: i32) -> Result<Option<String>, sqlx::Error> {
+        sqlx::query_scalar("SELECT name FROM published.data_category WHERE id = $1")
+            .bind(id)
+            .fetch_optional(pool)
+            .await
+    }
+
+    #[sqlx::test]
+    async fn it_publishes_and_reverts_the_drafts(pool: PgPool) -> Result<(), sqlx::Error> {
+        let user = curator(&pool).await?;
+        let database = Database::from_pool(pool.clone());
+        database.publication.publish(&user).await?;
+
+        let order = database.data_category.last_order().await? + 1;
+        let category = DataCategory {
+            id: 0,
+            name: "Health".to_string(),
+            normalized: false,
+            order,
+        };
+        let category = database.data_category.create(&category, &user).await?;
+        assert_eq!(
+            database.publication.changes().await?.data_categories,
+            vec![category.id]
+        );
+        assert_eq!(published_name(&pool, category.id).await?, None);
+
+        let published = database.publication.publish(&user).await?;
+        assert_eq!(published.data_categories, vec![category.id]);
+        assert!(database.publication.changes().await?.is_empty());
+        assert_eq!(
+            published_name(&pool, category.id).await?.as_deref(),
+            Some("Health")
+        );
+
+        let renamed = DataCategory {
+            name: "Public health".to_string(),
+            ..category
+        };
+        database.data_category.update(&renamed, &user).await?;
+        let reverted = database.publication.revert(&user).await?;
+        assert_eq!(reverted.data_categories, vec![renamed.id]);
+        assert_eq!(
+            database.data_category.by_id(renamed.id).await?.name,
+            "Health"
+        );
+        assert!(database.publication.changes().await?.is_empty());
+        Ok(())
+    }
+
+    #[sqlx::test]
+    async fn it_reverts_past_what_was_removed_since_publishing(
+        pool: PgPool,
+    ) -> Result<(), sqlx::Error> {
+        let user = curator(&pool).await?;
+        let database = Database::from_pool(pool.clone());
+        let json = dataset::Json {
+            column: "heat_days".to_string(),
+            name: "Heat days".to_string(),
+            units: "days".to_string(),
+            description: "Days above 90°F".to_string(),
+        };
+        let dataset = dataset_dao::insert(&pool, &dataset::Creator::from(json, 1)).await?;
+        let creator = Creator::suggested(&dataset, &[1.0, 2.0, 3.0]);
+        let map_visualization = database.map_visualization.create(&creator, &user).await?;
+        database.publication.publish(&user).await?;
+
+        database
+            .deletion
+            .dataset(dataset.id, false, None, &user)
+            .await?;
+        let reverted = database.publication.revert(&user).await?;
+
+        // Its dataset is gone, so it can't come back as a draft
+        assert!(reverted.map_visualizations.contains(&map_visualization));
+        assert!(database
+            .map_visualization
+            .get(map_visualization)
+            .await
+            .is_err());
+        Ok(())
+    }
+
+    #[sqlx::test]
+    async fn it_copies_the_changes_it_found_despite_edits_in_between(
+        pool: PgPool,
+    ) -> Result<(), sqlx::Error> {
+        let user = curator(&pool).await?;
+        let database = Database::from_pool(pool.clone());
+        let order = database.data_category.last_order().await? + 1;
+        let category = DataCategory {
+            id: 0,
+            name: "Health".to_string(),
+            normalized: false,
+            order,
+        };
+        let category = database.data_category.create(&category, &user).await?;
+        database.publication.publish(&user).await?;
+
+        let mut transaction = database.publication.begin().await?;
+        let changes = find_changes(&mut transaction).await?;
+        let renamed = DataCategory {
+            name: "Public health".to_string(),
+            ..category
+        };
+        database.data_category.update(&renamed, &user).await?;
+        copy_drafts(&mut transaction, "public", "published").await?;
+        transaction.commit().await?;
+
+        // The rename wasn't in the changes, so it isn't published either
+        assert!(changes.is_empty());
+        assert_eq!(
+            published_name(&pool, category.id).await?.as_deref(),
+            Some("Health")
+        );
+        assert_eq!(
+            database.publication.changes().await?.data_categories,
+            vec![category.id]
+        );
+        Ok(())
+    }
+}
@@ -0,0 +1,169 @@
+use super::Table;
+use crate::model::map_visualization::Patch;
+use crate::model::revision::Revision;
+use sqlx::postgres::PgConnection;
+
+/// Stores map visualizations' current settings as their next revisions, returning the revisions.
+/// The map visualizations stay locked until the transaction ends, so concurrent edits can't take
+/// the same revision.
+pub(super) async fn record(
+    connection: &mut PgConnection,
+    map_visualizations: &[i32],
+    editor_user: Option<i32>,
+) -> Result<Vec<i32>, sqlx::Error> {
+    sqlx::query!(
+        "SELECT id FROM map_visualization WHERE id = ANY($1) ORDER BY id FOR UPDATE",
+        map_visualizations
+    )
+    .fetch_all(&mut *connection)
+    .await?;
+    sqlx::query!(
+        "
+        INSERT INTO map_visualization_revision (map_visualization, revision, editor_user, settings)
+        SELECT
+            map_visualization.id,
+            COALESCE(
+                (
+                    SELECT MAX(revision)
+                    FROM map_visualization_revision
+                    WHERE map_visualization_revision.map_visualization = map_visualization.id
+                ),
+                0
+            ) + 1,
+            $2,
+            to_jsonb(map_visualization)
+        FROM map_visualization
+        WHERE map_visualization.id = ANY($1)
+        RETURNING revision
+        ",
+        map_visualizations,
+        editor_user,
+    )
+    .fetch_all(connection)
+    .await
+    .map(|rows| rows.into_iter().map(|row| row.revision).collect())
+}
+
+impl<'c> Table<'c, Revision> {
+    /// A map visualization's revisions, oldest first
+    pub async fn by_map_visualization(
+        &self,
+        map_visualization: i32,
+    ) -> Result<Vec<Revision>, sqlx::Error> {
+        sqlx::query_as!(
+            Revision,
+            r#"
+            SELECT
+                revision.map_visualization,
+                revision.revision,
+                revision.editor_user,
+                editor_user.username AS "username?",
+                revision.created_at,
+                revision.settings
+            FROM map_visualization_revision AS revision
+            LEFT JOIN editor_user ON editor_user.id = revision.editor_user
+            WHERE revision.map_visualization = $1
+            ORDER BY revision.revision
+            "#,
+            map_visualization
+        )
+        .fetch_all(&*self.pool)
+        .await
+    }
+
+    /// A revision, or the latest if `revision` is `None`
+    pub async fn get(
+        &self,
+        map_visualization: i32,
+        revision: Option<i32>,
+    ) -> Result<Option<Revision>, sqlx::Error> {
+        sqlx::query_as!(
+            Revision,
+            r#"
+            SELECT
+                revision.map_visualization,
+                revision.revision,
+                revision.editor_user,
+                editor_user.username AS "username?",
+                revision.created_at,
+                revision.settings
+            FROM map_visualization_revision AS revision
+            LEFT JOIN editor_user ON editor_user.id = revision.editor_user
+            WHERE revision.map_visualization = $1
+                AND ($2::INT IS NULL OR revision.revision = $2)
+            ORDER BY revision.revision DESC
+            LIMIT 1
+            "#,
+            map_visualization,
+            revision
+        )
+        .fetch_optional(&*self.pool)
+        .await
+    }
+
+    /// The settings a map visualization would have if set back to a revision, to validate them
+    /// before restoring them. Fields added since the revision keep their current values. `None`
+    /// if there's no such revision of a map visualization outside the trash.
+    pub async fn restored(
+        &self,
+        map_visualization: i32,
+        revision: i32,
+    ) -> Result<Option<Patch>, sqlx::Error> {
+        sqlx::query_as!(
+            Patch,
+            r#"
+            SELECT
+                restored.id AS "id!",
+                restored.dataset AS "dataset!",
+                restored.map_type AS "map_type!",
+                restored.subcategory,
+                NULL::INT AS data_tab,
+                restored.name,
+                restored.legend_ticks,
+                restored.color_palette AS "color_palette_id!",
+                restored.reverse_scale AS "reverse_scale!",
+                restored.invert_normalized AS "invert_normalized!",
+                restored.scale_type AS "scale_type!",
+                restored.show_pdf AS "show_pdf!",
+                restored.default_start_date,
+                restored.default_end_date,
+                restored.default_source,
+                restored.formatter_type AS "formatter_type!",
+                restored.legend_formatter_type,
+                restored.decimals AS "decimals!",
+                restored.legend_decimals,
+                restored.color_domain AS "color_domain!",
+                restored.pdf_domain AS "pdf_domain!",
+                restored.bubble_color AS "bubble_color!",
+                restored.delta_operation,
+                restored.delta_from_source,
+                restored.delta_from_start_date,
+                restored.delta_from_end_date,
+                restored.delta_to_source,
+                restored.delta_to_start_date,
+                restored.delta_to_end_date,
+                restored.delta_from_scenario,
+                restored.delta_to_scenario,
+                restored.bivariate_dataset,
+                restored.bivariate_source,
+                restored.bivariate_start_date,
+                restored.bivariate_end_date,
+                restored.bivariate_scenario,
+                restored.bivariate_statistic,
+                restored.classification_method,
+                restored.classification_classes AS "classification_classes!"
+            FROM map_visualization AS map
+            JOIN map_visualization_revision AS revision ON revision.map_visualization = map.id
+            CROSS JOIN LATERAL jsonb_populate_record(
+                NULL::map_visualization,
+                to_jsonb(map) || revision.settings
+            ) AS restored
+            WHERE map.id = $1 AND revision.revision = $2 AND map.deleted_at IS NULL
+            "#,
+            map_visualization,
+            revision
+        )
+        .fetch_optional(&*self.pool)
+        .await
+    }
+}
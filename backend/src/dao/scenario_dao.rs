@@ -0,0 +1,35 @@
+use crate::model::scenario::Scenario;
+
+use super::Table;
+
+impl<'c> Table<'c, Scenario> {
+    pub async fn all(&self) -> Result<Vec<Scenario>, sqlx::Error> {
+        sqlx::query_as!(
+            Scenario,
+            "SELECT id, name, description FROM scenario ORDER BY id"
+        )
+        .fetch_all(&*self.pool)
+        .await
+    }
+
+    pub async fn by_dataset(&self, dataset: i32) -> Result<Vec<Scenario>, sqlx::Error> {
+        sqlx::query_as!(
+            Scenario,
+            "
+            SELECT id, name, description
+            FROM scenario
+            WHERE EXISTS (
+                SELECT 1 FROM data
+                WHERE data.dataset = $1
+                AND data.scenario = scenario.id
+                AND data.dataset NOT IN (SELECT id FROM dataset WHERE deleted_at IS NOT NULL)
+                AND data.source NOT IN (SELECT id FROM data_source WHERE deleted_at IS NOT NULL)
+            )
+            ORDER BY id
+            ",
+            dataset
+        )
+        .fetch_all(&*self.pool)
+        .await
+    }
+}
@@ -7,9 +7,11 @@ impl<'c> Table<'c, SourceAndDate> {
         sqlx::query_as!(
             SourceAndDate,
             "
-            SELECT DISTINCT source, start_date, end_date
+            SELECT DISTINCT source, scenario, statistic, start_date, end_date
             FROM data
             WHERE dataset = $1
+            AND dataset NOT IN (SELECT id FROM dataset WHERE deleted_at IS NOT NULL)
+            AND source NOT IN (SELECT id FROM data_source WHERE deleted_at IS NOT NULL)
             ",
             dataset
         )
@@ -0,0 +1,14 @@
+use crate::model::statistic::Statistic;
+
+use super::Table;
+
+impl<'c> Table<'c, Statistic> {
+    pub async fn all(&self) -> Result<Vec<Statistic>, sqlx::Error> {
+        sqlx::query_as!(
+            Statistic,
+            "SELECT id, name, percentile FROM statistic ORDER BY id"
+        )
+        .fetch_all(&*self.pool)
+        .await
+    }
+}
@@ -1,6 +1,18 @@
+use crate::model::audit::{Change, Entity};
+use crate::model::order::{self, Mismatch};
 use crate::model::subcategory::Subcategory;
+use crate::model::user::User;
+use sqlx::postgres::PgExecutor;
 
-use super::Table;
+use super::{audit_dao, Table};
+
+/// The ids of the subcategories, in order, locked until the transaction ends
+async fn locked_ids<'e>(executor: impl PgExecutor<'e>) -> Result<Vec<i32>, sqlx::Error> {
+    sqlx::query!("SELECT id FROM subcategory ORDER BY \"order\" FOR UPDATE")
+        .fetch_all(executor)
+        .await
+        .map(|rows| rows.into_iter().map(|row| row.id).collect())
+}
 
 impl<'c> Table<'c, Subcategory> {
     pub async fn all(&self) -> Result<Vec<Subcategory>, sqlx::Error> {
@@ -11,4 +23,33 @@ impl<'c> Table<'c, Subcategory> {
         .fetch_all(&*self.pool)
         .await
     }
+
+    /// Numbers subcategories from 1 in the order of their ids, which must list every subcategory
+    /// once
+    pub async fn reorder(
+        &self,
+        ids: &[i32],
+        user: &User,
+    ) -> Result<Result<(), Mismatch>, sqlx::Error> {
+        let mut transaction = self.pool.begin().await?;
+        let before = locked_ids(&mut transaction).await?;
+        if let Err(mismatch) = order::check(ids, &before) {
+            return Ok(Err(mismatch));
+        }
+        sqlx::query!(
+            "
+            UPDATE subcategory
+            SET \"order\" = new.position::INT
+            FROM unnest($1::INT[]) WITH ORDINALITY AS new (id, position)
+            WHERE subcategory.id = new.id
+            ",
+            ids
+        )
+        .execute(&mut transaction)
+        .await?;
+        let change = Change::reordered(Entity::Subcategory, None, &before, ids);
+        audit_dao::record(&mut transaction, user, &change).await?;
+        transaction.commit().await?;
+        Ok(Ok(()))
+    }
 }
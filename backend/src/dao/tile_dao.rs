@@ -0,0 +1,381 @@
+use super::Table;
+use crate::model::data::SourceAndDate;
+use crate::model::delta::Delta;
+use crate::model::tile::{Coordinates, Tile};
+use sqlx::postgres::{PgConnection, PgQueryResult};
+
+/// Forget cached tiles for a geography type, for when its boundaries change
+pub(super) async fn clear_by_geography_type(
+    connection: &mut PgConnection,
+    geography_type: i32,
+) -> Result<(), sqlx::Error> {
+    sqlx::query!(
+        "
+        DELETE FROM tile_cache
+        USING dataset
+        WHERE tile_cache.dataset = dataset.id
+        AND dataset.geography_type = $1
+        ",
+        geography_type
+    )
+    .execute(&mut *connection)
+    .await?;
+    sqlx::query!(
+        "
+        DELETE FROM delta_tile_cache
+        USING dataset
+        WHERE delta_tile_cache.dataset = dataset.id
+        AND dataset.geography_type = $1
+        ",
+        geography_type
+    )
+    .execute(&mut *connection)
+    .await?;
+    Ok(())
+}
+
+impl<'c> Table<'c, Tile> {
+    /// A previously generated tile, if the map visualization's dataset hasn't changed since
+    pub async fn cached(
+        &self,
+        map_visualization: i32,
+        source_and_date: &SourceAndDate,
+        coordinates: &Coordinates,
+    ) -> Result<Option<Tile>, sqlx::Error> {
+        sqlx::query_as!(
+            Tile,
+            "
+            SELECT tile_cache.dataset, tile_cache.version, tile_cache.tile
+            FROM tile_cache
+            JOIN map_visualization
+                ON map_visualization.id = tile_cache.map_visualization
+                AND map_visualization.dataset = tile_cache.dataset
+            JOIN data_version
+                ON data_version.dataset = tile_cache.dataset
+                AND data_version.version = tile_cache.version
+            WHERE tile_cache.map_visualization = $1
+            AND map_visualization.deleted_at IS NULL
+            AND tile_cache.dataset NOT IN (SELECT id FROM dataset WHERE deleted_at IS NOT NULL)
+            AND tile_cache.source NOT IN (SELECT id FROM data_source WHERE deleted_at IS NOT NULL)
+            AND tile_cache.source = $2
+            AND tile_cache.start_date = $3
+            AND tile_cache.end_date = $4
+            AND tile_cache.z = $5
+            AND tile_cache.x = $6
+            AND tile_cache.y = $7
+            AND tile_cache.scenario = $8
+            AND tile_cache.statistic = $9
+            ",
+            map_visualization,
+            source_and_date.source,
+            source_and_date.start_date,
+            source_and_date.end_date,
+            coordinates.z,
+            coordinates.x,
+            coordinates.y,
+            source_and_date.scenario,
+            source_and_date.statistic,
+        )
+        .fetch_optional(&*self.pool)
+        .await
+    }
+
+    /// A previously generated tile of a delta map visualization comparing these slices, if the
+    /// dataset hasn't changed since
+    pub async fn cached_delta(
+        &self,
+        map_visualization: i32,
+        delta: &Delta,
+        coordinates: &Coordinates,
+    ) -> Result<Option<Tile>, sqlx::Error> {
+        sqlx::query_as!(
+            Tile,
+            "
+            SELECT cache.dataset, cache.version, cache.tile
+            FROM delta_tile_cache cache
+            JOIN map_visualization
+                ON map_visualization.id = cache.map_visualization
+                AND map_visualization.dataset = cache.dataset
+            JOIN data_version
+                ON data_version.dataset = cache.dataset
+                AND data_version.version = cache.version
+            WHERE cache.map_visualization = $1
+            AND cache.operation = $2
+            AND cache.from_source = $3
+            AND cache.from_scenario = $4
+            AND cache.from_statistic = $5
+            AND cache.from_start_date = $6
+            AND cache.from_end_date = $7
+            AND cache.to_source = $8
+            AND cache.to_scenario = $9
+            AND cache.to_statistic = $10
+            AND cache.to_start_date = $11
+            AND cache.to_end_date = $12
+            AND cache.z = $13
+            AND cache.x = $14
+            AND cache.y = $15
+            AND map_visualization.deleted_at IS NULL
+            AND cache.dataset NOT IN (SELECT id FROM dataset WHERE deleted_at IS NOT NULL)
+            AND cache.from_source NOT IN (SELECT id FROM data_source WHERE deleted_at IS NOT NULL)
+            AND cache.to_source NOT IN (SELECT id FROM data_source WHERE deleted_at IS NOT NULL)
+            ",
+            map_visualization,
+            delta.operation,
+            delta.from.source,
+            delta.from.scenario,
+            delta.from.statistic,
+            delta.from.start_date,
+            delta.from.end_date,
+            delta.to.source,
+            delta.to.scenario,
+            delta.to.statistic,
+            delta.to.start_date,
+            delta.to.end_date,
+            coordinates.z,
+            coordinates.x,
+            coordinates.y,
+        )
+        .fetch_optional(&*self.pool)
+        .await
+    }
+
+    /// Generate a tile with the boundaries of the map visualization's geography type,
+    /// and the map visualization's data as a `value` property on each feature. With a delta,
+    /// the value is its change instead of the slice's value.
+    pub async fn generate(
+        &self,
+        map_visualization: i32,
+        source_and_date: &SourceAndDate,
+        coordinates: &Coordinates,
+        delta: Option<&Delta>,
+    ) -> Result<Tile, sqlx::Error> {
+        sqlx::query_as!(
+            Tile,
+            r#"
+            WITH
+                visualization AS (
+                    SELECT map_visualization.dataset, dataset.geography_type
+                    FROM map_visualization
+                    JOIN dataset ON dataset.id = map_visualization.dataset
+                    WHERE map_visualization.id = $1
+                    AND map_visualization.deleted_at IS NULL
+                    AND dataset.deleted_at IS NULL
+                ),
+                bounds AS (
+                    SELECT ST_TileEnvelope($5, $6, $7) AS envelope
+                ),
+                features AS (
+                    SELECT
+                        ST_AsMVTGeom(ST_Transform(geo_boundary.geometry, 3857), bounds.envelope) AS geometry,
+                        geo_boundary.id,
+                        geo_id.name,
+                        CASE
+                            WHEN $10::int IS NULL THEN data.value
+                            ELSE delta_change($10, delta_from.value, delta_to.value)
+                        END AS value
+                    FROM visualization
+                    CROSS JOIN bounds
+                    JOIN geo_boundary
+                        ON geo_boundary.geography_type = visualization.geography_type
+                    JOIN geo_id
+                        ON geo_id.geography_type = geo_boundary.geography_type
+                        AND geo_id.id = geo_boundary.id
+                    LEFT JOIN data
+                        ON data.dataset = visualization.dataset
+                        AND data.geography_type = geo_boundary.geography_type
+                        AND data.id = geo_boundary.id
+                        AND data.source = $2
+                        AND data.start_date = $3
+                        AND data.end_date = $4
+                        AND data.scenario = $8
+                        AND data.statistic = $9
+                        AND data.source NOT IN (SELECT id FROM data_source WHERE deleted_at IS NOT NULL)
+                    LEFT JOIN data AS delta_from
+                        ON delta_from.dataset = visualization.dataset
+                        AND delta_from.geography_type = geo_boundary.geography_type
+                        AND delta_from.id = geo_boundary.id
+                        AND delta_from.source = $11
+                        AND delta_from.start_date = $12
+                        AND delta_from.end_date = $13
+                        AND delta_from.scenario = $14
+                        AND delta_from.statistic = $15
+                        AND delta_from.source NOT IN (SELECT id FROM data_source WHERE deleted_at IS NOT NULL)
+                    LEFT JOIN data AS delta_to
+                        ON delta_to.dataset = visualization.dataset
+                        AND delta_to.geography_type = geo_boundary.geography_type
+                        AND delta_to.id = geo_boundary.id
+                        AND delta_to.source = $16
+                        AND delta_to.start_date = $17
+                        AND delta_to.end_date = $18
+                        AND delta_to.scenario = $19
+                        AND delta_to.statistic = $20
+                        AND delta_to.source NOT IN (SELECT id FROM data_source WHERE deleted_at IS NOT NULL)
+                    WHERE ST_Intersects(geo_boundary.geometry, ST_Transform(bounds.envelope, 4326))
+                )
+            SELECT
+                visualization.dataset as "dataset!",
+                COALESCE(data_version.version, 0) as "version!",
+                COALESCE(
+                    (SELECT ST_AsMVT(features, 'data', 4096, 'geometry', 'id') FROM features),
+                    ''::bytea
+                ) as "tile!"
+            FROM visualization
+            LEFT JOIN data_version ON data_version.dataset = visualization.dataset
+            "#,
+            map_visualization,
+            source_and_date.source,
+            source_and_date.start_date,
+            source_and_date.end_date,
+            coordinates.z,
+            coordinates.x,
+            coordinates.y,
+            source_and_date.scenario,
+            source_and_date.statistic,
+            delta.map(|delta| delta.operation),
+            delta.map(|delta| delta.from.source),
+            delta.map(|delta| delta.from.start_date),
+            delta.map(|delta| delta.from.end_date),
+            delta.map(|delta| delta.from.scenario),
+            delta.map(|delta| delta.from.statistic),
+            delta.map(|delta| delta.to.source),
+            delta.map(|delta| delta.to.start_date),
+            delta.map(|delta| delta.to.end_date),
+            delta.map(|delta| delta.to.scenario),
+            delta.map(|delta| delta.to.statistic),
+        )
+        .fetch_one(&*self.pool)
+        .await
+    }
+
+    pub async fn store(
+        &self,
+        map_visualization: i32,
+        source_and_date: &SourceAndDate,
+        coordinates: &Coordinates,
+        tile: &Tile,
+    ) -> Result<PgQueryResult, sqlx::Error> {
+        sqlx::query!(
+            "
+            INSERT INTO tile_cache
+            (
+                map_visualization,
+                source,
+                start_date,
+                end_date,
+                z,
+                x,
+                y,
+                dataset,
+                version,
+                tile,
+                scenario,
+                statistic
+            )
+            VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10, $11, $12)
+            ON CONFLICT (
+                map_visualization,
+                source,
+                scenario,
+                statistic,
+                start_date,
+                end_date,
+                z,
+                x,
+                y
+            ) DO UPDATE
+            SET dataset = EXCLUDED.dataset,
+                version = EXCLUDED.version,
+                tile = EXCLUDED.tile
+            ",
+            map_visualization,
+            source_and_date.source,
+            source_and_date.start_date,
+            source_and_date.end_date,
+            coordinates.z,
+            coordinates.x,
+            coordinates.y,
+            tile.dataset,
+            tile.version,
+            &tile.tile,
+            source_and_date.scenario,
+            source_and_date.statistic,
+        )
+        .execute(&*self.pool)
+        .await
+    }
+
+    pub async fn store_delta(
+        &self,
+        map_visualization: i32,
+        delta: &Delta,
+        coordinates: &Coordinates,
+        tile: &Tile,
+    ) -> Result<PgQueryResult, sqlx::Error> {
+        sqlx::query!(
+            "
+            INSERT INTO delta_tile_cache
+            (
+                map_visualization,
+                operation,
+                from_source,
+                from_scenario,
+                from_statistic,
+                from_start_date,
+                from_end_date,
+                to_source,
+                to_scenario,
+                to_statistic,
+                to_start_date,
+                to_end_date,
+                z,
+                x,
+                y,
+                dataset,
+                version,
+                tile
+            )
+            VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10, $11, $12, $13, $14, $15, $16, $17, $18)
+            ON CONFLICT (
+                map_visualization,
+                operation,
+                from_source,
+                from_scenario,
+                from_statistic,
+                from_start_date,
+                from_end_date,
+                to_source,
+                to_scenario,
+                to_statistic,
+                to_start_date,
+                to_end_date,
+                z,
+                x,
+                y
+            ) DO UPDATE
+            SET dataset = EXCLUDED.dataset,
+                version = EXCLUDED.version,
+                tile = EXCLUDED.tile
+            ",
+            map_visualization,
+            delta.operation,
+            delta.from.source,
+            delta.from.scenario,
+            delta.from.statistic,
+            delta.from.start_date,
+            delta.from.end_date,
+            delta.to.source,
+            delta.to.scenario,
+            delta.to.statistic,
+            delta.to.start_date,
+            delta.to.end_date,
+            coordinates.z,
+            coordinates.x,
+            coordinates.y,
+            tile.dataset,
+            tile.version,
+            &tile.tile,
+        )
+        .execute(&*self.pool)
+        .await
+    }
+}
@@ -0,0 +1,109 @@
+use super::{audit_dao, Table};
+use crate::model::audit::{Change, Entity};
+use crate::model::user::{Token, User};
+use chrono::{DateTime, Utc};
+use sqlx::postgres::PgQueryResult;
+
+impl<'c> Table<'c, Token> {
+    pub async fn by_user(&self, user: i32) -> Result<Vec<Token>, sqlx::Error> {
+        sqlx::query_as!(
+            Token,
+            "
+            SELECT id, name, created_at, expires_at, last_used_at
+            FROM api_token
+            WHERE editor_user = $1
+                AND (expires_at IS NULL OR expires_at > NOW())
+            ORDER BY created_at
+            ",
+            user
+        )
+        .fetch_all(&*self.pool)
+        .await
+    }
+
+    /// Creates a token for a user. Its hash stays out of the audit log.
+    pub async fn create(
+        &self,
+        user: &User,
+        name: &str,
+        token_hash: &str,
+        expires_at: Option<DateTime<Utc>>,
+    ) -> Result<i32, sqlx::Error> {
+        let mut transaction = self.pool.begin().await?;
+        let created = sqlx::query_as!(
+            Token,
+            "
+            INSERT INTO api_token (editor_user, name, token_hash, expires_at)
+            VALUES ($1, $2, $3, $4)
+            RETURNING id, name, created_at, expires_at, last_used_at
+            ",
+            user.id,
+            name,
+            token_hash,
+            expires_at,
+        )
+        .fetch_one(&mut transaction)
+        .await?;
+        let change = Change::created(Entity::Token, created.id, &created);
+        audit_dao::record(&mut transaction, user, &change).await?;
+        transaction.commit().await?;
+        Ok(created.id)
+    }
+
+    /// Deletes a token, but only if it belongs to the user
+    pub async fn delete(&self, user: &User, id: i32) -> Result<Option<Token>, sqlx::Error> {
+        let mut transaction = self.pool.begin().await?;
+        let deleted = sqlx::query_as!(
+            Token,
+            "
+            DELETE FROM api_token
+            WHERE editor_user = $1 AND id = $2
+            RETURNING id, name, created_at, expires_at, last_used_at
+            ",
+            user.id,
+            id
+        )
+        .fetch_optional(&mut transaction)
+        .await?;
+        if let Some(deleted) = &deleted {
+            let change = Change::deleted(Entity::Token, deleted.id, deleted);
+            audit_dao::record(&mut transaction, user, &change).await?;
+        }
+        transaction.commit().await?;
+        Ok(deleted)
+    }
+
+    /// Deletes one of the user's tokens by its hash, like the session token when logging out
+    pub async fn delete_by_hash(
+        &self,
+        user: &User,
+        token_hash: &str,
+    ) -> Result<Option<Token>, sqlx::Error> {
+        let mut transaction = self.pool.begin().await?;
+        let deleted = sqlx::query_as!(
+            Token,
+            "
+            DELETE FROM api_token
+            WHERE editor_user = $1 AND token_hash = $2
+            RETURNING id, name, created_at, expires_at, last_used_at
+            ",
+            user.id,
+            token_hash
+        )
+        .fetch_optional(&mut transaction)
+        .await?;
+        if let Some(deleted) = &deleted {
+            let change = Change::deleted(Entity::Token, deleted.id, deleted);
+            audit_dao::record(&mut transaction, user, &change).await?;
+        }
+        transaction.commit().await?;
+        Ok(deleted)
+    }
+
+    /// Removes expired session tokens
+    pub async fn delete_expired(&self) -> Result<PgQueryResult, sqlx::Error> {
+        sqlx::query!("DELETE FROM api_token WHERE expires_at <= NOW()")
+            .execute(&*self.pool)
+            .await
+    }
+}
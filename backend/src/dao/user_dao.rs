@@ -0,0 +1,147 @@
+use super::{audit_dao, Table};
+use crate::model::audit::{Change, Entity};
+use crate::model::user::{Login, User};
+use serde_json::json;
+use sqlx::postgres::{PgExecutor, PgQueryResult};
+
+/// A user locked until the transaction ends
+async fn locked<'e>(executor: impl PgExecutor<'e>, id: i32) -> Result<Option<User>, sqlx::Error> {
+    sqlx::query_as!(
+        User,
+        "SELECT id, username, role FROM editor_user WHERE id = $1 FOR UPDATE",
+        id
+    )
+    .fetch_optional(executor)
+    .await
+}
+
+impl<'c> Table<'c, User> {
+    pub async fn all(&self) -> Result<Vec<User>, sqlx::Error> {
+        sqlx::query_as!(
+            User,
+            "SELECT id, username, role FROM editor_user ORDER BY username"
+        )
+        .fetch_all(&*self.pool)
+        .await
+    }
+
+    pub async fn by_username(&self, username: &str) -> Result<Option<Login>, sqlx::Error> {
+        sqlx::query_as!(
+            Login,
+            "
+            SELECT id, username, role, password_hash
+            FROM editor_user
+            WHERE username = $1
+            ",
+            username
+        )
+        .fetch_optional(&*self.pool)
+        .await
+    }
+
+    /// The user a bearer token belongs to, if the token exists and hasn't expired. Marks the
+    /// token as used.
+    pub async fn by_token(&self, token_hash: &str) -> Result<Option<User>, sqlx::Error> {
+        sqlx::query_as!(
+            User,
+            "
+            WITH token AS (
+                UPDATE api_token
+                SET last_used_at = NOW()
+                WHERE token_hash = $1
+                    AND (expires_at IS NULL OR expires_at > NOW())
+                RETURNING editor_user
+            )
+            SELECT editor_user.id, editor_user.username, editor_user.role
+            FROM editor_user
+            JOIN token ON token.editor_user = editor_user.id
+            ",
+            token_hash
+        )
+        .fetch_optional(&*self.pool)
+        .await
+    }
+
+    pub async fn count(&self) -> Result<i64, sqlx::Error> {
+        sqlx::query!(r#"SELECT COUNT(*) AS "count!" FROM editor_user"#)
+            .fetch_one(&*self.pool)
+            .await
+            .map(|row| row.count)
+    }
+
+    /// Creates a user. Audit entries leave out the password, only noting whether it was set.
+    pub async fn create(
+        &self,
+        username: &str,
+        password_hash: Option<&str>,
+        role: i32,
+        editor: &User,
+    ) -> Result<i32, sqlx::Error> {
+        let mut transaction = self.pool.begin().await?;
+        let created = sqlx::query_as!(
+            User,
+            "
+            INSERT INTO editor_user (username, password_hash, role)
+            VALUES ($1, $2, $3)
+            RETURNING id, username, role
+            ",
+            username,
+            password_hash,
+            role,
+        )
+        .fetch_one(&mut transaction)
+        .await?;
+        let after = json!({ "user": created, "password_set": password_hash.is_some() });
+        let change = Change::created(Entity::User, created.id, &after);
+        audit_dao::record(&mut transaction, editor, &change).await?;
+        transaction.commit().await?;
+        Ok(created.id)
+    }
+
+    pub async fn update(
+        &self,
+        id: i32,
+        password_hash: Option<&str>,
+        role: i32,
+        editor: &User,
+    ) -> Result<Option<User>, sqlx::Error> {
+        let mut transaction = self.pool.begin().await?;
+        let before = locked(&mut transaction, id).await?;
+        let updated = sqlx::query_as!(
+            User,
+            "
+            UPDATE editor_user
+            SET role = $1,
+                password_hash = COALESCE($2, password_hash)
+            WHERE id = $3
+            RETURNING id, username, role
+            ",
+            role,
+            password_hash,
+            id,
+        )
+        .fetch_optional(&mut transaction)
+        .await?;
+        if let Some(updated) = &updated {
+            let after = json!({ "user": updated, "password_set": password_hash.is_some() });
+            let change = Change::updated(Entity::User, id, &json!({ "user": before }), &after);
+            audit_dao::record(&mut transaction, editor, &change).await?;
+        }
+        transaction.commit().await?;
+        Ok(updated)
+    }
+
+    pub async fn delete(&self, id: i32, editor: &User) -> Result<PgQueryResult, sqlx::Error> {
+        let mut transaction = self.pool.begin().await?;
+        let before = locked(&mut transaction, id).await?;
+        let result = sqlx::query!("DELETE FROM editor_user WHERE id = $1", id)
+            .execute(&mut transaction)
+            .await?;
+        if result.rows_affected() > 0 {
+            let change = Change::deleted(Entity::User, id, &json!({ "user": before }));
+            audit_dao::record(&mut transaction, editor, &change).await?;
+        }
+        transaction.commit().await?;
+        Ok(result)
+    }
+}
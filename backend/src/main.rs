@@ -1,15 +1,43 @@
 use actix_web::{middleware::Logger, web, App, HttpServer};
-use climate_risk_map::config::Config;
+use climate_risk_map::config::{AdminConfig, Config};
+use climate_risk_map::controller::auth::Authentication;
 use climate_risk_map::dao::Database;
+use climate_risk_map::model::user::{self, Role};
 use climate_risk_map::{controller, AppState};
 use env_logger::Env;
 use futures::future;
+use log::{error, info};
 use std::sync::{Arc, Mutex};
 
+/// Creates the first admin so the editor can be signed into
+async fn bootstrap_admin(database: &Database<'_>, admin: &AdminConfig) {
+    match database.user.count().await {
+        Err(e) => error!("Error counting editor users: {}", e),
+        Ok(0) => {
+            let password_hash = match user::hash_password(&admin.password) {
+                Err(e) => {
+                    error!("Error hashing admin password: {}", e);
+                    return;
+                }
+                Ok(password_hash) => password_hash,
+            };
+            let created = database
+                .user
+                .create(&admin.username, Some(&password_hash), Role::Admin.id())
+                .await;
+            match created {
+                Err(e) => error!("Error creating admin {}: {}", admin.username, e),
+                Ok(_) => info!("Created editor admin {}", admin.username),
+            }
+        }
+        Ok(_) => {}
+    }
+}
+
 #[actix_web::main]
 async fn main() -> std::io::Result<()> {
     let config = Config::from_env();
-    let database = Database::new(&config.database_url()).await;
+    let database = Database::published(&config.database_url()).await;
     let editor_database = Database::new(&config.database_url()).await;
     let app_state = web::Data::new(AppState {
         connections: Mutex::new(0),
@@ -20,34 +48,39 @@ async fn main() -> std::io::Result<()> {
         database: Arc::new(editor_database),
     });
     env_logger::Builder::from_env(Env::default().default_filter_or("info,sqlx=error")).init();
+    if let Some(admin) = config.admin() {
+        bootstrap_admin(&editor_state.database, admin).await;
+    }
     let read_only_app = HttpServer::new(move || {
         App::new()
             .app_data(app_state.clone())
-            .configure(controller::state_controller::init)
-            .configure(controller::county_controller::init)
-            .configure(controller::data_controller::init)
-            .configure(controller::dataset_controller::init)
-            .configure(controller::map_visualization_controller::init)
-            .configure(controller::data_category_controller::init)
-            .configure(controller::color_palette_controller::init)
-            .configure(controller::scale_type_controller::init)
-            .configure(controller::subcategory_controller::init)
-            .configure(controller::data_source_controller::init)
-            .configure(controller::geography_type_controller::init)
-            .configure(controller::geo_id_controller::init)
+            .configure(controller::init)
             .wrap(Logger::default())
     })
     .bind(config.app_url())?;
     let editor_app = HttpServer::new(move || {
         App::new()
             .app_data(editor_state.clone())
+            .configure(controller::init)
             .configure(controller::map_visualization_controller::init_editor)
             .configure(controller::map_visualization_collection_controller::init_editor)
             .configure(controller::data_category_controller::init_editor)
+            .configure(controller::subcategory_controller::init_editor)
             .configure(controller::dataset_controller::init_editor)
             .configure(controller::data_source_controller::init_editor)
             .configure(controller::uploader_controller::init_editor)
             .configure(controller::data_controller::init_editor)
+            .configure(controller::geo_boundary_controller::init_editor)
+            .configure(controller::aggregation_controller::init_editor)
+            .configure(controller::crosswalk_controller::init_editor)
+            .configure(controller::custom_region_controller::init_editor)
+            .configure(controller::neighbor_controller::init_editor)
+            .configure(controller::user_controller::init_editor)
+            .configure(controller::audit_controller::init_editor)
+            .configure(controller::publication_controller::init_editor)
+            .configure(controller::revision_controller::init_editor)
+            .configure(controller::trash_controller::init_editor)
+            .wrap(Authentication)
             .wrap(Logger::default())
     })
     .bind(config.editor_url())?;
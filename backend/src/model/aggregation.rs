@@ -0,0 +1,156 @@
+use super::data::Simple;
+use derive_more::Display;
+use serde::{Deserialize, Serialize};
+use sqlx::FromRow;
+use std::collections::BTreeMap;
+
+/// How the values of child geo ids are combined into their parent's value
+#[derive(Deserialize, Serialize, Debug, Display, Clone, Copy, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum Method {
+    #[display(fmt = "sum")]
+    Sum,
+    #[display(fmt = "mean")]
+    Mean,
+    /// The mean weighted by another dataset, like population
+    #[display(fmt = "weighted_mean")]
+    WeightedMean,
+    #[display(fmt = "min")]
+    Min,
+    #[display(fmt = "max")]
+    Max,
+    #[display(fmt = "median")]
+    Median,
+}
+
+impl Method {
+    pub fn needs_weights(&self) -> bool {
+        *self == Method::WeightedMean
+    }
+}
+
+/// A child geo id's value, with its parent and its weight if a weight dataset was given
+#[derive(FromRow, Debug)]
+pub struct Child {
+    pub parent: i64,
+    pub value: f64,
+    pub weight: Option<f64>,
+}
+
+/// Combines children into a value per parent. For a weighted mean, children without a weight
+/// are left out, as are parents whose children have no weight in total.
+pub fn aggregate(method: Method, children: Vec<Child>) -> Vec<Simple> {
+    let mut by_parent: BTreeMap<i64, Vec<Child>> = BTreeMap::new();
+    for child in children {
+        by_parent.entry(child.parent).or_default().push(child);
+    }
+    by_parent
+        .into_iter()
+        .filter_map(|(id, children)| {
+            let value = combine(method, children)?;
+            Some(Simple { id, value })
+        })
+        .collect()
+}
+
+fn combine(method: Method, children: Vec<Child>) -> Option<f64> {
+    let count = children.len() as f64;
+    let values = children.iter().map(|child| child.value);
+    match method {
+        Method::Sum => Some(values.sum()),
+        Method::Mean => Some(values.sum::<f64>() / count),
+        Method::Min => values.reduce(f64::min),
+        Method::Max => values.reduce(f64::max),
+        Method::Median => median(values.collect()),
+        Method::WeightedMean => {
+            let (total, total_weight) = children
+                .iter()
+                .filter_map(|child| Some((child.value, child.weight?)))
+                .fold((0.0, 0.0), |(total, total_weight), (value, weight)| {
+                    (total + value * weight, total_weight + weight)
+                });
+            if total_weight == 0.0 {
+                None
+            } else {
+                Some(total / total_weight)
+            }
+        }
+    }
+}
+
+fn median(mut values: Vec<f64>) -> Option<f64> {
+    if values.is_empty() {
+        return None;
+    }
+    values.sort_by(|a, b| a.total_cmp(b));
+    let middle = values.len() / 2;
+    if values.len() % 2 == 0 {
+        Some((values[middle - 1] + values[middle]) / 2.0)
+    } else {
+        Some(values[middle])
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn children() -> Vec<Child> {
+        vec![
+            Child {
+                parent: 1,
+                value: 1.0,
+                weight: Some(3.0),
+            },
+            Child {
+                parent: 1,
+                value: 5.0,
+                weight: Some(1.0),
+            },
+            Child {
+                parent: 1,
+                value: 3.0,
+                weight: None,
+            },
+            Child {
+                parent: 2,
+                value: 4.0,
+                weight: None,
+            },
+        ]
+    }
+
+    fn values(method: Method) -> Vec<(i64, f64)> {
+        aggregate(method, children())
+            .into_iter()
+            .map(|row| (row.id, row.value))
+            .collect()
+    }
+
+    #[test]
+    fn it_aggregates_children_by_parent() {
+        assert_eq!(values(Method::Sum), vec![(1, 9.0), (2, 4.0)]);
+        assert_eq!(values(Method::Mean), vec![(1, 3.0), (2, 4.0)]);
+        assert_eq!(values(Method::Min), vec![(1, 1.0), (2, 4.0)]);
+        assert_eq!(values(Method::Max), vec![(1, 5.0), (2, 4.0)]);
+        assert_eq!(values(Method::Median), vec![(1, 3.0), (2, 4.0)]);
+    }
+
+    #[test]
+    fn it_weights_by_the_weight_dataset() {
+        assert_eq!(values(Method::WeightedMean), vec![(1, 2.0)]);
+    }
+
+    #[test]
+    fn it_averages_the_middle_values_for_an_even_median() {
+        assert_eq!(median(vec![4.0, 1.0, 3.0, 2.0]), Some(2.5));
+        assert_eq!(median(vec![]), None);
+    }
+
+    #[test]
+    fn it_parses_methods() {
+        let method: Method = serde_json::from_str(r#""weighted_mean""#).unwrap();
+        assert_eq!(method, Method::WeightedMean);
+        assert_eq!(method.to_string(), "weighted_mean");
+    }
+}
@@ -0,0 +1,295 @@
+use chrono::{DateTime, Utc};
+use derive_more::Display;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use sqlx::FromRow;
+
+/// The most entries returned at once
+pub const MAX_LIMIT: i64 = 1000;
+
+#[derive(Deserialize, Serialize, Debug, Display, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum Action {
+    #[display(fmt = "create")]
+    Create,
+    #[display(fmt = "update")]
+    Update,
+    #[display(fmt = "delete")]
+    Delete,
+    #[display(fmt = "upload")]
+    Upload,
+    #[display(fmt = "publish")]
+    Publish,
+    #[display(fmt = "revert")]
+    Revert,
+    #[display(fmt = "reorder")]
+    Reorder,
+    /// Taking something out of the trash
+    #[display(fmt = "restore")]
+    Restore,
+    /// Removing something for good, from the trash or instead of putting it there
+    #[display(fmt = "purge")]
+    Purge,
+}
+
+#[derive(Deserialize, Serialize, Debug, Display, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum Entity {
+    #[display(fmt = "dataset")]
+    Dataset,
+    /// A dataset's data, by dataset id
+    #[display(fmt = "data")]
+    Data,
+    #[display(fmt = "data_source")]
+    DataSource,
+    #[display(fmt = "data_category")]
+    DataCategory,
+    #[display(fmt = "map_visualization")]
+    MapVisualization,
+    /// A map visualization's place in a category, by map visualization id, or the order of a
+    /// category's map visualizations, by category id
+    #[display(fmt = "map_visualization_collection")]
+    MapVisualizationCollection,
+    #[display(fmt = "subcategory")]
+    Subcategory,
+    /// All the drafts at once, when they are published or reverted
+    #[display(fmt = "drafts")]
+    Drafts,
+    /// A geography type's boundaries, by geography type id
+    #[display(fmt = "geo_boundary")]
+    GeoBoundary,
+    /// A crosswalk's links, by crosswalk id
+    #[display(fmt = "crosswalk_link")]
+    CrosswalkLink,
+    #[display(fmt = "custom_region")]
+    CustomRegion,
+    /// A geography type's neighbors, by geography type id
+    #[display(fmt = "neighbor")]
+    Neighbor,
+    #[display(fmt = "user")]
+    User,
+    #[display(fmt = "token")]
+    Token,
+}
+
+#[derive(FromRow, Serialize, Debug)]
+pub struct Entry {
+    pub id: i64,
+    pub editor_user: Option<i32>,
+    pub username: String,
+    pub created_at: DateTime<Utc>,
+    pub action: String,
+    pub entity: String,
+    pub entity_id: Option<i32>,
+    pub before: Option<Value>,
+    pub after: Option<Value>,
+}
+
+/// A change to record, with the entity as JSON before and after it
+#[derive(Debug, PartialEq)]
+pub struct Change {
+    pub action: Action,
+    pub entity: Entity,
+    pub entity_id: Option<i32>,
+    pub before: Option<Value>,
+    pub after: Option<Value>,
+}
+
+/// Missing entities, like `None` from a failed lookup, are stored as SQL `NULL`
+fn json<T: Serialize>(value: &T) -> Option<Value> {
+    serde_json::to_value(value)
+        .ok()
+        .filter(|value| !value.is_null())
+}
+
+impl Change {
+    pub fn created<T: Serialize>(entity: Entity, id: i32, after: &T) -> Self {
+        Change {
+            action: Action::Create,
+            entity,
+            entity_id: Some(id),
+            before: None,
+            after: json(after),
+        }
+    }
+
+    pub fn updated<B: Serialize, A: Serialize>(
+        entity: Entity,
+        id: i32,
+        before: &B,
+        after: &A,
+    ) -> Self {
+        Change {
+            action: Action::Update,
+            entity,
+            entity_id: Some(id),
+            before: json(before),
+            after: json(after),
+        }
+    }
+
+    pub fn deleted<T: Serialize>(entity: Entity, id: i32, before: &T) -> Self {
+        Change {
+            action: Action::Delete,
+            entity,
+            entity_id: Some(id),
+            before: json(before),
+            after: None,
+        }
+    }
+
+    /// A change in the order of some entities, as their ids in order
+    pub fn reordered(entity: Entity, id: Option<i32>, before: &[i32], after: &[i32]) -> Self {
+        Change {
+            action: Action::Reorder,
+            entity,
+            entity_id: id,
+            before: json(&before),
+            after: json(&after),
+        }
+    }
+
+    pub fn uploaded<T: Serialize>(entity: Entity, id: i32, after: &T) -> Self {
+        Change {
+            action: Action::Upload,
+            ..Change::created(entity, id, after)
+        }
+    }
+
+    pub fn restored<T: Serialize>(entity: Entity, id: i32, after: &T) -> Self {
+        Change {
+            action: Action::Restore,
+            ..Change::created(entity, id, after)
+        }
+    }
+
+    pub fn purged<T: Serialize>(entity: Entity, id: i32, before: &T) -> Self {
+        Change {
+            action: Action::Purge,
+            ..Change::deleted(entity, id, before)
+        }
+    }
+
+    /// Publishing or reverting all the drafts, with what changed
+    pub fn of_drafts<T: Serialize>(action: Action, changes: &T) -> Self {
+        Change {
+            action,
+            entity: Entity::Drafts,
+            entity_id: None,
+            before: None,
+            after: json(changes),
+        }
+    }
+}
+
+fn default_limit() -> i64 {
+    100
+}
+
+/// Which entries to get, newest first. Times are inclusive of `from` and exclusive of `to`.
+#[derive(Deserialize)]
+pub struct Filter {
+    pub entity: Option<Entity>,
+    pub entity_id: Option<i32>,
+    pub editor_user: Option<i32>,
+    pub action: Option<Action>,
+    pub from: Option<DateTime<Utc>>,
+    pub to: Option<DateTime<Utc>>,
+    #[serde(default = "default_limit")]
+    pub limit: i64,
+    #[serde(default)]
+    pub offset: i64,
+}
+
+impl Filter {
+    pub fn limit(&self) -> i64 {
+        self.limit.clamp(1, MAX_LIMIT)
+    }
+
+    pub fn offset(&self) -> i64 {
+        self.offset.max(0)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[derive(Serialize)]
+    struct Category {
+        id: i32,
+        name: String,
+    }
+
+    #[test]
+    fn it_records_entities_as_json() {
+        let before = Category {
+            id: 1,
+            name: "Health".to_string(),
+        };
+        let after = Category {
+            id: 1,
+            name: "Public health".to_string(),
+        };
+
+        assert_eq!(
+            Change::updated(Entity::DataCategory, 1, &before, &after),
+            Change {
+                action: Action::Update,
+                entity: Entity::DataCategory,
+                entity_id: Some(1),
+                before: Some(json!({"id": 1, "name": "Health"})),
+                after: Some(json!({"id": 1, "name": "Public health"})),
+            }
+        );
+    }
+
+    #[test]
+    fn missing_entities_are_null() {
+        let change = Change::updated(Entity::Dataset, 2, &None::<Category>, &json!({"id": 2}));
+
+        assert_eq!(change.before, None);
+        assert_eq!(change.after, Some(json!({"id": 2})));
+        assert_eq!(
+            Change::uploaded(Entity::Dataset, 2, &json!({"rows": 3})).action,
+            Action::Upload
+        );
+    }
+
+    #[test]
+    fn it_records_what_was_restored_or_purged() {
+        let restored = Change::restored(Entity::DataSource, 3, &json!({"id": 3}));
+        let purged = Change::purged(Entity::DataSource, 3, &json!({"id": 3}));
+
+        assert_eq!(restored.action, Action::Restore);
+        assert_eq!(restored.after, Some(json!({"id": 3})));
+        assert_eq!(purged.action, Action::Purge);
+        assert_eq!(purged.before, Some(json!({"id": 3})));
+        assert_eq!(purged.after, None);
+    }
+
+    #[test]
+    fn it_names_entities_like_their_tables() {
+        assert_eq!(
+            Entity::MapVisualizationCollection.to_string(),
+            serde_json::to_value(Entity::MapVisualizationCollection).unwrap()
+        );
+        assert_eq!(Entity::DataSource.to_string(), "data_source");
+        assert_eq!(
+            Entity::CrosswalkLink.to_string(),
+            serde_json::to_value(Entity::CrosswalkLink).unwrap()
+        );
+        assert_eq!(Entity::User.to_string(), "user");
+    }
+
+    #[test]
+    fn it_records_drafts_by_what_changed() {
+        let change = Change::of_drafts(Action::Publish, &json!({"map_visualization": [1, 2]}));
+
+        assert_eq!(change.entity, Entity::Drafts);
+        assert_eq!(change.entity_id, None);
+        assert_eq!(change.before, None);
+        assert_eq!(change.after, Some(json!({"map_visualization": [1, 2]})));
+    }
+}
@@ -0,0 +1,233 @@
+use super::scale_type;
+use super::stats::quantile;
+use derive_more::Display;
+use serde::{Deserialize, Serialize};
+use sqlx::FromRow;
+
+/// The most values Jenks natural breaks are computed from. Larger slices are sampled evenly by
+/// rank, since the algorithm is quadratic in the number of values.
+const JENKS_MAX_VALUES: usize = 2000;
+
+/// How values are split into classes
+#[derive(Deserialize, Serialize, Debug, Display, Clone, Copy, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum Method {
+    #[display(fmt = "quantile")]
+    Quantile,
+    /// Jenks natural breaks, which minimize the variance within each class
+    #[display(fmt = "jenks")]
+    Jenks,
+    #[display(fmt = "equal_interval")]
+    EqualInterval,
+    /// Classes a standard deviation wide, centered on the mean
+    #[display(fmt = "standard_deviation")]
+    StandardDeviation,
+    /// Breaks set by hand, in a map visualization's `color_domain`
+    #[display(fmt = "manual")]
+    Manual,
+}
+
+impl Method {
+    /// The method with an id from the `classification_method` table
+    pub fn from_id(id: i32) -> Option<Method> {
+        match id {
+            1 => Some(Method::Quantile),
+            2 => Some(Method::Jenks),
+            3 => Some(Method::EqualInterval),
+            4 => Some(Method::StandardDeviation),
+            5 => Some(Method::Manual),
+            _ => None,
+        }
+    }
+}
+
+/// Breaks cached for a slice of a map visualization's dataset, valid while the dataset's data
+/// version matches
+#[derive(FromRow, Debug)]
+pub struct Cached {
+    pub dataset: i32,
+    pub version: i32,
+    pub breaks: Vec<f64>,
+}
+
+/// The bounds of each class, from the minimum to the maximum, so one more than the number of
+/// classes. Empty without values, and for manual breaks, which aren't derived from values.
+pub fn breaks(method: Method, values: &[f64], classes: usize) -> Vec<f64> {
+    if values.is_empty() || classes == 0 {
+        return vec![];
+    }
+    let mut sorted = values.to_vec();
+    sorted.sort_by(|a, b| a.total_cmp(b));
+    let (min, max) = (sorted[0], sorted[sorted.len() - 1]);
+    match method {
+        Method::Quantile => (0..=classes)
+            .map(|class| quantile(&sorted, class as f64 / classes as f64))
+            .collect(),
+        Method::EqualInterval => (0..=classes)
+            .map(|class| min + (max - min) * class as f64 / classes as f64)
+            .collect(),
+        Method::Jenks => jenks(&sample(&sorted), classes),
+        Method::StandardDeviation => standard_deviation(&sorted, classes),
+        Method::Manual => vec![],
+    }
+}
+
+/// Inner breaks a standard deviation apart, centered on the mean for an even number of classes
+/// and around a class centered on the mean for an odd number. Breaks outside the range of the
+/// values are clamped to it.
+fn standard_deviation(sorted: &[f64], classes: usize) -> Vec<f64> {
+    let n = sorted.len() as f64;
+    let (min, max) = (sorted[0], sorted[sorted.len() - 1]);
+    let mean = sorted.iter().sum::<f64>() / n;
+    let std = (sorted.iter().map(|x| (x - mean).powi(2)).sum::<f64>() / n).sqrt();
+    let mut breaks = vec![min];
+    breaks.extend(
+        (1..classes)
+            .map(|class| (mean + (class as f64 - classes as f64 / 2.0) * std).clamp(min, max)),
+    );
+    breaks.push(max);
+    breaks
+}
+
+fn sample(sorted: &[f64]) -> Vec<f64> {
+    if sorted.len() <= JENKS_MAX_VALUES {
+        return sorted.to_vec();
+    }
+    (0..JENKS_MAX_VALUES)
+        .map(|i| sorted[i * (sorted.len() - 1) / (JENKS_MAX_VALUES - 1)])
+        .collect()
+}
+
+/// Fisher's dynamic programming solution to Jenks natural breaks on sorted values
+fn jenks(sorted: &[f64], classes: usize) -> Vec<f64> {
+    let n = sorted.len();
+    let classes = classes.min(n);
+    // lower_limits[l][j] is the 1-based index of the first value in class j of the best split
+    // of the first l values, and variances[l][j] that split's total within class variance
+    let mut lower_limits = vec![vec![0usize; classes + 1]; n + 1];
+    let mut variances = vec![vec![f64::INFINITY; classes + 1]; n + 1];
+    for j in 1..=classes {
+        lower_limits[1][j] = 1;
+        variances[1][j] = 0.0;
+    }
+
+    for l in 2..=n {
+        let (mut sum, mut sum_squares, mut count) = (0.0, 0.0, 0.0);
+        let mut variance = 0.0;
+        for m in 1..=l {
+            let lower = l - m + 1;
+            let value = sorted[lower - 1];
+            count += 1.0;
+            sum += value;
+            sum_squares += value * value;
+            variance = sum_squares - sum * sum / count;
+            let before = lower - 1;
+            if before != 0 {
+                for j in 2..=classes {
+                    if variances[l][j] >= variance + variances[before][j - 1] {
+                        lower_limits[l][j] = lower;
+                        variances[l][j] = variance + variances[before][j - 1];
+                    }
+                }
+            }
+        }
+        lower_limits[l][1] = 1;
+        variances[l][1] = variance;
+    }
+
+    let mut breaks = vec![0.0; classes + 1];
+    breaks[0] = sorted[0];
+    breaks[classes] = sorted[n - 1];
+    let mut end = n;
+    for j in (2..=classes).rev() {
+        let lower = lower_limits[end][j];
+        breaks[j - 1] = sorted[lower - 1];
+        end = lower - 1;
+    }
+    breaks
+}
+
+/// A `color_domain` for a scale type from class breaks. Continuous scales span the inner
+/// breaks, leaving the lowest and highest classes to saturate the ends of the palette, and
+/// diverging ones are centered on the median. Threshold scales use the inner breaks.
+pub fn suggest_domain(scale_type: i32, breaks: &[f64], median: f64) -> Vec<f64> {
+    if breaks.is_empty() {
+        return vec![];
+    }
+    let last = breaks.len() - 1;
+    let (low, high) = if breaks.len() > 3 {
+        (breaks[1], breaks[last - 1])
+    } else {
+        (breaks[0], breaks[last])
+    };
+    match scale_type {
+        scale_type::DIVERGING | scale_type::DIVERGING_SYM_LOG => vec![low, median, high],
+        scale_type::THRESHOLD => breaks[1..last].to_vec(),
+        _ => vec![low, high],
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn it_breaks_by_quantile_and_interval() {
+        let values = [1.0, 2.0, 3.0, 4.0, 5.0, 100.0];
+
+        assert_eq!(
+            breaks(Method::EqualInterval, &values, 3),
+            vec![1.0, 34.0, 67.0, 100.0]
+        );
+        assert_eq!(breaks(Method::Quantile, &values, 2), vec![1.0, 3.5, 100.0]);
+        assert!(breaks(Method::Quantile, &[], 5).is_empty());
+    }
+
+    #[test]
+    fn it_finds_natural_breaks() {
+        let values = [11.0, 1.0, 2.0, 1.0, 10.0, 2.0, 10.0, 11.0, 50.0];
+
+        assert_eq!(
+            breaks(Method::Jenks, &values, 3),
+            vec![1.0, 10.0, 50.0, 50.0]
+        );
+    }
+
+    #[test]
+    fn it_breaks_by_standard_deviation() {
+        let values = [2.0, 4.0, 4.0, 4.0, 5.0, 5.0, 7.0, 9.0];
+
+        assert_eq!(
+            breaks(Method::StandardDeviation, &values, 4),
+            vec![2.0, 3.0, 5.0, 7.0, 9.0]
+        );
+        assert_eq!(
+            breaks(Method::StandardDeviation, &values, 5),
+            vec![2.0, 2.0, 4.0, 6.0, 8.0, 9.0]
+        );
+        assert!(breaks(Method::Manual, &values, 4).is_empty());
+    }
+
+    #[test]
+    fn it_caps_jenks_classes_at_the_number_of_values() {
+        assert_eq!(breaks(Method::Jenks, &[3.0, 1.0], 5), vec![1.0, 3.0, 3.0]);
+    }
+
+    #[test]
+    fn it_suggests_domains_for_scale_types() {
+        let breaks = [0.0, 1.0, 2.0, 3.0, 10.0];
+
+        assert_eq!(
+            suggest_domain(scale_type::SEQUENTIAL, &breaks, 1.5),
+            vec![1.0, 3.0]
+        );
+        assert_eq!(
+            suggest_domain(scale_type::DIVERGING, &breaks, 1.5),
+            vec![1.0, 1.5, 3.0]
+        );
+        assert_eq!(
+            suggest_domain(scale_type::THRESHOLD, &breaks, 1.5),
+            vec![1.0, 2.0, 3.0]
+        );
+    }
+}
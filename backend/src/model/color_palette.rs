@@ -6,3 +6,8 @@ pub struct ColorPalette {
     pub id: i32,
     pub name: String,
 }
+
+/// A sequential palette, from light to dark blue
+pub const BLUES: i32 = 1;
+/// A diverging palette, from red to blue
+pub const RD_BU: i32 = 11;
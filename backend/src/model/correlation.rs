@@ -0,0 +1,333 @@
+use super::data::{Simple, SourceAndDate};
+use super::{scenario, statistic};
+use chrono::NaiveDate;
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashMap};
+
+/// The number of classes per dataset in a bivariate choropleth, for a 3x3 legend
+pub const BIVARIATE_CLASSES: usize = 3;
+
+/// A slice of a dataset to correlate
+#[derive(Deserialize, Serialize, Debug, PartialEq)]
+pub struct Slice {
+    pub dataset: i32,
+    pub source: i32,
+    #[serde(default = "scenario::observed")]
+    pub scenario: i32,
+    #[serde(default = "statistic::mean")]
+    pub statistic: i32,
+    pub start_date: NaiveDate,
+    pub end_date: NaiveDate,
+}
+
+impl Slice {
+    pub fn source_and_date(&self) -> SourceAndDate {
+        SourceAndDate {
+            source: self.source,
+            scenario: self.scenario,
+            statistic: self.statistic,
+            start_date: self.start_date,
+            end_date: self.end_date,
+        }
+    }
+}
+
+/// The values of a geo id present in every slice, in the order of the slices
+#[derive(Serialize, Debug, PartialEq)]
+pub struct Paired {
+    pub id: i64,
+    pub values: Vec<f64>,
+}
+
+/// A least squares fit of `y = slope * x + intercept`
+#[derive(Serialize, Debug, PartialEq)]
+pub struct Regression {
+    pub slope: f64,
+    pub intercept: f64,
+    pub r_squared: f64,
+}
+
+/// How two of the slices, by index, relate over the geo ids they share
+#[derive(Serialize, Debug, PartialEq)]
+pub struct Pair {
+    pub x: usize,
+    pub y: usize,
+    pub count: usize,
+    pub pearson: Option<f64>,
+    pub spearman: Option<f64>,
+    pub regression: Option<Regression>,
+}
+
+#[derive(Serialize, Debug, PartialEq)]
+pub struct Analysis {
+    /// The number of geo ids with a value in each slice
+    pub counts: Vec<usize>,
+    /// The number of geo ids with a value in every slice
+    pub overlap: usize,
+    pub values: Vec<Paired>,
+    pub pairs: Vec<Pair>,
+}
+
+/// The second slice of a bivariate choropleth, set on the map visualization
+#[derive(Deserialize, Serialize, Debug, PartialEq)]
+pub struct Bivariate {
+    pub dataset: i32,
+    pub slice: SourceAndDate,
+}
+
+/// A geo id's class in each dataset of a bivariate choropleth, from 0 (lowest) to
+/// `BIVARIATE_CLASSES - 1`
+#[derive(Deserialize, Serialize, Debug, PartialEq)]
+pub struct BivariateClass {
+    pub id: i64,
+    pub x: f64,
+    pub y: f64,
+    pub x_class: usize,
+    pub y_class: usize,
+}
+
+fn mean(values: &[f64]) -> f64 {
+    values.iter().sum::<f64>() / values.len() as f64
+}
+
+/// Undefined with fewer than 2 values or when either side doesn't vary
+pub fn pearson(x: &[f64], y: &[f64]) -> Option<f64> {
+    if x.len() < 2 || x.len() != y.len() {
+        return None;
+    }
+    let (mean_x, mean_y) = (mean(x), mean(y));
+    let mut covariance = 0.0;
+    let mut variance_x = 0.0;
+    let mut variance_y = 0.0;
+    for (x, y) in x.iter().zip(y) {
+        covariance += (x - mean_x) * (y - mean_y);
+        variance_x += (x - mean_x).powi(2);
+        variance_y += (y - mean_y).powi(2);
+    }
+    if variance_x == 0.0 || variance_y == 0.0 {
+        return None;
+    }
+    Some(covariance / (variance_x * variance_y).sqrt())
+}
+
+/// Ranks from 1, giving ties the average of the ranks they span
+fn ranks(values: &[f64]) -> Vec<f64> {
+    let mut order: Vec<usize> = (0..values.len()).collect();
+    order.sort_by(|&a, &b| values[a].total_cmp(&values[b]));
+    let mut ranks = vec![0.0; values.len()];
+    let mut start = 0;
+    while start < order.len() {
+        let mut end = start;
+        while end + 1 < order.len() && values[order[end + 1]] == values[order[start]] {
+            end += 1;
+        }
+        let rank = (start + end) as f64 / 2.0 + 1.0;
+        for &index in &order[start..=end] {
+            ranks[index] = rank;
+        }
+        start = end + 1;
+    }
+    ranks
+}
+
+pub fn spearman(x: &[f64], y: &[f64]) -> Option<f64> {
+    pearson(&ranks(x), &ranks(y))
+}
+
+pub fn regression(x: &[f64], y: &[f64]) -> Option<Regression> {
+    if x.len() < 2 || x.len() != y.len() {
+        return None;
+    }
+    let (mean_x, mean_y) = (mean(x), mean(y));
+    let covariance: f64 = x
+        .iter()
+        .zip(y)
+        .map(|(x, y)| (x - mean_x) * (y - mean_y))
+        .sum();
+    let variance_x: f64 = x.iter().map(|x| (x - mean_x).powi(2)).sum();
+    if variance_x == 0.0 {
+        return None;
+    }
+    let slope = covariance / variance_x;
+    Some(Regression {
+        slope,
+        intercept: mean_y - slope * mean_x,
+        r_squared: pearson(x, y).map_or(0.0, |r| r.powi(2)),
+    })
+}
+
+fn pair(x: usize, y: usize, slices: &[HashMap<i64, f64>]) -> Pair {
+    let (xs, ys): (Vec<f64>, Vec<f64>) = slices[x]
+        .iter()
+        .filter_map(|(id, &value)| Some((value, *slices[y].get(id)?)))
+        .unzip();
+    Pair {
+        x,
+        y,
+        count: xs.len(),
+        pearson: pearson(&xs, &ys),
+        spearman: spearman(&xs, &ys),
+        regression: regression(&xs, &ys),
+    }
+}
+
+/// Pairs up the slices' values by geo id and relates each pair of slices
+pub fn analyze(slices: Vec<Vec<Simple>>) -> Analysis {
+    let counts = slices.iter().map(|slice| slice.len()).collect();
+    let slices: Vec<HashMap<i64, f64>> = slices
+        .into_iter()
+        .map(|slice| slice.into_iter().map(|row| (row.id, row.value)).collect())
+        .collect();
+
+    let values: Vec<Paired> = match slices.first() {
+        None => vec![],
+        Some(first) => first
+            .keys()
+            .filter_map(|&id| {
+                let values = slices
+                    .iter()
+                    .map(|slice| slice.get(&id).copied())
+                    .collect::<Option<Vec<f64>>>()?;
+                Some((id, Paired { id, values }))
+            })
+            .collect::<BTreeMap<i64, Paired>>()
+            .into_values()
+            .collect(),
+    };
+
+    let mut pairs = Vec::new();
+    for x in 0..slices.len() {
+        for y in x + 1..slices.len() {
+            pairs.push(pair(x, y, &slices));
+        }
+    }
+
+    Analysis {
+        counts,
+        overlap: values.len(),
+        values,
+        pairs,
+    }
+}
+
+/// The quantile class of each value among all of them. Equal values share a class.
+fn quantile_classes(values: &[f64], classes: usize) -> Vec<usize> {
+    let mut sorted = values.to_vec();
+    sorted.sort_by(|a, b| a.total_cmp(b));
+    values
+        .iter()
+        .map(|value| {
+            let below = sorted.partition_point(|other| other < value);
+            (below * classes / sorted.len()).min(classes - 1)
+        })
+        .collect()
+}
+
+/// Classes the geo ids with values in both slices by the quantiles of each slice
+pub fn bivariate_classes(x: Vec<Simple>, y: Vec<Simple>) -> Vec<BivariateClass> {
+    let y: HashMap<i64, f64> = y.into_iter().map(|row| (row.id, row.value)).collect();
+    let mut paired: Vec<(i64, f64, f64)> = x
+        .into_iter()
+        .filter_map(|row| Some((row.id, row.value, *y.get(&row.id)?)))
+        .collect();
+    paired.sort_by_key(|&(id, _, _)| id);
+
+    let xs: Vec<f64> = paired.iter().map(|&(_, x, _)| x).collect();
+    let ys: Vec<f64> = paired.iter().map(|&(_, _, y)| y).collect();
+    let x_classes = quantile_classes(&xs, BIVARIATE_CLASSES);
+    let y_classes = quantile_classes(&ys, BIVARIATE_CLASSES);
+    paired
+        .into_iter()
+        .zip(x_classes.into_iter().zip(y_classes))
+        .map(|((id, x, y), (x_class, y_class))| BivariateClass {
+            id,
+            x,
+            y,
+            x_class,
+            y_class,
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn slice(values: &[(i64, f64)]) -> Vec<Simple> {
+        values
+            .iter()
+            .map(|&(id, value)| Simple { id, value })
+            .collect()
+    }
+
+    #[test]
+    fn it_correlates_linear_values() {
+        let x = [1.0, 2.0, 3.0, 4.0];
+        let y = [3.0, 5.0, 7.0, 9.0];
+
+        assert!((pearson(&x, &y).unwrap() - 1.0).abs() < 1e-9);
+        assert_eq!(
+            regression(&x, &y),
+            Some(Regression {
+                slope: 2.0,
+                intercept: 1.0,
+                r_squared: 1.0,
+            })
+        );
+        assert_eq!(pearson(&x, &[1.0, 1.0, 1.0, 1.0]), None);
+    }
+
+    #[test]
+    fn it_ranks_monotonic_values() {
+        let x = [1.0, 2.0, 3.0, 4.0];
+        let y = [1.0, 10.0, 100.0, 1000.0];
+
+        assert!((spearman(&x, &y).unwrap() - 1.0).abs() < 1e-9);
+        assert!(pearson(&x, &y).unwrap() < 1.0);
+        assert_eq!(ranks(&[5.0, 1.0, 5.0]), vec![2.5, 1.0, 2.5]);
+    }
+
+    #[test]
+    fn it_pairs_slices_by_geo_id() {
+        let analysis = analyze(vec![
+            slice(&[(1, 1.0), (2, 2.0), (3, 3.0)]),
+            slice(&[(2, 4.0), (3, 6.0), (4, 8.0)]),
+            slice(&[(2, 1.0), (3, 0.0)]),
+        ]);
+
+        assert_eq!(analysis.counts, vec![3, 3, 2]);
+        assert_eq!(analysis.overlap, 2);
+        assert_eq!(
+            analysis.values[0],
+            Paired {
+                id: 2,
+                values: vec![2.0, 4.0, 1.0]
+            }
+        );
+        assert_eq!(analysis.pairs.len(), 3);
+        assert_eq!((analysis.pairs[0].x, analysis.pairs[0].y), (0, 1));
+        assert_eq!(analysis.pairs[0].count, 2);
+        assert!((analysis.pairs[1].pearson.unwrap() + 1.0).abs() < 1e-9);
+    }
+
+    #[test]
+    fn it_classes_bivariate_values() {
+        let classes = bivariate_classes(
+            slice(&[(1, 1.0), (2, 2.0), (3, 3.0), (4, 4.0), (5, 5.0), (6, 6.0)]),
+            slice(&[
+                (1, 60.0),
+                (2, 50.0),
+                (3, 40.0),
+                (4, 30.0),
+                (5, 20.0),
+                (6, 10.0),
+            ]),
+        );
+
+        let by_id: Vec<(usize, usize)> = classes
+            .iter()
+            .map(|class| (class.x_class, class.y_class))
+            .collect();
+        assert_eq!(by_id, vec![(0, 2), (0, 2), (1, 1), (1, 1), (2, 0), (2, 0)]);
+    }
+}
@@ -0,0 +1,153 @@
+use super::data::Simple;
+use derive_more::Display;
+use serde::{Deserialize, Serialize};
+use sqlx::FromRow;
+use std::collections::BTreeMap;
+
+#[derive(FromRow, Deserialize, Serialize, Debug)]
+pub struct Crosswalk {
+    pub id: i32,
+    pub name: String,
+    pub from_geography_type: i32,
+    pub to_geography_type: i32,
+}
+
+/// How much of two overlapping geo ids is in their overlap, as a fraction of each
+#[derive(FromRow, Deserialize, Serialize, Debug, PartialEq)]
+pub struct Link {
+    pub from_id: i64,
+    pub to_id: i64,
+    pub from_area_share: Option<f64>,
+    pub to_area_share: Option<f64>,
+    pub from_population_share: Option<f64>,
+    pub to_population_share: Option<f64>,
+}
+
+/// What the overlap between geo ids is measured by
+#[derive(Deserialize, Serialize, Debug, Display, Clone, Copy, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum Weight {
+    #[display(fmt = "area")]
+    Area,
+    #[display(fmt = "population")]
+    Population,
+}
+
+/// Whether values are split between targets (extensive, like a population count) or
+/// averaged over them (intensive, like a temperature or a rate)
+#[derive(Deserialize, Serialize, Debug, Display, Clone, Copy, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum Kind {
+    #[display(fmt = "intensive")]
+    Intensive,
+    #[display(fmt = "extensive")]
+    Extensive,
+}
+
+impl Kind {
+    /// Extensive values are split by the share of the source geo id in each target, while
+    /// intensive values are averaged by the share of the target in each source geo id.
+    /// Projecting in reverse swaps which side of the crosswalk is the source.
+    pub fn uses_from_share(&self, reverse: bool) -> bool {
+        (*self == Kind::Extensive) != reverse
+    }
+}
+
+/// A source geo id's value, the target geo id it overlaps and the share of the overlap
+#[derive(FromRow, Debug)]
+pub struct Overlap {
+    pub target: i64,
+    pub value: f64,
+    pub share: Option<f64>,
+}
+
+/// Projects values onto target geo ids. Overlaps without a share are left out.
+pub fn project(kind: Kind, overlaps: Vec<Overlap>) -> Vec<Simple> {
+    let mut by_target: BTreeMap<i64, (f64, f64)> = BTreeMap::new();
+    for overlap in overlaps {
+        if let Some(share) = overlap.share {
+            let (total, total_share) = by_target.entry(overlap.target).or_insert((0.0, 0.0));
+            *total += overlap.value * share;
+            *total_share += share;
+        }
+    }
+    by_target
+        .into_iter()
+        .filter_map(|(id, (total, total_share))| match kind {
+            Kind::Extensive => Some(Simple { id, value: total }),
+            Kind::Intensive if total_share == 0.0 => None,
+            Kind::Intensive => Some(Simple {
+                id,
+                value: total / total_share,
+            }),
+        })
+        .collect()
+}
+
+/// How a dataset was projected, for its provenance
+pub fn method(weight: Weight, kind: Kind) -> String {
+    format!("{weight}_weighted_{kind}")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn overlaps() -> Vec<Overlap> {
+        vec![
+            Overlap {
+                target: 1,
+                value: 100.0,
+                share: Some(0.25),
+            },
+            Overlap {
+                target: 1,
+                value: 40.0,
+                share: Some(0.75),
+            },
+            Overlap {
+                target: 2,
+                value: 100.0,
+                share: Some(0.5),
+            },
+            Overlap {
+                target: 3,
+                value: 100.0,
+                share: None,
+            },
+        ]
+    }
+
+    fn values(kind: Kind) -> Vec<(i64, f64)> {
+        project(kind, overlaps())
+            .into_iter()
+            .map(|row| (row.id, row.value))
+            .collect()
+    }
+
+    #[test]
+    fn it_splits_extensive_values() {
+        assert_eq!(values(Kind::Extensive), vec![(1, 55.0), (2, 50.0)]);
+    }
+
+    #[test]
+    fn it_averages_intensive_values() {
+        assert_eq!(values(Kind::Intensive), vec![(1, 55.0), (2, 100.0)]);
+    }
+
+    #[test]
+    fn it_picks_the_share_for_the_direction() {
+        assert!(Kind::Extensive.uses_from_share(false));
+        assert!(!Kind::Intensive.uses_from_share(false));
+        assert!(!Kind::Extensive.uses_from_share(true));
+        assert!(Kind::Intensive.uses_from_share(true));
+    }
+
+    #[test]
+    fn it_describes_the_method() {
+        assert_eq!(
+            method(Weight::Population, Kind::Extensive),
+            "population_weighted_extensive"
+        );
+    }
+}
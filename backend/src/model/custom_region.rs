@@ -0,0 +1,142 @@
+use super::data::Simple;
+use super::stats::{percent_rank, quantile};
+use serde::{Deserialize, Serialize};
+use sqlx::FromRow;
+use std::collections::HashSet;
+
+/// A named set of geo ids, like a utility's service area
+#[derive(FromRow, Deserialize, Serialize, Debug)]
+pub struct CustomRegion {
+    pub id: i32,
+    pub name: String,
+    pub owner: String,
+    pub description: String,
+    pub geography_type: i32,
+    pub members: Vec<i64>,
+}
+
+#[derive(Deserialize, Serialize, Debug)]
+pub struct Creator {
+    pub name: String,
+    pub owner: String,
+    #[serde(default)]
+    pub description: String,
+    pub geography_type: i32,
+    pub members: Vec<i64>,
+}
+
+/// A distribution of values
+#[derive(Serialize, Debug, PartialEq)]
+pub struct Summary {
+    pub count: usize,
+    pub mean: f64,
+    pub median: f64,
+    pub min: f64,
+    pub max: f64,
+}
+
+impl Summary {
+    pub fn of(values: &[f64]) -> Option<Summary> {
+        if values.is_empty() {
+            return None;
+        }
+        let mut sorted = values.to_vec();
+        sorted.sort_by(|a, b| a.total_cmp(b));
+        let count = sorted.len();
+        Some(Summary {
+            count,
+            mean: sorted.iter().sum::<f64>() / count as f64,
+            median: quantile(&sorted, 0.5),
+            min: sorted[0],
+            max: sorted[count - 1],
+        })
+    }
+}
+
+/// How a region's values compare to all geo ids of its geography type
+#[derive(Serialize, Debug, PartialEq)]
+pub struct Comparison {
+    pub region: Option<Summary>,
+    pub national: Option<Summary>,
+    /// The percentile of the region's mean among all geo ids, from 0 to 1, as if it were one more
+    /// geo id
+    pub percentile: Option<f64>,
+}
+
+/// Compares the members of a region to the whole slice they are part of
+pub fn compare(slice: &[Simple], members: &HashSet<i64>, invert: bool) -> Comparison {
+    let national: Vec<f64> = slice.iter().map(|row| row.value).collect();
+    let region: Vec<f64> = slice
+        .iter()
+        .filter(|row| members.contains(&row.id))
+        .map(|row| row.value)
+        .collect();
+    let region = Summary::of(&region);
+    let percentile = region
+        .as_ref()
+        .map(|region| percent_rank(region.mean, &national, invert));
+    Comparison {
+        region,
+        national: Summary::of(&national),
+        percentile,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn slice() -> Vec<Simple> {
+        [(1, 1.0), (2, 2.0), (3, 3.0), (4, 4.0), (5, 10.0)]
+            .iter()
+            .map(|&(id, value)| Simple { id, value })
+            .collect()
+    }
+
+    #[test]
+    fn it_summarizes_values() {
+        assert_eq!(
+            Summary::of(&[4.0, 1.0, 3.0, 2.0]),
+            Some(Summary {
+                count: 4,
+                mean: 2.5,
+                median: 2.5,
+                min: 1.0,
+                max: 4.0,
+            })
+        );
+        assert_eq!(Summary::of(&[]), None);
+    }
+
+    #[test]
+    fn it_compares_a_region_to_the_nation() {
+        let members: HashSet<i64> = [3, 4, 6].iter().copied().collect();
+        let comparison = compare(&slice(), &members, false);
+
+        assert_eq!(
+            comparison.region.as_ref().map(|region| region.mean),
+            Some(3.5)
+        );
+        assert_eq!(
+            comparison.national.as_ref().map(|national| national.count),
+            Some(5)
+        );
+        assert_eq!(comparison.percentile, Some(4.0 / 6.0));
+    }
+
+    #[test]
+    fn it_inverts_the_percentile() {
+        let members: HashSet<i64> = [3, 4].iter().copied().collect();
+        let comparison = compare(&slice(), &members, true);
+
+        assert_eq!(comparison.percentile, Some(2.0 / 6.0));
+    }
+
+    #[test]
+    fn it_has_no_percentile_without_members() {
+        let comparison = compare(&slice(), &HashSet::new(), false);
+
+        assert_eq!(comparison.region, None);
+        assert_eq!(comparison.percentile, None);
+    }
+}
@@ -3,7 +3,9 @@ use derivative::Derivative;
 use serde::{Deserialize, Serialize};
 use sqlx::FromRow;
 
-#[derive(Debug, FromRow, Serialize)]
+use super::{scenario, statistic};
+
+#[derive(Debug, FromRow, Deserialize, Serialize)]
 pub struct Percentile {
     pub dataset: i32,
     pub dataset_name: String,
@@ -17,6 +19,20 @@ pub struct Percentile {
     pub decimals: i16,
 }
 
+/// A map visualization's default slice in a category, as shown on a report card
+#[derive(FromRow, Debug)]
+pub struct ReportCardEntry {
+    pub dataset: i32,
+    pub dataset_name: String,
+    pub source: i32,
+    pub start_date: NaiveDate,
+    pub end_date: NaiveDate,
+    pub invert_normalized: bool,
+    pub units: String,
+    pub formatter_type: i32,
+    pub decimals: i16,
+}
+
 #[derive(FromRow, Deserialize, Serialize)]
 pub struct Data {
     pub id: i64,
@@ -32,6 +48,8 @@ pub struct Creator {
     pub id: i64,
     pub geography_type: i32,
     pub source: i32,
+    pub scenario: i32,
+    pub statistic: i32,
     pub dataset: i32,
     pub start_date: NaiveDate,
     pub end_date: NaiveDate,
@@ -40,7 +58,14 @@ pub struct Creator {
 }
 
 impl Creator {
-    pub fn new(data: &Parsed, dataset: i32, source: i32, geography_type: i32) -> Creator {
+    pub fn new(
+        data: &Parsed,
+        dataset: i32,
+        source: i32,
+        geography_type: i32,
+        scenario: i32,
+        statistic: i32,
+    ) -> Creator {
         Creator {
             id: data.id,
             start_date: data.start_date,
@@ -49,6 +74,8 @@ impl Creator {
             dataset,
             source,
             geography_type,
+            scenario,
+            statistic,
         }
     }
 }
@@ -64,7 +91,37 @@ pub struct Parsed {
     pub value: f64,
 }
 
-#[derive(FromRow, Deserialize, Serialize)]
+/// A row of a dataset in long format, as used by bulk exports
+#[derive(FromRow, Deserialize, Serialize, Debug, Clone)]
+pub struct Long {
+    pub dataset: i32,
+    pub source: i32,
+    pub scenario: i32,
+    pub statistic: i32,
+    pub start_date: NaiveDate,
+    pub end_date: NaiveDate,
+    pub geography_type: i32,
+    pub id: i64,
+    pub value: f64,
+}
+
+/// A geo id's value for one slice of a dataset, optionally with the national median and the
+/// geo id's percentile within that slice for context
+#[derive(FromRow, Deserialize, Serialize, Debug)]
+pub struct TimeseriesPoint {
+    pub dataset: i32,
+    pub source: i32,
+    pub source_name: String,
+    pub scenario: i32,
+    pub statistic: i32,
+    pub start_date: NaiveDate,
+    pub end_date: NaiveDate,
+    pub value: f64,
+    pub national_median: Option<f64>,
+    pub percentile: Option<f64>,
+}
+
+#[derive(FromRow, Deserialize, Serialize, Debug, PartialEq)]
 pub struct Simple {
     pub id: i64,
     pub value: f64,
@@ -78,9 +135,13 @@ pub struct DeleteInfo {
     pub end_date: NaiveDate,
 }
 
-#[derive(FromRow, Deserialize, Serialize)]
+#[derive(FromRow, Deserialize, Serialize, Debug, PartialEq, Clone, Copy)]
 pub struct SourceAndDate {
     pub source: i32,
+    #[serde(default = "scenario::observed")]
+    pub scenario: i32,
+    #[serde(default = "statistic::mean")]
+    pub statistic: i32,
     pub start_date: NaiveDate,
     pub end_date: NaiveDate,
 }
@@ -0,0 +1,428 @@
+use super::map_visualization_collection::Id;
+use derive_more::Display;
+use serde::{Deserialize, Serialize};
+use sqlx::FromRow;
+
+/// How to delete: into the trash by default, or for good with `permanent`. With `dry_run` a
+/// delete only lists what removing for good would remove, without removing anything. A data
+/// source with data is only removed for good `with_data`, which removes its data in every
+/// dataset.
+#[derive(Deserialize)]
+pub struct Options {
+    #[serde(default)]
+    pub permanent: bool,
+    #[serde(default)]
+    pub dry_run: bool,
+    #[serde(default)]
+    pub with_data: bool,
+}
+
+/// A map visualization's settings that point at datasets and data sources
+#[derive(FromRow, Debug, Clone, PartialEq)]
+pub struct Dependent {
+    pub id: i32,
+    pub dataset: i32,
+    pub default_source: Option<i32>,
+    pub delta_from_source: Option<i32>,
+    pub delta_to_source: Option<i32>,
+    pub bivariate_dataset: Option<i32>,
+    pub bivariate_source: Option<i32>,
+}
+
+/// The number of rows of data of a dataset from a source
+#[derive(FromRow, Serialize, Debug, Clone, PartialEq)]
+pub struct DataRows {
+    pub dataset: i32,
+    pub source: i32,
+    pub rows: i64,
+}
+
+/// A map visualization setting cleared because it points at what's deleted
+#[derive(Serialize, Debug, Display, Clone, Copy, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum Field {
+    #[display(fmt = "default_source")]
+    DefaultSource,
+    #[display(fmt = "delta_from_source")]
+    DeltaFromSource,
+    #[display(fmt = "delta_to_source")]
+    DeltaToSource,
+    #[display(fmt = "bivariate_dataset")]
+    BivariateDataset,
+    #[display(fmt = "bivariate_source")]
+    BivariateSource,
+}
+
+/// Settings that are set and cleared together, so clearing one field of a delta or bivariate
+/// map visualization clears all of its delta or bivariate settings
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Group {
+    /// `default_source`
+    DefaultSource,
+    /// `delta_operation` and both of its slices
+    Delta,
+    /// `bivariate_dataset` and its slice
+    Bivariate,
+}
+
+impl Field {
+    pub fn group(self) -> Group {
+        match self {
+            Field::DefaultSource => Group::DefaultSource,
+            Field::DeltaFromSource | Field::DeltaToSource => Group::Delta,
+            Field::BivariateDataset | Field::BivariateSource => Group::Bivariate,
+        }
+    }
+}
+
+#[derive(Serialize, Debug, PartialEq)]
+pub struct Reference {
+    pub map_visualization: i32,
+    pub field: Field,
+}
+
+/// Everything deleting a dataset, data source, map visualization or category removes or
+/// changes along with it
+#[derive(Serialize, Debug, Default, PartialEq)]
+pub struct Impact {
+    pub map_visualizations: Vec<i32>,
+    /// The places of map visualizations in categories
+    pub collections: Vec<Id>,
+    pub data: Vec<DataRows>,
+    /// Settings of the map visualizations that are kept
+    pub references: Vec<Reference>,
+}
+
+/// Why removing something for good was refused
+#[derive(Serialize, Debug, PartialEq)]
+#[serde(tag = "name", content = "info")]
+pub enum Conflict {
+    /// A data source still has data, by dataset, which only `with_data` removes with it
+    HasData(Vec<DataRows>),
+}
+
+/// The settings of map visualizations that point at something
+fn references(
+    dependents: &[Dependent],
+    fields: &[(Field, fn(&Dependent) -> Option<i32>)],
+    id: i32,
+) -> Vec<Reference> {
+    dependents
+        .iter()
+        .flat_map(|dependent| {
+            fields
+                .iter()
+                .filter(move |(_, value)| value(dependent) == Some(id))
+                .map(move |&(field, _)| Reference {
+                    map_visualization: dependent.id,
+                    field,
+                })
+        })
+        .collect()
+}
+
+impl Impact {
+    /// A dataset takes its map visualizations and data with it, and is cleared from other map
+    /// visualizations' bivariate settings
+    pub fn of_dataset(
+        id: i32,
+        dependents: &[Dependent],
+        collections: &[Id],
+        data: &[DataRows],
+    ) -> Impact {
+        let map_visualizations: Vec<i32> = dependents
+            .iter()
+            .filter(|dependent| dependent.dataset == id)
+            .map(|dependent| dependent.id)
+            .collect();
+        let kept: Vec<Dependent> = dependents
+            .iter()
+            .filter(|dependent| dependent.dataset != id)
+            .cloned()
+            .collect();
+        Impact {
+            collections: collections
+                .iter()
+                .filter(|collection| map_visualizations.contains(&collection.map_visualization))
+                .cloned()
+                .collect(),
+            map_visualizations,
+            data: data
+                .iter()
+                .filter(|rows| rows.dataset == id)
+                .cloned()
+                .collect(),
+            references: references(
+                &kept,
+                &[(Field::BivariateDataset, |dependent| {
+                    dependent.bivariate_dataset
+                })],
+                id,
+            ),
+        }
+    }
+
+    /// A data source is cleared from map visualizations' settings. It takes its data in every
+    /// dataset with it, but only `with_data`, since that may be all the data of datasets that
+    /// are kept.
+    pub fn of_data_source(
+        id: i32,
+        dependents: &[Dependent],
+        data: &[DataRows],
+        with_data: bool,
+    ) -> Result<Impact, Conflict> {
+        let data: Vec<DataRows> = data
+            .iter()
+            .filter(|rows| rows.source == id)
+            .cloned()
+            .collect();
+        if !with_data && !data.is_empty() {
+            return Err(Conflict::HasData(data));
+        }
+        Ok(Impact {
+            data,
+            references: references(
+                dependents,
+                &[
+                    (Field::DefaultSource, |dependent| dependent.default_source),
+                    (Field::DeltaFromSource, |dependent| {
+                        dependent.delta_from_source
+                    }),
+                    (Field::DeltaToSource, |dependent| dependent.delta_to_source),
+                    (Field::BivariateSource, |dependent| {
+                        dependent.bivariate_source
+                    }),
+                ],
+                id,
+            ),
+            ..Impact::default()
+        })
+    }
+
+    /// A map visualization is taken out of its categories
+    pub fn of_map_visualization(id: i32, collections: &[Id]) -> Impact {
+        Impact {
+            map_visualizations: vec![id],
+            collections: collections
+                .iter()
+                .filter(|collection| collection.map_visualization == id)
+                .cloned()
+                .collect(),
+            ..Impact::default()
+        }
+    }
+
+    /// A category takes the places of its map visualizations with it, but not the map
+    /// visualizations
+    pub fn of_data_category(id: i32, collections: &[Id]) -> Impact {
+        Impact {
+            collections: collections
+                .iter()
+                .filter(|collection| collection.category == id)
+                .cloned()
+                .collect(),
+            ..Impact::default()
+        }
+    }
+
+    /// The ids of the map visualizations with a setting cleared
+    pub fn referencing(&self, field: Field) -> Vec<i32> {
+        self.references
+            .iter()
+            .filter(|reference| reference.field == field)
+            .map(|reference| reference.map_visualization)
+            .collect()
+    }
+
+    /// The ids of the map visualizations with a group of settings cleared, each once
+    pub fn clearing(&self, group: Group) -> Vec<i32> {
+        let mut ids: Vec<i32> = self
+            .references
+            .iter()
+            .filter(|reference| reference.field.group() == group)
+            .map(|reference| reference.map_visualization)
+            .collect();
+        ids.sort_unstable();
+        ids.dedup();
+        ids
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn dependent(id: i32, dataset: i32) -> Dependent {
+        Dependent {
+            id,
+            dataset,
+            default_source: None,
+            delta_from_source: None,
+            delta_to_source: None,
+            bivariate_dataset: None,
+            bivariate_source: None,
+        }
+    }
+
+    fn collection(category: i32, map_visualization: i32) -> Id {
+        Id {
+            category,
+            map_visualization,
+        }
+    }
+
+    fn data() -> Vec<DataRows> {
+        vec![
+            DataRows {
+                dataset: 1,
+                source: 1,
+                rows: 100,
+            },
+            DataRows {
+                dataset: 1,
+                source: 2,
+                rows: 50,
+            },
+            DataRows {
+                dataset: 2,
+                source: 2,
+                rows: 30,
+            },
+        ]
+    }
+
+    #[test]
+    fn deleting_a_dataset_deletes_its_map_visualizations_collections_and_data() {
+        let dependents = [
+            dependent(10, 1),
+            dependent(11, 1),
+            Dependent {
+                bivariate_dataset: Some(1),
+                ..dependent(12, 2)
+            },
+        ];
+        let collections = [collection(1, 10), collection(2, 10), collection(1, 12)];
+
+        let impact = Impact::of_dataset(1, &dependents, &collections, &data());
+
+        assert_eq!(impact.map_visualizations, vec![10, 11]);
+        assert_eq!(
+            impact.collections,
+            vec![collection(1, 10), collection(2, 10)]
+        );
+        assert_eq!(impact.data, data()[..2].to_vec());
+        assert_eq!(
+            impact.references,
+            vec![Reference {
+                map_visualization: 12,
+                field: Field::BivariateDataset,
+            }]
+        );
+    }
+
+    #[test]
+    fn deleting_a_data_source_with_data_needs_with_data() {
+        let dependents = [Dependent {
+            default_source: Some(2),
+            ..dependent(10, 1)
+        }];
+
+        assert_eq!(
+            Impact::of_data_source(2, &dependents, &data(), false),
+            Err(Conflict::HasData(vec![
+                data()[1].clone(),
+                data()[2].clone()
+            ]))
+        );
+        let impact = Impact::of_data_source(3, &dependents, &data(), false).unwrap();
+        assert!(impact.data.is_empty());
+    }
+
+    #[test]
+    fn it_clears_whole_delta_and_bivariate_settings() {
+        let dependents = [
+            Dependent {
+                delta_from_source: Some(2),
+                delta_to_source: Some(2),
+                ..dependent(10, 1)
+            },
+            Dependent {
+                delta_to_source: Some(2),
+                bivariate_source: Some(1),
+                ..dependent(11, 1)
+            },
+            Dependent {
+                default_source: Some(2),
+                bivariate_source: Some(2),
+                ..dependent(12, 2)
+            },
+        ];
+
+        let impact = Impact::of_data_source(2, &dependents, &data(), true).unwrap();
+
+        assert_eq!(impact.clearing(Group::DefaultSource), vec![12]);
+        assert_eq!(impact.clearing(Group::Delta), vec![10, 11]);
+        assert_eq!(impact.clearing(Group::Bivariate), vec![12]);
+
+        let dependents = [Dependent {
+            bivariate_dataset: Some(1),
+            ..dependent(10, 2)
+        }];
+        let impact = Impact::of_dataset(1, &dependents, &[], &data());
+        assert_eq!(impact.clearing(Group::Bivariate), vec![10]);
+        assert!(impact.clearing(Group::Delta).is_empty());
+    }
+
+    #[test]
+    fn deleting_a_data_source_deletes_its_data_in_every_dataset_and_clears_references() {
+        let dependents = [
+            Dependent {
+                default_source: Some(2),
+                delta_to_source: Some(2),
+                ..dependent(10, 1)
+            },
+            Dependent {
+                default_source: Some(1),
+                bivariate_source: Some(2),
+                ..dependent(11, 2)
+            },
+        ];
+
+        let impact = Impact::of_data_source(2, &dependents, &data(), true).unwrap();
+
+        assert!(impact.map_visualizations.is_empty());
+        assert!(impact.collections.is_empty());
+        assert_eq!(impact.data, vec![data()[1].clone(), data()[2].clone()]);
+        assert_eq!(impact.referencing(Field::DefaultSource), vec![10]);
+        assert_eq!(impact.referencing(Field::DeltaToSource), vec![10]);
+        assert_eq!(impact.referencing(Field::BivariateSource), vec![11]);
+        assert!(impact.referencing(Field::DeltaFromSource).is_empty());
+    }
+
+    #[test]
+    fn deleting_a_map_visualization_takes_it_out_of_its_categories() {
+        let collections = [collection(1, 10), collection(2, 10), collection(1, 11)];
+
+        assert_eq!(
+            Impact::of_map_visualization(10, &collections),
+            Impact {
+                map_visualizations: vec![10],
+                collections: vec![collection(1, 10), collection(2, 10)],
+                ..Impact::default()
+            }
+        );
+    }
+
+    #[test]
+    fn deleting_a_category_keeps_its_map_visualizations() {
+        let collections = [collection(1, 10), collection(2, 10), collection(1, 11)];
+
+        assert_eq!(
+            Impact::of_data_category(1, &collections),
+            Impact {
+                collections: vec![collection(1, 10), collection(1, 11)],
+                ..Impact::default()
+            }
+        );
+    }
+}
@@ -0,0 +1,133 @@
+use super::data::{Simple, SourceAndDate};
+use super::statistic;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+
+/// How a delta map visualization compares its two slices, matching the `delta_operation` table
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Operation {
+    Difference,
+    Ratio,
+    PercentChange,
+}
+
+impl Operation {
+    pub fn from_id(id: i32) -> Option<Operation> {
+        match id {
+            1 => Some(Operation::Difference),
+            2 => Some(Operation::Ratio),
+            3 => Some(Operation::PercentChange),
+            _ => None,
+        }
+    }
+
+    /// The change from `from` to `to`, or None if it isn't defined because `from` is 0
+    pub fn apply(&self, from: f64, to: f64) -> Option<f64> {
+        match self {
+            Operation::Difference => Some(to - from),
+            Operation::Ratio if from == 0.0 => None,
+            Operation::Ratio => Some(to / from),
+            Operation::PercentChange if from == 0.0 => None,
+            Operation::PercentChange => Some((to - from) / from.abs() * 100.0),
+        }
+    }
+}
+
+/// The two slices of a dataset a delta map visualization compares
+#[derive(Deserialize, Serialize, Debug, PartialEq, Clone, Copy)]
+pub struct Delta {
+    pub operation: i32,
+    pub from: SourceAndDate,
+    pub to: SourceAndDate,
+}
+
+/// Which statistic of its slices a delta map visualization compares, from the `statistic` query
+/// parameter
+#[derive(Deserialize, Debug)]
+pub struct Info {
+    #[serde(default = "statistic::mean")]
+    pub statistic: i32,
+}
+
+impl Delta {
+    /// Compares the same statistic of both slices, like a confidence bound rather than the mean
+    pub fn with_statistic(self, statistic: i32) -> Delta {
+        Delta {
+            from: SourceAndDate {
+                statistic,
+                ..self.from
+            },
+            to: SourceAndDate {
+                statistic,
+                ..self.to
+            },
+            ..self
+        }
+    }
+}
+
+/// The per geo id change between two slices. Geo ids missing from either slice are left out.
+pub fn compute(operation: Operation, from: Vec<Simple>, to: Vec<Simple>) -> Vec<Simple> {
+    let from: HashMap<i64, f64> = from.into_iter().map(|row| (row.id, row.value)).collect();
+    to.into_iter()
+        .filter_map(|row| {
+            let value = operation.apply(*from.get(&row.id)?, row.value)?;
+            Some(Simple { id: row.id, value })
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::NaiveDate;
+
+    fn rows(rows: &[(i64, f64)]) -> Vec<Simple> {
+        rows.iter()
+            .map(|&(id, value)| Simple { id, value })
+            .collect()
+    }
+
+    #[test]
+    fn it_computes_each_operation() {
+        assert_eq!(Operation::Difference.apply(4.0, 5.0), Some(1.0));
+        assert_eq!(Operation::Ratio.apply(4.0, 5.0), Some(1.25));
+        assert_eq!(Operation::PercentChange.apply(4.0, 5.0), Some(25.0));
+        assert_eq!(Operation::PercentChange.apply(-4.0, -5.0), Some(-25.0));
+    }
+
+    #[test]
+    fn it_skips_undefined_changes() {
+        assert_eq!(Operation::Difference.apply(0.0, 5.0), Some(5.0));
+        assert_eq!(Operation::Ratio.apply(0.0, 5.0), None);
+        assert_eq!(Operation::PercentChange.apply(0.0, 5.0), None);
+    }
+
+    #[test]
+    fn it_compares_a_statistic_of_both_slices() {
+        let slice = |source| SourceAndDate {
+            source,
+            scenario: 1,
+            statistic: 1,
+            start_date: NaiveDate::from_ymd_opt(2020, 1, 1).unwrap(),
+            end_date: NaiveDate::from_ymd_opt(2020, 12, 31).unwrap(),
+        };
+        let delta = Delta {
+            operation: 1,
+            from: slice(1),
+            to: slice(2),
+        }
+        .with_statistic(3);
+
+        assert_eq!((delta.from.statistic, delta.to.statistic), (3, 3));
+        assert_eq!((delta.from.source, delta.to.source), (1, 2));
+    }
+
+    #[test]
+    fn it_joins_slices_by_geo_id() {
+        let from = rows(&[(1001, 2.0), (1003, 0.0), (1005, 1.0)]);
+        let to = rows(&[(1001, 3.0), (1003, 1.0), (1007, 1.0)]);
+
+        assert_eq!(compute(Operation::Ratio, from, to), rows(&[(1001, 1.5)]));
+    }
+}
@@ -0,0 +1,28 @@
+use chrono::{DateTime, Utc};
+use serde::Serialize;
+use sqlx::FromRow;
+
+/// How a dataset was derived from another one, so users can tell where its values came from
+#[derive(FromRow, Serialize, Debug)]
+pub struct DerivedDataset {
+    pub dataset: i32,
+    pub derived_from: Option<i32>,
+    /// What was done to the original dataset, like "aggregation"
+    pub operation: String,
+    /// How it was done, like "weighted_mean"
+    pub method: String,
+    pub weight_dataset: Option<i32>,
+    /// The crosswalk a projected dataset was projected with
+    pub crosswalk: Option<i32>,
+    pub created_at: DateTime<Utc>,
+}
+
+#[derive(Serialize, Debug)]
+pub struct Creator {
+    pub dataset: i32,
+    pub derived_from: i32,
+    pub operation: String,
+    pub method: String,
+    pub weight_dataset: Option<i32>,
+    pub crosswalk: Option<i32>,
+}
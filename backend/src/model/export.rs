@@ -0,0 +1,34 @@
+use chrono::NaiveDate;
+use serde::Serialize;
+use sqlx::FromRow;
+
+/// A geo id's boundary and data value, with the geometry as GeoJSON
+#[derive(FromRow, Debug)]
+pub struct Feature {
+    pub id: i64,
+    pub name: String,
+    pub value: f64,
+    pub geometry: String,
+}
+
+/// A geo id's boundary and data value, with the geometry as well-known binary
+#[derive(FromRow, Debug)]
+pub struct WkbFeature {
+    pub id: i64,
+    pub name: String,
+    pub value: f64,
+    pub geometry: Vec<u8>,
+}
+
+/// Describes the dataset slice that was exported
+#[derive(Serialize, Debug, Clone)]
+pub struct Metadata {
+    pub dataset: String,
+    pub short_name: String,
+    pub description: String,
+    pub units: String,
+    pub source: String,
+    pub source_link: String,
+    pub start_date: NaiveDate,
+    pub end_date: NaiveDate,
+}
@@ -0,0 +1,30 @@
+pub const MONEY: i32 = 1;
+pub const NEAREST_SI_UNIT: i32 = 2;
+pub const DEFAULT: i32 = 3;
+pub const PERCENT: i32 = 4;
+
+/// The formatter for values in some units, like `percent` for "% of households"
+pub fn for_units(units: &str) -> i32 {
+    let units = units.to_lowercase();
+    if units.contains('%') || units.contains("percent") {
+        PERCENT
+    } else if units.contains('$') || units.contains("dollar") || units.contains("usd") {
+        MONEY
+    } else {
+        DEFAULT
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn it_picks_formatters_from_units() {
+        assert_eq!(for_units("% of households"), PERCENT);
+        assert_eq!(for_units("Percent"), PERCENT);
+        assert_eq!(for_units("2020 US$"), MONEY);
+        assert_eq!(for_units("dollars per capita"), MONEY);
+        assert_eq!(for_units("people"), DEFAULT);
+    }
+}
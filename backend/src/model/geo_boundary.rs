@@ -0,0 +1,85 @@
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use sqlx::FromRow;
+use std::collections::HashMap;
+
+/// A boundary of a geo id, with its geometry as GeoJSON in WGS 84 (EPSG:4326)
+#[derive(FromRow, Serialize, Debug, PartialEq)]
+pub struct Boundary {
+    pub id: i64,
+    pub geometry: String,
+}
+
+#[derive(Deserialize, Serialize, Debug, PartialEq)]
+pub struct UploadMetadata {
+    pub geography_type: i32,
+    /// The feature property holding the geo id. Uses the feature's `id` if not set.
+    pub id_property: Option<String>,
+}
+
+#[derive(Deserialize)]
+pub struct FeatureCollection {
+    pub features: Vec<Feature>,
+}
+
+#[derive(Deserialize)]
+pub struct Feature {
+    pub id: Option<Value>,
+    pub properties: Option<HashMap<String, Value>>,
+    pub geometry: Value,
+}
+
+impl Feature {
+    /// The geo id of the feature, from either a number or a numeric string like "01001"
+    pub fn geo_id(&self, id_property: &Option<String>) -> Option<i64> {
+        let id = match id_property {
+            None => self.id.as_ref(),
+            Some(property) => self.properties.as_ref()?.get(property),
+        }?;
+        match id {
+            Value::Number(number) => number.as_i64(),
+            Value::String(string) => string.parse::<i64>().ok(),
+            _ => None,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn it_reads_geo_ids_from_properties_or_id() {
+        let collection: FeatureCollection = serde_json::from_str(
+            r#"{
+                "type": "FeatureCollection",
+                "features": [
+                    {
+                        "type": "Feature",
+                        "id": 1001,
+                        "properties": { "GEOID": "01003" },
+                        "geometry": { "type": "Polygon", "coordinates": [] }
+                    },
+                    {
+                        "type": "Feature",
+                        "id": "county",
+                        "properties": null,
+                        "geometry": { "type": "Polygon", "coordinates": [] }
+                    }
+                ]
+            }"#,
+        )
+        .unwrap();
+
+        assert_eq!(collection.features[0].geo_id(&None), Some(1001));
+        assert_eq!(
+            collection.features[0].geo_id(&Some("GEOID".to_string())),
+            Some(1003)
+        );
+        assert_eq!(collection.features[1].geo_id(&None), None);
+        assert_eq!(
+            collection.features[1].geo_id(&Some("GEOID".to_string())),
+            None
+        );
+    }
+}
@@ -2,7 +2,7 @@ use derive_more::Display;
 use serde::{Deserialize, Serialize};
 use sqlx::FromRow;
 
-#[derive(Serialize)]
+#[derive(Deserialize, Serialize)]
 pub struct Named {
     pub id: i64,
     pub name: String,
@@ -0,0 +1,259 @@
+use super::data::{Simple, SourceAndDate};
+use chrono::NaiveDate;
+use serde::{Deserialize, Serialize};
+use sqlx::FromRow;
+use std::collections::{HashMap, HashSet};
+
+/// A coordinate in WGS 84 (EPSG:4326)
+#[derive(Deserialize, Serialize, Debug, PartialEq, Clone, Copy)]
+pub struct Point {
+    #[serde(alias = "lat")]
+    pub latitude: f64,
+    #[serde(alias = "lon", alias = "lng")]
+    pub longitude: f64,
+}
+
+impl Point {
+    pub fn is_valid(&self) -> bool {
+        (-90.0..=90.0).contains(&self.latitude) && (-180.0..=180.0).contains(&self.longitude)
+    }
+}
+
+/// A geo id whose boundary contains a point, by the point's index
+#[derive(FromRow, Debug, PartialEq)]
+pub struct Containing {
+    pub point: i32,
+    pub geography_type: i32,
+    pub id: i64,
+    pub name: String,
+}
+
+/// A geo id's value and its percent rank among all geo ids in the same slice
+#[derive(FromRow, Debug, PartialEq)]
+pub struct Ranked {
+    pub id: i64,
+    pub value: f64,
+    pub percent_rank: f64,
+}
+
+/// Ranks values like `percent_rank()` does for a slice in the database, for the geo ids in `ids`.
+/// Delta map visualizations rank their changes this way, since those aren't stored.
+pub fn rank(values: &[Simple], ids: &HashSet<i64>, invert: bool) -> HashMap<i64, Ranked> {
+    let ranked = |value: f64| if invert { -value } else { value };
+    let others = values.len().saturating_sub(1).max(1) as f64;
+    values
+        .iter()
+        .filter(|row| ids.contains(&row.id))
+        .map(|row| {
+            let below = values
+                .iter()
+                .filter(|other| ranked(other.value) < ranked(row.value))
+                .count();
+            let ranked = Ranked {
+                id: row.id,
+                value: row.value,
+                percent_rank: below as f64 / others,
+            };
+            (row.id, ranked)
+        })
+        .collect()
+}
+
+/// A map visualization's default slice, ranked for the geo ids containing the points. For a delta
+/// map visualization, the slice is the one it changes to and the ranks are of the change.
+pub struct Values {
+    pub map_visualization: i32,
+    pub dataset: i32,
+    pub geography_type: i32,
+    pub slice: Option<SourceAndDate>,
+    pub ranks: HashMap<i64, Ranked>,
+}
+
+/// A point, a geo id containing it, and a map visualization's value there. Points outside every
+/// boundary get a row without a geo id, and geo ids without selected map visualizations get a
+/// row without a value.
+#[derive(Deserialize, Serialize, Debug, PartialEq)]
+pub struct Row {
+    pub point: usize,
+    pub latitude: f64,
+    pub longitude: f64,
+    pub geography_type: Option<i32>,
+    pub id: Option<i64>,
+    pub name: Option<String>,
+    pub map_visualization: Option<i32>,
+    pub dataset: Option<i32>,
+    pub source: Option<i32>,
+    pub start_date: Option<NaiveDate>,
+    pub end_date: Option<NaiveDate>,
+    pub value: Option<f64>,
+    pub percent_rank: Option<f64>,
+}
+
+impl Row {
+    fn new(index: usize, point: &Point, containing: Option<&Containing>) -> Row {
+        Row {
+            point: index,
+            latitude: point.latitude,
+            longitude: point.longitude,
+            geography_type: containing.map(|containing| containing.geography_type),
+            id: containing.map(|containing| containing.id),
+            name: containing.map(|containing| containing.name.clone()),
+            map_visualization: None,
+            dataset: None,
+            source: None,
+            start_date: None,
+            end_date: None,
+            value: None,
+            percent_rank: None,
+        }
+    }
+}
+
+pub fn rows(points: &[Point], containing: &[Containing], values: &[Values]) -> Vec<Row> {
+    let mut by_point: HashMap<usize, Vec<&Containing>> = HashMap::new();
+    for geo_id in containing {
+        by_point
+            .entry(geo_id.point as usize)
+            .or_default()
+            .push(geo_id);
+    }
+
+    let mut rows = Vec::new();
+    for (index, point) in points.iter().enumerate() {
+        let geo_ids = match by_point.get(&index) {
+            None => {
+                rows.push(Row::new(index, point, None));
+                continue;
+            }
+            Some(geo_ids) => geo_ids,
+        };
+        for geo_id in geo_ids {
+            let selected: Vec<&Values> = values
+                .iter()
+                .filter(|values| values.geography_type == geo_id.geography_type)
+                .collect();
+            if selected.is_empty() {
+                rows.push(Row::new(index, point, Some(geo_id)));
+            }
+            for values in selected {
+                let ranked = values.ranks.get(&geo_id.id);
+                rows.push(Row {
+                    map_visualization: Some(values.map_visualization),
+                    dataset: Some(values.dataset),
+                    source: values.slice.as_ref().map(|slice| slice.source),
+                    start_date: values.slice.as_ref().map(|slice| slice.start_date),
+                    end_date: values.slice.as_ref().map(|slice| slice.end_date),
+                    value: ranked.map(|ranked| ranked.value),
+                    percent_rank: ranked.map(|ranked| ranked.percent_rank),
+                    ..Row::new(index, point, Some(geo_id))
+                });
+            }
+        }
+    }
+    rows
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn points() -> Vec<Point> {
+        csv::Reader::from_reader("lat,lon\n42.36,-71.06\n0,0\n".as_bytes())
+            .deserialize()
+            .collect::<Result<_, _>>()
+            .unwrap()
+    }
+
+    fn containing() -> Vec<Containing> {
+        vec![
+            Containing {
+                point: 0,
+                geography_type: 1,
+                id: 25025,
+                name: "Suffolk County".to_string(),
+            },
+            Containing {
+                point: 0,
+                geography_type: 3,
+                id: 25,
+                name: "Massachusetts".to_string(),
+            },
+        ]
+    }
+
+    #[test]
+    fn it_ranks_changes_like_percent_rank() {
+        let values: Vec<Simple> = [(1, 3.0), (2, -1.0), (3, 2.0)]
+            .iter()
+            .map(|&(id, value)| Simple { id, value })
+            .collect();
+        let ids: HashSet<i64> = [1, 3].iter().copied().collect();
+
+        let ranks = rank(&values, &ids, false);
+        assert_eq!(ranks.len(), 2);
+        assert_eq!(ranks[&1].percent_rank, 1.0);
+        assert_eq!(ranks[&3].percent_rank, 0.5);
+        assert_eq!(rank(&values, &ids, true)[&1].percent_rank, 0.0);
+        assert_eq!(rank(&values[..1], &ids, false)[&1].percent_rank, 0.0);
+    }
+
+    #[test]
+    fn it_reads_points_with_short_column_names() {
+        assert_eq!(
+            points(),
+            vec![
+                Point {
+                    latitude: 42.36,
+                    longitude: -71.06
+                },
+                Point {
+                    latitude: 0.0,
+                    longitude: 0.0
+                }
+            ]
+        );
+        assert!(!Point {
+            latitude: 91.0,
+            longitude: 0.0
+        }
+        .is_valid());
+    }
+
+    #[test]
+    fn it_lists_geo_ids_without_selected_map_visualizations() {
+        let rows = rows(&points(), &containing(), &[]);
+
+        assert_eq!(rows.len(), 3);
+        assert_eq!(rows[0].id, Some(25025));
+        assert_eq!(rows[1].name, Some("Massachusetts".to_string()));
+        assert_eq!(rows[2].point, 1);
+        assert_eq!(rows[2].id, None);
+    }
+
+    #[test]
+    fn it_adds_values_for_map_visualizations_of_each_geography_type() {
+        let values = Values {
+            map_visualization: 7,
+            dataset: 2,
+            geography_type: 1,
+            slice: None,
+            ranks: vec![(
+                25025,
+                Ranked {
+                    id: 25025,
+                    value: 3.5,
+                    percent_rank: 0.9,
+                },
+            )]
+            .into_iter()
+            .collect(),
+        };
+        let rows = rows(&points(), &containing(), &[values]);
+
+        assert_eq!(rows.len(), 3);
+        assert_eq!(rows[0].map_visualization, Some(7));
+        assert_eq!(rows[0].value, Some(3.5));
+        assert_eq!(rows[0].percent_rank, Some(0.9));
+        assert_eq!(rows[1].map_visualization, None);
+    }
+}
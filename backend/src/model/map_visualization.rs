@@ -1,13 +1,27 @@
-use super::color_palette::ColorPalette;
+use super::classification::{self, Method};
+use super::color_palette::{self, ColorPalette};
+use super::correlation::Bivariate;
 use super::data::SourceAndDate;
 use super::data_source;
+use super::dataset::Dataset;
+use super::delta::Delta;
+use super::formatter_type;
 use super::scale_type;
+use super::scenario::{self, Scenario};
+use super::statistic;
+use super::stats::quantile;
 use chrono::NaiveDate;
 use derive_more::Display;
 use serde::{Deserialize, Serialize};
 use sqlx::FromRow;
 use std::collections::HashMap;
 
+pub const CHOROPLETH: i32 = 1;
+pub const BIVARIATE_CHOROPLETH: i32 = 3;
+
+/// The number of quantiles suggested color domains are picked from
+const SUGGESTED_CLASSES: usize = 5;
+
 #[derive(Debug, Display)]
 pub struct Error {
     pub message: String,
@@ -15,12 +29,176 @@ pub struct Error {
 
 impl std::error::Error for Error {}
 
+fn default_show_pdf() -> bool {
+    true
+}
+
+fn default_bubble_color() -> String {
+    "#000000".to_string()
+}
+
+/// The settings of a new map visualization. Those left out get the database's defaults.
+#[derive(Deserialize)]
 pub struct Creator {
     pub dataset: i32,
     pub map_type: i32,
     pub color_palette: i32,
     pub scale_type: i32,
     pub formatter_type: i32,
+    pub subcategory: Option<i32>,
+    pub name: Option<String>,
+    pub legend_ticks: Option<i16>,
+    #[serde(default)]
+    pub reverse_scale: bool,
+    #[serde(default)]
+    pub invert_normalized: bool,
+    #[serde(default = "default_show_pdf")]
+    pub show_pdf: bool,
+    pub default_start_date: Option<NaiveDate>,
+    pub default_end_date: Option<NaiveDate>,
+    pub default_source: Option<i32>,
+    pub legend_formatter_type: Option<i32>,
+    #[serde(default)]
+    pub decimals: i16,
+    pub legend_decimals: Option<i16>,
+    #[serde(default)]
+    pub color_domain: Vec<f64>,
+    #[serde(default)]
+    pub pdf_domain: Vec<f64>,
+    #[serde(default = "default_bubble_color")]
+    pub bubble_color: String,
+    pub classification_method: Option<i32>,
+    #[serde(default = "default_classification_classes")]
+    pub classification_classes: i16,
+    /// The data category to list the new map visualization in, after the others
+    pub category: Option<i32>,
+}
+
+impl Creator {
+    /// A map visualization with only the required settings
+    pub fn new(
+        dataset: i32,
+        map_type: i32,
+        color_palette: i32,
+        scale_type: i32,
+        formatter_type: i32,
+    ) -> Creator {
+        Creator {
+            dataset,
+            map_type,
+            color_palette,
+            scale_type,
+            formatter_type,
+            subcategory: None,
+            name: None,
+            legend_ticks: None,
+            reverse_scale: false,
+            invert_normalized: false,
+            show_pdf: default_show_pdf(),
+            default_start_date: None,
+            default_end_date: None,
+            default_source: None,
+            legend_formatter_type: None,
+            decimals: 0,
+            legend_decimals: None,
+            color_domain: vec![],
+            pdf_domain: vec![],
+            bubble_color: default_bubble_color(),
+            classification_method: None,
+            classification_classes: default_classification_classes(),
+            category: None,
+        }
+    }
+
+    /// A choropleth of a dataset with settings suggested from its values. Values of both signs
+    /// get a diverging scale centered on zero, and others a sequential one. The color domain
+    /// spans the inner quantiles, and the formatter comes from the units.
+    pub fn suggested(dataset: &Dataset, values: &[f64]) -> Creator {
+        let mut sorted = values.to_vec();
+        sorted.sort_by(|a, b| a.total_cmp(b));
+        let range = match (sorted.first(), sorted.last()) {
+            (Some(&min), Some(&max)) => Some((min, max)),
+            _ => None,
+        };
+        let diverging = range.map_or(false, |(min, max)| min < 0.0 && max > 0.0);
+        let (scale_type, color_palette) = if diverging {
+            (scale_type::DIVERGING, color_palette::RD_BU)
+        } else {
+            (scale_type::SEQUENTIAL, color_palette::BLUES)
+        };
+        let breaks = classification::breaks(Method::Quantile, &sorted, SUGGESTED_CLASSES);
+        let color_domain = if diverging {
+            let domain = classification::suggest_domain(scale_type, &breaks, 0.0);
+            // Keeps zero in the middle when most values have the same sign
+            vec![domain[0].min(0.0), 0.0, domain[2].max(0.0)]
+        } else {
+            let median = range.map_or(0.0, |_| quantile(&sorted, 0.5));
+            classification::suggest_domain(scale_type, &breaks, median)
+        };
+        let decimals = match range {
+            Some((min, max)) if max - min < 1.0 => 2,
+            Some((min, max)) if max - min < 100.0 => 1,
+            _ => 0,
+        };
+        Creator {
+            color_domain,
+            pdf_domain: range.map_or(vec![], |(min, max)| vec![min, max]),
+            decimals,
+            ..Creator::new(
+                dataset.id,
+                CHOROPLETH,
+                color_palette,
+                scale_type,
+                formatter_type::for_units(&dataset.units),
+            )
+        }
+    }
+
+    /// The settings as a patch of a map visualization that doesn't exist yet, to validate them
+    /// like edits
+    pub fn patch(&self) -> Patch {
+        Patch {
+            id: 0,
+            dataset: self.dataset,
+            map_type: self.map_type,
+            subcategory: self.subcategory,
+            data_tab: self.category,
+            name: self.name.clone(),
+            legend_ticks: self.legend_ticks,
+            color_palette_id: self.color_palette,
+            reverse_scale: self.reverse_scale,
+            invert_normalized: self.invert_normalized,
+            scale_type: self.scale_type,
+            show_pdf: self.show_pdf,
+            default_start_date: self.default_start_date,
+            default_end_date: self.default_end_date,
+            default_source: self.default_source,
+            formatter_type: self.formatter_type,
+            legend_formatter_type: self.legend_formatter_type,
+            decimals: self.decimals,
+            legend_decimals: self.legend_decimals,
+            color_domain: self.color_domain.clone(),
+            pdf_domain: self.pdf_domain.clone(),
+            bubble_color: self.bubble_color.clone(),
+            delta_operation: None,
+            delta_from_source: None,
+            delta_from_start_date: None,
+            delta_from_end_date: None,
+            delta_to_source: None,
+            delta_to_start_date: None,
+            delta_to_end_date: None,
+            delta_from_scenario: None,
+            delta_to_scenario: None,
+            bivariate_dataset: None,
+            bivariate_source: None,
+            bivariate_start_date: None,
+            bivariate_end_date: None,
+            bivariate_scenario: None,
+            bivariate_statistic: None,
+            classification_method: self.classification_method,
+            classification_classes: self.classification_classes,
+        }
+    }
 }
 
 #[derive(FromRow, Deserialize, Serialize)]
@@ -47,10 +225,28 @@ pub struct Patch {
     pub color_domain: Vec<f64>,
     pub pdf_domain: Vec<f64>,
     pub bubble_color: String,
+    pub delta_operation: Option<i32>,
+    pub delta_from_source: Option<i32>,
+    pub delta_from_start_date: Option<NaiveDate>,
+    pub delta_from_end_date: Option<NaiveDate>,
+    pub delta_to_source: Option<i32>,
+    pub delta_to_start_date: Option<NaiveDate>,
+    pub delta_to_end_date: Option<NaiveDate>,
+    pub delta_from_scenario: Option<i32>,
+    pub delta_to_scenario: Option<i32>,
+    pub bivariate_dataset: Option<i32>,
+    pub bivariate_source: Option<i32>,
+    pub bivariate_start_date: Option<NaiveDate>,
+    pub bivariate_end_date: Option<NaiveDate>,
+    pub bivariate_scenario: Option<i32>,
+    pub bivariate_statistic: Option<i32>,
+    pub classification_method: Option<i32>,
+    pub classification_classes: i16,
 }
 
 impl Patch {
-    pub fn new(patch: JsonPatch) -> Patch {
+    /// The settings after applying the patch to the stored ones
+    pub fn new(patch: JsonPatch, stored: Patch) -> Patch {
         Patch {
             id: patch.id,
             dataset: patch.dataset,
@@ -74,11 +270,63 @@ impl Patch {
             color_domain: patch.color_domain,
             pdf_domain: patch.pdf_domain,
             bubble_color: patch.bubble_color,
+            delta_operation: patch.delta_operation.unwrap_or(stored.delta_operation),
+            delta_from_source: patch.delta_from_source.unwrap_or(stored.delta_from_source),
+            delta_from_start_date: patch
+                .delta_from_start_date
+                .unwrap_or(stored.delta_from_start_date),
+            delta_from_end_date: patch
+                .delta_from_end_date
+                .unwrap_or(stored.delta_from_end_date),
+            delta_to_source: patch.delta_to_source.unwrap_or(stored.delta_to_source),
+            delta_to_start_date: patch
+                .delta_to_start_date
+                .unwrap_or(stored.delta_to_start_date),
+            delta_to_end_date: patch.delta_to_end_date.unwrap_or(stored.delta_to_end_date),
+            delta_from_scenario: patch
+                .delta_from_scenario
+                .unwrap_or(stored.delta_from_scenario),
+            delta_to_scenario: patch.delta_to_scenario.unwrap_or(stored.delta_to_scenario),
+            bivariate_dataset: patch.bivariate_dataset.unwrap_or(stored.bivariate_dataset),
+            bivariate_source: patch.bivariate_source.unwrap_or(stored.bivariate_source),
+            bivariate_start_date: patch
+                .bivariate_start_date
+                .unwrap_or(stored.bivariate_start_date),
+            bivariate_end_date: patch
+                .bivariate_end_date
+                .unwrap_or(stored.bivariate_end_date),
+            bivariate_scenario: patch
+                .bivariate_scenario
+                .unwrap_or(stored.bivariate_scenario),
+            bivariate_statistic: patch
+                .bivariate_statistic
+                .unwrap_or(stored.bivariate_statistic),
+            classification_method: patch
+                .classification_method
+                .unwrap_or(stored.classification_method),
+            classification_classes: patch
+                .classification_classes
+                .unwrap_or(stored.classification_classes),
         }
     }
 }
 
-#[derive(FromRow, Deserialize, Serialize)]
+/// Tells a field sent as `null` apart from one left out, which `#[serde(default)]` makes `None`
+fn present<'de, T, D>(deserializer: D) -> Result<Option<T>, D::Error>
+where
+    T: Deserialize<'de>,
+    D: serde::Deserializer<'de>,
+{
+    T::deserialize(deserializer).map(Some)
+}
+
+fn default_classification_classes() -> i16 {
+    5
+}
+
+/// The settings sent to update a map visualization. The delta, bivariate and classification
+/// settings are kept as stored when left out, and cleared when sent as `null`.
+#[derive(Deserialize, Serialize)]
 pub struct JsonPatch {
     pub id: i32,
     pub dataset: i32,
@@ -102,6 +350,40 @@ pub struct JsonPatch {
     pub decimals: i16,
     pub legend_decimals: Option<i16>,
     pub bubble_color: String,
+    #[serde(default, deserialize_with = "present")]
+    pub delta_operation: Option<Option<i32>>,
+    #[serde(default, deserialize_with = "present")]
+    pub delta_from_source: Option<Option<i32>>,
+    #[serde(default, deserialize_with = "present")]
+    pub delta_from_start_date: Option<Option<NaiveDate>>,
+    #[serde(default, deserialize_with = "present")]
+    pub delta_from_end_date: Option<Option<NaiveDate>>,
+    #[serde(default, deserialize_with = "present")]
+    pub delta_to_source: Option<Option<i32>>,
+    #[serde(default, deserialize_with = "present")]
+    pub delta_to_start_date: Option<Option<NaiveDate>>,
+    #[serde(default, deserialize_with = "present")]
+    pub delta_to_end_date: Option<Option<NaiveDate>>,
+    #[serde(default, deserialize_with = "present")]
+    pub delta_from_scenario: Option<Option<i32>>,
+    #[serde(default, deserialize_with = "present")]
+    pub delta_to_scenario: Option<Option<i32>>,
+    #[serde(default, deserialize_with = "present")]
+    pub bivariate_dataset: Option<Option<i32>>,
+    #[serde(default, deserialize_with = "present")]
+    pub bivariate_source: Option<Option<i32>>,
+    #[serde(default, deserialize_with = "present")]
+    pub bivariate_start_date: Option<Option<NaiveDate>>,
+    #[serde(default, deserialize_with = "present")]
+    pub bivariate_end_date: Option<Option<NaiveDate>>,
+    #[serde(default, deserialize_with = "present")]
+    pub bivariate_scenario: Option<Option<i32>>,
+    #[serde(default, deserialize_with = "present")]
+    pub bivariate_statistic: Option<Option<i32>>,
+    #[serde(default, deserialize_with = "present")]
+    pub classification_method: Option<Option<i32>>,
+    #[serde(default)]
+    pub classification_classes: Option<i16>,
 }
 
 #[derive(FromRow, Deserialize, Serialize, Debug)]
@@ -136,6 +418,89 @@ pub struct MapVisualization {
     pub pdf_domain: Vec<f64>,
     pub geography_type: i32,
     pub bubble_color: String,
+    pub delta_operation: Option<i32>,
+    pub delta_from_source: Option<i32>,
+    pub delta_from_start_date: Option<NaiveDate>,
+    pub delta_from_end_date: Option<NaiveDate>,
+    pub delta_to_source: Option<i32>,
+    pub delta_to_start_date: Option<NaiveDate>,
+    pub delta_to_end_date: Option<NaiveDate>,
+    pub delta_from_scenario: Option<i32>,
+    pub delta_to_scenario: Option<i32>,
+    pub bivariate_dataset: Option<i32>,
+    pub bivariate_source: Option<i32>,
+    pub bivariate_start_date: Option<NaiveDate>,
+    pub bivariate_end_date: Option<NaiveDate>,
+    pub bivariate_scenario: Option<i32>,
+    pub bivariate_statistic: Option<i32>,
+    pub classification_method: Option<i32>,
+    pub classification_classes: i16,
+}
+
+impl MapVisualization {
+    /// The slices a delta map visualization compares, if it is one. They compare the mean unless
+    /// `Delta::with_statistic` picks another statistic.
+    pub fn delta(&self) -> Option<Delta> {
+        Some(Delta {
+            operation: self.delta_operation?,
+            from: SourceAndDate {
+                source: self.delta_from_source?,
+                scenario: self.delta_from_scenario.unwrap_or(scenario::OBSERVED),
+                statistic: statistic::MEAN,
+                start_date: self.delta_from_start_date?,
+                end_date: self.delta_from_end_date?,
+            },
+            to: SourceAndDate {
+                source: self.delta_to_source?,
+                scenario: self.delta_to_scenario.unwrap_or(scenario::OBSERVED),
+                statistic: statistic::MEAN,
+                start_date: self.delta_to_start_date?,
+                end_date: self.delta_to_end_date?,
+            },
+        })
+    }
+
+    /// The second slice of a bivariate choropleth, if it is one
+    pub fn bivariate(&self) -> Option<Bivariate> {
+        Some(Bivariate {
+            dataset: self.bivariate_dataset?,
+            slice: SourceAndDate {
+                source: self.bivariate_source?,
+                scenario: self.bivariate_scenario.unwrap_or(scenario::OBSERVED),
+                statistic: self.bivariate_statistic.unwrap_or(statistic::MEAN),
+                start_date: self.bivariate_start_date?,
+                end_date: self.bivariate_end_date?,
+            },
+        })
+    }
+
+    /// The observed slice shown when the map is first opened: its default source and dates,
+    /// with the most recent slice filling in any that aren't set
+    pub fn default_slice(&self, slices: &[SourceAndDate]) -> Option<SourceAndDate> {
+        let latest = slices
+            .iter()
+            .filter(|slice| {
+                slice.scenario == scenario::OBSERVED
+                    && slice.statistic == statistic::MEAN
+                    && self
+                        .default_source
+                        .map_or(true, |source| source == slice.source)
+            })
+            .max_by_key(|slice| (slice.end_date, slice.start_date, slice.source));
+        let (start_date, end_date) = match (self.default_start_date, self.default_end_date) {
+            (Some(start_date), Some(end_date)) => (start_date, end_date),
+            _ => (latest?.start_date, latest?.end_date),
+        };
+        Some(SourceAndDate {
+            source: self
+                .default_source
+                .or_else(|| latest.map(|slice| slice.source))?,
+            scenario: scenario::OBSERVED,
+            statistic: statistic::MEAN,
+            start_date,
+            end_date,
+        })
+    }
 }
 
 #[derive(Deserialize, Serialize)]
@@ -157,6 +522,9 @@ pub struct Json {
     pub color_domain: Vec<f64>,
     pub date_ranges_by_source: HashMap<i32, Vec<DateRange>>,
     pub sources: HashMap<i32, data_source::DataSource>,
+    pub scenarios_by_source: HashMap<i32, Vec<i32>>,
+    pub statistics_by_source: HashMap<i32, Vec<i32>>,
+    pub scenarios: HashMap<i32, Scenario>,
     pub show_pdf: bool,
     pub pdf_domain: Vec<f64>,
     pub default_date_range: Option<DateRange>,
@@ -168,6 +536,14 @@ pub struct Json {
     pub order: i16,
     pub geography_type: i32,
     pub bubble_color: String,
+    /// Set for delta map visualizations, which show the change between two slices
+    pub delta: Option<Delta>,
+    /// Set for bivariate choropleths, which class geo ids by this slice as well as their own
+    pub bivariate: Option<Bivariate>,
+    pub classification_method: Option<i32>,
+    pub classification_classes: i16,
+    /// The class breaks for the selected slice, empty without a classification method
+    pub breaks: Vec<f64>,
 }
 
 impl Json {
@@ -175,14 +551,29 @@ impl Json {
         map_visualization: MapVisualization,
         source_and_dates: Vec<SourceAndDate>,
         data_sources: Vec<data_source::DataSource>,
+        scenarios: Vec<Scenario>,
+        breaks: Vec<f64>,
     ) -> Json {
         let mut date_ranges_by_source = HashMap::new();
+        let mut scenarios_by_source = HashMap::new();
+        let mut statistics_by_source = HashMap::new();
         for source_and_date in source_and_dates {
             let date_range = DateRange::from(&source_and_date);
-            let dates = date_ranges_by_source
-                .entry(source_and_date.source)
-                .or_insert_with(Vec::new);
-            dates.push(date_range);
+            push_unique(
+                &mut date_ranges_by_source,
+                source_and_date.source,
+                date_range,
+            );
+            push_unique(
+                &mut scenarios_by_source,
+                source_and_date.source,
+                source_and_date.scenario,
+            );
+            push_unique(
+                &mut statistics_by_source,
+                source_and_date.source,
+                source_and_date.statistic,
+            );
         }
         let default_date_range = match (
             map_visualization.default_start_date,
@@ -194,6 +585,8 @@ impl Json {
             }),
             _ => Option::None,
         };
+        let delta = map_visualization.delta();
+        let bivariate = map_visualization.bivariate();
         Json {
             id: map_visualization.id,
             dataset: map_visualization.dataset,
@@ -221,6 +614,12 @@ impl Json {
                 .into_iter()
                 .map(|data_source| (data_source.id, data_source))
                 .collect::<HashMap<i32, data_source::DataSource>>(),
+            scenarios_by_source,
+            statistics_by_source,
+            scenarios: scenarios
+                .into_iter()
+                .map(|scenario| (scenario.id, scenario))
+                .collect(),
             show_pdf: map_visualization.show_pdf,
             pdf_domain: map_visualization.pdf_domain,
             default_date_range,
@@ -232,10 +631,24 @@ impl Json {
             order: map_visualization.order,
             geography_type: map_visualization.geography_type,
             bubble_color: map_visualization.bubble_color,
+            delta,
+            bivariate,
+            classification_method: map_visualization.classification_method,
+            classification_classes: map_visualization.classification_classes,
+            breaks,
         }
     }
 }
 
+/// Data has a row per source, scenario, statistic and date range, so the same value
+/// shows up for many slices
+fn push_unique<T: PartialEq>(by_source: &mut HashMap<i32, Vec<T>>, source: i32, value: T) {
+    let values = by_source.entry(source).or_insert_with(Vec::new);
+    if !values.contains(&value) {
+        values.push(value);
+    }
+}
+
 #[derive(FromRow, Deserialize, Serialize, PartialEq, Debug)]
 pub struct DateRange {
     pub start_date: NaiveDate,
@@ -296,11 +709,30 @@ mod tests {
                 pdf_domain: vec![],
                 geography_type: 1,
                 bubble_color: "black".to_string(),
+                delta_operation: None,
+                delta_from_source: None,
+                delta_from_start_date: None,
+                delta_from_end_date: None,
+                delta_to_source: None,
+                delta_to_start_date: None,
+                delta_to_end_date: None,
+                delta_from_scenario: None,
+                delta_to_scenario: None,
+                bivariate_dataset: None,
+                bivariate_source: None,
+                bivariate_start_date: None,
+                bivariate_end_date: None,
+                bivariate_scenario: None,
+                bivariate_statistic: None,
+                classification_method: None,
+                classification_classes: 5,
             },
             source_ids
                 .iter()
                 .map(|&source| SourceAndDate {
                     source,
+                    scenario: scenario::OBSERVED,
+                    statistic: statistic::MEAN,
                     start_date: NaiveDate::from_ymd_opt(
                         2019,
                         source.try_into().unwrap(),
@@ -333,7 +765,13 @@ mod tests {
         let (map_visualization, source_and_dates, data_sources) = get_models(vec![source_id]);
 
         let expected_date_range = DateRange::from(source_and_dates.first().unwrap());
-        let result = Json::new(map_visualization, source_and_dates, data_sources);
+        let result = Json::new(
+            map_visualization,
+            source_and_dates,
+            data_sources,
+            vec![],
+            vec![],
+        );
 
         assert_eq!(
             result.date_ranges_by_source[&source_id][0],
@@ -344,7 +782,13 @@ mod tests {
     #[test]
     fn no_default_source_carries_through() {
         let (map_visualization, source_and_dates, data_sources) = get_models(vec![1, 2]);
-        let result = Json::new(map_visualization, source_and_dates, data_sources);
+        let result = Json::new(
+            map_visualization,
+            source_and_dates,
+            data_sources,
+            vec![],
+            vec![],
+        );
 
         assert_eq!(result.default_source, None)
     }
@@ -358,7 +802,13 @@ mod tests {
             default_start_date: NaiveDate::from_ymd_opt(2019, 4, 4),
             ..map_visualization
         };
-        let result = Json::new(map_visualization, source_and_dates, data_sources);
+        let result = Json::new(
+            map_visualization,
+            source_and_dates,
+            data_sources,
+            vec![],
+            vec![],
+        );
 
         assert_eq!(result.default_source, Some(4));
         assert_eq!(
@@ -373,7 +823,13 @@ mod tests {
     #[test]
     fn it_handles_no_sources() {
         let (map_visualization, source_and_dates, data_sources) = get_models(vec![]);
-        let result = Json::new(map_visualization, source_and_dates, data_sources);
+        let result = Json::new(
+            map_visualization,
+            source_and_dates,
+            data_sources,
+            vec![],
+            vec![],
+        );
 
         assert_eq!(result.default_source, None)
     }
@@ -385,8 +841,284 @@ mod tests {
             default_source: Some(3),
             ..map_visualization
         };
-        let result = Json::new(map_visualization, source_and_dates, data_sources);
+        let result = Json::new(
+            map_visualization,
+            source_and_dates,
+            data_sources,
+            vec![],
+            vec![],
+        );
 
         assert_eq!(result.default_date_range, None)
     }
+
+    #[test]
+    fn it_only_has_a_delta_when_both_slices_are_set() {
+        let (map_visualization, source_and_dates, data_sources) = get_models(vec![1]);
+        let map_visualization = MapVisualization {
+            delta_operation: Some(1),
+            delta_from_source: Some(1),
+            delta_from_start_date: NaiveDate::from_ymd_opt(2020, 1, 1),
+            delta_from_end_date: NaiveDate::from_ymd_opt(2020, 12, 31),
+            ..map_visualization
+        };
+        assert_eq!(map_visualization.delta(), None);
+
+        let map_visualization = MapVisualization {
+            delta_to_source: Some(1),
+            delta_to_start_date: NaiveDate::from_ymd_opt(2050, 1, 1),
+            delta_to_end_date: NaiveDate::from_ymd_opt(2050, 12, 31),
+            ..map_visualization
+        };
+        let result = Json::new(
+            map_visualization,
+            source_and_dates,
+            data_sources,
+            vec![],
+            vec![],
+        );
+
+        assert_eq!(
+            result.delta,
+            Some(Delta {
+                operation: 1,
+                from: SourceAndDate {
+                    source: 1,
+                    scenario: scenario::OBSERVED,
+                    statistic: statistic::MEAN,
+                    start_date: NaiveDate::from_ymd_opt(2020, 1, 1).unwrap(),
+                    end_date: NaiveDate::from_ymd_opt(2020, 12, 31).unwrap(),
+                },
+                to: SourceAndDate {
+                    source: 1,
+                    scenario: scenario::OBSERVED,
+                    statistic: statistic::MEAN,
+                    start_date: NaiveDate::from_ymd_opt(2050, 1, 1).unwrap(),
+                    end_date: NaiveDate::from_ymd_opt(2050, 12, 31).unwrap(),
+                },
+            })
+        )
+    }
+
+    #[test]
+    fn it_lists_scenarios_by_source_once() {
+        let (map_visualization, mut source_and_dates, data_sources) = get_models(vec![1]);
+        let start_date = source_and_dates[0].start_date;
+        let end_date = source_and_dates[0].end_date;
+        source_and_dates.push(SourceAndDate {
+            source: 1,
+            scenario: 3,
+            statistic: statistic::MEAN,
+            start_date,
+            end_date,
+        });
+        source_and_dates.push(SourceAndDate {
+            source: 1,
+            scenario: 3,
+            statistic: 2,
+            start_date,
+            end_date,
+        });
+        let scenarios = vec![Scenario {
+            id: 3,
+            name: "SSP2-4.5".to_string(),
+            description: "".to_string(),
+        }];
+        let result = Json::new(
+            map_visualization,
+            source_and_dates,
+            data_sources,
+            scenarios,
+            vec![],
+        );
+
+        assert_eq!(result.date_ranges_by_source[&1].len(), 1);
+        assert_eq!(result.scenarios_by_source[&1], vec![scenario::OBSERVED, 3]);
+        assert_eq!(result.statistics_by_source[&1], vec![statistic::MEAN, 2]);
+        assert_eq!(result.scenarios[&3].name, "SSP2-4.5");
+    }
+
+    #[test]
+    fn its_default_slice_is_the_latest_without_defaults() {
+        let (map_visualization, source_and_dates, _) = get_models(vec![1, 3, 2]);
+
+        assert_eq!(
+            map_visualization.default_slice(&source_and_dates),
+            Some(SourceAndDate {
+                source: 3,
+                scenario: scenario::OBSERVED,
+                statistic: statistic::MEAN,
+                start_date: NaiveDate::from_ymd_opt(2019, 3, 3).unwrap(),
+                end_date: NaiveDate::from_ymd_opt(2020, 3, 3).unwrap(),
+            })
+        );
+        assert_eq!(map_visualization.default_slice(&[]), None);
+    }
+
+    #[test]
+    fn its_default_slice_uses_default_source_and_dates() {
+        let (map_visualization, source_and_dates, _) = get_models(vec![1, 2, 3]);
+        let map_visualization = MapVisualization {
+            default_source: Some(2),
+            ..map_visualization
+        };
+
+        assert_eq!(
+            map_visualization
+                .default_slice(&source_and_dates)
+                .map(|slice| (slice.source, slice.end_date)),
+            Some((2, NaiveDate::from_ymd_opt(2020, 2, 2).unwrap()))
+        );
+
+        let map_visualization = MapVisualization {
+            default_start_date: NaiveDate::from_ymd_opt(2010, 1, 1),
+            default_end_date: NaiveDate::from_ymd_opt(2010, 12, 31),
+            ..map_visualization
+        };
+
+        assert_eq!(
+            map_visualization
+                .default_slice(&[])
+                .map(|slice| (slice.source, slice.start_date)),
+            Some((2, NaiveDate::from_ymd_opt(2010, 1, 1).unwrap()))
+        );
+    }
+
+    fn dataset(units: &str) -> Dataset {
+        Dataset {
+            id: 7,
+            short_name: "".to_string(),
+            name: "".to_string(),
+            description: "".to_string(),
+            geography_type: 1,
+            units: units.to_string(),
+        }
+    }
+
+    #[test]
+    fn it_suggests_sequential_settings_for_values_of_one_sign() {
+        let values = [5.0, 1.0, 2.0, 3.0, 4.0, 6.0, 7.0, 8.0, 9.0, 10.0];
+        let creator = Creator::suggested(&dataset("% of households"), &values);
+
+        assert_eq!(creator.dataset, 7);
+        assert_eq!(creator.map_type, CHOROPLETH);
+        assert_eq!(creator.scale_type, scale_type::SEQUENTIAL);
+        assert_eq!(creator.color_palette, color_palette::BLUES);
+        assert_eq!(creator.formatter_type, formatter_type::PERCENT);
+        assert_eq!(creator.color_domain.len(), 2);
+        assert!(creator.color_domain[0] > 1.0 && creator.color_domain[1] < 10.0);
+        assert_eq!(creator.pdf_domain, vec![1.0, 10.0]);
+        assert_eq!(creator.decimals, 1);
+    }
+
+    #[test]
+    fn it_suggests_diverging_settings_centered_on_zero_for_values_of_both_signs() {
+        let values = [-1.0, 10.0, 20.0, 30.0, 40.0, 50.0, 60.0, 70.0, 80.0, 900.0];
+        let creator = Creator::suggested(&dataset("dollars"), &values);
+
+        assert_eq!(creator.scale_type, scale_type::DIVERGING);
+        assert_eq!(creator.color_palette, color_palette::RD_BU);
+        assert_eq!(creator.formatter_type, formatter_type::MONEY);
+        assert_eq!(creator.color_domain[..2], [0.0, 0.0]);
+        assert!(creator.color_domain[2] > 0.0);
+        assert_eq!(creator.decimals, 0);
+    }
+
+    #[test]
+    fn it_suggests_defaults_without_values() {
+        let creator = Creator::suggested(&dataset("people"), &[]);
+
+        assert_eq!(creator.scale_type, scale_type::SEQUENTIAL);
+        assert_eq!(creator.formatter_type, formatter_type::DEFAULT);
+        assert!(creator.color_domain.is_empty());
+        assert!(creator.pdf_domain.is_empty());
+    }
+
+    fn stored() -> Patch {
+        let date = NaiveDate::from_ymd_opt(2020, 1, 1);
+        Patch {
+            delta_operation: Some(1),
+            delta_from_source: Some(1),
+            delta_from_start_date: date,
+            delta_from_end_date: date,
+            delta_to_source: Some(2),
+            delta_to_start_date: date,
+            delta_to_end_date: date,
+            delta_from_scenario: Some(scenario::OBSERVED),
+            delta_to_scenario: Some(3),
+            bivariate_dataset: Some(8),
+            bivariate_source: Some(2),
+            bivariate_start_date: date,
+            bivariate_end_date: date,
+            bivariate_scenario: Some(3),
+            bivariate_statistic: Some(statistic::MEAN),
+            classification_method: Some(2),
+            classification_classes: 7,
+            ..Creator::new(7, CHOROPLETH, 1, 1, 1).patch()
+        }
+    }
+
+    fn json_patch(fields: serde_json::Value) -> JsonPatch {
+        let mut patch = serde_json::json!({
+            "id": 0,
+            "dataset": 7,
+            "map_type": CHOROPLETH,
+            "subcategory": null,
+            "data_tab": null,
+            "name": "renamed",
+            "legend_ticks": null,
+            "color_palette": { "id": 1, "name": "Blues" },
+            "reverse_scale": false,
+            "invert_normalized": false,
+            "scale_type": { "id": 1, "name": "Diverging" },
+            "color_domain": [],
+            "show_pdf": true,
+            "pdf_domain": [],
+            "default_start_date": null,
+            "default_end_date": null,
+            "default_source": null,
+            "formatter_type": 1,
+            "legend_formatter_type": null,
+            "decimals": 0,
+            "legend_decimals": null,
+            "bubble_color": "#000000",
+        });
+        patch
+            .as_object_mut()
+            .unwrap()
+            .extend(fields.as_object().unwrap().clone());
+        serde_json::from_value(patch).unwrap()
+    }
+
+    #[test]
+    fn its_patch_keeps_the_stored_settings_it_leaves_out() {
+        let patch = Patch::new(json_patch(serde_json::json!({})), stored());
+
+        assert_eq!(patch.name.as_deref(), Some("renamed"));
+        assert_eq!(patch.delta_operation, Some(1));
+        assert_eq!(patch.delta_to_source, Some(2));
+        assert_eq!(patch.delta_to_scenario, Some(3));
+        assert_eq!(patch.delta_to_end_date, NaiveDate::from_ymd_opt(2020, 1, 1));
+        assert_eq!(patch.bivariate_dataset, Some(8));
+        assert_eq!(patch.bivariate_statistic, Some(statistic::MEAN));
+        assert_eq!(patch.classification_method, Some(2));
+        assert_eq!(patch.classification_classes, 7);
+    }
+
+    #[test]
+    fn its_patch_clears_the_settings_sent_as_null() {
+        let fields = serde_json::json!({
+            "delta_operation": null,
+            "bivariate_dataset": null,
+            "classification_method": 1,
+            "classification_classes": 4,
+        });
+        let patch = Patch::new(json_patch(fields), stored());
+
+        assert_eq!(patch.delta_operation, None);
+        assert_eq!(patch.delta_from_source, Some(1));
+        assert_eq!(patch.bivariate_dataset, None);
+        assert_eq!(patch.classification_method, Some(1));
+        assert_eq!(patch.classification_classes, 4);
+    }
 }
@@ -8,7 +8,7 @@ pub struct Collection {
     pub map_visualization: i32,
 }
 
-#[derive(FromRow, Deserialize, Serialize)]
+#[derive(FromRow, Deserialize, Serialize, Debug, Clone, PartialEq)]
 pub struct Id {
     pub category: i32,
     pub map_visualization: i32,
@@ -1,12 +1,36 @@
+pub mod aggregation;
+pub mod audit;
+pub mod classification;
 pub mod color_palette;
+pub mod correlation;
+pub mod crosswalk;
+pub mod custom_region;
 pub mod data;
 pub mod data_category;
 pub mod data_source;
 pub mod dataset;
+pub mod deletion;
+pub mod delta;
+pub mod derived_dataset;
+pub mod export;
+pub mod formatter_type;
+pub mod geo_boundary;
 pub mod geo_id;
 pub mod geography_type;
+pub mod lookup;
 pub mod map_visualization;
 pub mod map_visualization_collection;
+pub mod neighbor;
+pub mod order;
+pub mod publication;
+pub mod revision;
 pub mod scale_type;
+pub mod scenario;
+pub mod statistic;
+pub mod stats;
 pub mod subcategory;
+pub mod tile;
+pub mod trash;
 pub mod upload_metadata;
+pub mod user;
+pub mod validation;
@@ -0,0 +1,275 @@
+use super::data::Simple;
+use serde::{Deserialize, Serialize};
+use sqlx::FromRow;
+use std::collections::{BTreeMap, HashMap, HashSet};
+
+/// Two adjacent geo ids of the same geography type
+#[derive(FromRow, Deserialize, Serialize, Debug, PartialEq)]
+pub struct Neighbor {
+    pub id: i64,
+    pub neighbor_id: i64,
+}
+
+#[derive(Deserialize, Serialize, Debug, PartialEq)]
+pub struct NeighborAverage {
+    pub id: i64,
+    pub value: f64,
+    /// The mean of the neighbors' values, if any neighbor has a value
+    pub neighbor_average: Option<f64>,
+    pub neighbors: usize,
+}
+
+/// Global Moran's I with binary contiguity weights and its z-score under the normality assumption
+#[derive(Serialize, Debug, PartialEq)]
+pub struct MoransI {
+    pub moran_i: f64,
+    pub expected: f64,
+    pub variance: f64,
+    pub z_score: f64,
+    pub count: usize,
+}
+
+/// A geo id's local Getis-Ord Gi* z-score. High positive scores are hot spots and low negative
+/// scores are cold spots; beyond ±1.96 is significant at the 95% level.
+#[derive(Deserialize, Serialize, Debug, PartialEq)]
+pub struct HotSpot {
+    pub id: i64,
+    pub value: f64,
+    pub z_score: f64,
+}
+
+/// The neighbors of each geo id that has a value, limited to neighbors that have values too
+struct Weights {
+    values: BTreeMap<i64, f64>,
+    neighbors: HashMap<i64, HashSet<i64>>,
+}
+
+impl Weights {
+    fn new(values: &[Simple], neighbors: &[Neighbor]) -> Weights {
+        let values: BTreeMap<i64, f64> = values.iter().map(|row| (row.id, row.value)).collect();
+        let mut by_id: HashMap<i64, HashSet<i64>> = HashMap::new();
+        for neighbor in neighbors {
+            if neighbor.id != neighbor.neighbor_id
+                && values.contains_key(&neighbor.id)
+                && values.contains_key(&neighbor.neighbor_id)
+            {
+                by_id
+                    .entry(neighbor.id)
+                    .or_default()
+                    .insert(neighbor.neighbor_id);
+            }
+        }
+        Weights {
+            values,
+            neighbors: by_id,
+        }
+    }
+
+    fn neighbors(&self, id: i64) -> impl Iterator<Item = &i64> {
+        self.neighbors.get(&id).into_iter().flatten()
+    }
+
+    fn is_neighbor(&self, id: i64, neighbor_id: i64) -> bool {
+        self.neighbors
+            .get(&id)
+            .map_or(false, |neighbors| neighbors.contains(&neighbor_id))
+    }
+
+    fn mean(&self) -> f64 {
+        self.values.values().sum::<f64>() / self.values.len() as f64
+    }
+}
+
+pub fn neighbor_averages(values: &[Simple], neighbors: &[Neighbor]) -> Vec<NeighborAverage> {
+    let weights = Weights::new(values, neighbors);
+    weights
+        .values
+        .iter()
+        .map(|(&id, &value)| {
+            let neighbor_values: Vec<f64> =
+                weights.neighbors(id).map(|j| weights.values[j]).collect();
+            NeighborAverage {
+                id,
+                value,
+                neighbor_average: if neighbor_values.is_empty() {
+                    None
+                } else {
+                    Some(neighbor_values.iter().sum::<f64>() / neighbor_values.len() as f64)
+                },
+                neighbors: neighbor_values.len(),
+            }
+        })
+        .collect()
+}
+
+/// Undefined without at least 3 geo ids, any adjacency or any variation in the values
+pub fn morans_i(values: &[Simple], neighbors: &[Neighbor]) -> Option<MoransI> {
+    let weights = Weights::new(values, neighbors);
+    let count = weights.values.len();
+    if count < 3 {
+        return None;
+    }
+    let n = count as f64;
+    let mean = weights.mean();
+    let deviation = |id: &i64| weights.values[id] - mean;
+
+    let total_weight: f64 = weights.neighbors.values().map(|set| set.len() as f64).sum();
+    let squares: f64 = weights.values.keys().map(|id| deviation(id).powi(2)).sum();
+    if total_weight == 0.0 || squares == 0.0 {
+        return None;
+    }
+    let cross: f64 = weights
+        .values
+        .keys()
+        .map(|i| {
+            weights
+                .neighbors(*i)
+                .map(|j| deviation(i) * deviation(j))
+                .sum::<f64>()
+        })
+        .sum();
+    let moran_i = n / total_weight * cross / squares;
+    let expected = -1.0 / (n - 1.0);
+
+    // S1 sums each pair's combined weight in both directions, S2 each geo id's in and out weight
+    let mut pairs: HashSet<(i64, i64)> = HashSet::new();
+    let mut in_weights: HashMap<i64, f64> = HashMap::new();
+    for (&i, set) in &weights.neighbors {
+        for &j in set {
+            pairs.insert((i, j));
+            pairs.insert((j, i));
+            *in_weights.entry(j).or_default() += 1.0;
+        }
+    }
+    let s1: f64 = 0.5
+        * pairs
+            .iter()
+            .map(|&(i, j)| {
+                (weights.is_neighbor(i, j) as u8 + weights.is_neighbor(j, i) as u8) as f64
+            })
+            .map(|weight| weight.powi(2))
+            .sum::<f64>();
+    let s2: f64 = weights
+        .values
+        .keys()
+        .map(|id| {
+            let out_weight = weights.neighbors(*id).count() as f64;
+            (out_weight + in_weights.get(id).copied().unwrap_or_default()).powi(2)
+        })
+        .sum();
+    let variance = (n.powi(2) * s1 - n * s2 + 3.0 * total_weight.powi(2))
+        / ((n.powi(2) - 1.0) * total_weight.powi(2))
+        - expected.powi(2);
+
+    Some(MoransI {
+        moran_i,
+        expected,
+        variance,
+        z_score: (moran_i - expected) / variance.sqrt(),
+        count,
+    })
+}
+
+/// Gi* for every geo id with a value, counting each geo id as its own neighbor
+pub fn getis_ord(values: &[Simple], neighbors: &[Neighbor]) -> Vec<HotSpot> {
+    let weights = Weights::new(values, neighbors);
+    let count = weights.values.len();
+    if count < 2 {
+        return vec![];
+    }
+    let n = count as f64;
+    let mean = weights.mean();
+    let deviation =
+        (weights.values.values().map(|x| x.powi(2)).sum::<f64>() / n - mean.powi(2)).sqrt();
+
+    weights
+        .values
+        .iter()
+        .filter_map(|(&id, &value)| {
+            let sum = value
+                + weights
+                    .neighbors(id)
+                    .map(|j| weights.values[j])
+                    .sum::<f64>();
+            let k = 1.0 + weights.neighbors(id).count() as f64;
+            let denominator = deviation * ((n * k - k.powi(2)) / (n - 1.0)).sqrt();
+            if denominator == 0.0 || !denominator.is_finite() {
+                return None;
+            }
+            Some(HotSpot {
+                id,
+                value,
+                z_score: (sum - mean * k) / denominator,
+            })
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// 4 geo ids in a row, with values rising along it
+    fn chain() -> (Vec<Simple>, Vec<Neighbor>) {
+        let values = (1..=4)
+            .map(|id| Simple {
+                id,
+                value: id as f64,
+            })
+            .collect();
+        let neighbors = [(1, 2), (2, 3), (3, 4)]
+            .iter()
+            .flat_map(|&(id, neighbor_id)| {
+                [
+                    Neighbor { id, neighbor_id },
+                    Neighbor {
+                        id: neighbor_id,
+                        neighbor_id: id,
+                    },
+                ]
+            })
+            .collect();
+        (values, neighbors)
+    }
+
+    #[test]
+    fn it_averages_neighbors() {
+        let (values, neighbors) = chain();
+        let averages = neighbor_averages(&values[..3], &neighbors);
+
+        assert_eq!(averages[0].neighbor_average, Some(2.0));
+        assert_eq!(averages[1].neighbor_average, Some(2.0));
+        // 4 has no value, so 3 only has 2 as a neighbor
+        assert_eq!(averages[2].neighbor_average, Some(2.0));
+        assert_eq!(averages[2].neighbors, 1);
+    }
+
+    #[test]
+    fn it_finds_positive_autocorrelation() {
+        let (values, neighbors) = chain();
+        let result = morans_i(&values, &neighbors).unwrap();
+
+        assert!((result.moran_i - 1.0 / 3.0).abs() < 1e-9);
+        assert!((result.expected + 1.0 / 3.0).abs() < 1e-9);
+        assert!(result.z_score > 0.0);
+        assert_eq!(result.count, 4);
+    }
+
+    #[test]
+    fn it_has_no_morans_i_without_neighbors() {
+        let (values, _) = chain();
+
+        assert_eq!(morans_i(&values, &[]), None);
+    }
+
+    #[test]
+    fn it_finds_hot_and_cold_spots() {
+        let (values, neighbors) = chain();
+        let hot_spots = getis_ord(&values, &neighbors);
+
+        assert_eq!(hot_spots.len(), 4);
+        assert!(hot_spots[0].z_score < 0.0);
+        assert!(hot_spots[3].z_score > 0.0);
+        assert!((hot_spots[0].z_score + hot_spots[3].z_score).abs() < 1e-9);
+    }
+}
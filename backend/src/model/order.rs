@@ -0,0 +1,61 @@
+use serde::Serialize;
+use std::collections::HashSet;
+
+/// How a new order differs from the rows it should renumber, which it must list exactly once
+/// each
+#[derive(Debug, Serialize, PartialEq)]
+pub struct Mismatch {
+    pub duplicated: Vec<i32>,
+    pub missing: Vec<i32>,
+    pub unknown: Vec<i32>,
+}
+
+/// Checks that ids in a new order are the existing ids, each once
+pub fn check(ordered: &[i32], existing: &[i32]) -> Result<(), Mismatch> {
+    let existing: HashSet<i32> = existing.iter().copied().collect();
+    let mut listed = HashSet::new();
+    let mut duplicated: Vec<i32> = ordered
+        .iter()
+        .copied()
+        .filter(|id| !listed.insert(*id))
+        .collect();
+    duplicated.sort_unstable();
+    duplicated.dedup();
+    let mut missing: Vec<i32> = existing.difference(&listed).copied().collect();
+    missing.sort_unstable();
+    let mut unknown: Vec<i32> = listed.difference(&existing).copied().collect();
+    unknown.sort_unstable();
+
+    if duplicated.is_empty() && missing.is_empty() && unknown.is_empty() {
+        Ok(())
+    } else {
+        Err(Mismatch {
+            duplicated,
+            missing,
+            unknown,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn it_accepts_every_id_once_in_any_order() {
+        assert_eq!(check(&[3, 1, 2], &[1, 2, 3]), Ok(()));
+        assert_eq!(check(&[], &[]), Ok(()));
+    }
+
+    #[test]
+    fn it_reports_duplicated_missing_and_unknown_ids() {
+        assert_eq!(
+            check(&[3, 3, 5, 1, 5], &[1, 2, 3, 4]),
+            Err(Mismatch {
+                duplicated: vec![3, 5],
+                missing: vec![2, 4],
+                unknown: vec![5],
+            })
+        );
+    }
+}
@@ -0,0 +1,34 @@
+use serde::Serialize;
+use sqlx::FromRow;
+
+/// Where the drafts differ from what the read-only server serves, by id
+#[derive(FromRow, Serialize, Debug, Default, PartialEq)]
+pub struct Changes {
+    pub map_visualizations: Vec<i32>,
+    pub data_categories: Vec<i32>,
+    /// Map visualizations added to, moved within or removed from categories
+    pub collections: Vec<i32>,
+}
+
+impl Changes {
+    pub fn is_empty(&self) -> bool {
+        self.map_visualizations.is_empty()
+            && self.data_categories.is_empty()
+            && self.collections.is_empty()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn any_change_is_a_change() {
+        assert!(Changes::default().is_empty());
+        assert!(!Changes {
+            collections: vec![3],
+            ..Changes::default()
+        }
+        .is_empty());
+    }
+}
@@ -0,0 +1,155 @@
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use sqlx::FromRow;
+use std::collections::BTreeSet;
+
+/// A version of a map visualization's settings, as its row in JSON
+#[derive(FromRow, Serialize, Debug)]
+pub struct Revision {
+    pub map_visualization: i32,
+    pub revision: i32,
+    pub editor_user: Option<i32>,
+    pub username: Option<String>,
+    pub created_at: DateTime<Utc>,
+    pub settings: Value,
+}
+
+/// A revision without its settings, with the fields changed since the revision before it
+#[derive(Serialize, Debug, PartialEq)]
+pub struct Summary {
+    pub revision: i32,
+    pub editor_user: Option<i32>,
+    pub username: Option<String>,
+    pub created_at: DateTime<Utc>,
+    pub changed: Vec<String>,
+}
+
+#[derive(Serialize, Debug, PartialEq)]
+pub struct FieldChange {
+    pub field: String,
+    pub from: Value,
+    pub to: Value,
+}
+
+#[derive(Deserialize)]
+pub struct DiffInfo {
+    pub from: i32,
+    /// Defaults to the latest revision
+    pub to: Option<i32>,
+}
+
+/// The fields that differ between two versions of settings, by name. Fields missing from one
+/// version are `null` in it.
+pub fn diff(from: &Value, to: &Value) -> Vec<FieldChange> {
+    let field = |settings: &Value, name: &str| settings.get(name).cloned().unwrap_or(Value::Null);
+    let names: BTreeSet<&String> = [from, to]
+        .iter()
+        .copied()
+        .filter_map(|settings| settings.as_object())
+        .flat_map(|settings| settings.keys())
+        .collect();
+    names
+        .into_iter()
+        .map(|name| FieldChange {
+            field: name.clone(),
+            from: field(from, name),
+            to: field(to, name),
+        })
+        .filter(|change| change.from != change.to)
+        .collect()
+}
+
+/// Summarizes revisions in order, newest first
+pub fn summaries(revisions: &[Revision]) -> Vec<Summary> {
+    let mut summaries: Vec<Summary> = revisions
+        .iter()
+        .enumerate()
+        .map(|(i, revision)| Summary {
+            revision: revision.revision,
+            editor_user: revision.editor_user,
+            username: revision.username.clone(),
+            created_at: revision.created_at,
+            changed: match i {
+                0 => vec![],
+                _ => diff(&revisions[i - 1].settings, &revision.settings)
+                    .into_iter()
+                    .map(|change| change.field)
+                    .collect(),
+            },
+        })
+        .collect();
+    summaries.reverse();
+    summaries
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn revision(revision: i32, settings: Value) -> Revision {
+        Revision {
+            map_visualization: 1,
+            revision,
+            editor_user: Some(1),
+            username: Some("curator".to_string()),
+            created_at: Utc::now(),
+            settings,
+        }
+    }
+
+    #[test]
+    fn it_diffs_fields() {
+        let from =
+            json!({"id": 1, "color_domain": [0.0, 1.0], "scale_type": 2, "legend_ticks": null});
+        let to = json!({"id": 1, "color_domain": [0.0, 2.0], "scale_type": 2, "legend_ticks": 5, "name": "Heat"});
+
+        assert_eq!(
+            diff(&from, &to),
+            vec![
+                FieldChange {
+                    field: "color_domain".to_string(),
+                    from: json!([0.0, 1.0]),
+                    to: json!([0.0, 2.0]),
+                },
+                FieldChange {
+                    field: "legend_ticks".to_string(),
+                    from: Value::Null,
+                    to: json!(5),
+                },
+                FieldChange {
+                    field: "name".to_string(),
+                    from: Value::Null,
+                    to: json!("Heat"),
+                },
+            ]
+        );
+        assert!(diff(&to, &to).is_empty());
+    }
+
+    #[test]
+    fn it_summarizes_changes_since_the_previous_revision() {
+        let revisions = vec![
+            revision(1, json!({"scale_type": 2, "legend_ticks": 5})),
+            revision(2, json!({"scale_type": 1, "legend_ticks": 5})),
+            revision(3, json!({"scale_type": 2, "legend_ticks": 3})),
+        ];
+
+        let changed: Vec<(i32, Vec<String>)> = summaries(&revisions)
+            .into_iter()
+            .map(|summary| (summary.revision, summary.changed))
+            .collect();
+        assert_eq!(
+            changed,
+            vec![
+                (
+                    3,
+                    vec!["legend_ticks".to_string(), "scale_type".to_string()]
+                ),
+                (2, vec!["scale_type".to_string()]),
+                (1, vec![]),
+            ]
+        );
+    }
+}
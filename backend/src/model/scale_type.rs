@@ -6,3 +6,9 @@ pub struct Type {
     pub id: i32,
     pub name: String,
 }
+
+pub const DIVERGING: i32 = 1;
+pub const SEQUENTIAL: i32 = 2;
+pub const DIVERGING_SYM_LOG: i32 = 3;
+pub const THRESHOLD: i32 = 4;
+pub const SEQUENTIAL_SQRT: i32 = 5;
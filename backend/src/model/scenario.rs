@@ -0,0 +1,17 @@
+use serde::{Deserialize, Serialize};
+use sqlx::FromRow;
+
+/// The scenario of data without one, like historical observations
+pub const OBSERVED: i32 = 1;
+
+/// A projection scenario, like SSP2-4.5 or RCP8.5
+#[derive(FromRow, Deserialize, Serialize, Debug, PartialEq)]
+pub struct Scenario {
+    pub id: i32,
+    pub name: String,
+    pub description: String,
+}
+
+pub fn observed() -> i32 {
+    OBSERVED
+}
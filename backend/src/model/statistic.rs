@@ -0,0 +1,17 @@
+use serde::{Deserialize, Serialize};
+use sqlx::FromRow;
+
+/// The statistic of data that is a single value rather than a summary of an ensemble
+pub const MEAN: i32 = 1;
+
+/// What a value summarizes, like the mean or a confidence bound
+#[derive(FromRow, Deserialize, Serialize, Debug, PartialEq)]
+pub struct Statistic {
+    pub id: i32,
+    pub name: String,
+    pub percentile: Option<f64>,
+}
+
+pub fn mean() -> i32 {
+    MEAN
+}
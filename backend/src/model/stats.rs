@@ -0,0 +1,201 @@
+use serde::Serialize;
+
+#[derive(Serialize, Debug, PartialEq)]
+pub struct Quantile {
+    pub quantile: f64,
+    pub value: f64,
+}
+
+/// A histogram bin, including its start and, for the last bin, its end
+#[derive(Serialize, Debug, PartialEq)]
+pub struct Bin {
+    pub start: f64,
+    pub end: f64,
+    pub count: usize,
+}
+
+/// The distribution of a slice. Everything but the counts is unset for an empty slice.
+#[derive(Serialize, Debug, PartialEq)]
+pub struct Stats {
+    pub count: usize,
+    pub zeros: usize,
+    /// Geo ids of the dataset's geography type without a value
+    pub missing: usize,
+    pub min: Option<f64>,
+    pub max: Option<f64>,
+    pub mean: Option<f64>,
+    pub median: Option<f64>,
+    /// The population standard deviation
+    pub std: Option<f64>,
+    pub quantiles: Vec<Quantile>,
+    pub histogram: Vec<Bin>,
+}
+
+/// The value at `q` between 0 and 1 of sorted values, interpolating between neighbors
+pub fn quantile(sorted: &[f64], q: f64) -> f64 {
+    let position = q.clamp(0.0, 1.0) * (sorted.len() - 1) as f64;
+    let lower = position.floor() as usize;
+    let upper = position.ceil() as usize;
+    sorted[lower] + (sorted[upper] - sorted[lower]) * (position - lower as f64)
+}
+
+/// Where `value` ranks among `others` from 0 to 1, the way `/percentile` ranks a geo id with
+/// `percent_rank()`: zeros rank 0 and count as a single value. With `invert`, lower values rank
+/// higher. `others` doesn't include `value` itself.
+pub fn percent_rank(value: f64, others: &[f64], invert: bool) -> f64 {
+    if value == 0.0 {
+        return 0.0;
+    }
+    let ranked = |other: f64| if invert { -other } else { other };
+    let nonzero: Vec<f64> = others
+        .iter()
+        .copied()
+        .filter(|&other| other != 0.0)
+        .collect();
+    let below = nonzero
+        .iter()
+        .chain(&[0.0])
+        .filter(|&&other| ranked(other) < ranked(value))
+        .count();
+    below as f64 / (nonzero.len() + 1) as f64
+}
+
+fn histogram(sorted: &[f64], bins: usize) -> Vec<Bin> {
+    let (min, max) = (sorted[0], sorted[sorted.len() - 1]);
+    if bins == 0 {
+        return vec![];
+    }
+    if min == max {
+        return vec![Bin {
+            start: min,
+            end: max,
+            count: sorted.len(),
+        }];
+    }
+    let width = (max - min) / bins as f64;
+    let mut histogram: Vec<Bin> = (0..bins)
+        .map(|bin| Bin {
+            start: min + width * bin as f64,
+            end: if bin == bins - 1 {
+                max
+            } else {
+                min + width * (bin + 1) as f64
+            },
+            count: 0,
+        })
+        .collect();
+    for value in sorted {
+        let bin = (((value - min) / width) as usize).min(bins - 1);
+        histogram[bin].count += 1;
+    }
+    histogram
+}
+
+pub fn summarize(values: &[f64], geo_ids: usize, quantiles: &[f64], bins: usize) -> Stats {
+    let count = values.len();
+    let mut stats = Stats {
+        count,
+        zeros: values.iter().filter(|&&value| value == 0.0).count(),
+        missing: geo_ids.saturating_sub(count),
+        min: None,
+        max: None,
+        mean: None,
+        median: None,
+        std: None,
+        quantiles: vec![],
+        histogram: vec![],
+    };
+    if count == 0 {
+        return stats;
+    }
+
+    let mut sorted = values.to_vec();
+    sorted.sort_by(|a, b| a.total_cmp(b));
+    let mean = sorted.iter().sum::<f64>() / count as f64;
+    let variance = sorted.iter().map(|x| (x - mean).powi(2)).sum::<f64>() / count as f64;
+    stats.min = Some(sorted[0]);
+    stats.max = Some(sorted[count - 1]);
+    stats.mean = Some(mean);
+    stats.median = Some(quantile(&sorted, 0.5));
+    stats.std = Some(variance.sqrt());
+    stats.quantiles = quantiles
+        .iter()
+        .map(|&q| Quantile {
+            quantile: q,
+            value: quantile(&sorted, q),
+        })
+        .collect();
+    stats.histogram = histogram(&sorted, bins);
+    stats
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn it_interpolates_quantiles() {
+        let sorted = [1.0, 2.0, 3.0, 4.0];
+
+        assert_eq!(quantile(&sorted, 0.0), 1.0);
+        assert_eq!(quantile(&sorted, 0.5), 2.5);
+        assert_eq!(quantile(&sorted, 1.0), 4.0);
+        assert_eq!(quantile(&[7.0], 0.9), 7.0);
+    }
+
+    #[test]
+    fn it_ranks_like_percent_rank() {
+        let others = [0.0, 0.0, 1.0, 2.0, 5.0];
+
+        assert_eq!(percent_rank(3.0, &others, false), 0.75);
+        assert_eq!(percent_rank(3.0, &others, true), 0.25);
+        assert_eq!(percent_rank(0.0, &others, false), 0.0);
+        assert_eq!(percent_rank(-1.0, &others, false), 0.0);
+        assert_eq!(percent_rank(4.0, &[], false), 1.0);
+    }
+
+    #[test]
+    fn it_summarizes_a_slice() {
+        let stats = summarize(&[4.0, 0.0, 2.0, 0.0, 4.0], 8, &[0.25], 2);
+
+        assert_eq!(stats.count, 5);
+        assert_eq!(stats.zeros, 2);
+        assert_eq!(stats.missing, 3);
+        assert_eq!(stats.min, Some(0.0));
+        assert_eq!(stats.max, Some(4.0));
+        assert_eq!(stats.mean, Some(2.0));
+        assert_eq!(stats.median, Some(2.0));
+        assert_eq!(stats.std, Some(3.2f64.sqrt()));
+        assert_eq!(
+            stats.quantiles,
+            vec![Quantile {
+                quantile: 0.25,
+                value: 0.0
+            }]
+        );
+        assert_eq!(
+            stats.histogram,
+            vec![
+                Bin {
+                    start: 0.0,
+                    end: 2.0,
+                    count: 2
+                },
+                Bin {
+                    start: 2.0,
+                    end: 4.0,
+                    count: 3
+                }
+            ]
+        );
+    }
+
+    #[test]
+    fn it_only_counts_an_empty_slice() {
+        let stats = summarize(&[], 3, &[0.5], 10);
+
+        assert_eq!(stats.missing, 3);
+        assert_eq!(stats.median, None);
+        assert!(stats.histogram.is_empty());
+    }
+}
@@ -0,0 +1,39 @@
+use serde::Deserialize;
+use sqlx::FromRow;
+
+/// The maximum zoom level we generate tiles for
+pub const MAX_ZOOM: i32 = 22;
+
+#[derive(Deserialize, Debug, Clone, Copy)]
+pub struct Coordinates {
+    pub z: i32,
+    pub x: i32,
+    pub y: i32,
+}
+
+impl Coordinates {
+    pub fn is_valid(&self) -> bool {
+        if !(0..=MAX_ZOOM).contains(&self.z) {
+            return false;
+        }
+        let tiles_per_side = 1 << self.z;
+        (0..tiles_per_side).contains(&self.x) && (0..tiles_per_side).contains(&self.y)
+    }
+}
+
+/// A mapbox vector tile, generated from a specific version of a dataset
+#[derive(FromRow)]
+pub struct Tile {
+    pub dataset: i32,
+    pub version: i32,
+    pub tile: Vec<u8>,
+}
+
+#[test]
+fn test_tile_coordinates_are_in_range() {
+    assert!(Coordinates { z: 0, x: 0, y: 0 }.is_valid());
+    assert!(Coordinates { z: 3, x: 7, y: 7 }.is_valid());
+    assert!(!Coordinates { z: 3, x: 8, y: 0 }.is_valid());
+    assert!(!Coordinates { z: -1, x: 0, y: 0 }.is_valid());
+    assert!(!Coordinates { z: 23, x: 0, y: 0 }.is_valid());
+}
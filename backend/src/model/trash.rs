@@ -0,0 +1,106 @@
+use super::deletion::Impact;
+use chrono::{DateTime, Duration, Utc};
+use derive_more::Display;
+use serde::{Deserialize, Serialize};
+
+/// How many days deleted items stay in the trash before purging removes them
+pub const RETENTION_DAYS: i64 = 30;
+
+/// The longest retention a purge can be given, to keep its cutoff a valid time
+const MAX_DAYS: i64 = 36_500;
+
+/// What deleting puts in the trash rather than removing
+#[derive(Deserialize, Serialize, Debug, Display, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum Kind {
+    #[display(fmt = "dataset")]
+    Dataset,
+    #[display(fmt = "data_source")]
+    DataSource,
+    #[display(fmt = "map_visualization")]
+    MapVisualization,
+    #[display(fmt = "data_category")]
+    DataCategory,
+}
+
+/// A deleted dataset, data source, map visualization or category
+#[derive(Serialize, Debug, Clone, PartialEq)]
+pub struct Item {
+    pub kind: Kind,
+    pub id: i32,
+    pub name: Option<String>,
+    pub deleted_at: DateTime<Utc>,
+}
+
+fn default_days() -> i64 {
+    RETENTION_DAYS
+}
+
+/// Which items to remove from the trash: those deleted more than `days` ago. With `dry_run` a
+/// purge only lists what it would remove. Data sources with data are kept unless `with_data`.
+#[derive(Deserialize)]
+pub struct Purge {
+    #[serde(default = "default_days")]
+    pub days: i64,
+    #[serde(default)]
+    pub dry_run: bool,
+    #[serde(default)]
+    pub with_data: bool,
+}
+
+impl Purge {
+    /// Items deleted before this are purged
+    pub fn cutoff(&self, now: DateTime<Utc>) -> DateTime<Utc> {
+        now - Duration::days(self.days.clamp(0, MAX_DAYS))
+    }
+}
+
+/// An item removed from the trash, with everything removed or changed along with it
+#[derive(Serialize, Debug)]
+pub struct Purged {
+    #[serde(flatten)]
+    pub item: Item,
+    pub impact: Impact,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::model::audit::Entity;
+    use chrono::TimeZone;
+
+    #[test]
+    fn it_purges_after_the_retention_period() {
+        let now = Utc.with_ymd_and_hms(2026, 10, 19, 12, 0, 0).unwrap();
+        let purge: Purge = serde_json::from_str("{}").unwrap();
+
+        assert_eq!(purge.days, RETENTION_DAYS);
+        assert!(!purge.dry_run);
+        assert!(!purge.with_data);
+        assert_eq!(
+            purge.cutoff(now),
+            Utc.with_ymd_and_hms(2026, 9, 19, 12, 0, 0).unwrap()
+        );
+        assert_eq!(Purge { days: -5, ..purge }.cutoff(now), now);
+    }
+
+    #[test]
+    fn it_names_kinds_like_their_entities() {
+        let kinds = [
+            Kind::Dataset,
+            Kind::DataSource,
+            Kind::MapVisualization,
+            Kind::DataCategory,
+        ];
+        for kind in kinds {
+            let entity: Entity =
+                serde_json::from_value(serde_json::Value::String(kind.to_string())).unwrap();
+            assert_eq!(kind.to_string(), entity.to_string());
+            assert_eq!(
+                serde_json::from_value::<Kind>(serde_json::Value::String(kind.to_string()))
+                    .unwrap(),
+                kind
+            );
+        }
+    }
+}
@@ -1,7 +1,10 @@
 use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, HashSet};
 use std::fmt;
 
-use super::{data_source, dataset};
+use super::data::SourceAndDate;
+use super::validation::FieldError;
+use super::{data, data_source, dataset, map_visualization, scenario, statistic};
 
 #[derive(Deserialize, Serialize, Debug, PartialEq)]
 pub enum Source {
@@ -14,6 +17,13 @@ pub struct Column {
     pub name: String,
 }
 
+/// Creates a map visualization of each uploaded dataset, with settings suggested from its data
+#[derive(Deserialize, Serialize, Debug, PartialEq)]
+pub struct NewMapVisualizations {
+    /// The data category to list them in. Without one, they're drafts.
+    pub category: Option<i32>,
+}
+
 #[derive(Deserialize, Serialize, Debug, PartialEq)]
 pub struct UploadMetadata {
     pub id_column: String,
@@ -21,6 +31,95 @@ pub struct UploadMetadata {
     pub geography_type: i32,
     pub source: Source,
     pub datasets: Vec<dataset::Json>,
+    /// The projection scenario of all the uploaded data. Defaults to observed.
+    #[serde(default = "scenario::observed")]
+    pub scenario: i32,
+    /// The statistic of all the uploaded data, like a confidence bound. Defaults to the mean.
+    #[serde(default = "statistic::mean")]
+    pub statistic: i32,
+    pub map_visualizations: Option<NewMapVisualizations>,
+}
+
+/// What an upload stored: the id of its data source, its datasets by column and their data
+#[derive(Debug)]
+pub struct Uploaded {
+    pub source: i32,
+    pub datasets: HashMap<String, dataset::Dataset>,
+    pub data: HashSet<data::Creator>,
+}
+
+/// Why an upload was rolled back
+#[derive(Debug, Serialize, PartialEq)]
+pub enum Rejected {
+    /// The existing data source to upload to is missing or in the trash
+    UnknownSource(i32),
+    /// Some data is for a column that isn't a dataset
+    UnmatchedData,
+    /// The settings suggested for a dataset's map visualization don't fit its data
+    InvalidMapVisualization {
+        column: String,
+        errors: Vec<FieldError>,
+    },
+}
+
+impl Uploaded {
+    /// The slices uploaded for a dataset, in order
+    pub fn slices(&self, dataset: i32) -> Vec<SourceAndDate> {
+        let slices: HashSet<_> = self
+            .data
+            .iter()
+            .filter(|row| row.dataset == dataset)
+            .map(|row| {
+                (
+                    row.source,
+                    row.scenario,
+                    row.statistic,
+                    row.start_date,
+                    row.end_date,
+                )
+            })
+            .collect();
+        let mut slices: Vec<_> = slices.into_iter().collect();
+        slices.sort();
+        slices
+            .into_iter()
+            .map(
+                |(source, scenario, statistic, start_date, end_date)| SourceAndDate {
+                    source,
+                    scenario,
+                    statistic,
+                    start_date,
+                    end_date,
+                },
+            )
+            .collect()
+    }
+
+    /// A map visualization of an uploaded dataset with suggested settings. Maps open on observed
+    /// data, so projections don't get default dates.
+    pub fn map_visualization(
+        &self,
+        dataset: &dataset::Dataset,
+        options: &NewMapVisualizations,
+    ) -> map_visualization::Creator {
+        let rows: Vec<&data::Creator> = self
+            .data
+            .iter()
+            .filter(|row| row.dataset == dataset.id)
+            .collect();
+        let values: Vec<f64> = rows.iter().map(|row| row.value).collect();
+        let latest = rows
+            .iter()
+            .filter(|row| row.scenario == scenario::OBSERVED)
+            .max_by_key(|row| (row.end_date, row.start_date));
+        map_visualization::Creator {
+            default_source: Some(self.source),
+            default_start_date: latest.map(|row| row.start_date),
+            default_end_date: latest.map(|row| row.end_date),
+            category: options.category,
+            ..map_visualization::Creator::suggested(dataset, &values)
+        }
+    }
 }
 
 impl fmt::Display for UploadMetadata {
@@ -72,6 +171,82 @@ fn test_parse_metadata() {
                 description: "this is the description".to_string(),
                 column: "POPESTIMATE".to_string(),
             }],
+            scenario: scenario::OBSERVED,
+            statistic: statistic::MEAN,
+            map_visualizations: None,
         }
     )
 }
+
+#[cfg(test)]
+fn uploaded() -> Uploaded {
+    use chrono::NaiveDate;
+
+    let row = |id: i64, scenario: i32, year: i32, value: f64| data::Creator {
+        id,
+        geography_type: 1,
+        source: 3,
+        scenario,
+        statistic: statistic::MEAN,
+        dataset: 5,
+        start_date: NaiveDate::from_ymd_opt(year, 1, 1).unwrap(),
+        end_date: NaiveDate::from_ymd_opt(year, 12, 31).unwrap(),
+        value,
+    };
+    Uploaded {
+        source: 3,
+        datasets: HashMap::from([(
+            "value".to_string(),
+            dataset::Dataset {
+                id: 5,
+                short_name: "value".to_string(),
+                name: "Value".to_string(),
+                description: "A value".to_string(),
+                geography_type: 1,
+                units: "%".to_string(),
+            },
+        )]),
+        data: HashSet::from([
+            row(1, scenario::OBSERVED, 2020, 1.0),
+            row(2, scenario::OBSERVED, 2020, 2.0),
+            row(1, scenario::OBSERVED, 2022, 3.0),
+            row(1, 2, 2050, 4.0),
+        ]),
+    }
+}
+
+#[test]
+fn test_uploaded_slices() {
+    let uploaded = uploaded();
+    let slices: Vec<(i32, i32)> = uploaded
+        .slices(5)
+        .iter()
+        .map(|slice| (slice.scenario, chrono::Datelike::year(&slice.start_date)))
+        .collect();
+    assert_eq!(
+        slices,
+        vec![
+            (scenario::OBSERVED, 2020),
+            (scenario::OBSERVED, 2022),
+            (2, 2050)
+        ]
+    );
+    assert!(uploaded.slices(6).is_empty());
+}
+
+#[test]
+fn test_suggested_map_visualization_fits_its_data() {
+    let uploaded = uploaded();
+    let dataset = &uploaded.datasets["value"];
+    let creator = uploaded.map_visualization(dataset, &NewMapVisualizations { category: Some(4) });
+    assert_eq!(creator.default_source, Some(3));
+    assert_eq!(
+        creator.default_start_date,
+        chrono::NaiveDate::from_ymd_opt(2022, 1, 1)
+    );
+    assert_eq!(creator.category, Some(4));
+    assert_eq!(
+        super::validation::validate(&creator.patch(), &uploaded.slices(dataset.id)),
+        vec![]
+    );
+}
@@ -0,0 +1,176 @@
+use argon2::password_hash::{PasswordHash, PasswordHasher, PasswordVerifier, SaltString};
+use argon2::Argon2;
+use chrono::{DateTime, Utc};
+use derive_more::Display;
+use rand::rngs::OsRng;
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use sqlx::FromRow;
+
+/// What an editor user may do, matching the `editor_role` table. Each role can do everything the
+/// roles before it can.
+#[derive(Deserialize, Serialize, Debug, Display, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+#[serde(rename_all = "snake_case")]
+pub enum Role {
+    #[display(fmt = "viewer")]
+    Viewer = 1,
+    #[display(fmt = "curator")]
+    Curator = 2,
+    #[display(fmt = "admin")]
+    Admin = 3,
+}
+
+impl Role {
+    pub fn from_id(id: i32) -> Option<Role> {
+        match id {
+            1 => Some(Role::Viewer),
+            2 => Some(Role::Curator),
+            3 => Some(Role::Admin),
+            _ => None,
+        }
+    }
+
+    pub fn id(&self) -> i32 {
+        *self as i32
+    }
+}
+
+#[derive(FromRow, Deserialize, Serialize, Debug, Clone, PartialEq)]
+pub struct User {
+    pub id: i32,
+    pub username: String,
+    pub role: i32,
+}
+
+impl User {
+    /// Whether the user has at least a role. Unknown roles have none.
+    pub fn can(&self, role: Role) -> bool {
+        Role::from_id(self.role).map_or(false, |own| own >= role)
+    }
+}
+
+/// A user with their password hash, for logging in
+#[derive(FromRow)]
+pub struct Login {
+    pub id: i32,
+    pub username: String,
+    pub role: i32,
+    pub password_hash: Option<String>,
+}
+
+impl Login {
+    pub fn user(&self) -> User {
+        User {
+            id: self.id,
+            username: self.username.clone(),
+            role: self.role,
+        }
+    }
+}
+
+#[derive(Deserialize)]
+pub struct Credentials {
+    pub username: String,
+    pub password: String,
+}
+
+#[derive(Deserialize)]
+pub struct Creator {
+    pub username: String,
+    /// Users without a password can only use API tokens
+    pub password: Option<String>,
+    pub role: Role,
+}
+
+#[derive(Deserialize)]
+pub struct Patch {
+    pub id: i32,
+    pub role: Role,
+    /// Keeps the current password if not set
+    pub password: Option<String>,
+}
+
+/// A token without its secret, for listing
+#[derive(FromRow, Serialize, Debug)]
+pub struct Token {
+    pub id: i32,
+    pub name: String,
+    pub created_at: DateTime<Utc>,
+    pub expires_at: Option<DateTime<Utc>>,
+    pub last_used_at: Option<DateTime<Utc>>,
+}
+
+/// A token's secret, only shown when it is created
+#[derive(Serialize, Debug)]
+pub struct NewToken {
+    pub id: i32,
+    pub token: String,
+    pub expires_at: Option<DateTime<Utc>>,
+}
+
+pub fn hash_password(password: &str) -> Result<String, argon2::password_hash::Error> {
+    let salt = SaltString::generate(&mut OsRng);
+    Ok(Argon2::default()
+        .hash_password(password.as_bytes(), &salt)?
+        .to_string())
+}
+
+pub fn verify_password(password: &str, hash: &str) -> bool {
+    PasswordHash::new(hash).map_or(false, |hash| {
+        Argon2::default()
+            .verify_password(password.as_bytes(), &hash)
+            .is_ok()
+    })
+}
+
+/// A random bearer token, as 64 hex characters
+pub fn generate_token() -> String {
+    let bytes: [u8; 32] = rand::random();
+    bytes.iter().map(|byte| format!("{byte:02x}")).collect()
+}
+
+/// Tokens are stored hashed, so a leaked database doesn't leak working tokens
+pub fn hash_token(token: &str) -> String {
+    format!("{:x}", Sha256::digest(token.as_bytes()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn user(role: i32) -> User {
+        User {
+            id: 1,
+            username: "editor".to_string(),
+            role,
+        }
+    }
+
+    #[test]
+    fn roles_include_lower_roles() {
+        assert!(user(3).can(Role::Curator));
+        assert!(user(2).can(Role::Curator));
+        assert!(!user(1).can(Role::Curator));
+        assert!(!user(4).can(Role::Viewer));
+        assert_eq!(Role::from_id(Role::Admin.id()), Some(Role::Admin));
+    }
+
+    #[test]
+    fn it_verifies_hashed_passwords() {
+        let hash = hash_password("correct horse").unwrap();
+
+        assert!(verify_password("correct horse", &hash));
+        assert!(!verify_password("battery staple", &hash));
+        assert!(!verify_password("correct horse", "not a hash"));
+    }
+
+    #[test]
+    fn it_hashes_tokens() {
+        let token = generate_token();
+
+        assert_eq!(token.len(), 64);
+        assert_ne!(token, generate_token());
+        assert_eq!(hash_token(&token), hash_token(&token));
+        assert_eq!(hash_token(&token).len(), 64);
+    }
+}
@@ -0,0 +1,528 @@
+use super::classification::Method;
+use super::data::SourceAndDate;
+use super::map_visualization::Patch;
+use super::scale_type;
+use super::scenario;
+use chrono::NaiveDate;
+use derive_more::Display;
+use serde::Serialize;
+
+pub const MAX_DECIMALS: i16 = 20;
+pub const MAX_CLASSES: i16 = 20;
+
+/// Why a field is invalid
+#[derive(Debug, Display, Serialize, PartialEq)]
+#[serde(tag = "name", content = "info")]
+pub enum Invalid {
+    #[display(fmt = "The dataset has no data for it")]
+    NoData,
+    #[display(fmt = "Needs {_0} too")]
+    Needs(&'static str),
+    #[display(fmt = "Is needed for {_0}")]
+    NeededFor(&'static str),
+    #[display(fmt = "Starts after it ends")]
+    Reversed,
+    #[display(fmt = "Needs {expected} values for the scale type, not {actual}")]
+    WrongLength { expected: String, actual: usize },
+    #[display(fmt = "Isn't sorted from low to high")]
+    Unsorted,
+    #[display(fmt = "Has values that aren't numbers")]
+    NotFinite,
+    #[display(fmt = "{_0} isn't a color")]
+    NotAColor(String),
+    #[display(fmt = "Isn't between {min} and {max}")]
+    OutOfRange { min: i16, max: i16 },
+}
+
+#[derive(Debug, Serialize, PartialEq)]
+pub struct FieldError {
+    pub field: &'static str,
+    pub error: Invalid,
+    pub message: String,
+}
+
+impl FieldError {
+    pub fn new(field: &'static str, error: Invalid) -> Self {
+        FieldError {
+            field,
+            message: error.to_string(),
+            error,
+        }
+    }
+}
+
+/// CSS color keywords
+const COLOR_NAMES: &str = "\
+    aliceblue antiquewhite aqua aquamarine azure beige bisque black blanchedalmond blue \
+    blueviolet brown burlywood cadetblue chartreuse chocolate coral cornflowerblue cornsilk \
+    crimson cyan darkblue darkcyan darkgoldenrod darkgray darkgreen darkgrey darkkhaki \
+    darkmagenta darkolivegreen darkorange darkorchid darkred darksalmon darkseagreen \
+    darkslateblue darkslategray darkslategrey darkturquoise darkviolet deeppink deepskyblue \
+    dimgray dimgrey dodgerblue firebrick floralwhite forestgreen fuchsia gainsboro \
+    ghostwhite gold goldenrod gray green greenyellow grey honeydew hotpink indianred indigo \
+    ivory khaki lavender lavenderblush lawngreen lemonchiffon lightblue lightcoral lightcyan \
+    lightgoldenrodyellow lightgray lightgreen lightgrey lightpink lightsalmon lightseagreen \
+    lightskyblue lightslategray lightslategrey lightsteelblue lightyellow lime limegreen \
+    linen magenta maroon mediumaquamarine mediumblue mediumorchid mediumpurple \
+    mediumseagreen mediumslateblue mediumspringgreen mediumturquoise mediumvioletred \
+    midnightblue mintcream mistyrose moccasin navajowhite navy oldlace olive olivedrab \
+    orange orangered orchid palegoldenrod palegreen paleturquoise palevioletred papayawhip \
+    peachpuff peru pink plum powderblue purple rebeccapurple red rosybrown royalblue \
+    saddlebrown salmon sandybrown seagreen seashell sienna silver skyblue slateblue \
+    slategray slategrey snow springgreen steelblue tan teal thistle tomato turquoise violet \
+    wheat white whitesmoke yellow yellowgreen transparent";
+
+/// The units a color function's arguments can have, as percentages or hue angles. `grad` comes
+/// before `rad` so it is stripped whole.
+const UNITS: [&str; 5] = ["%", "deg", "grad", "rad", "turn"];
+
+/// Whether a string is a CSS color: a hex color, an `rgb()`, `rgba()`, `hsl()` or `hsla()`
+/// function, or a color keyword
+pub fn is_color(color: &str) -> bool {
+    let color = color.trim().to_ascii_lowercase();
+    if let Some(hex) = color.strip_prefix('#') {
+        return [3, 4, 6, 8].contains(&hex.len()) && hex.chars().all(|c| c.is_ascii_hexdigit());
+    }
+    let function = ["rgba(", "rgb(", "hsla(", "hsl("]
+        .iter()
+        .find_map(|name| color.strip_prefix(name));
+    if let Some(arguments) = function.and_then(|rest| rest.strip_suffix(')')) {
+        let arguments: Vec<&str> = arguments
+            .split(|c: char| c == ',' || c == '/' || c.is_whitespace())
+            .filter(|argument| !argument.is_empty())
+            .collect();
+        return (arguments.len() == 3 || arguments.len() == 4)
+            && arguments.iter().all(|argument| {
+                let number = UNITS
+                    .iter()
+                    .find_map(|unit| argument.strip_suffix(unit))
+                    .unwrap_or(argument);
+                number.parse::<f64>().map_or(false, f64::is_finite)
+            });
+    }
+    COLOR_NAMES.split_whitespace().any(|name| name == color)
+}
+
+/// The number of color domain values a scale type needs, as the smallest and largest
+fn color_domain_length(scale_type: i32) -> (usize, Option<usize>) {
+    match scale_type {
+        scale_type::DIVERGING | scale_type::DIVERGING_SYM_LOG => (3, Some(3)),
+        scale_type::THRESHOLD => (1, None),
+        _ => (2, Some(2)),
+    }
+}
+
+fn domain(errors: &mut Vec<FieldError>, field: &'static str, values: &[f64]) {
+    if values.iter().any(|value| !value.is_finite()) {
+        errors.push(FieldError::new(field, Invalid::NotFinite));
+    } else if values.windows(2).any(|pair| pair[0] > pair[1]) {
+        errors.push(FieldError::new(field, Invalid::Unsorted));
+    }
+}
+
+/// Both or neither dates of a range, in order
+fn date_range(
+    errors: &mut Vec<FieldError>,
+    (start_field, start_date): (&'static str, Option<NaiveDate>),
+    (end_field, end_date): (&'static str, Option<NaiveDate>),
+) -> Option<(NaiveDate, NaiveDate)> {
+    match (start_date, end_date) {
+        (Some(start_date), Some(end_date)) if start_date > end_date => {
+            errors.push(FieldError::new(start_field, Invalid::Reversed));
+            None
+        }
+        (Some(start_date), Some(end_date)) => Some((start_date, end_date)),
+        (Some(_), None) => {
+            errors.push(FieldError::new(start_field, Invalid::Needs(end_field)));
+            None
+        }
+        (None, Some(_)) => {
+            errors.push(FieldError::new(end_field, Invalid::Needs(start_field)));
+            None
+        }
+        (None, None) => None,
+    }
+}
+
+fn in_range(errors: &mut Vec<FieldError>, field: &'static str, value: i16, min: i16, max: i16) {
+    if value < min || value > max {
+        errors.push(FieldError::new(field, Invalid::OutOfRange { min, max }));
+    }
+}
+
+/// Checks one side of a delta map visualization, which needs a source and dates with data
+fn delta_slice(
+    errors: &mut Vec<FieldError>,
+    slices: &[SourceAndDate],
+    source: (&'static str, Option<i32>),
+    scenario: Option<i32>,
+    start_date: (&'static str, Option<NaiveDate>),
+    end_date: (&'static str, Option<NaiveDate>),
+) {
+    let dates = date_range(errors, start_date, end_date);
+    let (source_field, source) = source;
+    let scenario = scenario.unwrap_or(scenario::OBSERVED);
+    match (source, dates) {
+        (None, _) => errors.push(FieldError::new(
+            source_field,
+            Invalid::NeededFor("delta_operation"),
+        )),
+        (Some(_), None) if start_date.1.is_none() && end_date.1.is_none() => errors.push(
+            FieldError::new(start_date.0, Invalid::NeededFor("delta_operation")),
+        ),
+        (Some(source), Some((start_date, end_date))) => {
+            let has_data = slices.iter().any(|slice| {
+                slice.source == source
+                    && slice.scenario == scenario
+                    && slice.start_date == start_date
+                    && slice.end_date == end_date
+            });
+            if !has_data {
+                errors.push(FieldError::new(source_field, Invalid::NoData));
+            }
+        }
+        _ => {}
+    }
+}
+
+/// The problems with a patch, given the slices of its dataset. A valid patch has none.
+pub fn validate(patch: &Patch, slices: &[SourceAndDate]) -> Vec<FieldError> {
+    let mut errors = vec![];
+
+    let default_dates = date_range(
+        &mut errors,
+        ("default_start_date", patch.default_start_date),
+        ("default_end_date", patch.default_end_date),
+    );
+    if let Some(source) = patch.default_source {
+        if !slices.iter().any(|slice| slice.source == source) {
+            errors.push(FieldError::new("default_source", Invalid::NoData));
+        }
+    }
+    if let Some((start_date, end_date)) = default_dates {
+        let has_data = slices.iter().any(|slice| {
+            slice.start_date == start_date
+                && slice.end_date == end_date
+                && patch
+                    .default_source
+                    .map_or(true, |source| source == slice.source)
+        });
+        if !has_data {
+            errors.push(FieldError::new("default_start_date", Invalid::NoData));
+        }
+    }
+
+    domain(&mut errors, "color_domain", &patch.color_domain);
+    domain(&mut errors, "pdf_domain", &patch.pdf_domain);
+    let (min, max) = color_domain_length(patch.scale_type);
+    let length = patch.color_domain.len();
+    // An empty color domain is filled in from the data
+    if length > 0 && (length < min || max.map_or(false, |max| length > max)) {
+        errors.push(FieldError::new(
+            "color_domain",
+            Invalid::WrongLength {
+                expected: match max {
+                    Some(max) if max == min => min.to_string(),
+                    Some(max) => format!("{min} to {max}"),
+                    None => format!("at least {min}"),
+                },
+                actual: length,
+            },
+        ));
+    }
+    if patch.classification_method.and_then(Method::from_id) == Some(Method::Manual) && length == 0
+    {
+        errors.push(FieldError::new(
+            "classification_method",
+            Invalid::Needs("color_domain"),
+        ));
+    }
+
+    if !is_color(&patch.bubble_color) {
+        errors.push(FieldError::new(
+            "bubble_color",
+            Invalid::NotAColor(patch.bubble_color.clone()),
+        ));
+    }
+
+    in_range(&mut errors, "decimals", patch.decimals, 0, MAX_DECIMALS);
+    if let Some(legend_decimals) = patch.legend_decimals {
+        in_range(
+            &mut errors,
+            "legend_decimals",
+            legend_decimals,
+            0,
+            MAX_DECIMALS,
+        );
+    }
+    if let Some(legend_ticks) = patch.legend_ticks {
+        in_range(&mut errors, "legend_ticks", legend_ticks, 1, i16::MAX);
+    }
+    in_range(
+        &mut errors,
+        "classification_classes",
+        patch.classification_classes,
+        1,
+        MAX_CLASSES,
+    );
+
+    if patch.delta_operation.is_some() {
+        delta_slice(
+            &mut errors,
+            slices,
+            ("delta_from_source", patch.delta_from_source),
+            patch.delta_from_scenario,
+            ("delta_from_start_date", patch.delta_from_start_date),
+            ("delta_from_end_date", patch.delta_from_end_date),
+        );
+        delta_slice(
+            &mut errors,
+            slices,
+            ("delta_to_source", patch.delta_to_source),
+            patch.delta_to_scenario,
+            ("delta_to_start_date", patch.delta_to_start_date),
+            ("delta_to_end_date", patch.delta_to_end_date),
+        );
+    }
+    date_range(
+        &mut errors,
+        ("bivariate_start_date", patch.bivariate_start_date),
+        ("bivariate_end_date", patch.bivariate_end_date),
+    );
+
+    errors
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::model::statistic;
+
+    fn date(year: i32) -> Option<NaiveDate> {
+        NaiveDate::from_ymd_opt(year, 1, 1)
+    }
+
+    fn slices() -> Vec<SourceAndDate> {
+        [(1, 2020), (1, 2050), (2, 2020)]
+            .iter()
+            .map(|&(source, year)| SourceAndDate {
+                source,
+                scenario: scenario::OBSERVED,
+                statistic: statistic::MEAN,
+                start_date: date(year).unwrap(),
+                end_date: date(year + 1).unwrap(),
+            })
+            .collect()
+    }
+
+    fn patch() -> Patch {
+        Patch {
+            id: 1,
+            dataset: 1,
+            map_type: 1,
+            subcategory: None,
+            data_tab: None,
+            name: None,
+            legend_ticks: Some(5),
+            color_palette_id: 1,
+            reverse_scale: false,
+            invert_normalized: false,
+            scale_type: scale_type::SEQUENTIAL,
+            show_pdf: false,
+            default_start_date: date(2020),
+            default_end_date: date(2021),
+            default_source: Some(1),
+            formatter_type: 3,
+            legend_formatter_type: None,
+            decimals: 1,
+            legend_decimals: None,
+            color_domain: vec![0.0, 1.0],
+            pdf_domain: vec![0.0, 10.0],
+            bubble_color: "rgb(34, 139, 69)".to_string(),
+            delta_operation: None,
+            delta_from_source: None,
+            delta_from_start_date: None,
+            delta_from_end_date: None,
+            delta_to_source: None,
+            delta_to_start_date: None,
+            delta_to_end_date: None,
+            delta_from_scenario: None,
+            delta_to_scenario: None,
+            bivariate_dataset: None,
+            bivariate_source: None,
+            bivariate_start_date: None,
+            bivariate_end_date: None,
+            bivariate_scenario: None,
+            bivariate_statistic: None,
+            classification_method: None,
+            classification_classes: 5,
+        }
+    }
+
+    fn fields(errors: Vec<FieldError>) -> Vec<(&'static str, Invalid)> {
+        errors
+            .into_iter()
+            .map(|error| (error.field, error.error))
+            .collect()
+    }
+
+    #[test]
+    fn a_valid_patch_has_no_errors() {
+        assert_eq!(validate(&patch(), &slices()), vec![]);
+    }
+
+    #[test]
+    fn it_checks_default_slices_have_data() {
+        let patch = Patch {
+            default_source: Some(3),
+            default_start_date: date(2050),
+            default_end_date: date(2051),
+            ..patch()
+        };
+
+        assert_eq!(
+            fields(validate(&patch, &slices())),
+            vec![
+                ("default_source", Invalid::NoData),
+                ("default_start_date", Invalid::NoData),
+            ]
+        );
+    }
+
+    #[test]
+    fn it_checks_date_ranges() {
+        let patch = Patch {
+            default_end_date: None,
+            bivariate_start_date: date(2030),
+            bivariate_end_date: date(2020),
+            ..patch()
+        };
+
+        assert_eq!(
+            fields(validate(&patch, &slices())),
+            vec![
+                ("default_start_date", Invalid::Needs("default_end_date")),
+                ("bivariate_start_date", Invalid::Reversed),
+            ]
+        );
+    }
+
+    #[test]
+    fn it_checks_domains() {
+        let patch = Patch {
+            scale_type: scale_type::DIVERGING,
+            pdf_domain: vec![10.0, 0.0],
+            color_domain: vec![0.0, f64::NAN],
+            ..patch()
+        };
+
+        assert_eq!(
+            fields(validate(&patch, &slices())),
+            vec![
+                ("color_domain", Invalid::NotFinite),
+                ("pdf_domain", Invalid::Unsorted),
+                (
+                    "color_domain",
+                    Invalid::WrongLength {
+                        expected: "3".to_string(),
+                        actual: 2
+                    }
+                ),
+            ]
+        );
+
+        let patch = Patch {
+            scale_type: scale_type::THRESHOLD,
+            color_domain: vec![0.1, 0.5, 0.9],
+            ..patch
+        };
+        assert_eq!(
+            fields(validate(&patch, &slices())),
+            vec![("pdf_domain", Invalid::Unsorted)]
+        );
+    }
+
+    #[test]
+    fn it_checks_delta_slices() {
+        let patch = Patch {
+            delta_operation: Some(1),
+            delta_from_source: Some(1),
+            delta_from_start_date: date(2020),
+            delta_from_end_date: date(2021),
+            delta_to_source: Some(2),
+            delta_to_start_date: date(2050),
+            delta_to_end_date: date(2051),
+            ..patch()
+        };
+
+        assert_eq!(
+            fields(validate(&patch, &slices())),
+            vec![("delta_to_source", Invalid::NoData)]
+        );
+
+        let patch = Patch {
+            delta_to_source: None,
+            ..patch
+        };
+        assert_eq!(
+            fields(validate(&patch, &slices())),
+            vec![("delta_to_source", Invalid::NeededFor("delta_operation"))]
+        );
+    }
+
+    #[test]
+    fn it_checks_colors() {
+        for color in [
+            "#000",
+            "#22Aa45",
+            "#00000080",
+            "rgb(34, 139, 69)",
+            "rgba(0 0 0 / 50%)",
+            "hsl(120deg, 50%, 50%)",
+            "hsl(0.5turn 50% 50%)",
+            "Black",
+        ] {
+            assert!(is_color(color), "{color}");
+        }
+        for color in [
+            "",
+            "#00",
+            "#ggg",
+            "rgb(1, 2)",
+            "rgb(a, b, c)",
+            "rgb(1e, 2d, 3g)",
+            "hsl(120ee, 50%, 50%)",
+            "rgb(inf, NaN, 1)",
+            "blackish",
+        ] {
+            assert!(!is_color(color), "{color}");
+        }
+
+        let patch = Patch {
+            bubble_color: "green-ish".to_string(),
+            ..patch()
+        };
+        assert_eq!(
+            fields(validate(&patch, &slices())),
+            vec![("bubble_color", Invalid::NotAColor("green-ish".to_string()))]
+        );
+    }
+
+    #[test]
+    fn it_checks_numbers_are_in_range() {
+        let patch = Patch {
+            decimals: -1,
+            legend_ticks: Some(0),
+            classification_classes: 50,
+            ..patch()
+        };
+
+        assert_eq!(
+            fields(validate(&patch, &slices()))
+                .into_iter()
+                .map(|(field, _)| field)
+                .collect::<Vec<_>>(),
+            vec!["decimals", "legend_ticks", "classification_classes"]
+        );
+    }
+}
@@ -3,6 +3,8 @@ import Home from './Home'
 import DataSourceEditor from './editor/DataSourceEditor'
 import DatasetEditor from './editor/DatasetEditor'
 import Editor from './editor/Editor'
+import { RequireLogin } from './editor/Login'
+import { ReadDrafts } from './editor/ReadDrafts'
 import ReportCard from './report-card/ReportCard'
 import Uploader from './uploader/Uploader'
 
@@ -10,13 +12,78 @@ function App() {
     return (
         <Router>
             <Routes>
-                <Route path="/uploader" element={<Uploader />} />
-                <Route path="/editor" element={<Editor />} />
+                <Route
+                    path="/uploader"
+                    element={
+                        <RequireLogin>
+                            <ReadDrafts>
+                                <Uploader />
+                            </ReadDrafts>
+                        </RequireLogin>
+                    }
+                />
+                <Route
+                    path="/editor"
+                    element={
+                        <RequireLogin>
+                            <ReadDrafts>
+                                <Editor />
+                            </ReadDrafts>
+                        </RequireLogin>
+                    }
+                />
                 <Route path="/report-card/:category" element={<ReportCard />} />
                 <Route path="/report-card/:category/:countyId" element={<ReportCard />} />
-                <Route path="/editor/:tabId" element={<Editor />} />
-                <Route path="/dataset-editor" element={<DatasetEditor />} />
-                <Route path="/data-source-editor" element={<DataSourceEditor />} />
+                <Route
+                    path="/editor/:tabId"
+                    element={
+                        <RequireLogin>
+                            <ReadDrafts>
+                                <Editor />
+                            </ReadDrafts>
+                        </RequireLogin>
+                    }
+                />
+                <Route
+                    path="/dataset-editor"
+                    element={
+                        <RequireLogin>
+                            <ReadDrafts>
+                                <DatasetEditor />
+                            </ReadDrafts>
+                        </RequireLogin>
+                    }
+                />
+                <Route
+                    path="/data-source-editor"
+                    element={
+                        <RequireLogin>
+                            <ReadDrafts>
+                                <DataSourceEditor />
+                            </ReadDrafts>
+                        </RequireLogin>
+                    }
+                />
+                <Route
+                    path="/preview"
+                    element={
+                        <RequireLogin>
+                            <ReadDrafts>
+                                <Home root="/preview/" />
+                            </ReadDrafts>
+                        </RequireLogin>
+                    }
+                />
+                <Route
+                    path="/preview/:tabId"
+                    element={
+                        <RequireLogin>
+                            <ReadDrafts>
+                                <Home root="/preview/" />
+                            </ReadDrafts>
+                        </RequireLogin>
+                    }
+                />
                 <Route path="/" element={<Home />} />
                 <Route path="/:tabId" element={<Home />} />
                 <Route path="/:tabId/:region" element={<Home />} />
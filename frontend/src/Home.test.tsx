@@ -60,6 +60,11 @@ const MAP_VISUALIZATIONS: { [key: string]: { [key: string]: MapVisualizationJson
             order: 1,
             geography_type: 1,
             bubble_color: '#000000',
+            delta: null,
+            bivariate: null,
+            classification_method: null,
+            classification_classes: 5,
+            breaks: [],
         },
     },
 }
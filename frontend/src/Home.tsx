@@ -47,7 +47,8 @@ const FILE_FOR: Record<Region, TopoJsonFile> = {
     EssexMassachusetts: 'essex-ma-towns.json',
 }
 
-function Home() {
+/** The maps, with `root` the path their tabs are under */
+function Home({ root = '/' }: { root?: string }) {
     const dispatch = useDispatch()
     const region = useSelector((state: RootState) => state.app.region)
     const { data: tabs, isLoading: tabsLoading } = useGetTabsQuery(false)
@@ -90,6 +91,7 @@ function Home() {
                         tabs={displayedTabs}
                         onTabClick={(tab) => dispatch(setTab(tab))}
                         selectedTabId={tab?.id}
+                        root={root}
                     />
                 ) : (
                     <EmptyNavigation />
@@ -1,4 +1,10 @@
-import { createApi, fetchBaseQuery } from '@reduxjs/toolkit/query/react'
+import {
+    BaseQueryFn,
+    FetchArgs,
+    FetchBaseQueryError,
+    createApi,
+    fetchBaseQuery,
+} from '@reduxjs/toolkit/query/react'
 import { DSVParsedArray, autoType, csv as loadCsv } from 'd3'
 import { Map } from 'immutable'
 import { Dataset, DatasetPatch } from './Dataset'
@@ -12,8 +18,11 @@ import {
     fetchMapVisualization,
     fetchMapVisualizations,
     fetchMapVisualizationsByDataset,
+    patchToJson,
 } from './MapVisualization'
 import { GeoId } from './appSlice'
+import { readInit, readUrl, readsDrafts } from './editor/drafts'
+import { authorize, setToken } from './editor/token'
 import UploadData from './uploader/UploadData'
 
 export type DatasetId = number
@@ -40,6 +49,17 @@ export type NewTab = {
     name: string
     normalized: boolean
 }
+export type NewMapVisualization = {
+    dataset: DatasetId
+    map_type: number
+    color_palette: number
+    scale_type: number
+    formatter_type: number
+}
+export type Credentials = {
+    username: string
+    password: string
+}
 export type DataSource = {
     id: number
     name: string
@@ -198,12 +218,47 @@ export type Data2 = [GeoId, number][]
 const transformData = (dataByMapId: [number, DSVParsedArray<CsvRow>][]): Map<number, Data2> =>
     Map(dataByMapId.map(([mapId, data]) => [mapId, data.map((row) => [row.id, row.value])]))
 
+const fetchWithToken = fetchBaseQuery({ baseUrl: '/api/', prepareHeaders: authorize })
+
+// the editor server serves the read-only endpoints too, over the drafts
+const draftArgs = (args: string | FetchArgs): string | FetchArgs => {
+    const url = typeof args === 'string' ? args : args.url
+    if (!readsDrafts() || url.startsWith('editor/')) {
+        return args
+    }
+    return typeof args === 'string' ? `editor/${args}` : { ...args, url: `editor/${url}` }
+}
+
+// an expired or revoked token is dropped so the editor asks to log in again
+const baseQuery: BaseQueryFn<string | FetchArgs, unknown, FetchBaseQueryError> = async (
+    args,
+    api,
+    extraOptions
+) => {
+    const result = await fetchWithToken(draftArgs(args), api, extraOptions)
+    if (result.error?.status === 401) {
+        setToken(null)
+    }
+    return result
+}
+
 export const mapApi = createApi({
     reducerPath: 'mapApi',
     keepUnusedDataFor: 5 * 60, // 5 minutes
-    baseQuery: fetchBaseQuery({ baseUrl: '/api/' }),
+    baseQuery,
     tagTypes: ['MapVisualization', 'Dataset', 'Subcategory', 'Tab', 'DataSource'],
     endpoints: (builder) => ({
+        login: builder.mutation<undefined, Credentials>({
+            query: (credentials) => ({
+                url: 'editor/login',
+                method: 'POST',
+                body: credentials,
+            }),
+            transformResponse: ({ token }: { token: string }) => {
+                setToken(token)
+                return undefined
+            },
+        }),
         getCounties: builder.query<Record<GeoId, County>, undefined>({
             query: () => 'county',
         }),
@@ -213,7 +268,10 @@ export const mapApi = createApi({
         getPercentiles: builder.query<Percentiles, PercentileQueryParams>({
             queryFn: ({ geoId, category, geographyType }) => {
                 const loadingCsv = loadCsv<CountyCsvRow>(
-                    `/api/percentile?geo_id=${geoId}&category=${category}&geography_type=${geographyType}`,
+                    readUrl(
+                        `percentile?geo_id=${geoId}&category=${category}&geography_type=${geographyType}`
+                    ),
+                    readInit(),
                     autoType
                 )
                 return loadingCsv.then(transformCountySummary).then(
@@ -225,7 +283,10 @@ export const mapApi = createApi({
         getStatePercentiles: builder.query<Percentiles, PercentileQueryParams>({
             queryFn: ({ geoId, category, geographyType }) => {
                 const loadingCsv = loadCsv<CountyCsvRow>(
-                    `/api/state_percentile?geo_id=${geoId}&category=${category}&geography_type=${geographyType}`,
+                    readUrl(
+                        `state_percentile?geo_id=${geoId}&category=${category}&geography_type=${geographyType}`
+                    ),
+                    readInit(),
                     autoType
                 )
                 return loadingCsv.then(transformCountySummary).then(
@@ -239,7 +300,10 @@ export const mapApi = createApi({
                 const loadingCsvs = queryParams.map(
                     async ({ mapVisualization, source, startDate, endDate }) => {
                         const csvRow = await loadCsv<CsvRow>(
-                            `/api/map-visualization/${mapVisualization}/data?source=${source}&start_date=${startDate}&end_date=${endDate}`,
+                            readUrl(
+                                `map-visualization/${mapVisualization}/data?source=${source}&start_date=${startDate}&end_date=${endDate}`
+                            ),
+                            readInit(),
                             autoType
                         )
                         return [mapVisualization, csvRow] as [number, DSVParsedArray<CsvRow>]
@@ -306,7 +370,7 @@ export const mapApi = createApi({
             query: (patch) => ({
                 url: 'editor/map-visualization',
                 method: 'PATCH',
-                body: patch,
+                body: patchToJson(patch),
             }),
             invalidatesTags: (_result, _error, { id }) => [{ type: 'MapVisualization', id }],
             async onQueryStarted(patch, { dispatch, queryFulfilled }) {
@@ -318,10 +382,11 @@ export const mapApi = createApi({
                 queryFulfilled.catch(() => patchResult.undo())
             },
         }),
-        createMapVisualization: builder.mutation<MapVisualization, undefined>({
-            query: () => ({
+        createMapVisualization: builder.mutation<{ id: MapVisualizationId }, NewMapVisualization>({
+            query: (mapVisualization) => ({
                 url: 'editor/map-visualization',
                 method: 'POST',
+                body: mapVisualization,
             }),
             invalidatesTags: [{ type: 'MapVisualization', id: 'ALL' }],
         }),
@@ -426,6 +491,7 @@ export const mapApi = createApi({
                 formData.append('metadata', JSON.stringify(metadata))
                 return fetch('api/editor/upload', {
                     method: 'POST',
+                    headers: authorize(new Headers()),
                     body: formData,
                 }).then(
                     (response) => {
@@ -435,6 +501,9 @@ export const mapApi = createApi({
                         if (response.status === 400) {
                             return response.json().then((error) => ({ error }))
                         }
+                        if (response.status === 401) {
+                            setToken(null)
+                        }
                         return { error: new Error('Unknown error') }
                     },
                     (error) => ({ error })
@@ -463,6 +532,7 @@ export const mapApi = createApi({
 })
 
 export const {
+    useLoginMutation,
     useLazyGetDataQuery,
     useGetDataQuery,
     useGetMapVisualizationsQuery,
@@ -2,6 +2,7 @@ import { json as loadJson } from 'd3'
 import { DateTime, Interval } from 'luxon'
 import { MapSelection } from './DataSelector'
 import { DataQueryParams, TabId } from './MapApi'
+import { readInit, readUrl } from './editor/drafts'
 
 export type MapVisualizationId = number
 export type ScaleTypeName =
@@ -38,6 +39,19 @@ export enum GeographyType {
     USACity = 4,
 }
 
+/** A source, scenario, statistic and date range of a dataset's data */
+export type Slice = {
+    source: number
+    scenario: number
+    statistic: number
+    start_date: string
+    end_date: string
+}
+/** The two slices a delta map visualization compares */
+export type Delta = { operation: number; from: Slice; to: Slice }
+/** The second dataset and slice of a bivariate choropleth */
+export type Bivariate = { dataset: number; slice: Slice }
+
 export function isGeographyType(x: number): x is GeographyType {
     return x in GeographyType
 }
@@ -65,6 +79,10 @@ export interface MapVisualizationPatch {
     legend_decimals?: number
     geography_type: GeographyType
     bubble_color: string
+    delta?: Delta
+    bivariate?: Bivariate
+    classification_method?: number
+    classification_classes: number
 }
 
 export interface MapVisualization {
@@ -97,6 +115,11 @@ export interface MapVisualization {
     order: number
     geography_type: GeographyType
     bubble_color: string
+    delta?: Delta
+    bivariate?: Bivariate
+    classification_method?: number
+    classification_classes: number
+    breaks: number[]
 }
 
 export interface MapVisualizationJson {
@@ -128,6 +151,11 @@ export interface MapVisualizationJson {
     order: number
     geography_type: GeographyType
     bubble_color: string
+    delta: Delta | null
+    bivariate: Bivariate | null
+    classification_method: number | null
+    classification_classes: number
+    breaks: number[]
 }
 
 export const applyPatch = (draft: MapVisualization, patch: MapVisualizationPatch) => {
@@ -141,6 +169,30 @@ export const applyPatch = (draft: MapVisualization, patch: MapVisualizationPatch
     }
 }
 
+/**
+ * The body of a patch, with the delta and bivariate settings in the flat fields the server stores
+ * them in, so that edits to other settings send them back unchanged
+ */
+export const patchToJson = ({ delta, bivariate, ...patch }: MapVisualizationPatch) => ({
+    ...patch,
+    delta_operation: delta?.operation ?? null,
+    delta_from_source: delta?.from.source ?? null,
+    delta_from_scenario: delta?.from.scenario ?? null,
+    delta_from_start_date: delta?.from.start_date ?? null,
+    delta_from_end_date: delta?.from.end_date ?? null,
+    delta_to_source: delta?.to.source ?? null,
+    delta_to_scenario: delta?.to.scenario ?? null,
+    delta_to_start_date: delta?.to.start_date ?? null,
+    delta_to_end_date: delta?.to.end_date ?? null,
+    bivariate_dataset: bivariate?.dataset ?? null,
+    bivariate_source: bivariate?.slice.source ?? null,
+    bivariate_scenario: bivariate?.slice.scenario ?? null,
+    bivariate_statistic: bivariate?.slice.statistic ?? null,
+    bivariate_start_date: bivariate?.slice.start_date ?? null,
+    bivariate_end_date: bivariate?.slice.end_date ?? null,
+    classification_method: patch.classification_method ?? null,
+})
+
 const intervalFromJson = (json: { start_date: string; end_date: string }) =>
     Interval.fromISO(`${json.start_date}/${json.end_date}`)
 
@@ -189,6 +241,11 @@ export const jsonToMapVisualization = (json: MapVisualizationJson): MapVisualiza
         displayName: json.name ?? json.dataset_name,
         geography_type: json.geography_type,
         bubble_color: json.bubble_color,
+        delta: json.delta ?? undefined,
+        bivariate: json.bivariate ?? undefined,
+        classification_method: json.classification_method ?? undefined,
+        classification_classes: json.classification_classes,
+        breaks: json.breaks,
     }
 }
 
@@ -224,7 +281,10 @@ export const getDefaultSelection = (mapVisualization: MapVisualization): MapSele
 }
 
 export const fetchMapVisualization = async (id: number): Promise<MapVisualization> => {
-    const rawJson = await loadJson<MapVisualizationJson>(`/api/map-visualization/${id}`)
+    const rawJson = await loadJson<MapVisualizationJson>(
+        readUrl(`map-visualization/${id}`),
+        readInit()
+    )
     if (rawJson === undefined) {
         return Promise.reject(new Error('Failed to fetch map visualization'))
     }
@@ -253,9 +313,12 @@ export const fetchMapVisualizations = async (props: {
     geographyType?: GeographyType
 }): Promise<MapVisualizationsByTab> => {
     const rawJson = await loadJson<RawJson>(
-        `/api/map-visualization?include_drafts=${props.includeDrafts ?? false}${
-            props.geographyType !== undefined ? `&geography_type=${props.geographyType}` : ''
-        }`
+        readUrl(
+            `map-visualization?include_drafts=${props.includeDrafts ?? false}${
+                props.geographyType !== undefined ? `&geography_type=${props.geographyType}` : ''
+            }`
+        ),
+        readInit()
     )
     if (rawJson === undefined) {
         return Promise.reject(new Error('Failed to fetch map visualizations'))
@@ -266,7 +329,10 @@ export const fetchMapVisualizations = async (props: {
 export const fetchMapVisualizationsByDataset = async (
     dataset: number
 ): Promise<MapVisualizationsByTab> => {
-    const rawJson = await loadJson<RawJson>(`/api/dataset/${dataset}/map-visualization`)
+    const rawJson = await loadJson<RawJson>(
+        readUrl(`dataset/${dataset}/map-visualization`),
+        readInit()
+    )
     if (rawJson === undefined) {
         return Promise.reject(new Error('Failed to fetch map visualizations'))
     }
@@ -9,6 +9,7 @@ import {
     Tab,
     useCreateMapVisualizationMutation,
     useDeleteTabMutation,
+    useGetDatasetsQuery,
     useGetMapVisualizationQuery,
     useGetMapVisualizationsQuery,
     useGetTabsQuery,
@@ -35,6 +36,7 @@ function Editor() {
     const dispatch = useDispatch()
     const { data: allMapVisualizations } = useGetMapVisualizationsQuery({ includeDrafts: true })
     const { data: tabs } = useGetTabsQuery(true)
+    const { data: datasets } = useGetDatasetsQuery(undefined)
     const [createMap] = useCreateMapVisualizationMutation()
     const [deleteTab] = useDeleteTabMutation()
 
@@ -111,10 +113,18 @@ function Editor() {
                             </button>
                         </div>
                     )}
-                    {isDrafts(tab) && (
+                    {isDrafts(tab) && datasets && datasets.length > 0 && (
                         <Button
                             id={editorCss.createMap}
-                            onClick={() => createMap(undefined)}
+                            onClick={() =>
+                                createMap({
+                                    dataset: datasets[0].id,
+                                    map_type: 1,
+                                    color_palette: 1,
+                                    scale_type: 2,
+                                    formatter_type: 3,
+                                })
+                            }
                             variant="contained"
                         >
                             Create new map
@@ -0,0 +1,48 @@
+import { LoadingButton } from '@mui/lab'
+import { TextField } from '@mui/material'
+import { ReactNode, useEffect, useState } from 'react'
+import { useLoginMutation } from '../MapApi'
+import css from './DatasetEditor.module.css'
+import { getToken, onTokenChange } from './token'
+
+function Login() {
+    const [login, { isLoading, isError }] = useLoginMutation()
+    const [username, setUsername] = useState('')
+    const [password, setPassword] = useState('')
+
+    return (
+        <form
+            className={css.datasetOptions}
+            onSubmit={(e) => {
+                login({ username, password })
+                e.preventDefault()
+            }}
+        >
+            <TextField
+                label="Username"
+                value={username}
+                onChange={(e) => setUsername(e.target.value)}
+            />
+            <TextField
+                label="Password"
+                type="password"
+                value={password}
+                error={isError}
+                helperText={isError ? 'Invalid username or password' : undefined}
+                onChange={(e) => setPassword(e.target.value)}
+            />
+            <LoadingButton variant="contained" type="submit" loading={isLoading}>
+                Log in
+            </LoadingButton>
+        </form>
+    )
+}
+
+/** Shows the login form instead of its children until there's an editor token */
+export function RequireLogin({ children }: { children: ReactNode }) {
+    const [hasToken, setHasToken] = useState(getToken() !== null)
+
+    useEffect(() => onTokenChange(() => setHasToken(getToken() !== null)), [])
+
+    return hasToken ? <>{children}</> : <Login />
+}
@@ -0,0 +1,26 @@
+import { ReactNode, useEffect, useState } from 'react'
+import { useDispatch } from 'react-redux'
+import { mapApi } from '../MapApi'
+import { readsDrafts, setReadsDrafts } from './drafts'
+
+/**
+ * Shows its children with the drafts instead of what's published, so curators see their changes
+ * before publishing them. The cache is cleared on the way in and out so the two don't mix.
+ */
+export function ReadDrafts({ children }: { children: ReactNode }) {
+    const dispatch = useDispatch()
+    // children only render once reads go to the drafts, so their first queries do too
+    const [ready, setReady] = useState(readsDrafts())
+
+    useEffect(() => {
+        setReadsDrafts(true)
+        dispatch(mapApi.util.resetApiState())
+        setReady(true)
+        return () => {
+            setReadsDrafts(false)
+            dispatch(mapApi.util.resetApiState())
+        }
+    }, [dispatch])
+
+    return ready ? <>{children}</> : null
+}
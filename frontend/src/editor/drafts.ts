@@ -0,0 +1,16 @@
+import { authorize } from './token'
+
+let drafts = false
+
+/** Whether reads go to the editor server, which serves the read-only endpoints over the drafts */
+export const readsDrafts = () => drafts
+
+export const setReadsDrafts = (value: boolean) => {
+    drafts = value
+}
+
+/** The url of a read-only endpoint, on the editor server while reading the drafts */
+export const readUrl = (path: string) => (drafts ? `/api/editor/${path}` : `/api/${path}`)
+
+/** The fetch options for a `readUrl`, with the editor token while reading the drafts */
+export const readInit = (): RequestInit => (drafts ? { headers: authorize(new Headers()) } : {})
@@ -0,0 +1,29 @@
+const TOKEN_KEY = 'editorToken'
+const TOKEN_CHANGED = 'editorTokenChanged'
+
+/** The editor session token from `/login`, kept across page loads */
+export const getToken = (): string | null => localStorage.getItem(TOKEN_KEY)
+
+export const setToken = (token: string | null) => {
+    if (token === null) {
+        localStorage.removeItem(TOKEN_KEY)
+    } else {
+        localStorage.setItem(TOKEN_KEY, token)
+    }
+    window.dispatchEvent(new Event(TOKEN_CHANGED))
+}
+
+/** Calls `listener` whenever the token is set or cleared, and returns a function to stop */
+export const onTokenChange = (listener: () => void) => {
+    window.addEventListener(TOKEN_CHANGED, listener)
+    return () => window.removeEventListener(TOKEN_CHANGED, listener)
+}
+
+/** The headers editor requests need, with the token if there is one */
+export const authorize = (headers: Headers) => {
+    const token = getToken()
+    if (token !== null) {
+        headers.set('Authorization', `Bearer ${token}`)
+    }
+    return headers
+}